@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS alert_status_history;
+DROP TABLE IF EXISTS notifications;
+DROP TABLE IF EXISTS llm_usage;
+DROP TABLE IF EXISTS tool_calls;
+DROP TABLE IF EXISTS incidents;
+DROP TABLE IF EXISTS analysis_jobs;
+DROP TABLE IF EXISTS chat_messages;
+DROP TABLE IF EXISTS alerts;
+DROP TABLE IF EXISTS mcp_servers;
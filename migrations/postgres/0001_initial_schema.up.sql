@@ -0,0 +1,147 @@
+-- Schema of the first versioned release. Timestamps are RFC 3339 text and prefix bounds
+-- fixed-width hex, both compared byte by byte, hence the "C" collation.
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id BIGSERIAL PRIMARY KEY,
+    alert_data TEXT NOT NULL,
+    initial_response TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'bgp_alerter',
+    analysis_status TEXT NOT NULL DEFAULT 'done',
+    severity TEXT,
+    affected_prefix TEXT,
+    expected_asn TEXT,
+    observed_asn TEXT,
+    duration TEXT,
+    peer_count BIGINT,
+    group_name TEXT,
+    status TEXT NOT NULL DEFAULT 'new',
+    assignee TEXT,
+    resolution_notes TEXT,
+    created_at TEXT COLLATE "C" NOT NULL,
+    updated_at TEXT COLLATE "C" NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT COLLATE "C" NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mcp_servers (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    transport_type TEXT NOT NULL CHECK(transport_type IN ('http', 'stdio')),
+    url TEXT,
+    command TEXT,
+    args TEXT,
+    env TEXT,
+    enabled BIGINT NOT NULL DEFAULT 1,
+    is_native BIGINT NOT NULL DEFAULT 0,
+    created_at TEXT COLLATE "C" NOT NULL,
+    updated_at TEXT COLLATE "C" NOT NULL
+);
+
+-- Analysis work queue
+CREATE TABLE IF NOT EXISTS analysis_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'queued' CHECK(status IN ('queued', 'running', 'done', 'failed')),
+    attempts BIGINT NOT NULL DEFAULT 0,
+    max_attempts BIGINT NOT NULL,
+    next_attempt_at TEXT COLLATE "C" NOT NULL,
+    last_error TEXT,
+    created_at TEXT COLLATE "C" NOT NULL,
+    updated_at TEXT COLLATE "C" NOT NULL
+);
+
+-- Deduplicated groups of repeated alerts
+CREATE TABLE IF NOT EXISTS incidents (
+    id BIGSERIAL PRIMARY KEY,
+    fingerprint TEXT NOT NULL,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    asn TEXT NOT NULL,
+    neworigin TEXT,
+    newprefix TEXT,
+    occurrence_count BIGINT NOT NULL DEFAULT 1,
+    first_seen TEXT COLLATE "C" NOT NULL,
+    last_seen TEXT COLLATE "C" NOT NULL,
+    peers BIGINT NOT NULL DEFAULT 0,
+    paths BIGINT NOT NULL DEFAULT 0,
+    analyzed_peers BIGINT NOT NULL DEFAULT 0,
+    analyzed_paths BIGINT NOT NULL DEFAULT 0,
+    prefix_family BIGINT,
+    prefix_start TEXT COLLATE "C",
+    prefix_end TEXT COLLATE "C",
+    asns TEXT
+);
+
+-- Audit trail of MCP tool calls made by the agents
+CREATE TABLE IF NOT EXISTS tool_calls (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    chat_message_id BIGINT REFERENCES chat_messages(id) ON DELETE CASCADE,
+    server_name TEXT,
+    tool_name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    latency_ms BIGINT NOT NULL,
+    created_at TEXT COLLATE "C" NOT NULL
+);
+
+-- LLM token usage per prompt; kept when the alert is deleted so spend stays accounted for
+CREATE TABLE IF NOT EXISTS llm_usage (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT REFERENCES alerts(id) ON DELETE SET NULL,
+    chat_message_id BIGINT REFERENCES chat_messages(id) ON DELETE SET NULL,
+    alert_kind TEXT,
+    model TEXT NOT NULL,
+    input_tokens BIGINT NOT NULL,
+    output_tokens BIGINT NOT NULL,
+    turns BIGINT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    created_at TEXT COLLATE "C" NOT NULL
+);
+
+-- Delivery of alert notifications to each channel
+CREATE TABLE IF NOT EXISTS notifications (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    channel TEXT NOT NULL,
+    status TEXT NOT NULL,
+    attempts BIGINT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TEXT COLLATE "C" NOT NULL,
+    updated_at TEXT COLLATE "C" NOT NULL
+);
+
+-- Lifecycle history of alerts: status transitions and changes of assignee
+CREATE TABLE IF NOT EXISTS alert_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    alert_id BIGINT NOT NULL REFERENCES alerts(id) ON DELETE CASCADE,
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    assignee TEXT,
+    changed_by TEXT,
+    note TEXT,
+    created_at TEXT COLLATE "C" NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_alerts_created_at ON alerts(created_at, id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_alert_id ON chat_messages(alert_id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_created_at ON chat_messages(created_at);
+CREATE INDEX IF NOT EXISTS idx_mcp_servers_enabled ON mcp_servers(enabled);
+CREATE INDEX IF NOT EXISTS idx_analysis_jobs_status_next ON analysis_jobs(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_incidents_fingerprint ON incidents(fingerprint, last_seen);
+CREATE INDEX IF NOT EXISTS idx_incidents_alert_id ON incidents(alert_id);
+CREATE INDEX IF NOT EXISTS idx_incidents_prefix ON incidents(prefix_family, prefix_start);
+CREATE INDEX IF NOT EXISTS idx_tool_calls_alert_id ON tool_calls(alert_id);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_created_at ON llm_usage(created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_alert_id ON llm_usage(alert_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_alert_id ON notifications(alert_id);
+CREATE INDEX IF NOT EXISTS idx_alert_status_history_alert_id ON alert_status_history(alert_id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_chat_messages_search;
+DROP INDEX IF EXISTS idx_alerts_search;
@@ -0,0 +1,8 @@
+-- Full-text indexes over the analyses and chat messages; queries must use the same
+-- expressions for PostgreSQL to pick them
+
+CREATE INDEX IF NOT EXISTS idx_alerts_search
+ON alerts USING GIN (to_tsvector('simple', initial_response));
+
+CREATE INDEX IF NOT EXISTS idx_chat_messages_search
+ON chat_messages USING GIN (to_tsvector('simple', content));
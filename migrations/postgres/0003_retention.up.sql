@@ -0,0 +1,14 @@
+-- Purges of expired alerts and chat messages, and the index finding expired alerts
+
+CREATE TABLE IF NOT EXISTS retention_runs (
+    id BIGSERIAL PRIMARY KEY,
+    started_at TEXT COLLATE "C" NOT NULL,
+    finished_at TEXT COLLATE "C" NOT NULL,
+    alerts_deleted BIGINT NOT NULL DEFAULT 0,
+    chat_messages_deleted BIGINT NOT NULL DEFAULT 0,
+    archive_path TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_started_at ON retention_runs(started_at);
+CREATE INDEX IF NOT EXISTS idx_alerts_status_updated_at ON alerts(status, updated_at);
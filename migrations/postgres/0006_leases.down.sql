@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_notifications_status;
+ALTER TABLE notifications DROP COLUMN lease_expires_at;
+ALTER TABLE notifications DROP COLUMN owner;
+ALTER TABLE analysis_jobs DROP COLUMN lease_expires_at;
+ALTER TABLE analysis_jobs DROP COLUMN owner;
@@ -0,0 +1,156 @@
+-- Schema of the first versioned release. Tables are created only when missing so that
+-- databases from before versioned migrations are adopted as they are.
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_data TEXT NOT NULL,
+    initial_response TEXT NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'bgp_alerter',
+    analysis_status TEXT NOT NULL DEFAULT 'done',
+    severity TEXT,
+    affected_prefix TEXT,
+    expected_asn TEXT,
+    observed_asn TEXT,
+    duration TEXT,
+    peer_count INTEGER,
+    group_name TEXT,
+    status TEXT NOT NULL DEFAULT 'new',
+    assignee TEXT,
+    resolution_notes TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS mcp_servers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    transport_type TEXT NOT NULL CHECK(transport_type IN ('http', 'stdio')),
+    url TEXT,
+    command TEXT,
+    args TEXT,
+    env TEXT,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    is_native INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Analysis work queue
+CREATE TABLE IF NOT EXISTS analysis_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued' CHECK(status IN ('queued', 'running', 'done', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    next_attempt_at TEXT NOT NULL,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
+);
+
+-- Deduplicated groups of repeated alerts
+CREATE TABLE IF NOT EXISTS incidents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fingerprint TEXT NOT NULL,
+    alert_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    asn TEXT NOT NULL,
+    neworigin TEXT,
+    newprefix TEXT,
+    occurrence_count INTEGER NOT NULL DEFAULT 1,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL,
+    peers INTEGER NOT NULL DEFAULT 0,
+    paths INTEGER NOT NULL DEFAULT 0,
+    analyzed_peers INTEGER NOT NULL DEFAULT 0,
+    analyzed_paths INTEGER NOT NULL DEFAULT 0,
+    prefix_family INTEGER,
+    prefix_start TEXT,
+    prefix_end TEXT,
+    asns TEXT,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
+);
+
+-- Audit trail of MCP tool calls made by the agents
+CREATE TABLE IF NOT EXISTS tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER NOT NULL,
+    chat_message_id INTEGER,
+    server_name TEXT,
+    tool_name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    latency_ms INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE,
+    FOREIGN KEY (chat_message_id) REFERENCES chat_messages(id) ON DELETE CASCADE
+);
+
+-- LLM token usage per prompt; kept when the alert is deleted so spend stays accounted for
+CREATE TABLE IF NOT EXISTS llm_usage (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER,
+    chat_message_id INTEGER,
+    alert_kind TEXT,
+    model TEXT NOT NULL,
+    input_tokens INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    turns INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE SET NULL,
+    FOREIGN KEY (chat_message_id) REFERENCES chat_messages(id) ON DELETE SET NULL
+);
+
+-- Delivery of alert notifications to each channel
+CREATE TABLE IF NOT EXISTS notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    status TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
+);
+
+-- Lifecycle history of alerts: status transitions and changes of assignee
+CREATE TABLE IF NOT EXISTS alert_status_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    alert_id INTEGER NOT NULL,
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    assignee TEXT,
+    changed_by TEXT,
+    note TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_alerts_created_at ON alerts(created_at, id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_alert_id ON chat_messages(alert_id);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_created_at ON chat_messages(created_at);
+CREATE INDEX IF NOT EXISTS idx_mcp_servers_enabled ON mcp_servers(enabled);
+CREATE INDEX IF NOT EXISTS idx_analysis_jobs_status_next ON analysis_jobs(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_incidents_fingerprint ON incidents(fingerprint, last_seen);
+CREATE INDEX IF NOT EXISTS idx_incidents_alert_id ON incidents(alert_id);
+CREATE INDEX IF NOT EXISTS idx_incidents_prefix ON incidents(prefix_family, prefix_start);
+CREATE INDEX IF NOT EXISTS idx_tool_calls_alert_id ON tool_calls(alert_id);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_created_at ON llm_usage(created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_alert_id ON llm_usage(alert_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_alert_id ON notifications(alert_id);
+CREATE INDEX IF NOT EXISTS idx_alert_status_history_alert_id ON alert_status_history(alert_id);
@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS chat_messages_fts_delete;
+DROP TRIGGER IF EXISTS chat_messages_fts_insert;
+DROP TRIGGER IF EXISTS alerts_fts_update;
+DROP TRIGGER IF EXISTS alerts_fts_delete;
+DROP TRIGGER IF EXISTS alerts_fts_insert;
+DROP TABLE IF EXISTS chat_messages_fts;
+DROP TABLE IF EXISTS alerts_fts;
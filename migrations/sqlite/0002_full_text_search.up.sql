@@ -0,0 +1,35 @@
+-- FTS5 index over the analyses and chat messages, kept in sync by triggers
+
+CREATE VIRTUAL TABLE IF NOT EXISTS alerts_fts
+USING fts5(initial_response, content='alerts', content_rowid='id');
+
+CREATE VIRTUAL TABLE IF NOT EXISTS chat_messages_fts
+USING fts5(content, content='chat_messages', content_rowid='id');
+
+CREATE TRIGGER IF NOT EXISTS alerts_fts_insert AFTER INSERT ON alerts BEGIN
+    INSERT INTO alerts_fts(rowid, initial_response) VALUES (new.id, new.initial_response);
+END;
+
+CREATE TRIGGER IF NOT EXISTS alerts_fts_delete AFTER DELETE ON alerts BEGIN
+    INSERT INTO alerts_fts(alerts_fts, rowid, initial_response)
+    VALUES ('delete', old.id, old.initial_response);
+END;
+
+CREATE TRIGGER IF NOT EXISTS alerts_fts_update AFTER UPDATE OF initial_response ON alerts BEGIN
+    INSERT INTO alerts_fts(alerts_fts, rowid, initial_response)
+    VALUES ('delete', old.id, old.initial_response);
+    INSERT INTO alerts_fts(rowid, initial_response) VALUES (new.id, new.initial_response);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chat_messages_fts_insert AFTER INSERT ON chat_messages BEGIN
+    INSERT INTO chat_messages_fts(rowid, content) VALUES (new.id, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS chat_messages_fts_delete AFTER DELETE ON chat_messages BEGIN
+    INSERT INTO chat_messages_fts(chat_messages_fts, rowid, content)
+    VALUES ('delete', old.id, old.content);
+END;
+
+-- Index the rows stored before the index existed
+INSERT INTO alerts_fts(alerts_fts) VALUES ('rebuild');
+INSERT INTO chat_messages_fts(chat_messages_fts) VALUES ('rebuild');
@@ -0,0 +1,14 @@
+-- Purges of expired alerts and chat messages, and the index finding expired alerts
+
+CREATE TABLE IF NOT EXISTS retention_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    alerts_deleted INTEGER NOT NULL DEFAULT 0,
+    chat_messages_deleted INTEGER NOT NULL DEFAULT 0,
+    archive_path TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_started_at ON retention_runs(started_at);
+CREATE INDEX IF NOT EXISTS idx_alerts_status_updated_at ON alerts(status, updated_at);
@@ -0,0 +1,7 @@
+-- ROA coverage state of every monitored prefix at the last check, compared against after restarts
+
+CREATE TABLE IF NOT EXISTS roa_coverage (
+    prefix TEXT PRIMARY KEY,
+    state TEXT NOT NULL,
+    checked_at TEXT NOT NULL
+);
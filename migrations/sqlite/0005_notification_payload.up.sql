@@ -0,0 +1,3 @@
+-- Notification sent to the channels, kept so pending deliveries can be resumed after a restart
+
+ALTER TABLE notifications ADD COLUMN payload TEXT;
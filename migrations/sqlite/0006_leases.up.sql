@@ -0,0 +1,9 @@
+-- Replica that claimed a running analysis job or a pending delivery, and until when; other
+-- replicas sharing the database only take it over once the lease has expired
+
+ALTER TABLE analysis_jobs ADD COLUMN owner TEXT;
+ALTER TABLE analysis_jobs ADD COLUMN lease_expires_at TEXT;
+ALTER TABLE notifications ADD COLUMN owner TEXT;
+ALTER TABLE notifications ADD COLUMN lease_expires_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_status ON notifications(status);
@@ -1,148 +1,92 @@
-use crate::alerts::http::server::BGPAlerterAlert;
-use crate::config::ANTHROPIC_MAX_TOKENS;
-use crate::mcp_clients::{self, MCPConnection};
+use crate::agents::llm::{LlmClient, LlmSettings};
+use crate::agents::report;
+use crate::agents::trace::AgentTrace;
+use crate::alerts::sources::{self, AlertEnvelope};
+use crate::database::models::IncidentReport;
+use crate::mcp_manager::McpManager;
 use color_eyre::Result;
-use rig::client::ProviderClient;
-use rig::completion::Prompt;
-use rig::prelude::CompletionClient;
-use rig::providers::anthropic;
-use sqlx::SqlitePool;
 
 pub struct AlertAnalyzer;
 
-const PREAMBLE: &str = r#"
-You are a BGP security analyst for busy NOC operators who need FAST, ACTIONABLE insights.
-
-CRITICAL: Use your available tools to PROACTIVELY gather enrichment data.
-The operator should NOT need to run queries themselves - you do the lookups and include relevant 
-context directly in your report (ownership info, ASN details, historical patterns, etc.).
-
-COMMUNICATION RULES:
-- Be extremely concise - every word must add value
-- Lead with the most critical information
-- Assume the operator understands BGP basics
-- Focus on "what to do" over "what happened"
-- No emojis, minimal formatting
-- If tools fail, provide analysis based solely on alert data and mention tool failures briefly
-
-Your reports should take 30 seconds to read and act upon, not 5 minutes."#;
-
 impl AlertAnalyzer {
+    /// Analyze an alert; `profile` holds extra instructions from the prompt profile of the
+    /// alert's group
     pub async fn run(
-        alert: BGPAlerterAlert,
+        alert: &AlertEnvelope,
         config: &crate::config::AppConfig,
-        db_pool: &SqlitePool,
-    ) -> Result<String> {
+        profile: Option<&str>,
+        mcp_manager: &McpManager,
+        trace: &AgentTrace,
+    ) -> Result<IncidentReport> {
         dotenv::dotenv().ok();
 
-        tracing::info!("Starting hijack agent run");
+        tracing::info!("Starting {} analysis agent run", alert.kind.as_str());
 
-        // Connect to all enabled MCP servers from database
-        let mcp_connections = mcp_clients::connect_all_enabled(db_pool).await?;
+        // Reuse the pooled connections to all enabled MCP servers
+        let mcp_connections = mcp_manager.connections().await;
 
         if mcp_connections.is_empty() {
             tracing::warn!("No MCP servers available - agent will run without tools");
         } else {
             let total_tools: usize = mcp_connections.iter().map(|c| c.tool_count()).sum();
+            let names: Vec<&str> = mcp_connections.iter().map(|c| c.name.as_str()).collect();
             tracing::info!(
-                "Connected to {} MCP server(s) with {} total tool(s)",
+                "Using {} MCP server(s) ({}) with {} total tool(s)",
                 mcp_connections.len(),
+                names.join(", "),
                 total_tools
             );
         }
 
-        let completion_model = anthropic::Client::from_env();
-
-        let alert_json = serde_json::to_string_pretty(&alert)?;
-        let prompt = Self::build_prompt(&alert_json);
-
-        // Build and run agent with or without MCP tools
-        let res = Self::run_agent_with_tools(
-            completion_model,
-            &config.llm_model_name,
-            mcp_connections,
-            &prompt,
-        )
-        .await?;
-
-        Ok(res)
-    }
-
-    fn build_prompt(alert_json: &str) -> String {
-        format!(
-            r#"Analyze this BGP alert and respond with ONLY a valid JSON object. NO markdown, NO explanations, JUST the JSON.
-
-BGP Alert:
-{alert_json}
-
-CRITICAL INSTRUCTIONS:
-1. USE YOUR TOOLS: Query WHOIS/RIPEstat for ASN ownership, prefix registration, and historical data
-2. ENRICH YOUR RESPONSE: Include context the operator would need (who owns the ASNs, legitimacy indicators, etc.)
-3. SAVE OPERATOR TIME: They should NOT need to run additional queries - you provide all relevant context
-4. BE SPECIFIC: Include actual organization names, registration details, and concrete evidence in your assessment
-
-Required JSON structure:
-{{
-  "summary": "2-3 sentence executive summary with enriched context (include ASN owner names, registration status, etc.)",
-  "severity": "Critical|High|Medium|Low|Info",
-  "key_facts": {{
-    "affected_prefix": "prefix from alert with registration owner if found",
-    "expected_asn": "expected ASN with organization name (e.g. 'AS3333 (RIPE NCC)')",
-    "observed_asn": "observed ASN with organization name (e.g. 'AS9999 (Unknown Operator)')",
-    "duration": "human readable duration from earliest to latest",
-    "peer_count": number of peers (count from alert)
-  }},
-  "immediate_actions": [
-    "First action with specific contact info or validation method if available",
-    "Second action with concrete steps based on enrichment data",
-    "Third action informed by historical patterns or registration info"
-  ],
-  "risk_assessment": "1-2 sentence analysis informed by tool lookups (ownership conflicts, legitimacy indicators, known relationships)",
-  "tool_notes": "Brief summary of enrichment data gathered or any tool failures"
-}}
-
-EXAMPLES of enriched responses:
-- Good: "AS9999 (Suspicious Networks Inc.) announcing prefix registered to AS3333 (RIPE NCC)"
-- Bad: "AS9999 announcing prefix expected from AS3333"
-
-CRITICAL: Output ONLY valid JSON. No markdown code blocks, no extra text."#
-        )
-    }
-
-    async fn run_agent_with_tools(
-        client: anthropic::Client,
-        model_name: &str,
-        connections: Vec<MCPConnection>,
-        prompt: &str,
-    ) -> Result<String> {
-        // Handle the case with no MCP connections
-        if connections.is_empty() {
-            let agent = client
-                .agent(model_name)
-                .preamble(PREAMBLE)
-                .max_tokens(ANTHROPIC_MAX_TOKENS)
-                .build();
-            return Ok(agent.prompt(prompt).multi_turn(3).await?);
+        let client = LlmClient::from_config(config)?;
+
+        let source = sources::source_for(alert.kind);
+        let preamble = match profile {
+            Some(instructions) => format!("{}\n\n{}", source.analysis_preamble(), instructions),
+            None => source.analysis_preamble(),
+        };
+        let mut prompt = source.analysis_prompt(alert)?;
+        if let Some(relevance) = &alert.relevance {
+            prompt.push_str(&format!(
+                "\n\nWhy this alert is relevant: {}.",
+                relevance.describe()
+            ));
+            if relevance.prefix_info.is_some() {
+                prompt.push_str(
+                    " The expected origins come from prefixes.yml; any other origin is \
+                     unexpected unless your tools show it is authorized.",
+                );
+            }
+        }
+        if let Some(rpki) = &alert.rpki {
+            prompt.push_str(&format!(
+                "\n\nRPKI origin validation against the local VRP set (RFC 6811): {}. \
+                 Use this instead of querying remote RPKI tools for the same route.",
+                rpki.describe()
+            ));
         }
 
-        // Build agent with MCP tools
-        // We need to handle the type transformation that happens when adding rmcp_tools
-        let mut connections_iter = connections.into_iter();
-
-        // Start with the first connection
-        let first_conn = connections_iter.next().unwrap();
-        let mut agent_builder = client
-            .agent(model_name)
-            .preamble(PREAMBLE)
-            .max_tokens(ANTHROPIC_MAX_TOKENS)
-            .rmcp_tools(first_conn.tools, first_conn.peer);
+        let settings = LlmSettings::from_config(config);
 
-        // Add remaining connections
-        for conn in connections_iter {
-            agent_builder = agent_builder.rmcp_tools(conn.tools, conn.peer);
+        // Build and run agent with or without MCP tools
+        let res = client
+            .prompt(&settings, &preamble, mcp_connections, &prompt, trace)
+            .await?;
+
+        match report::parse_report(&res) {
+            Ok(report) => Ok(report),
+            Err(e) => {
+                // Give the model one chance to fix its own output, without tools
+                tracing::warn!(
+                    "Analysis output was not a valid report ({}), re-prompting",
+                    e
+                );
+                let repair_prompt = report::repair_prompt(&res, &e.to_string());
+                let retry = client
+                    .prompt(&settings, &preamble, Vec::new(), &repair_prompt, trace)
+                    .await?;
+                report::parse_report(&retry)
+            }
         }
-
-        let agent = agent_builder.build();
-        Ok(agent.prompt(prompt).multi_turn(3).await?)
     }
 }
@@ -1,54 +1,107 @@
-use crate::alerts::http::server::BGPAlerterAlert;
-use crate::config::ANTHROPIC_MAX_TOKENS;
+use crate::agents::llm::{LlmClient, LlmSettings, StreamUpdate};
+use crate::agents::trace::AgentTrace;
+use crate::alerts::sources::{self, AlertEnvelope};
 use crate::database::models;
-use crate::mcp_clients::{self, MCPConnection};
+use crate::mcp_clients::MCPConnection;
+use crate::mcp_manager::McpManager;
 use color_eyre::Result;
-use rig::client::ProviderClient;
-use rig::completion::Prompt;
-use rig::prelude::CompletionClient;
-use rig::providers::anthropic;
-use sqlx::SqlitePool;
+use tokio::sync::mpsc::UnboundedSender;
 
 pub struct Chat;
 
-const PREAMBLE: &str = r#"
-You are a BGP security analyst assistant helping NOC operators with follow-up questions about BGP alerts.
-You have access to tools to gather information about IP prefixes, ASNs, and routing announcements.
-You are answering questions about a BGP alert that has already been analyzed. Provide clear, concise answers
-based on the original alert data and your access to current routing information.
-Do not use emojis in your responses. Use plain text formatting only."#;
-
 impl Chat {
     pub async fn run(
-        alert: BGPAlerterAlert,
+        alert: AlertEnvelope,
         initial_response: &str,
         chat_history: &[models::ChatMessage],
         user_question: &str,
         config: &crate::config::AppConfig,
-        db_pool: &SqlitePool,
+        mcp_manager: &McpManager,
+        trace: &AgentTrace,
     ) -> Result<String> {
         dotenv::dotenv().ok();
 
         tracing::info!("Starting chat agent run");
 
-        // Connect to all enabled MCP servers from database
-        let mcp_connections = mcp_clients::connect_all_enabled(db_pool).await?;
+        let mcp_connections = Self::connect_tools(mcp_manager).await;
+        let client = LlmClient::from_config(config)?;
+        let prompt = Self::build_prompt(&alert, initial_response, chat_history, user_question)?;
+
+        // Build and run agent with or without MCP tools
+        let res = client
+            .prompt(
+                &LlmSettings::from_config(config),
+                &sources::source_for(alert.kind).chat_preamble(),
+                mcp_connections,
+                &prompt,
+                trace,
+            )
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Like `run`, but forwards text deltas and tool calls to `updates` as they arrive
+    #[allow(clippy::too_many_arguments)]
+    pub async fn stream(
+        alert: AlertEnvelope,
+        initial_response: &str,
+        chat_history: &[models::ChatMessage],
+        user_question: &str,
+        config: &crate::config::AppConfig,
+        mcp_manager: &McpManager,
+        trace: &AgentTrace,
+        updates: UnboundedSender<StreamUpdate>,
+    ) -> Result<String> {
+        dotenv::dotenv().ok();
+
+        tracing::info!("Starting streaming chat agent run");
+
+        let mcp_connections = Self::connect_tools(mcp_manager).await;
+        let client = LlmClient::from_config(config)?;
+        let prompt = Self::build_prompt(&alert, initial_response, chat_history, user_question)?;
+
+        client
+            .stream_prompt(
+                &LlmSettings::from_config(config),
+                &sources::source_for(alert.kind).chat_preamble(),
+                mcp_connections,
+                &prompt,
+                trace,
+                updates,
+            )
+            .await
+    }
+
+    async fn connect_tools(mcp_manager: &McpManager) -> Vec<MCPConnection> {
+        // Reuse the pooled connections to all enabled MCP servers
+        let mcp_connections = mcp_manager.connections().await;
 
         if mcp_connections.is_empty() {
             tracing::warn!("No MCP servers available - chat agent will run without tools");
         } else {
             let total_tools: usize = mcp_connections.iter().map(|c| c.tool_count()).sum();
+            let names: Vec<&str> = mcp_connections.iter().map(|c| c.name.as_str()).collect();
             tracing::info!(
-                "Connected to {} MCP server(s) with {} total tool(s)",
+                "Using {} MCP server(s) ({}) with {} total tool(s)",
                 mcp_connections.len(),
+                names.join(", "),
                 total_tools
             );
         }
 
-        let completion_model = anthropic::Client::from_env();
+        mcp_connections
+    }
 
+    fn build_prompt(
+        alert: &AlertEnvelope,
+        initial_response: &str,
+        chat_history: &[models::ChatMessage],
+        user_question: &str,
+    ) -> Result<String> {
         // Build context from original alert and chat history
-        let alert_json = serde_json::to_string_pretty(&alert)?;
+        let alert_json = serde_json::to_string_pretty(&alert.stored_payload())?;
+        let label = sources::source_for(alert.kind).label();
 
         // Format chat history (last 10-15 messages)
         let recent_history: Vec<_> = chat_history.iter().rev().take(15).rev().collect();
@@ -62,10 +115,10 @@ impl Chat {
             context
         };
 
-        let prompt = format!(
-            r#"You are answering a follow-up question about a BGP alert that was previously analyzed.
+        Ok(format!(
+            r#"You are answering a follow-up question about a {label} that was previously analyzed.
 
-Original BGP Alert:
+Original {label}:
 {alert_json}
 
 Initial Analysis Report:
@@ -76,54 +129,6 @@ User's Question: {user_question}
 
 Please provide a clear, concise answer to the user's question. You can use the available tools to gather additional information if needed.
 Do not use emojis - use plain text formatting only."#
-        );
-
-        // Build and run agent with or without MCP tools
-        let res = Self::run_agent_with_tools(
-            completion_model,
-            &config.llm_model_name,
-            mcp_connections,
-            &prompt,
-        )
-        .await?;
-
-        Ok(res)
-    }
-
-    async fn run_agent_with_tools(
-        client: anthropic::Client,
-        model_name: &str,
-        connections: Vec<MCPConnection>,
-        prompt: &str,
-    ) -> Result<String> {
-        // Handle the case with no MCP connections
-        if connections.is_empty() {
-            let agent = client
-                .agent(model_name)
-                .preamble(PREAMBLE)
-                .max_tokens(ANTHROPIC_MAX_TOKENS)
-                .build();
-            return Ok(agent.prompt(prompt).multi_turn(3).await?);
-        }
-
-        // Build agent with MCP tools
-        // We need to handle the type transformation that happens when adding rmcp_tools
-        let mut connections_iter = connections.into_iter();
-
-        // Start with the first connection
-        let first_conn = connections_iter.next().unwrap();
-        let mut agent_builder = client
-            .agent(model_name)
-            .preamble(PREAMBLE)
-            .max_tokens(ANTHROPIC_MAX_TOKENS)
-            .rmcp_tools(first_conn.tools, first_conn.peer);
-
-        // Add remaining connections
-        for conn in connections_iter {
-            agent_builder = agent_builder.rmcp_tools(conn.tools, conn.peer);
-        }
-
-        let agent = agent_builder.build();
-        Ok(agent.prompt(prompt).multi_turn(3).await?)
+        ))
     }
 }
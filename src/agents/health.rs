@@ -1,15 +1,13 @@
 use color_eyre::Result;
-use rig::client::ProviderClient;
-use rig::providers::anthropic;
 use serde::Serialize;
-use sqlx::SqlitePool;
 use std::collections::HashMap;
-use std::time::Duration;
 use utoipa::ToSchema;
 
+use crate::agents::llm::LlmClient;
 use crate::config::AppConfig;
+use crate::database::DbPool;
 use crate::database::db::get_enabled_mcp_servers;
-use crate::mcp_clients;
+use crate::mcp_manager::{McpManager, McpServerStatus};
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct HealthStatus {
@@ -17,7 +15,11 @@ pub struct HealthStatus {
     pub services: HashMap<String, String>,
 }
 
-pub async fn run(_config: &AppConfig, db_pool: &SqlitePool) -> Result<HealthStatus> {
+pub async fn run(
+    config: &AppConfig,
+    db_pool: &DbPool,
+    mcp_manager: &McpManager,
+) -> Result<HealthStatus> {
     dotenv::dotenv().ok();
 
     tracing::info!("Starting health check");
@@ -36,35 +38,35 @@ pub async fn run(_config: &AppConfig, db_pool: &SqlitePool) -> Result<HealthStat
                     "no servers configured".to_string(),
                 );
             } else {
+                // Report the pooled connections instead of opening new ones
+                let status: HashMap<String, McpServerStatus> =
+                    mcp_manager.status().await.into_iter().collect();
+
                 for server in servers {
                     let server_name = format!("mcp_{}", server.name());
 
-                    // Check connection with timeout
-                    let check_result = tokio::time::timeout(
-                        Duration::from_secs(5),
-                        mcp_clients::test_connection(&server),
-                    )
-                    .await;
-
-                    match check_result {
-                        Ok(Ok(tool_count)) => {
+                    match status.get(server.name()) {
+                        Some(McpServerStatus::Connected { tool_count }) => {
                             health_status
                                 .services
                                 .insert(server_name, format!("healthy ({tool_count} tools)"));
                         }
-                        Ok(Err(e)) => {
+                        Some(McpServerStatus::Disconnected { error, retry_in }) => {
                             health_status.status = "degraded".to_string();
-                            health_status
-                                .services
-                                .insert(server_name, format!("error: {e}"));
-                            tracing::warn!("MCP server '{}' check failed: {}", server.name(), e);
+                            health_status.services.insert(
+                                server_name,
+                                format!("error: {error} (retry in {}s)", retry_in.as_secs()),
+                            );
+                            tracing::warn!(
+                                "MCP server '{}' is disconnected: {}",
+                                server.name(),
+                                error
+                            );
                         }
-                        Err(_) => {
-                            health_status.status = "degraded".to_string();
+                        None => {
                             health_status
                                 .services
-                                .insert(server_name, "timeout".to_string());
-                            tracing::warn!("MCP server '{}' check timed out", server.name());
+                                .insert(server_name, "connecting".to_string());
                         }
                     }
                 }
@@ -80,7 +82,14 @@ pub async fn run(_config: &AppConfig, db_pool: &SqlitePool) -> Result<HealthStat
     }
 
     // Check LLM client initialization (without making API calls)
-    let llm_status = check_llm_client();
+    let llm_status = match check_llm_client(config) {
+        Ok(status) => status,
+        Err(e) => {
+            health_status.status = "degraded".to_string();
+            tracing::warn!("LLM client check failed: {}", e);
+            format!("error: {e}")
+        }
+    };
     health_status
         .services
         .insert("llm_client".to_string(), llm_status);
@@ -90,8 +99,12 @@ pub async fn run(_config: &AppConfig, db_pool: &SqlitePool) -> Result<HealthStat
     Ok(health_status)
 }
 
-fn check_llm_client() -> String {
+fn check_llm_client(config: &AppConfig) -> Result<String> {
     // Check if we can create a client instance (doesn't make API calls)
-    anthropic::Client::from_env();
-    "healthy".to_string()
+    LlmClient::from_config(config)?;
+    Ok(format!(
+        "healthy ({}: {})",
+        config.llm_provider.as_str(),
+        config.llm_model_name
+    ))
 }
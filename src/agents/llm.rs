@@ -0,0 +1,468 @@
+use color_eyre::Result;
+use futures::StreamExt;
+use rig::agent::{Agent, AgentBuilder, MultiTurnStreamItem};
+use rig::client::Nothing;
+use rig::completion::{CompletionModel, Prompt};
+use rig::prelude::CompletionClient;
+use rig::providers::{anthropic, ollama, openai};
+use rig::streaming::{StreamedAssistantContent, StreamingPrompt};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::agents::trace::{AgentTrace, TracedModel};
+use crate::config::{AppConfig, LlmProvider};
+use crate::mcp_clients::MCPConnection;
+
+/// Maximum number of tool-calling turns an agent may take for one prompt
+const MAX_TURNS: usize = 3;
+
+/// A completion client for the provider selected in `AppConfig`
+pub enum LlmClient {
+    Anthropic(anthropic::Client),
+    OpenAi(openai::Client),
+    OpenAiCompatible(openai::CompletionsClient),
+    Ollama(ollama::Client),
+}
+
+/// Incremental output of a streamed prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamUpdate {
+    /// A chunk of assistant text
+    Text(String),
+    /// The model is calling a tool
+    ToolCall { name: String },
+}
+
+/// Model settings applied to every agent
+#[derive(Debug, Clone)]
+pub struct LlmSettings {
+    pub model: String,
+    pub max_tokens: u64,
+    pub temperature: Option<f64>,
+}
+
+impl LlmSettings {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            model: config.llm_model_name.clone(),
+            max_tokens: config.llm_max_tokens,
+            temperature: config.llm_temperature,
+        }
+    }
+}
+
+impl LlmClient {
+    /// Build the client for the configured provider
+    ///
+    /// The API key is read from `LLM_API_KEY`, falling back to the provider's
+    /// usual variable (`ANTHROPIC_API_KEY`, `OPENAI_API_KEY`).
+    pub fn from_config(config: &AppConfig) -> Result<Self> {
+        let base_url = config.llm_base_url.as_deref();
+
+        let client = match config.llm_provider {
+            LlmProvider::Anthropic => {
+                let builder = anthropic::Client::builder().api_key(api_key("ANTHROPIC_API_KEY")?);
+                let builder = match base_url {
+                    Some(url) => builder.base_url(url),
+                    None => builder,
+                };
+                LlmClient::Anthropic(builder.build()?)
+            }
+            LlmProvider::OpenAi => {
+                let builder = openai::Client::builder().api_key(api_key("OPENAI_API_KEY")?);
+                let builder = match base_url {
+                    Some(url) => builder.base_url(url),
+                    None => builder,
+                };
+                LlmClient::OpenAi(builder.build()?)
+            }
+            LlmProvider::OpenAiCompatible => {
+                let url = base_url.ok_or_else(|| {
+                    color_eyre::eyre::eyre!("LLM_BASE_URL must be set for openai_compatible")
+                })?;
+                // Local servers usually accept any key
+                let key = api_key("OPENAI_API_KEY").unwrap_or_default();
+                LlmClient::OpenAiCompatible(
+                    openai::CompletionsClient::builder()
+                        .api_key(key)
+                        .base_url(url)
+                        .build()?,
+                )
+            }
+            LlmProvider::Ollama => {
+                let builder = ollama::Client::builder().api_key(Nothing);
+                let builder = match base_url {
+                    Some(url) => builder.base_url(url),
+                    None => builder,
+                };
+                LlmClient::Ollama(builder.build()?)
+            }
+        };
+
+        Ok(client)
+    }
+
+    /// Run a multi-turn prompt with the given preamble and MCP tools
+    ///
+    /// Tool calls and token usage are recorded in `trace`.
+    pub async fn prompt(
+        &self,
+        settings: &LlmSettings,
+        preamble: &str,
+        connections: Vec<MCPConnection>,
+        prompt: &str,
+        trace: &AgentTrace,
+    ) -> Result<String> {
+        self.run(settings, preamble, connections, prompt, trace, None)
+            .await
+    }
+
+    /// Run a multi-turn prompt, forwarding text deltas and tool calls as they arrive
+    ///
+    /// Returns the full assistant text once the model is done.
+    pub async fn stream_prompt(
+        &self,
+        settings: &LlmSettings,
+        preamble: &str,
+        connections: Vec<MCPConnection>,
+        prompt: &str,
+        trace: &AgentTrace,
+        updates: UnboundedSender<StreamUpdate>,
+    ) -> Result<String> {
+        self.run(
+            settings,
+            preamble,
+            connections,
+            prompt,
+            trace,
+            Some(updates),
+        )
+        .await
+    }
+
+    /// Run a prompt on the configured provider, streaming it when `updates` is set
+    async fn run(
+        &self,
+        settings: &LlmSettings,
+        preamble: &str,
+        connections: Vec<MCPConnection>,
+        prompt: &str,
+        trace: &AgentTrace,
+        updates: Option<UnboundedSender<StreamUpdate>>,
+    ) -> Result<String> {
+        trace.register_tools(&connections);
+        trace.start_llm_call(&settings.model);
+        let request = AgentRequest {
+            settings,
+            preamble,
+            connections,
+            prompt,
+            trace,
+            updates,
+        };
+        let result = match self {
+            LlmClient::Anthropic(client) => request.run(client).await,
+            LlmClient::OpenAi(client) => request.run(client).await,
+            LlmClient::OpenAiCompatible(client) => request.run(client).await,
+            LlmClient::Ollama(client) => request.run(client).await,
+        };
+        trace.finish_llm_call();
+        result
+    }
+}
+
+/// Everything needed to run one prompt, whichever provider serves it
+struct AgentRequest<'a> {
+    settings: &'a LlmSettings,
+    preamble: &'a str,
+    connections: Vec<MCPConnection>,
+    prompt: &'a str,
+    trace: &'a AgentTrace,
+    updates: Option<UnboundedSender<StreamUpdate>>,
+}
+
+impl AgentRequest<'_> {
+    async fn run<C>(self, client: &C) -> Result<String>
+    where
+        C: CompletionClient,
+        C::CompletionModel: 'static,
+    {
+        let model = client.completion_model(&self.settings.model);
+        match self.updates {
+            None => {
+                let agent = build_agent(
+                    AgentBuilder::new(model),
+                    self.settings,
+                    self.preamble,
+                    self.connections,
+                );
+                run_agent(agent, self.prompt, self.trace).await
+            }
+            // Streamed runs are recorded through the model, see `TracedModel`
+            Some(updates) => {
+                let agent = build_agent(
+                    AgentBuilder::new(TracedModel::new(model, self.trace)),
+                    self.settings,
+                    self.preamble,
+                    self.connections,
+                );
+                stream_agent(agent, self.prompt, self.trace, updates).await
+            }
+        }
+    }
+}
+
+fn api_key(provider_var: &str) -> Result<String> {
+    std::env::var("LLM_API_KEY")
+        .or_else(|_| std::env::var(provider_var))
+        .map_err(|_| color_eyre::eyre::eyre!("Neither LLM_API_KEY nor {} is set", provider_var))
+}
+
+fn build_agent<M>(
+    builder: AgentBuilder<M>,
+    settings: &LlmSettings,
+    preamble: &str,
+    connections: Vec<MCPConnection>,
+) -> Agent<M>
+where
+    M: CompletionModel,
+{
+    let mut builder = builder.preamble(preamble).max_tokens(settings.max_tokens);
+    if let Some(temperature) = settings.temperature {
+        builder = builder.temperature(temperature);
+    }
+
+    // Handle the case with no MCP connections
+    if connections.is_empty() {
+        return builder.build();
+    }
+
+    // Build agent with MCP tools
+    // We need to handle the type transformation that happens when adding rmcp_tools
+    let mut connections_iter = connections.into_iter();
+
+    // Start with the first connection
+    let first_conn = connections_iter.next().unwrap();
+    let mut agent_builder = builder.rmcp_tools(first_conn.tools, first_conn.peer);
+
+    // Add remaining connections
+    for conn in connections_iter {
+        agent_builder = agent_builder.rmcp_tools(conn.tools, conn.peer);
+    }
+
+    agent_builder.build()
+}
+
+async fn run_agent<M>(agent: Agent<M>, prompt: &str, trace: &AgentTrace) -> Result<String>
+where
+    M: CompletionModel + 'static,
+{
+    Ok(agent
+        .prompt(prompt)
+        .multi_turn(MAX_TURNS)
+        .with_hook(trace.clone())
+        .await?)
+}
+
+async fn stream_agent<M>(
+    agent: Agent<M>,
+    prompt: &str,
+    trace: &AgentTrace,
+    updates: UnboundedSender<StreamUpdate>,
+) -> Result<String>
+where
+    M: CompletionModel + 'static,
+{
+    let mut stream = agent.stream_prompt(prompt).multi_turn(MAX_TURNS).await;
+    let mut streamed = String::new();
+
+    while let Some(item) = stream.next().await {
+        let item = item?;
+        trace.record_stream_item(&item);
+        match item {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::Text(text)) => {
+                streamed.push_str(&text.text);
+                // The receiver going away must not abort the run
+                let _ = updates.send(StreamUpdate::Text(text.text));
+            }
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall(call)) => {
+                // Text before a tool call ("let me look that up") is not part of the answer
+                streamed.clear();
+                let _ = updates.send(StreamUpdate::ToolCall {
+                    name: call.function.name,
+                });
+            }
+            MultiTurnStreamItem::FinalResponse(response) if streamed.is_empty() => {
+                streamed = response.response().to_string();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(streamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::OneOrMany;
+    use rig::completion::{
+        AssistantContent, CompletionError, CompletionRequest, CompletionResponse, GetTokenUsage,
+        Usage,
+    };
+    use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn test_config(provider: LlmProvider, base_url: Option<&str>) -> AppConfig {
+        AppConfig {
+            llm_provider: provider,
+            llm_base_url: base_url.map(str::to_string),
+            llm_max_tokens: 1024,
+            llm_temperature: Some(0.2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let settings = LlmSettings::from_config(&test_config(LlmProvider::Anthropic, None));
+        assert_eq!(settings.model, "test-model");
+        assert_eq!(settings.max_tokens, 1024);
+        assert_eq!(settings.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn test_ollama_client_needs_no_api_key() {
+        let config = test_config(LlmProvider::Ollama, Some("http://127.0.0.1:11434"));
+        let client = LlmClient::from_config(&config).unwrap();
+        assert!(matches!(client, LlmClient::Ollama(_)));
+    }
+
+    #[test]
+    fn test_openai_compatible_requires_base_url() {
+        let config = test_config(LlmProvider::OpenAiCompatible, None);
+        assert!(LlmClient::from_config(&config).is_err());
+
+        let config = test_config(
+            LlmProvider::OpenAiCompatible,
+            Some("http://127.0.0.1:8080/v1"),
+        );
+        let client = LlmClient::from_config(&config).unwrap();
+        assert!(matches!(client, LlmClient::OpenAiCompatible(_)));
+    }
+
+    /// Model that calls a tool on its first turn and answers on the next
+    #[derive(Clone, Default)]
+    struct ScriptedModel {
+        turns: Arc<AtomicUsize>,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct ScriptedStreamResponse;
+
+    impl GetTokenUsage for ScriptedStreamResponse {
+        fn token_usage(&self) -> Option<Usage> {
+            None
+        }
+    }
+
+    impl ScriptedModel {
+        fn next_turn(&self) -> Vec<AssistantContent> {
+            if self.turns.fetch_add(1, Ordering::SeqCst) == 0 {
+                vec![
+                    AssistantContent::text("Let me check."),
+                    AssistantContent::tool_call("call-1", "lookup", serde_json::json!({})),
+                ]
+            } else {
+                vec![AssistantContent::text(
+                    "The prefix is announced by AS65000.",
+                )]
+            }
+        }
+    }
+
+    impl CompletionModel for ScriptedModel {
+        type Response = ();
+        type StreamingResponse = ScriptedStreamResponse;
+        type Client = ();
+
+        fn make(_: &Self::Client, _: impl Into<String>) -> Self {
+            Self::default()
+        }
+
+        async fn completion(
+            &self,
+            _: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::many(self.next_turn()).unwrap(),
+                usage: Usage::new(),
+                raw_response: (),
+            })
+        }
+
+        async fn stream(
+            &self,
+            _: CompletionRequest,
+        ) -> Result<StreamingCompletionResponse<ScriptedStreamResponse>, CompletionError> {
+            let mut chunks: Vec<_> = self
+                .next_turn()
+                .into_iter()
+                .map(|content| match content {
+                    AssistantContent::ToolCall(call) => RawStreamingChoice::ToolCall {
+                        id: call.id,
+                        call_id: None,
+                        name: call.function.name,
+                        arguments: call.function.arguments,
+                    },
+                    AssistantContent::Text(text) => RawStreamingChoice::Message(text.text),
+                    _ => unreachable!(),
+                })
+                .map(Ok)
+                .collect();
+            chunks.push(Ok(RawStreamingChoice::FinalResponse(
+                ScriptedStreamResponse,
+            )));
+            Ok(StreamingCompletionResponse::stream(Box::pin(
+                futures::stream::iter(chunks),
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_answer_matches_prompted_answer() {
+        let prompt_trace = AgentTrace::new();
+        prompt_trace.start_llm_call("test-model");
+        let agent = AgentBuilder::new(ScriptedModel::default()).build();
+        let prompted = run_agent(agent, "Who announces it?", &prompt_trace)
+            .await
+            .unwrap();
+
+        let stream_trace = AgentTrace::new();
+        stream_trace.start_llm_call("test-model");
+        let agent =
+            AgentBuilder::new(TracedModel::new(ScriptedModel::default(), &stream_trace)).build();
+        let (updates, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let streamed = stream_agent(agent, "Who announces it?", &stream_trace, updates)
+            .await
+            .unwrap();
+
+        assert_eq!(prompted, "The prefix is announced by AS65000.");
+        assert_eq!(streamed, prompted);
+        // The text before the tool call is still shown while streaming
+        assert_eq!(
+            received.recv().await,
+            Some(StreamUpdate::Text("Let me check.".to_string()))
+        );
+
+        // Both runs are recorded alike
+        for trace in [prompt_trace, stream_trace] {
+            assert_eq!(trace.llm_calls()[0].turns, 2);
+            let tool_calls = trace.tool_calls();
+            assert_eq!(tool_calls.len(), 1);
+            assert_eq!(tool_calls[0].tool_name, "lookup");
+            assert_eq!(tool_calls[0].arguments, "{}");
+        }
+    }
+}
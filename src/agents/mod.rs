@@ -1,3 +1,6 @@
 pub mod alert_analyzer;
 pub mod chat;
 pub mod health;
+pub mod llm;
+pub mod report;
+pub mod trace;
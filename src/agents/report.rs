@@ -0,0 +1,168 @@
+use color_eyre::Result;
+
+use crate::database::models::IncidentReport;
+
+/// Parse the analysis agent's output into an `IncidentReport`
+///
+/// Tolerates markdown fences, chatter around the JSON object, smart quotes
+/// and trailing commas. Anything else is reported as an error so the caller
+/// can re-prompt the model.
+pub fn parse_report(raw: &str) -> Result<IncidentReport> {
+    let candidate = extract_json_object(raw)
+        .ok_or_else(|| color_eyre::eyre::eyre!("No JSON object found in model output"))?;
+
+    match serde_json::from_str(candidate) {
+        Ok(report) => Ok(report),
+        Err(first_err) => serde_json::from_str(&repair_json(candidate))
+            .map_err(|_| color_eyre::eyre::eyre!("Invalid incident report JSON: {}", first_err)),
+    }
+}
+
+/// Prompt asking the model to fix its previous, unparseable answer
+pub fn repair_prompt(raw: &str, error: &str) -> String {
+    format!(
+        r#"Your previous answer could not be parsed as the required JSON incident report.
+
+Parse error: {error}
+
+Previous answer:
+{raw}
+
+Respond again with ONLY the corrected JSON object, using exactly the fields summary, severity
+(one of Critical, High, Medium, Low, Info), key_facts, immediate_actions, risk_assessment and tool_notes.
+No markdown code blocks, no extra text."#
+    )
+}
+
+/// Strip markdown fences and surrounding text, keeping the outermost `{...}`
+fn extract_json_object(raw: &str) -> Option<&str> {
+    let start = raw.find('{')?;
+    let end = raw.rfind('}')?;
+    (start < end).then(|| &raw[start..=end])
+}
+
+/// Fix the most common ways models break JSON: smart quotes and trailing commas
+///
+/// Smart quotes are only replaced where they delimit a string; inside a string
+/// opened by a plain quote they are part of the text.
+fn repair_json(json: &str) -> String {
+    let is_smart_quote = |c: char| matches!(c, '\u{201c}' | '\u{201d}');
+    let mut repaired = String::with_capacity(json.len());
+    // Delimiter that opened the current string, if inside one
+    let mut in_string: Option<char> = None;
+    let mut escaped = false;
+    let mut chars = json.chars();
+
+    while let Some(c) = chars.next() {
+        if let Some(open) = in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = None,
+                _ if is_smart_quote(c) && is_smart_quote(open) => {
+                    in_string = None;
+                    repaired.push('"');
+                    continue;
+                }
+                _ => {}
+            }
+            repaired.push(c);
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = Some(c);
+                repaired.push(c);
+            }
+            _ if is_smart_quote(c) => {
+                in_string = Some(c);
+                repaired.push('"');
+            }
+            ',' => {
+                // Drop the comma if the next significant character closes a container
+                let rest = chars.clone().find(|n| !n.is_whitespace());
+                if !matches!(rest, Some('}') | Some(']')) {
+                    repaired.push(c);
+                }
+            }
+            _ => repaired.push(c),
+        }
+    }
+
+    repaired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::Severity;
+
+    const REPORT: &str = r#"{
+  "summary": "AS9999 is announcing 10.0.0.0/8.",
+  "severity": "High",
+  "key_facts": {
+    "affected_prefix": "10.0.0.0/8",
+    "expected_asn": "AS65000 (Example)",
+    "observed_asn": "AS9999 (Unknown)",
+    "duration": "5 minutes",
+    "peer_count": 12
+  },
+  "immediate_actions": ["Contact AS9999"],
+  "risk_assessment": "Likely hijack.",
+  "tool_notes": "RIPEstat lookups succeeded"
+}"#;
+
+    #[test]
+    fn test_parse_plain_report() {
+        let report = parse_report(REPORT).unwrap();
+        assert_eq!(report.severity, Severity::High);
+        assert_eq!(report.key_facts.peer_count, Some(12));
+        assert_eq!(report.immediate_actions.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_fenced_report() {
+        let raw = format!("Here is the report:\n```json\n{REPORT}\n```\n");
+        let report = parse_report(&raw).unwrap();
+        assert_eq!(
+            report.key_facts.affected_prefix.as_deref(),
+            Some("10.0.0.0/8")
+        );
+    }
+
+    #[test]
+    fn test_parse_repairs_trailing_commas_and_smart_quotes() {
+        let raw = r#"{
+  "summary": "Prefix seen from AS9999, AS65000 expected",
+  “severity”: "critical",
+  "key_facts": {"peer_count": "7 peers",},
+  "immediate_actions": ["Call upstream",],
+}"#;
+        let report = parse_report(raw).unwrap();
+        assert_eq!(report.severity, Severity::Critical);
+        assert_eq!(report.key_facts.peer_count, Some(7));
+        assert_eq!(report.summary, "Prefix seen from AS9999, AS65000 expected");
+        assert_eq!(report.immediate_actions, vec!["Call upstream"]);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_report() {
+        assert!(parse_report("I could not analyze this alert").is_err());
+        assert!(parse_report(r#"{"summary": "missing severity"}"#).is_err());
+        assert!(parse_report(r#"{"summary": "x", "severity": "Urgent"}"#).is_err());
+    }
+
+    #[test]
+    fn test_repair_keeps_commas_inside_strings() {
+        assert_eq!(repair_json(r#"{"a": "x, }",}"#), r#"{"a": "x, }"}"#);
+    }
+
+    #[test]
+    fn test_repair_keeps_smart_quotes_inside_strings() {
+        assert_eq!(
+            repair_json("{\u{201c}a\u{201d}: \"the \u{201c}core\u{201d} router\",}"),
+            "{\"a\": \"the \u{201c}core\u{201d} router\"}"
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use rig::agent::{CancelSignal, MultiTurnStreamItem, PromptHook};
+use rig::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage,
+};
+use rig::message::{Message, ToolResultContent};
+use rig::streaming::{StreamedAssistantContent, StreamedUserContent, StreamingCompletionResponse};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::database::models::{NewLlmUsage, NewToolCall, get_current_timestamp};
+use crate::mcp_clients::MCPConnection;
+
+/// Prefixes rig puts in front of a tool result when the call failed
+const TOOL_ERROR_PREFIXES: [&str; 3] = ["ToolCallError: ", "ToolNotFoundError: ", "JsonError: "];
+
+/// Record of what an agent did during a run
+///
+/// Clones share the same record, so one handle can be given to the agent as a
+/// hook while the caller keeps another to read the results afterwards.
+#[derive(Clone, Default)]
+pub struct AgentTrace {
+    inner: Arc<Mutex<TraceState>>,
+}
+
+#[derive(Default)]
+struct TraceState {
+    /// Tool name -> MCP server providing it
+    servers: HashMap<String, String>,
+    /// Calls that were started but have not returned yet
+    pending: Vec<PendingCall>,
+    tool_calls: Vec<NewToolCall>,
+    /// One entry per prompt sent through `LlmClient`
+    llm_calls: Vec<NewLlmUsage>,
+    /// Start of the prompt currently running
+    llm_started: Option<Instant>,
+    /// Tool name and arguments of streamed calls awaiting their result, by call ID
+    streamed_calls: HashMap<String, (String, String)>,
+}
+
+struct PendingCall {
+    tool_name: String,
+    arguments: String,
+    started: Instant,
+    created_at: String,
+}
+
+impl AgentTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember which server provides each tool so calls can be attributed
+    pub fn register_tools(&self, connections: &[MCPConnection]) {
+        let mut state = self.lock();
+        for connection in connections {
+            for tool in &connection.tools {
+                state
+                    .servers
+                    .insert(tool.name.to_string(), connection.name.clone());
+            }
+        }
+    }
+
+    /// Tool calls completed so far, in call order
+    pub fn tool_calls(&self) -> Vec<NewToolCall> {
+        self.lock().tool_calls.clone()
+    }
+
+    /// LLM usage recorded so far, one entry per prompt
+    pub fn llm_calls(&self) -> Vec<NewLlmUsage> {
+        self.lock().llm_calls.clone()
+    }
+
+    /// Begin accounting for a prompt sent to `model`
+    pub fn start_llm_call(&self, model: &str) {
+        let mut state = self.lock();
+        state.llm_started = Some(Instant::now());
+        state.llm_calls.push(NewLlmUsage {
+            model: model.to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            turns: 0,
+            duration_ms: 0,
+            created_at: get_current_timestamp(),
+        });
+    }
+
+    /// Stop the clock for the prompt started last, whether or not it succeeded
+    pub fn finish_llm_call(&self) {
+        let mut state = self.lock();
+        let Some(started) = state.llm_started.take() else {
+            return;
+        };
+        if let Some(call) = state.llm_calls.last_mut() {
+            call.duration_ms = started.elapsed().as_millis() as i64;
+        }
+    }
+
+    fn record_turn(&self) {
+        if let Some(call) = self.lock().llm_calls.last_mut() {
+            call.turns += 1;
+        }
+    }
+
+    fn record_usage(&self, usage: Usage) {
+        if let Some(call) = self.lock().llm_calls.last_mut() {
+            call.input_tokens += usage.input_tokens as i64;
+            call.output_tokens += usage.output_tokens as i64;
+        }
+    }
+
+    fn start_tool_call(&self, tool_name: &str, arguments: &str) {
+        self.lock().pending.push(PendingCall {
+            tool_name: tool_name.to_string(),
+            arguments: arguments.to_string(),
+            started: Instant::now(),
+            created_at: get_current_timestamp(),
+        });
+    }
+
+    fn finish_tool_call(&self, tool_name: &str, arguments: &str, output: &str) {
+        let mut state = self.lock();
+        let pending = state
+            .pending
+            .iter()
+            .position(|p| p.tool_name == tool_name && p.arguments == arguments)
+            .map(|index| state.pending.remove(index));
+        let (latency_ms, created_at) = match pending {
+            Some(p) => (p.started.elapsed().as_millis() as i64, p.created_at),
+            None => (0, get_current_timestamp()),
+        };
+
+        let (result, error) = match tool_error(output) {
+            Some(error) => (None, Some(error.to_string())),
+            None => (Some(output.to_string()), None),
+        };
+
+        let server_name = state.servers.get(tool_name).cloned();
+        state.tool_calls.push(NewToolCall {
+            server_name,
+            tool_name: tool_name.to_string(),
+            arguments: arguments.to_string(),
+            result,
+            error,
+            latency_ms,
+            created_at,
+        });
+    }
+
+    /// Record a tool call, its result or the final usage of a streamed run
+    pub fn record_stream_item<R>(&self, item: &MultiTurnStreamItem<R>) {
+        match item {
+            MultiTurnStreamItem::StreamAssistantItem(StreamedAssistantContent::ToolCall(call)) => {
+                let arguments = call.function.arguments.to_string();
+                self.start_tool_call(&call.function.name, &arguments);
+                self.lock()
+                    .streamed_calls
+                    .insert(call.id.clone(), (call.function.name.clone(), arguments));
+            }
+            MultiTurnStreamItem::StreamUserItem(StreamedUserContent::ToolResult(result)) => {
+                let call = self.lock().streamed_calls.remove(&result.id);
+                if let Some((tool_name, arguments)) = call {
+                    let output: Vec<&str> = result
+                        .content
+                        .iter()
+                        .filter_map(|content| match content {
+                            ToolResultContent::Text(text) => Some(text.text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    self.finish_tool_call(&tool_name, &arguments, &output.join("\n"));
+                }
+            }
+            MultiTurnStreamItem::FinalResponse(response) => self.record_usage(response.usage()),
+            _ => {}
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TraceState> {
+        // A panic while holding the lock cannot leave the record inconsistent
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The error message if `output` is a failed tool call rather than a result
+///
+/// rig hands failures to the model as text, e.g. `ToolCallError: ToolCallError: timed out`.
+fn tool_error(output: &str) -> Option<&str> {
+    let mut message = output;
+    let mut failed = false;
+    while let Some(rest) = TOOL_ERROR_PREFIXES
+        .iter()
+        .find_map(|prefix| message.strip_prefix(prefix))
+    {
+        message = rest;
+        failed = true;
+    }
+    failed.then_some(message)
+}
+
+impl<M: CompletionModel> PromptHook<M> for AgentTrace {
+    async fn on_completion_call(
+        &self,
+        _prompt: &Message,
+        _history: &[Message],
+        _cancel_sig: CancelSignal,
+    ) {
+        self.record_turn();
+    }
+
+    async fn on_completion_response(
+        &self,
+        _prompt: &Message,
+        response: &CompletionResponse<M::Response>,
+        _cancel_sig: CancelSignal,
+    ) {
+        self.record_usage(response.usage);
+    }
+
+    async fn on_tool_call(&self, tool_name: &str, args: &str, _cancel_sig: CancelSignal) {
+        self.start_tool_call(tool_name, args);
+    }
+
+    async fn on_tool_result(
+        &self,
+        tool_name: &str,
+        args: &str,
+        result: &str,
+        _cancel_sig: CancelSignal,
+    ) {
+        self.finish_tool_call(tool_name, args, result);
+    }
+}
+
+/// Completion model counting the turns of a streamed run in a trace
+///
+/// rig panics when a streaming hook is set on a prompt without chat history, so
+/// streamed runs are recorded from the model calls and `AgentTrace::record_stream_item`.
+#[derive(Clone)]
+pub struct TracedModel<M> {
+    model: M,
+    trace: AgentTrace,
+}
+
+impl<M> TracedModel<M> {
+    pub fn new(model: M, trace: &AgentTrace) -> Self {
+        Self {
+            model,
+            trace: trace.clone(),
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for TracedModel<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+    type Client = M::Client;
+
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
+        Self::new(M::make(client, model), &AgentTrace::new())
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.trace.record_turn();
+        self.model.completion(request).await
+    }
+
+    async fn stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.trace.record_turn();
+        self.model.stream(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_error_detection() {
+        assert_eq!(tool_error(r#"{"asn": 65000}"#), None);
+        assert_eq!(
+            tool_error("ToolCallError: ToolCallError: timed out"),
+            Some("timed out")
+        );
+        assert_eq!(tool_error("ToolNotFoundError: whois"), Some("whois"));
+    }
+
+    #[test]
+    fn test_tool_calls_are_recorded() {
+        let trace = AgentTrace::new();
+        let handle = trace.clone();
+
+        handle.start_tool_call("as_overview", r#"{"resource":"AS65000"}"#);
+        handle.finish_tool_call(
+            "as_overview",
+            r#"{"resource":"AS65000"}"#,
+            "holder: Example",
+        );
+        handle.start_tool_call("whois", "{}");
+        handle.finish_tool_call("whois", "{}", "ToolCallError: connection refused");
+
+        let calls = trace.tool_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool_name, "as_overview");
+        assert_eq!(calls[0].result.as_deref(), Some("holder: Example"));
+        assert!(calls[0].error.is_none());
+        assert!(calls[0].latency_ms >= 0);
+        assert_eq!(calls[1].error.as_deref(), Some("connection refused"));
+        assert!(calls[1].result.is_none());
+        // No connection registered, so the server is unknown
+        assert!(calls[1].server_name.is_none());
+    }
+
+    #[test]
+    fn test_llm_usage_is_accumulated_per_prompt() {
+        let trace = AgentTrace::new();
+
+        trace.start_llm_call("test-model");
+        trace.record_turn();
+        trace.record_usage(Usage {
+            input_tokens: 1200,
+            output_tokens: 80,
+            total_tokens: 1280,
+        });
+        trace.record_turn();
+        trace.record_usage(Usage {
+            input_tokens: 1500,
+            output_tokens: 300,
+            total_tokens: 1800,
+        });
+        trace.finish_llm_call();
+        trace.start_llm_call("test-model");
+        trace.record_turn();
+        trace.finish_llm_call();
+
+        let calls = trace.llm_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].model, "test-model");
+        assert_eq!(calls[0].turns, 2);
+        assert_eq!(calls[0].input_tokens, 2700);
+        assert_eq!(calls[0].output_tokens, 380);
+        assert_eq!(calls[1].turns, 1);
+        assert_eq!(calls[1].input_tokens, 0);
+    }
+}
@@ -0,0 +1,261 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::agents::alert_analyzer::AlertAnalyzer;
+use crate::agents::trace::AgentTrace;
+use crate::alerts::budget;
+use crate::alerts::http::server::{AppState, SseEvent, SseMessage};
+use crate::alerts::notify;
+use crate::alerts::sources::{self, AlertEnvelope};
+use crate::database::db;
+use crate::database::models::{AnalysisJob, AnalysisStatus, IncidentReport};
+
+/// How often idle workers re-check the queue for retries that have become due
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Upper bound for the delay between two analysis attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// How long a worker holds a running job without renewing it; once it expires, e.g. because
+/// the replica stopped, any replica sharing the database may take the job over
+pub const ANALYSIS_LEASE: Duration = Duration::from_secs(60);
+
+/// Spawn the analysis workers
+///
+/// Jobs interrupted by a shutdown of this or another replica are picked up again once
+/// their lease has expired.
+pub async fn start(state: AppState) -> Result<()> {
+    let workers = state.config.analysis_workers;
+    for worker_id in 0..workers {
+        tokio::spawn(worker_loop(worker_id, state.clone()));
+    }
+    tracing::info!("Started {} analysis worker(s)", workers);
+
+    Ok(())
+}
+
+/// Delay before the next attempt after `attempts` failed attempts (exponential backoff)
+pub fn retry_delay(base_secs: u64, attempts: i64) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::from_secs(base_secs.saturating_mul(1 << exponent)).min(MAX_RETRY_DELAY)
+}
+
+async fn worker_loop(worker_id: usize, state: AppState) {
+    loop {
+        match db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE).await {
+            Ok(Some(job)) => {
+                tracing::info!(
+                    "Worker {} picked up analysis job {} for alert {} (attempt {}/{})",
+                    worker_id,
+                    job.id,
+                    job.alert_id,
+                    job.attempts,
+                    job.max_attempts
+                );
+                let (lost, lease_lost) = watch::channel(false);
+                let heartbeat = tokio::spawn(renew_lease(state.clone(), job.id, lost));
+                process_job(&state, &job, lease_lost).await;
+                heartbeat.abort();
+            }
+            Ok(None) => {
+                // Sleep until new work is queued or a retry may have become due
+                tokio::select! {
+                    _ = state.analysis_notify.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+            Err(e) => {
+                tracing::error!("Worker {} failed to claim analysis job: {}", worker_id, e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Keep renewing the lease of a running job until the worker is done with it, and flag
+/// `lost` once another replica took the job over
+async fn renew_lease(state: AppState, job_id: i64, lost: watch::Sender<bool>) {
+    loop {
+        tokio::time::sleep(ANALYSIS_LEASE / 3).await;
+        match db::renew_analysis_lease(&state.db_pool, job_id, &state.replica_id, ANALYSIS_LEASE)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!(
+                    "Analysis job {} was taken over by another replica after its lease expired",
+                    job_id
+                );
+                let _ = lost.send(true);
+                return;
+            }
+            Err(e) => tracing::error!(
+                "Failed to renew the lease of analysis job {}: {}",
+                job_id,
+                e
+            ),
+        }
+    }
+}
+
+/// Log that the result of a job is dropped because another replica took it over
+fn log_taken_over(job: &AnalysisJob) {
+    tracing::warn!(
+        "Dropping the result of analysis job {} for alert {}: another replica took it over",
+        job.id,
+        job.alert_id
+    );
+}
+
+async fn process_job(state: &AppState, job: &AnalysisJob, mut lease_lost: watch::Receiver<bool>) {
+    let group = db::get_alert_group(&state.db_pool, job.alert_id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to get the group of alert {}: {}", job.alert_id, e);
+            None
+        });
+    let broadcast = |status| broadcast_status(state, job.alert_id, status, group.clone());
+
+    // Jobs queued before the budget ran out are dropped rather than analyzed
+    if let Some(budget) = budget::check_and_warn(state).await
+        && budget.exceeded()
+    {
+        tracing::warn!(
+            "Skipping analysis of alert {}: {}",
+            job.alert_id,
+            budget.message()
+        );
+        match db::skip_analysis_job(&state.db_pool, job, &budget.message()).await {
+            Ok(Some(status)) => broadcast(status),
+            Ok(None) => log_taken_over(job),
+            Err(e) => tracing::error!("Failed to skip analysis job {}: {}", job.id, e),
+        }
+        return;
+    }
+
+    if let Err(e) =
+        db::set_alert_analysis_status(&state.db_pool, job.alert_id, AnalysisStatus::Analyzing).await
+    {
+        tracing::error!("Failed to mark alert {} as analyzing: {}", job.alert_id, e);
+    }
+    broadcast(AnalysisStatus::Analyzing);
+
+    // The replica that took the job over runs it again, so this run stops right away
+    let result = tokio::select! {
+        result = analyze_and_store(state, job) => result,
+        Ok(_) = lease_lost.wait_for(|lost| *lost) => Ok(None),
+    };
+    match result {
+        Ok(Some((alert, report))) => {
+            tracing::info!("Analysis for alert {} completed", job.alert_id);
+            broadcast(AnalysisStatus::Done);
+            notify::dispatch(state, job.alert_id, &alert, &report).await;
+        }
+        Ok(None) => log_taken_over(job),
+        Err(e) => {
+            tracing::error!(
+                "Analysis attempt {}/{} for alert {} failed: {}",
+                job.attempts,
+                job.max_attempts,
+                job.alert_id,
+                e
+            );
+            let delay = retry_delay(state.config.analysis_retry_base_secs, job.attempts);
+            match db::fail_analysis_job(&state.db_pool, job, &e.to_string(), delay).await {
+                Ok(None) => log_taken_over(job),
+                Ok(Some(status)) => {
+                    broadcast(status);
+                    if status == AnalysisStatus::Failed {
+                        let event = SseEvent::Error {
+                            message: format!("Alert Analysis Agent error: {e}"),
+                        };
+                        let _ = state.tx.send(SseMessage::from(&event));
+                    }
+                }
+                Err(db_err) => {
+                    tracing::error!(
+                        "Failed to record analysis failure for alert {}: {}",
+                        job.alert_id,
+                        db_err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Run the analysis and store its report; a report that cannot be stored fails the attempt
+/// Returns None when another replica took the job over, so the report was not stored.
+async fn analyze_and_store(
+    state: &AppState,
+    job: &AnalysisJob,
+) -> Result<Option<(AlertEnvelope, IncidentReport)>> {
+    let (alert, report) = run_analysis(state, job.alert_id).await?;
+    let stored = db::complete_analysis_job(&state.db_pool, job, &report)
+        .await
+        .map_err(|e| eyre!("Failed to store the analysis: {e}"))?;
+    Ok(stored.then_some((alert, report)))
+}
+
+async fn run_analysis(state: &AppState, alert_id: i64) -> Result<(AlertEnvelope, IncidentReport)> {
+    let (kind, alert_data, _) = db::get_alert_for_chat(&state.db_pool, alert_id)
+        .await?
+        .ok_or_else(|| eyre!("Alert {} no longer exists", alert_id))?;
+    let alert = sources::normalize(kind, &alert_data)?;
+
+    let trace = AgentTrace::new();
+    let profile = state.groups.profile_instructions(alert.group());
+    let result =
+        AlertAnalyzer::run(&alert, &state.config, profile, &state.mcp_manager, &trace).await;
+
+    // Keep the tool call evidence and token usage even when the attempt failed
+    if let Err(e) = db::insert_tool_calls(&state.db_pool, alert_id, None, &trace.tool_calls()).await
+    {
+        tracing::error!("Failed to store tool calls for alert {}: {}", alert_id, e);
+    }
+    if let Err(e) = db::insert_llm_usage(&state.db_pool, alert_id, None, &trace.llm_calls()).await {
+        tracing::error!("Failed to store LLM usage for alert {}: {}", alert_id, e);
+    }
+    budget::check_and_warn(state).await;
+
+    result.map(|report| (alert, report))
+}
+
+fn broadcast_status(
+    state: &AppState,
+    alert_id: i64,
+    status: AnalysisStatus,
+    group: Option<String>,
+) {
+    let event = SseEvent::AnalysisStatus {
+        alert_id,
+        status,
+        group,
+    };
+    let _ = state.tx.send(SseMessage::from(&event));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles_per_attempt() {
+        assert_eq!(retry_delay(30, 1), Duration::from_secs(30));
+        assert_eq!(retry_delay(30, 2), Duration::from_secs(60));
+        assert_eq!(retry_delay(30, 3), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        assert_eq!(retry_delay(30, 10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u64::MAX, 40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_retry_delay_zero_attempts() {
+        assert_eq!(retry_delay(30, 0), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+use crate::alerts::http::server::{AppState, SseEvent, SseMessage};
+use crate::config::AppConfig;
+use crate::database::DbPool;
+use crate::database::db;
+
+/// Share of a budget at which the UI is warned, in percent
+const WARNING_THRESHOLDS: [u8; 2] = [80, 100];
+
+/// Window an LLM budget applies to (UTC calendar hour or day)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Hour,
+    Day,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Hour => "hourly",
+            BudgetPeriod::Day => "daily",
+        }
+    }
+
+    /// Start of the window containing `now`, as a prefix of RFC 3339 timestamps
+    fn window_start(&self, now: DateTime<Utc>) -> String {
+        match self {
+            BudgetPeriod::Hour => now.format("%Y-%m-%dT%H:00:00").to_string(),
+            BudgetPeriod::Day => now.format("%Y-%m-%dT00:00:00").to_string(),
+        }
+    }
+
+    fn limits(&self, config: &AppConfig) -> (Option<u64>, Option<f64>) {
+        match self {
+            BudgetPeriod::Hour => (config.budget_hourly_tokens, config.budget_hourly_cost_usd),
+            BudgetPeriod::Day => (config.budget_daily_tokens, config.budget_daily_cost_usd),
+        }
+    }
+}
+
+/// The most consumed of the configured budgets
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetStatus {
+    pub period: BudgetPeriod,
+    /// Share of the budget used so far (1.0 = exhausted)
+    pub used: f64,
+    /// Start of the current window
+    window: String,
+}
+
+impl BudgetStatus {
+    pub fn exceeded(&self) -> bool {
+        self.used >= 1.0
+    }
+
+    pub fn message(&self) -> String {
+        if self.exceeded() {
+            format!(
+                "The {} LLM budget is exhausted; analysis and chat are paused until the budget resets",
+                self.period.as_str()
+            )
+        } else {
+            format!(
+                "{:.0}% of the {} LLM budget has been used",
+                self.used * 100.0,
+                self.period.as_str()
+            )
+        }
+    }
+}
+
+/// Share of the token and cost limits used, whichever is higher
+fn budget_used(
+    tokens: i64,
+    cost_usd: f64,
+    token_limit: Option<u64>,
+    cost_limit: Option<f64>,
+) -> Option<f64> {
+    let by_tokens = token_limit.map(|limit| tokens as f64 / limit as f64);
+    let by_cost = cost_limit.map(|limit| cost_usd / limit);
+    match (by_tokens, by_cost) {
+        (Some(t), Some(c)) => Some(t.max(c)),
+        (t, c) => t.or(c),
+    }
+}
+
+/// Find the most consumed budget, or `None` when no budget is configured
+pub async fn check(pool: &DbPool, config: &AppConfig) -> Result<Option<BudgetStatus>> {
+    let now = Utc::now();
+    let mut most_used: Option<BudgetStatus> = None;
+
+    for period in [BudgetPeriod::Hour, BudgetPeriod::Day] {
+        let (token_limit, cost_limit) = period.limits(config);
+        if token_limit.is_none() && cost_limit.is_none() {
+            continue;
+        }
+
+        let window = period.window_start(now);
+        let mut tokens = 0;
+        let mut cost_usd = 0.0;
+        for (model, input, output) in db::llm_usage_by_model_since(pool, &window).await? {
+            tokens += input + output;
+            if let Some(pricing) = config.llm_pricing.get(&model) {
+                cost_usd += pricing.cost(input, output);
+            }
+        }
+
+        let Some(used) = budget_used(tokens, cost_usd, token_limit, cost_limit) else {
+            continue;
+        };
+        if most_used.as_ref().is_none_or(|m| used > m.used) {
+            most_used = Some(BudgetStatus {
+                period,
+                used,
+                window,
+            });
+        }
+    }
+
+    Ok(most_used)
+}
+
+/// Remembers which warnings were sent so each threshold is announced once per window
+#[derive(Default)]
+pub struct BudgetWarnings {
+    /// Period -> (window start, highest threshold announced)
+    sent: Mutex<HashMap<BudgetPeriod, (String, u8)>>,
+}
+
+impl BudgetWarnings {
+    /// The threshold to announce for `status`, if it was not announced yet in this window
+    fn next_warning(&self, status: &BudgetStatus) -> Option<u8> {
+        let threshold = WARNING_THRESHOLDS
+            .iter()
+            .rev()
+            .copied()
+            .find(|t| status.used * 100.0 >= f64::from(*t))?;
+
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = sent
+            .get(&status.period)
+            .filter(|(window, _)| *window == status.window)
+            .map(|(_, t)| *t);
+        if previous.is_some_and(|p| p >= threshold) {
+            return None;
+        }
+        sent.insert(status.period, (status.window.clone(), threshold));
+        Some(threshold)
+    }
+}
+
+/// Check the budgets and warn connected clients when a threshold was crossed
+///
+/// Errors are logged and treated as "no budget", so accounting problems never block alerts.
+pub async fn check_and_warn(state: &AppState) -> Option<BudgetStatus> {
+    let status = match check(&state.db_pool, &state.config).await {
+        Ok(status) => status?,
+        Err(e) => {
+            tracing::error!("Failed to check LLM budget: {}", e);
+            return None;
+        }
+    };
+
+    if let Some(percent) = state.budget_warnings.next_warning(&status) {
+        tracing::warn!("{}", status.message());
+        let event = SseEvent::BudgetWarning {
+            period: status.period,
+            percent,
+            message: status.message(),
+        };
+        let _ = state.tx.send(SseMessage::from(&event));
+    }
+
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(period: BudgetPeriod, used: f64, window: &str) -> BudgetStatus {
+        BudgetStatus {
+            period,
+            used,
+            window: window.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_budget_used_takes_highest_limit() {
+        assert_eq!(budget_used(500, 1.0, Some(1000), None), Some(0.5));
+        assert_eq!(budget_used(500, 1.0, None, Some(4.0)), Some(0.25));
+        assert_eq!(budget_used(500, 3.0, Some(1000), Some(4.0)), Some(0.75));
+        assert_eq!(budget_used(500, 3.0, None, None), None);
+    }
+
+    #[test]
+    fn test_window_start() {
+        let now = DateTime::parse_from_rfc3339("2025-01-15T10:42:07+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(BudgetPeriod::Hour.window_start(now), "2025-01-15T10:00:00");
+        assert_eq!(BudgetPeriod::Day.window_start(now), "2025-01-15T00:00:00");
+        // Stored timestamps compare correctly against the window start
+        assert!("2025-01-15T10:00:00.123+00:00" >= "2025-01-15T10:00:00");
+        assert!("2025-01-15T09:59:59+00:00" < "2025-01-15T10:00:00");
+    }
+
+    #[test]
+    fn test_each_warning_is_sent_once_per_window() {
+        let warnings = BudgetWarnings::default();
+        let hour = "2025-01-15T10:00:00";
+
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 0.5, hour)),
+            None
+        );
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 0.85, hour)),
+            Some(80)
+        );
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 0.9, hour)),
+            None
+        );
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 1.2, hour)),
+            Some(100)
+        );
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 1.3, hour)),
+            None
+        );
+        // A new window starts over
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Hour, 0.8, "2025-01-15T11:00:00")),
+            Some(80)
+        );
+        // Periods are tracked separately
+        assert_eq!(
+            warnings.next_warning(&status(BudgetPeriod::Day, 1.0, "2025-01-15T00:00:00")),
+            Some(100)
+        );
+    }
+}
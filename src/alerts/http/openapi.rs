@@ -1,14 +1,27 @@
 use utoipa::OpenApi;
 
 use crate::agents::health::HealthStatus;
+use crate::alerts::budget::BudgetPeriod;
 use crate::alerts::http::routes::alerts::ChatRequest;
+use crate::alerts::http::routes::lifecycle::{UpdateAlertAssignee, UpdateAlertStatus};
 use crate::alerts::http::routes::mcp::{
     EnableNativeRequest, ListMcpServersQuery, TestConnectionResponse,
 };
-use crate::alerts::http::server::{BGPAlerterAlert, Details, SseEvent};
+use crate::alerts::http::routes::prefixes::{MonitoredAsn, MonitoredPrefix};
+use crate::alerts::http::routes::rpki::ValidateQuery;
+use crate::alerts::http::routes::usage::UsageQuery;
+use crate::alerts::http::server::{BGPAlerterAlert, ChatStreamEvent, Details, SseEvent};
+use crate::alerts::retention::{RetentionPolicy, StorageReport};
+use crate::config::{AsnInfo, MatchReason, PrefixInfo, RelevanceMatch};
 use crate::database::models::{
-    Alert, AlertKind, ChatMessage, CreateMcpServer, McpServer, McpServerDetails, UpdateMcpServer,
+    Alert, AlertDetail, AlertKind, AlertLifecycle, AlertPage, AlertStatus, AlertStatusChange,
+    AnalysisStatus, ChatMessage, CreateMcpServer, DeliveryStatus, Incident, IncidentReport,
+    KeyFacts, McpServer, McpServerDetails, NotificationDelivery, PrefixMatch, RetentionRun,
+    Severity, TableUsage, ToolCall, UpdateMcpServer, UsageReport, UsageSummary,
 };
+use crate::groups::{GroupConfig, GroupRegistry, PromptProfile};
+use crate::rpki::coverage::{CoverageReport, CoverageState, PrefixCoverage};
+use crate::rpki::{RpkiState, RpkiValidation};
 
 #[derive(OpenApi)]
 #[openapi(
@@ -20,6 +33,12 @@ use crate::database::models::{
         crate::alerts::http::routes::alerts::process_alert,
         crate::alerts::http::routes::alerts::delete_alert,
         crate::alerts::http::routes::alerts::chat_with_alert,
+        crate::alerts::http::routes::alerts::chat_with_alert_stream,
+        crate::alerts::http::routes::alerts::list_alert_tool_calls,
+        crate::alerts::http::routes::alerts::list_alert_notifications,
+        crate::alerts::http::routes::lifecycle::update_alert_status,
+        crate::alerts::http::routes::lifecycle::update_alert_assignee,
+        crate::alerts::http::routes::lifecycle::list_alert_history,
         crate::alerts::http::routes::mcp::list_mcp_servers,
         crate::alerts::http::routes::mcp::get_mcp_server,
         crate::alerts::http::routes::mcp::create_mcp_server,
@@ -27,15 +46,45 @@ use crate::database::models::{
         crate::alerts::http::routes::mcp::delete_mcp_server,
         crate::alerts::http::routes::mcp::test_mcp_server,
         crate::alerts::http::routes::mcp::enable_native_mcp_servers,
+        crate::alerts::http::routes::usage::get_usage,
+        crate::alerts::http::routes::storage::get_storage,
+        crate::alerts::http::routes::rpki::validate,
+        crate::alerts::http::routes::rpki::get_coverage,
+        crate::alerts::http::routes::prefixes::list_prefixes,
+        crate::alerts::http::routes::prefixes::get_prefix,
+        crate::alerts::http::routes::prefixes::create_prefix,
+        crate::alerts::http::routes::prefixes::update_prefix,
+        crate::alerts::http::routes::prefixes::delete_prefix,
+        crate::alerts::http::routes::prefixes::list_asns,
+        crate::alerts::http::routes::prefixes::create_asn,
+        crate::alerts::http::routes::prefixes::update_asn,
+        crate::alerts::http::routes::prefixes::delete_asn,
+        crate::alerts::http::routes::groups::list_groups,
     ),
     components(schemas(
         HealthStatus,
         BGPAlerterAlert,
         Details,
         Alert,
+        AlertDetail,
+        AlertPage,
+        PrefixMatch,
         AlertKind,
+        AnalysisStatus,
+        AlertStatus,
+        AlertLifecycle,
+        AlertStatusChange,
+        UpdateAlertStatus,
+        UpdateAlertAssignee,
+        Incident,
+        IncidentReport,
+        KeyFacts,
+        Severity,
         ChatMessage,
         ChatRequest,
+        ToolCall,
+        DeliveryStatus,
+        NotificationDelivery,
         McpServer,
         McpServerDetails,
         CreateMcpServer,
@@ -43,12 +92,41 @@ use crate::database::models::{
         ListMcpServersQuery,
         TestConnectionResponse,
         EnableNativeRequest,
+        UsageQuery,
+        UsageReport,
+        UsageSummary,
+        StorageReport,
+        TableUsage,
+        RetentionPolicy,
+        RetentionRun,
+        ValidateQuery,
+        RpkiState,
+        RpkiValidation,
+        CoverageState,
+        PrefixCoverage,
+        CoverageReport,
+        PrefixInfo,
+        AsnInfo,
+        MatchReason,
+        RelevanceMatch,
+        MonitoredPrefix,
+        MonitoredAsn,
+        GroupRegistry,
+        GroupConfig,
+        PromptProfile,
         SseEvent,
+        BudgetPeriod,
+        ChatStreamEvent,
     )),
     tags(
         (name = "health", description = "Health check endpoints"),
         (name = "alerts", description = "Alert management endpoints"),
         (name = "mcp", description = "MCP server management endpoints"),
+        (name = "usage", description = "LLM usage and cost accounting"),
+        (name = "storage", description = "Database usage and retention of alerts and chat messages"),
+        (name = "rpki", description = "RPKI origin validation and ROA coverage against the local VRP set"),
+        (name = "prefixes", description = "Monitored prefixes and ASNs in prefixes.yml"),
+        (name = "groups", description = "Routing of alerts to teams by prefixes.yml group"),
         (name = "streaming", description = "Server-sent events streaming"),
     ),
     info(
@@ -1,20 +1,140 @@
-use crate::agents::{alert_analyzer, chat};
+use crate::agents::chat;
+use crate::agents::llm::StreamUpdate;
+use crate::agents::trace::AgentTrace;
+use crate::alerts::budget;
+use crate::alerts::incidents::IncidentOutcome;
+use crate::alerts::ingest::{self, Ingested};
+use crate::alerts::sources::{self, AlertEnvelope};
+use crate::config::parse_asn;
 use crate::database::db;
+use crate::database::models::{
+    AlertCursor, AlertDetail, AlertFilter, AlertKind, AlertPage, AlertStatus, AnalysisStatus,
+    ChatMessage, NotificationDelivery, PrefixMatch, Severity, ToolCall,
+};
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::sse::{Event, Sse},
 };
+use chrono::{DateTime, Utc};
+use futures::stream::Stream;
+use ipnet::IpNet;
 use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt as _;
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use utoipa::{IntoParams, ToSchema};
 
-use crate::alerts::http::server::{AppState, BGPAlerterAlert, SseEvent};
+use crate::alerts::http::server::{
+    AppState, BGPAlerterAlert, ChatStreamEvent, SseEvent, SseMessage,
+};
 
 #[derive(Deserialize, ToSchema)]
 pub struct ChatRequest {
     pub message: String,
 }
 
+/// Page size of `GET /api/alerts` when the request does not set one
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Largest page size accepted by `GET /api/alerts`
+const MAX_PAGE_SIZE: u32 = 500;
+
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAlertsQuery {
+    /// Only alerts routed to these groups (comma-separated)
+    pub group: Option<String>,
+    /// Only alerts of these sources (comma-separated), e.g. `bgp_alerter,syslog`
+    pub kind: Option<String>,
+    /// Only alerts analyzed at these severities (comma-separated)
+    pub severity: Option<String>,
+    /// Only alerts in these lifecycle statuses (comma-separated)
+    pub status: Option<String>,
+    /// Only alerts created at or after this RFC 3339 timestamp
+    pub since: Option<String>,
+    /// Only alerts created before this RFC 3339 timestamp
+    pub until: Option<String>,
+    /// Only alerts whose prefix relates to this prefix or address
+    pub prefix: Option<String>,
+    /// How the alert's prefix must relate to `prefix`
+    pub prefix_match: Option<PrefixMatch>,
+    /// Only alerts whose expected or observed origin is this ASN, with or without `AS`
+    pub asn: Option<String>,
+    /// Full-text search over the analysis and the chat messages
+    pub q: Option<String>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Page size, 50 by default and at most 500
+    pub limit: Option<u32>,
+}
+
+impl ListAlertsQuery {
+    /// Validate the query into a listing filter
+    fn filter(&self) -> Result<AlertFilter, String> {
+        Ok(AlertFilter {
+            kinds: list(&self.kind, |kind| AlertKind::try_from(kind))?,
+            severities: list(&self.severity, |severity| Severity::try_from(severity))?,
+            statuses: list(&self.status, |status| AlertStatus::try_from(status))?,
+            groups: list(&self.group, |group| Ok(group.to_string()))?,
+            since: self.since.as_deref().map(timestamp).transpose()?,
+            until: self.until.as_deref().map(timestamp).transpose()?,
+            prefix: self
+                .prefix
+                .as_deref()
+                .map(|prefix| {
+                    let prefix = prefix.trim();
+                    let net = prefix
+                        .parse::<IpNet>()
+                        .or_else(|_| prefix.parse::<IpAddr>().map(IpNet::from))
+                        .map_err(|_| format!("Invalid prefix: {}", prefix))?;
+                    Ok::<_, String>((net.trunc(), self.prefix_match.unwrap_or_default()))
+                })
+                .transpose()?,
+            asn: self
+                .asn
+                .as_deref()
+                .map(|asn| parse_asn(asn).map_err(|_| format!("Invalid ASN: {}", asn)))
+                .transpose()?,
+            search: self.q.clone().filter(|q| !q.trim().is_empty()),
+            cursor: self
+                .cursor
+                .as_deref()
+                .map(AlertCursor::decode)
+                .transpose()?,
+            limit: Some(
+                self.limit
+                    .unwrap_or(DEFAULT_PAGE_SIZE)
+                    .clamp(1, MAX_PAGE_SIZE),
+            ),
+        })
+    }
+}
+
+/// Parse the non-empty entries of a comma-separated query parameter
+fn list<T>(
+    value: &Option<String>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Vec<T>, String> {
+    value
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(parse)
+        .collect()
+}
+
+/// Normalize an RFC 3339 timestamp to UTC, the form alerts are stored with
+fn timestamp(value: &str) -> Result<String, String> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|t| t.with_timezone(&Utc).to_rfc3339())
+        .map_err(|_| format!("Invalid timestamp: {}", value))
+}
+
 #[derive(IntoParams)]
 pub struct AlertId {
     /// Alert ID
@@ -22,13 +142,14 @@ pub struct AlertId {
     pub id: i64,
 }
 
-/// Process a new BGP alert
+/// Accept a BGP alert, folding repeats into open incidents, and queue analysis when needed
 #[utoipa::path(
     post,
     path = "/api/alerts",
     request_body = BGPAlerterAlert,
     responses(
-        (status = 200, description = "Alert processed successfully", body = serde_json::Value),
+        (status = 202, description = "Alert stored and queued for analysis", body = serde_json::Value),
+        (status = 200, description = "Alert folded into an open incident without new analysis, or ignored because it is not relevant to monitored resources", body = serde_json::Value),
         (status = 500, description = "Internal server error")
     ),
     tag = "alerts"
@@ -36,7 +157,7 @@ pub struct AlertId {
 pub async fn process_alert(
     State(state): State<AppState>,
     Json(payload): Json<BGPAlerterAlert>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
     tracing::info!(
         "Received alert: prefix={}, asn={}, neworigin={:?}",
         payload.details.prefix,
@@ -44,8 +165,15 @@ pub async fn process_alert(
         payload.details.neworigin
     );
 
+    let source = sources::source_for(AlertKind::BgpAlerter);
+    let mut alert = payload.envelope(source.kind()).map_err(|e| {
+        tracing::error!("Failed to serialize alert: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
     // Check if alert is relevant to our monitored resources
-    if !state.prefixes_config.is_alert_relevant(&payload) {
+    alert.relevance = source.relevance(&alert, &state.prefixes.get());
+    let Some(relevance) = alert.relevance.clone() else {
         tracing::warn!(
             "Alert for prefix {} (ASN: {}) is not relevant to monitored resources, skipping. \
             Check prefixes.yml to ensure this prefix or ASN is monitored.",
@@ -59,97 +187,100 @@ pub async fn process_alert(
                 payload.details.prefix, payload.details.asn
             ),
         };
-        let _ = state
-            .tx
-            .send(serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string()));
-        return Ok(Json(serde_json::json!({
-            "error": format!(
-                "Alert ignored: prefix {} (ASN: {}) not in monitored resources. \
-                Add it to prefixes.yml to process alerts for this prefix/ASN.",
-                payload.details.prefix,
-                payload.details.asn
-            ),
-            "ignored": true
-        })));
-    }
+        let _ = state.tx.send(SseMessage::from(&event));
+        return Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "error": format!(
+                    "Alert ignored: prefix {} (ASN: {}) not in monitored resources. \
+                    Add it to prefixes.yml to process alerts for this prefix/ASN.",
+                    payload.details.prefix,
+                    payload.details.asn
+                ),
+                "ignored": true
+            })),
+        ));
+    };
 
     tracing::info!(
-        "Processing alert for prefix {} (ASN: {})",
+        "Queueing alert for prefix {} (ASN: {})",
         payload.details.prefix,
         payload.details.asn
     );
 
-    match alert_analyzer::AlertAnalyzer::run(payload.clone(), &state.config, &state.db_pool).await {
-        Ok(result) => {
-            // Save alert and initial response to database
-            let alert_data_json = serde_json::to_string(&payload).map_err(|e| {
-                tracing::error!("Failed to serialize alert: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-            let timestamp = crate::database::models::get_current_timestamp();
-
-            let alert_id = sqlx::query_scalar::<_, i64>(
-                r#"
-                INSERT INTO alerts (alert_data, initial_response, kind, created_at, updated_at)
-                VALUES (?, ?, ?, ?, ?)
-                RETURNING id
-                "#,
-            )
-            .bind(&alert_data_json)
-            .bind(&result)
-            .bind(crate::database::models::AlertKind::BgpAlerter.as_str())
-            .bind(&timestamp)
-            .bind(&timestamp)
-            .fetch_one(&*state.db_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Database error: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
-
-            // Broadcast SSE notification
-            let event = SseEvent::NewAlert { alert_id };
-            let event_json = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
-            let _ = state.tx.send(event_json);
-
-            Ok(Json(serde_json::json!({
-                "alert_id": alert_id,
-                "response": result
-            })))
-        }
-        Err(e) => {
-            let event = SseEvent::Error {
-                message: format!("Alert Analysis Agent error: {e}"),
-            };
-            let _ = state
-                .tx
-                .send(serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string()));
-            tracing::error!("Alert processing failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let Ingested {
+        outcome,
+        budget_exceeded,
+    } = ingest::ingest(&state, &alert).await.map_err(|e| {
+        tracing::error!("Failed to store alert: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let incident = outcome.incident();
+    let alert_id = incident.alert_id;
+
+    let deduplicated = matches!(outcome, IncidentOutcome::Repeat { .. });
+    let analysis_status = if outcome.analysis_queued() {
+        Some(AnalysisStatus::Pending)
+    } else if !deduplicated && budget_exceeded {
+        Some(AnalysisStatus::SkippedBudget)
+    } else {
+        None
+    };
+    let status = if outcome.analysis_queued() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+
+    Ok((
+        status,
+        Json(serde_json::json!({
+            "alert_id": alert_id,
+            "incident_id": incident.id,
+            "occurrence_count": incident.occurrence_count,
+            "deduplicated": deduplicated,
+            "analysis_queued": outcome.analysis_queued(),
+            "analysis_status": analysis_status,
+            "budget_exceeded": budget_exceeded,
+            "relevance": relevance
+        })),
+    ))
 }
 
-/// List all alerts
+/// List alerts, newest first, a page at a time
 #[utoipa::path(
     get,
     path = "/api/alerts",
+    params(ListAlertsQuery),
     responses(
-        (status = 200, description = "List of all alerts", body = Vec<serde_json::Value>),
+        (status = 200, description = "Page of the alerts matching the filters", body = AlertPage),
+        (status = 400, description = "Invalid filter or cursor", body = serde_json::Value),
         (status = 500, description = "Internal server error")
     ),
     tag = "alerts"
 )]
 pub async fn list_alerts(
     State(state): State<AppState>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    let alerts = db::list_alerts(&state.db_pool).await.map_err(|e| {
-        tracing::error!("Database error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+    Query(query): Query<ListAlertsQuery>,
+) -> Result<Json<AlertPage>, (StatusCode, Json<serde_json::Value>)> {
+    let filter = query.filter().map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        )
     })?;
+    let page = db::list_alerts(&state.db_pool, &filter)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Database error" })),
+            )
+        })?;
 
-    Ok(Json(alerts))
+    Ok(Json(page))
 }
 
 /// Get a specific alert by ID
@@ -158,7 +289,7 @@ pub async fn list_alerts(
     path = "/api/alerts/{id}",
     params(AlertId),
     responses(
-        (status = 200, description = "Alert found", body = serde_json::Value),
+        (status = 200, description = "Alert found", body = AlertDetail),
         (status = 404, description = "Alert not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -167,7 +298,7 @@ pub async fn list_alerts(
 pub async fn get_alert(
     State(state): State<AppState>,
     Path(id): Path<i64>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<AlertDetail>, StatusCode> {
     let alert = db::get_alert_by_id(&state.db_pool, id).await.map_err(|e| {
         tracing::error!("Database error: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
@@ -188,6 +319,7 @@ pub async fn get_alert(
     responses(
         (status = 200, description = "Chat response", body = serde_json::Value),
         (status = 404, description = "Alert not found"),
+        (status = 429, description = "LLM budget exhausted", body = serde_json::Value),
         (status = 500, description = "Internal server error")
     ),
     tag = "alerts"
@@ -196,75 +328,299 @@ pub async fn chat_with_alert(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(payload): Json<ChatRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Get alert data and initial response
-    let (alert_data, initial_response) = db::get_alert_for_chat(&state.db_pool, id)
-        .await
-        .map_err(|e| {
-            tracing::error!("Database error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    let alert: BGPAlerterAlert = serde_json::from_str(&alert_data).map_err(|e| {
-        tracing::error!("Failed to parse alert data: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    // Get chat history
-    let chat_history = db::get_chat_history(&state.db_pool, id)
-        .await
-        .map_err(|e| {
-            tracing::error!("Database error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    // Save user message
-    db::insert_chat_message(&state.db_pool, id, "user", &payload.message)
-        .await
-        .map_err(|e| {
-            tracing::error!("Database error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+) -> Result<Json<serde_json::Value>, ChatError> {
+    let (alert, initial_response, chat_history) = start_chat(&state, id, &payload.message).await?;
 
     // Run chat agent
+    let trace = AgentTrace::new();
     let assistant_response = match chat::Chat::run(
         alert,
         &initial_response,
         &chat_history,
         &payload.message,
         &state.config,
-        &state.db_pool,
+        &state.mcp_manager,
+        &trace,
     )
     .await
     {
         Ok(response) => response,
         Err(e) => {
             tracing::error!("Chat agent error: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            save_failed_chat_usage(&state, id, &trace).await;
+            return Err(chat_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Chat agent failed",
+            ));
         }
     };
 
-    // Save assistant response
-    let message_id = db::insert_chat_message(&state.db_pool, id, "assistant", &assistant_response)
+    let message_id = save_assistant_message(&state, id, &assistant_response, &trace)
         .await
         .map_err(|e| {
             tracing::error!("Database error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            chat_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to save chat response",
+            )
         })?;
 
+    Ok(Json(serde_json::json!({
+        "response": assistant_response,
+        "message_id": message_id
+    })))
+}
+
+/// Chat with an alert, streaming the answer as server-sent events
+#[utoipa::path(
+    post,
+    path = "/api/alerts/{id}/chat/stream",
+    params(AlertId),
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Stream of ChatStreamEvent objects", content_type = "text/event-stream", body = ChatStreamEvent),
+        (status = 404, description = "Alert not found"),
+        (status = 429, description = "LLM budget exhausted", body = serde_json::Value),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "alerts"
+)]
+pub async fn chat_with_alert_stream(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ChatError> {
+    let (alert, initial_response, chat_history) = start_chat(&state, id, &payload.message).await?;
+
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let (updates_tx, mut updates_rx) = mpsc::unbounded_channel();
+
+    // Forward agent updates to the client
+    let forward_tx = events_tx.clone();
+    let forwarder = tokio::spawn(async move {
+        while let Some(update) = updates_rx.recv().await {
+            let event = match update {
+                StreamUpdate::Text(text) => ChatStreamEvent::Token { text },
+                StreamUpdate::ToolCall { name } => ChatStreamEvent::ToolCall { name },
+            };
+            let _ = forward_tx.send(event);
+        }
+    });
+
+    // Run the agent detached so the answer is still saved if the client disconnects
+    tokio::spawn(async move {
+        let trace = AgentTrace::new();
+        let result = chat::Chat::stream(
+            alert,
+            &initial_response,
+            &chat_history,
+            &payload.message,
+            &state.config,
+            &state.mcp_manager,
+            &trace,
+            updates_tx,
+        )
+        .await;
+
+        let event = match result {
+            Ok(response) => match save_assistant_message(&state, id, &response, &trace).await {
+                Ok(message_id) => ChatStreamEvent::Done {
+                    message_id,
+                    response,
+                },
+                Err(e) => {
+                    tracing::error!("Database error: {}", e);
+                    ChatStreamEvent::Error {
+                        message: "Failed to save chat response".to_string(),
+                    }
+                }
+            },
+            Err(e) => {
+                tracing::error!("Chat agent error: {}", e);
+                save_failed_chat_usage(&state, id, &trace).await;
+                ChatStreamEvent::Error {
+                    message: format!("Chat agent error: {e}"),
+                }
+            }
+        };
+        // The updates channel closed with the agent run; let the last tokens go out first
+        let _ = forwarder.await;
+        let _ = events_tx.send(event);
+    });
+
+    let stream = UnboundedReceiverStream::new(events_rx).map(|event| {
+        let data = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+        Ok(Event::default().data(data))
+    });
+
+    Ok(Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(std::time::Duration::from_secs(15))
+            .text("keep-alive-text"),
+    ))
+}
+
+/// Error response of the chat endpoints: status code and `{"error": ...}` body
+type ChatError = (StatusCode, Json<serde_json::Value>);
+
+fn chat_error(status: StatusCode, message: &str) -> ChatError {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+/// Load everything a chat turn needs and record the user's message
+async fn start_chat(
+    state: &AppState,
+    id: i64,
+    message: &str,
+) -> Result<(AlertEnvelope, String, Vec<ChatMessage>), ChatError> {
+    let db_error = |e: color_eyre::Report| {
+        tracing::error!("Database error: {}", e);
+        chat_error(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    };
+
+    // Get alert data and initial response
+    let (kind, alert_data, initial_response) = db::get_alert_for_chat(&state.db_pool, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| chat_error(StatusCode::NOT_FOUND, "Alert not found"))?;
+
+    let alert = sources::normalize(kind, &alert_data).map_err(|e| {
+        tracing::error!("Failed to parse alert data: {}", e);
+        chat_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to parse alert data",
+        )
+    })?;
+
+    if let Some(budget) = budget::check_and_warn(state).await
+        && budget.exceeded()
+    {
+        return Err(chat_error(StatusCode::TOO_MANY_REQUESTS, &budget.message()));
+    }
+
+    // Get chat history
+    let chat_history = db::get_chat_history(&state.db_pool, id)
+        .await
+        .map_err(db_error)?;
+
+    // Save user message
+    db::insert_chat_message(&state.db_pool, id, "user", message)
+        .await
+        .map_err(db_error)?;
+
+    Ok((alert, initial_response, chat_history))
+}
+
+/// Persist the assistant's answer with the tool calls and usage behind it and notify connected clients
+async fn save_assistant_message(
+    state: &AppState,
+    alert_id: i64,
+    response: &str,
+    trace: &AgentTrace,
+) -> color_eyre::Result<i64> {
+    let message_id =
+        db::insert_chat_message(&state.db_pool, alert_id, "assistant", response).await?;
+    db::insert_tool_calls(
+        &state.db_pool,
+        alert_id,
+        Some(message_id),
+        &trace.tool_calls(),
+    )
+    .await?;
+    db::insert_llm_usage(
+        &state.db_pool,
+        alert_id,
+        Some(message_id),
+        &trace.llm_calls(),
+    )
+    .await?;
+    budget::check_and_warn(state).await;
+
     // Broadcast SSE notification
     let event = SseEvent::ChatMessage {
-        alert_id: id,
+        alert_id,
         message_id,
+        group: db::get_alert_group(&state.db_pool, alert_id).await?,
     };
-    let event_json = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
-    let _ = state.tx.send(event_json);
+    let _ = state.tx.send(SseMessage::from(&event));
 
-    Ok(Json(serde_json::json!({
-        "response": assistant_response,
-        "message_id": message_id
-    })))
+    Ok(message_id)
+}
+
+/// Account for the tokens a failed chat turn still consumed
+async fn save_failed_chat_usage(state: &AppState, alert_id: i64, trace: &AgentTrace) {
+    if let Err(e) = db::insert_llm_usage(&state.db_pool, alert_id, None, &trace.llm_calls()).await {
+        tracing::error!("Failed to store LLM usage for alert {}: {}", alert_id, e);
+    }
+    budget::check_and_warn(state).await;
+}
+
+/// List the MCP tool calls the agents made while investigating an alert
+#[utoipa::path(
+    get,
+    path = "/api/alerts/{id}/tool-calls",
+    params(AlertId),
+    responses(
+        (status = 200, description = "Tool calls, oldest first", body = Vec<ToolCall>),
+        (status = 404, description = "Alert not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "alerts"
+)]
+pub async fn list_alert_tool_calls(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ToolCall>>, StatusCode> {
+    db::get_alert_for_chat(&state.db_pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let calls = db::list_tool_calls_for_alert(&state.db_pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(calls))
+}
+
+/// List the deliveries of an alert's notification to its group's channels
+#[utoipa::path(
+    get,
+    path = "/api/alerts/{id}/notifications",
+    params(AlertId),
+    responses(
+        (status = 200, description = "Deliveries, oldest first", body = Vec<NotificationDelivery>),
+        (status = 404, description = "Alert not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "alerts"
+)]
+pub async fn list_alert_notifications(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<NotificationDelivery>>, StatusCode> {
+    db::get_alert_for_chat(&state.db_pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let deliveries = db::list_notifications_for_alert(&state.db_pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(deliveries))
 }
 
 /// Delete an alert
@@ -283,6 +639,11 @@ pub async fn delete_alert(
     State(state): State<AppState>,
     Path(id): Path<i64>,
 ) -> Result<StatusCode, StatusCode> {
+    // Looked up first, the group is gone with the row
+    let group = db::get_alert_group(&state.db_pool, id).await.map_err(|e| {
+        tracing::error!("Database error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
     let deleted = db::delete_alert(&state.db_pool, id).await.map_err(|e| {
         tracing::error!("Database error: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
@@ -293,9 +654,11 @@ pub async fn delete_alert(
     }
 
     // Broadcast SSE notification
-    let event = SseEvent::AlertDeleted { alert_id: id };
-    let event_json = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
-    let _ = state.tx.send(event_json);
+    let event = SseEvent::AlertDeleted {
+        alert_id: id,
+        group,
+    };
+    let _ = state.tx.send(SseMessage::from(&event));
 
     Ok(StatusCode::NO_CONTENT)
 }
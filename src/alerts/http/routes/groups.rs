@@ -0,0 +1,17 @@
+use axum::{Json, extract::State};
+
+use crate::alerts::http::server::AppState;
+use crate::groups::GroupRegistry;
+
+/// Groups with their notification channels, default severity and prompt profile
+#[utoipa::path(
+    get,
+    path = "/api/groups",
+    responses(
+        (status = 200, description = "Group registry loaded from groups.yml", body = GroupRegistry)
+    ),
+    tag = "groups"
+)]
+pub async fn list_groups(State(state): State<AppState>) -> Json<GroupRegistry> {
+    Json(state.groups.as_ref().clone())
+}
@@ -0,0 +1,204 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::alerts::http::routes::alerts::AlertId;
+use crate::alerts::http::server::{AppState, SseEvent, SseMessage};
+use crate::database::db;
+use crate::database::models::{AlertLifecycle, AlertStatus, AlertStatusChange};
+
+/// Request body of `PATCH /api/alerts/{id}/status`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAlertStatus {
+    pub status: AlertStatus,
+    /// How the alert was resolved; only kept when it is resolved or a false positive
+    #[serde(default)]
+    pub resolution_notes: Option<String>,
+    /// Operator making the change
+    #[serde(default)]
+    pub changed_by: Option<String>,
+    /// Comment recorded in the history
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Request body of `PATCH /api/alerts/{id}/assignee`
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAlertAssignee {
+    /// Operator handling the alert; `null` unassigns it
+    pub assignee: Option<String>,
+    /// Operator making the change
+    #[serde(default)]
+    pub changed_by: Option<String>,
+    /// Comment recorded in the history
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Error response of the lifecycle endpoints: status code and `{"error": ...}` body
+type LifecycleError = (StatusCode, Json<serde_json::Value>);
+
+fn lifecycle_error(status: StatusCode, message: &str) -> LifecycleError {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+fn database_error(e: color_eyre::Report) -> LifecycleError {
+    tracing::error!("Database error: {}", e);
+    lifecycle_error(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+}
+
+/// Trimmed text, or `None` when blank
+fn text(value: Option<&String>) -> Option<&str> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
+async fn lifecycle(state: &AppState, id: i64) -> Result<AlertLifecycle, LifecycleError> {
+    db::get_alert_lifecycle(&state.db_pool, id)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(|| lifecycle_error(StatusCode::NOT_FOUND, "Alert not found"))
+}
+
+async fn broadcast(state: &AppState, id: i64, event: impl FnOnce(Option<String>) -> SseEvent) {
+    let group = db::get_alert_group(&state.db_pool, id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to get the group of alert {}: {}", id, e);
+            None
+        });
+    let _ = state.tx.send(SseMessage::from(&event(group)));
+}
+
+/// Acknowledge, investigate, resolve or reopen an alert
+#[utoipa::path(
+    patch,
+    path = "/api/alerts/{id}/status",
+    params(AlertId),
+    request_body = UpdateAlertStatus,
+    responses(
+        (status = 200, description = "Status changed", body = AlertLifecycle),
+        (status = 404, description = "Alert not found", body = serde_json::Value),
+        (status = 409, description = "The alert cannot move to this status", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "alerts"
+)]
+pub async fn update_alert_status(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateAlertStatus>,
+) -> Result<Json<AlertLifecycle>, LifecycleError> {
+    let from = lifecycle(&state, id).await?.status;
+    let to = payload.status;
+    if !from.can_transition_to(to) {
+        return Err(lifecycle_error(
+            StatusCode::CONFLICT,
+            &format!(
+                "Cannot move an alert from {} to {}",
+                from.as_str(),
+                to.as_str()
+            ),
+        ));
+    }
+
+    let changed_by = text(payload.changed_by.as_ref());
+    let moved = db::transition_alert_status(
+        &state.db_pool,
+        id,
+        from,
+        to,
+        text(payload.resolution_notes.as_ref()),
+        changed_by,
+        text(payload.note.as_ref()),
+    )
+    .await
+    .map_err(database_error)?;
+    if !moved {
+        return Err(lifecycle_error(
+            StatusCode::CONFLICT,
+            "The alert was changed by someone else, reload it and retry",
+        ));
+    }
+
+    broadcast(&state, id, |group| SseEvent::AlertStatusChanged {
+        alert_id: id,
+        from_status: from,
+        status: to,
+        changed_by: changed_by.map(str::to_string),
+        group,
+    })
+    .await;
+
+    Ok(Json(lifecycle(&state, id).await?))
+}
+
+/// Assign an alert to an operator, or unassign it
+#[utoipa::path(
+    patch,
+    path = "/api/alerts/{id}/assignee",
+    params(AlertId),
+    request_body = UpdateAlertAssignee,
+    responses(
+        (status = 200, description = "Assignee changed", body = AlertLifecycle),
+        (status = 404, description = "Alert not found", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "alerts"
+)]
+pub async fn update_alert_assignee(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateAlertAssignee>,
+) -> Result<Json<AlertLifecycle>, LifecycleError> {
+    let assignee = text(payload.assignee.as_ref());
+    let changed_by = text(payload.changed_by.as_ref());
+    let assigned = db::assign_alert(
+        &state.db_pool,
+        id,
+        assignee,
+        changed_by,
+        text(payload.note.as_ref()),
+    )
+    .await
+    .map_err(database_error)?;
+    if !assigned {
+        return Err(lifecycle_error(StatusCode::NOT_FOUND, "Alert not found"));
+    }
+
+    broadcast(&state, id, |group| SseEvent::AlertAssigned {
+        alert_id: id,
+        assignee: assignee.map(str::to_string),
+        changed_by: changed_by.map(str::to_string),
+        group,
+    })
+    .await;
+
+    Ok(Json(lifecycle(&state, id).await?))
+}
+
+/// List the status transitions and assignments of an alert
+#[utoipa::path(
+    get,
+    path = "/api/alerts/{id}/history",
+    params(AlertId),
+    responses(
+        (status = 200, description = "Lifecycle history, oldest first", body = Vec<AlertStatusChange>),
+        (status = 404, description = "Alert not found", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "alerts"
+)]
+pub async fn list_alert_history(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<AlertStatusChange>>, LifecycleError> {
+    lifecycle(&state, id).await?;
+    let history = db::list_alert_status_history(&state.db_pool, id)
+        .await
+        .map_err(database_error)?;
+    Ok(Json(history))
+}
@@ -1,5 +1,6 @@
 use crate::database::{db, models};
 use crate::mcp_clients;
+use crate::mcp_manager::McpManager;
 use axum::{
     Json,
     extract::{Path, Query, State},
@@ -110,8 +111,7 @@ pub async fn create_mcp_server(
         .map_err(|e| {
             tracing::error!("Database error: {}", e);
             // Check if it's a unique constraint violation
-            let error_msg = e.to_string();
-            if error_msg.contains("UNIQUE constraint") {
+            if db::is_unique_violation(&e) {
                 (
                     StatusCode::CONFLICT,
                     Json(serde_json::json!({ "error": "A server with this name already exists" })),
@@ -124,6 +124,9 @@ pub async fn create_mcp_server(
             }
         })?;
 
+    // Connect to the new server right away
+    McpManager::refresh_in_background(&state.mcp_manager);
+
     Ok((StatusCode::CREATED, Json(server)))
 }
 
@@ -150,8 +153,7 @@ pub async fn update_mcp_server(
         .await
         .map_err(|e| {
             tracing::error!("Database error: {}", e);
-            let error_msg = e.to_string();
-            if error_msg.contains("UNIQUE constraint") {
+            if db::is_unique_violation(&e) {
                 (
                     StatusCode::CONFLICT,
                     Json(serde_json::json!({ "error": "A server with this name already exists" })),
@@ -165,7 +167,11 @@ pub async fn update_mcp_server(
         })?;
 
     match server {
-        Some(s) => Ok(Json(s)),
+        Some(s) => {
+            // Reconnect with the new configuration
+            McpManager::refresh_in_background(&state.mcp_manager);
+            Ok(Json(s))
+        }
         None => Err((
             StatusCode::NOT_FOUND,
             Json(serde_json::json!({ "error": "Server not found" })),
@@ -197,6 +203,8 @@ pub async fn delete_mcp_server(
         })?;
 
     if deleted {
+        // Close the connection to the deleted server
+        McpManager::refresh_in_background(&state.mcp_manager);
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(StatusCode::NOT_FOUND)
@@ -308,5 +316,7 @@ pub async fn enable_native_mcp_servers(
             )
         })?;
 
+    McpManager::refresh_in_background(&state.mcp_manager);
+
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,24 +1,49 @@
 pub mod alerts;
+pub mod groups;
+pub mod lifecycle;
 pub mod mcp;
+pub mod prefixes;
+pub mod rpki;
+pub mod storage;
+pub mod usage;
 
 use crate::agents::health;
 use axum::{
     Json,
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
     response::sse::{Event, Sse},
 };
 use futures::stream::Stream;
+use serde::Deserialize;
 use std::convert::Infallible;
 use tokio_stream::StreamExt as _;
 use tokio_stream::wrappers::BroadcastStream;
+use utoipa::IntoParams;
 
-use crate::alerts::http::server::{AppState, SseEvent};
+use crate::alerts::http::server::{AppState, SseEvent, SseMessage};
+
+#[derive(Deserialize, IntoParams)]
+pub struct MessageStreamQuery {
+    /// Comma-separated groups; alert events of other groups are not sent
+    pub group: Option<String>,
+}
+
+/// Whether an event is wanted by a subscriber to `groups`; events that are not about an
+/// alert of some group, like health checks and budget warnings, are always sent
+fn is_for_groups(message: &SseMessage, groups: &[String]) -> bool {
+    groups.is_empty()
+        || message
+            .group
+            .as_ref()
+            .is_none_or(|group| groups.contains(group))
+}
 
 /// Server-sent events stream for real-time updates
 #[utoipa::path(
     get,
     path = "/api/messages/stream",
+    params(MessageStreamQuery),
     responses(
         (status = 200, description = "SSE stream", content_type = "text/event-stream")
     ),
@@ -26,11 +51,21 @@ use crate::alerts::http::server::{AppState, SseEvent};
 )]
 pub async fn message_stream(
     State(state): State<AppState>,
+    Query(query): Query<MessageStreamQuery>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let groups: Vec<String> = query
+        .group
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+        .map(str::to_string)
+        .collect();
     let rx = state.tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|msg| match msg {
-        Ok(msg) => Some(Ok(Event::default().data(msg))),
-        Err(_) => None,
+    let stream = BroadcastStream::new(rx).filter_map(move |msg| match msg {
+        Ok(msg) if is_for_groups(&msg, &groups) => Some(Ok(Event::default().data(msg.data))),
+        _ => None,
     });
 
     Sse::new(stream).keep_alive(
@@ -53,7 +88,7 @@ pub async fn message_stream(
 pub async fn health_check(
     State(state): State<AppState>,
 ) -> Result<Json<health::HealthStatus>, StatusCode> {
-    match health::run(&state.config, &state.db_pool).await {
+    match health::run(&state.config, &state.db_pool, &state.mcp_manager).await {
         Ok(status) => {
             // Broadcast health status to web clients
             let status_json =
@@ -61,20 +96,57 @@ pub async fn health_check(
             let event = SseEvent::HealthCheck {
                 status: status_json,
             };
-            let _ = state
-                .tx
-                .send(serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string()));
+            let _ = state.tx.send(SseMessage::from(&event));
             Ok(Json(status))
         }
         Err(e) => {
             let event = SseEvent::Error {
                 message: format!("Health check error: {e}"),
             };
-            let _ = state
-                .tx
-                .send(serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string()));
+            let _ = state.tx.send(SseMessage::from(&event));
             tracing::error!("Health check failed: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_group_filter() {
+        let groups = vec!["noc".to_string(), "peering".to_string()];
+        let event = |group: Option<&str>| {
+            SseMessage::from(&SseEvent::NewAlert {
+                alert_id: 1,
+                group: group.map(str::to_string),
+            })
+        };
+
+        assert!(is_for_groups(&event(Some("noc")), &groups));
+        assert!(!is_for_groups(&event(Some("customers")), &groups));
+        // Events without a group, e.g. for alerts of ungrouped resources, are not filtered
+        assert!(is_for_groups(&event(None), &groups));
+        assert!(is_for_groups(&event(Some("customers")), &[]));
+
+        // Chat and delete events carry the group of their alert
+        let chat = |group: Option<&str>| {
+            SseMessage::from(&SseEvent::ChatMessage {
+                alert_id: 1,
+                message_id: 2,
+                group: group.map(str::to_string),
+            })
+        };
+        let deleted = |group: Option<&str>| {
+            SseMessage::from(&SseEvent::AlertDeleted {
+                alert_id: 1,
+                group: group.map(str::to_string),
+            })
+        };
+        assert!(is_for_groups(&chat(Some("noc")), &groups));
+        assert!(!is_for_groups(&chat(Some("customers")), &groups));
+        assert!(is_for_groups(&deleted(Some("peering")), &groups));
+        assert!(!is_for_groups(&deleted(Some("customers")), &groups));
+    }
+}
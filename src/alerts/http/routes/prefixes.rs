@@ -0,0 +1,305 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::alerts::http::server::AppState;
+use crate::config::{AsnInfo, PrefixInfo};
+use crate::prefixes::{EditError, WriteMode};
+
+/// A monitored prefix with its prefixes.yml entry
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MonitoredPrefix {
+    /// e.g. `192.0.2.0/24`
+    pub prefix: String,
+    #[serde(flatten)]
+    pub info: PrefixInfo,
+}
+
+/// A monitored ASN with its `options.monitorASns` entry
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MonitoredAsn {
+    /// e.g. `64500`
+    pub asn: String,
+    #[serde(flatten)]
+    pub info: AsnInfo,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PrefixPath {
+    /// Monitored prefix, e.g. `192.0.2.0/24`
+    pub prefix: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AsnPath {
+    /// Monitored ASN, with or without the `AS` prefix
+    pub asn: String,
+}
+
+fn edit_error(error: EditError, what: &str) -> (StatusCode, Json<Value>) {
+    let (status, message) = match error {
+        EditError::NotFound => (StatusCode::NOT_FOUND, format!("{what} not found")),
+        EditError::Exists => (StatusCode::CONFLICT, format!("{what} already exists")),
+        EditError::Invalid(message) => (StatusCode::BAD_REQUEST, message),
+        EditError::Io(e) => {
+            tracing::error!("Failed to update prefixes.yml: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to update prefixes.yml".to_string(),
+            )
+        }
+    };
+    (status, Json(json!({ "error": message })))
+}
+
+/// List monitored prefixes
+#[utoipa::path(
+    get,
+    path = "/api/prefixes",
+    responses(
+        (status = 200, description = "Monitored prefixes", body = Vec<MonitoredPrefix>)
+    ),
+    tag = "prefixes"
+)]
+pub async fn list_prefixes(State(state): State<AppState>) -> Json<Vec<MonitoredPrefix>> {
+    let config = state.prefixes.get();
+    let mut prefixes: Vec<MonitoredPrefix> = config
+        .prefixes
+        .iter()
+        .map(|(prefix, info)| MonitoredPrefix {
+            prefix: prefix.clone(),
+            info: info.clone(),
+        })
+        .collect();
+    prefixes.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    Json(prefixes)
+}
+
+/// Get a monitored prefix
+#[utoipa::path(
+    get,
+    path = "/api/prefixes/{prefix}",
+    params(PrefixPath),
+    responses(
+        (status = 200, description = "Monitored prefix found", body = MonitoredPrefix),
+        (status = 400, description = "Invalid prefix"),
+        (status = 404, description = "Prefix not monitored")
+    ),
+    tag = "prefixes"
+)]
+pub async fn get_prefix(
+    State(state): State<AppState>,
+    Path(PrefixPath { prefix }): Path<PrefixPath>,
+) -> Result<Json<MonitoredPrefix>, StatusCode> {
+    let net = prefix
+        .trim()
+        .parse::<IpNet>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let config = state.prefixes.get();
+    config
+        .prefixes
+        .iter()
+        .find(|(key, _)| key.trim().parse::<IpNet>().ok() == Some(net))
+        .map(|(prefix, info)| {
+            Json(MonitoredPrefix {
+                prefix: prefix.clone(),
+                info: info.clone(),
+            })
+        })
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Start monitoring a prefix
+#[utoipa::path(
+    post,
+    path = "/api/prefixes",
+    request_body = MonitoredPrefix,
+    responses(
+        (status = 201, description = "Prefix added to prefixes.yml", body = MonitoredPrefix),
+        (status = 400, description = "Invalid prefix or entry", body = serde_json::Value),
+        (status = 409, description = "Prefix already monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn create_prefix(
+    State(state): State<AppState>,
+    Json(payload): Json<MonitoredPrefix>,
+) -> Result<(StatusCode, Json<MonitoredPrefix>), (StatusCode, Json<Value>)> {
+    let prefix = state
+        .prefixes
+        .write_prefix(&payload.prefix, &payload.info, WriteMode::Create)
+        .await
+        .map_err(|e| edit_error(e, "Prefix"))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(MonitoredPrefix {
+            prefix,
+            info: payload.info,
+        }),
+    ))
+}
+
+/// Update a monitored prefix
+#[utoipa::path(
+    put,
+    path = "/api/prefixes/{prefix}",
+    params(PrefixPath),
+    request_body = PrefixInfo,
+    responses(
+        (status = 200, description = "Prefix updated in prefixes.yml", body = MonitoredPrefix),
+        (status = 400, description = "Invalid prefix or entry", body = serde_json::Value),
+        (status = 404, description = "Prefix not monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn update_prefix(
+    State(state): State<AppState>,
+    Path(PrefixPath { prefix }): Path<PrefixPath>,
+    Json(info): Json<PrefixInfo>,
+) -> Result<Json<MonitoredPrefix>, (StatusCode, Json<Value>)> {
+    let prefix = state
+        .prefixes
+        .write_prefix(&prefix, &info, WriteMode::Update)
+        .await
+        .map_err(|e| edit_error(e, "Prefix"))?;
+    Ok(Json(MonitoredPrefix { prefix, info }))
+}
+
+/// Stop monitoring a prefix
+#[utoipa::path(
+    delete,
+    path = "/api/prefixes/{prefix}",
+    params(PrefixPath),
+    responses(
+        (status = 204, description = "Prefix removed from prefixes.yml"),
+        (status = 400, description = "Invalid prefix", body = serde_json::Value),
+        (status = 404, description = "Prefix not monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn delete_prefix(
+    State(state): State<AppState>,
+    Path(PrefixPath { prefix }): Path<PrefixPath>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    state
+        .prefixes
+        .remove_prefix(&prefix)
+        .await
+        .map_err(|e| edit_error(e, "Prefix"))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List monitored ASNs
+#[utoipa::path(
+    get,
+    path = "/api/monitored-asns",
+    responses(
+        (status = 200, description = "Monitored ASNs", body = Vec<MonitoredAsn>)
+    ),
+    tag = "prefixes"
+)]
+pub async fn list_asns(State(state): State<AppState>) -> Json<Vec<MonitoredAsn>> {
+    let config = state.prefixes.get();
+    let mut asns: Vec<MonitoredAsn> = config
+        .monitored_asns
+        .iter()
+        .map(|(asn, info)| MonitoredAsn {
+            asn: asn.clone(),
+            info: info.clone(),
+        })
+        .collect();
+    asns.sort_by_key(|a| a.asn.parse::<u32>().unwrap_or(u32::MAX));
+    Json(asns)
+}
+
+/// Start monitoring an ASN
+#[utoipa::path(
+    post,
+    path = "/api/monitored-asns",
+    request_body = MonitoredAsn,
+    responses(
+        (status = 201, description = "ASN added to prefixes.yml", body = MonitoredAsn),
+        (status = 400, description = "Invalid ASN or entry", body = serde_json::Value),
+        (status = 409, description = "ASN already monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn create_asn(
+    State(state): State<AppState>,
+    Json(payload): Json<MonitoredAsn>,
+) -> Result<(StatusCode, Json<MonitoredAsn>), (StatusCode, Json<Value>)> {
+    let asn = state
+        .prefixes
+        .write_asn(&payload.asn, &payload.info, WriteMode::Create)
+        .await
+        .map_err(|e| edit_error(e, "ASN"))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(MonitoredAsn {
+            asn,
+            info: payload.info,
+        }),
+    ))
+}
+
+/// Update a monitored ASN
+#[utoipa::path(
+    put,
+    path = "/api/monitored-asns/{asn}",
+    params(AsnPath),
+    request_body = AsnInfo,
+    responses(
+        (status = 200, description = "ASN updated in prefixes.yml", body = MonitoredAsn),
+        (status = 400, description = "Invalid ASN or entry", body = serde_json::Value),
+        (status = 404, description = "ASN not monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn update_asn(
+    State(state): State<AppState>,
+    Path(AsnPath { asn }): Path<AsnPath>,
+    Json(info): Json<AsnInfo>,
+) -> Result<Json<MonitoredAsn>, (StatusCode, Json<Value>)> {
+    let asn = state
+        .prefixes
+        .write_asn(&asn, &info, WriteMode::Update)
+        .await
+        .map_err(|e| edit_error(e, "ASN"))?;
+    Ok(Json(MonitoredAsn { asn, info }))
+}
+
+/// Stop monitoring an ASN
+#[utoipa::path(
+    delete,
+    path = "/api/monitored-asns/{asn}",
+    params(AsnPath),
+    responses(
+        (status = 204, description = "ASN removed from prefixes.yml"),
+        (status = 400, description = "Invalid ASN", body = serde_json::Value),
+        (status = 404, description = "ASN not monitored", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = serde_json::Value)
+    ),
+    tag = "prefixes"
+)]
+pub async fn delete_asn(
+    State(state): State<AppState>,
+    Path(AsnPath { asn }): Path<AsnPath>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    state
+        .prefixes
+        .remove_asn(&asn)
+        .await
+        .map_err(|e| edit_error(e, "ASN"))?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,65 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use ipnet::IpNet;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::alerts::http::server::AppState;
+use crate::config::parse_asn;
+use crate::rpki::RpkiValidation;
+use crate::rpki::coverage::{self, CoverageReport};
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct ValidateQuery {
+    /// Announced prefix, e.g. `192.0.2.0/24`
+    pub prefix: String,
+    /// Origin ASN, with or without the `AS` prefix
+    pub asn: String,
+}
+
+/// RFC 6811 origin validation of a prefix/origin pair against the local VRP set
+#[utoipa::path(
+    get,
+    path = "/api/rpki/validate",
+    params(ValidateQuery),
+    responses(
+        (status = 200, description = "Validation result", body = RpkiValidation),
+        (status = 400, description = "Invalid prefix or ASN"),
+        (status = 503, description = "No VRP set loaded")
+    ),
+    tag = "rpki"
+)]
+pub async fn validate(
+    State(state): State<AppState>,
+    Query(query): Query<ValidateQuery>,
+) -> Result<Json<RpkiValidation>, StatusCode> {
+    let prefix = query
+        .prefix
+        .trim()
+        .parse::<IpNet>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let asn = parse_asn(&query.asn).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let vrps = state.rpki.get().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(vrps.validate(&prefix, asn)))
+}
+
+/// ROA coverage of every monitored prefix against the local VRP set
+#[utoipa::path(
+    get,
+    path = "/api/rpki/coverage",
+    responses(
+        (status = 200, description = "Coverage report", body = CoverageReport),
+        (status = 503, description = "No VRP set loaded")
+    ),
+    tag = "rpki"
+)]
+pub async fn get_coverage(
+    State(state): State<AppState>,
+) -> Result<Json<CoverageReport>, StatusCode> {
+    let vrps = state.rpki.get().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(coverage::check(&state.prefixes.get(), &vrps)))
+}
@@ -0,0 +1,24 @@
+use axum::{Json, extract::State, http::StatusCode};
+
+use crate::alerts::http::server::AppState;
+use crate::alerts::retention::{self, StorageReport};
+
+/// Database size, row counts, retention policy and the last and next purges
+#[utoipa::path(
+    get,
+    path = "/api/storage",
+    responses(
+        (status = 200, description = "Storage usage and retention state", body = StorageReport),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "storage"
+)]
+pub async fn get_storage(State(state): State<AppState>) -> Result<Json<StorageReport>, StatusCode> {
+    retention::report(&state.db_pool, &state.config)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
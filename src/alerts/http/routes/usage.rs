@@ -0,0 +1,138 @@
+use crate::config::ModelPricing;
+use crate::database::db;
+use crate::database::models::{UsageReport, UsageSummary};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::alerts::http::server::AppState;
+
+/// Default reporting period in days
+const DEFAULT_USAGE_DAYS: u32 = 30;
+
+/// Longest reporting period in days
+const MAX_USAGE_DAYS: u32 = 366;
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct UsageQuery {
+    /// Number of days to report, including today (default 30, max 366)
+    pub days: Option<u32>,
+}
+
+/// LLM token usage and estimated spend, aggregated by day, model and alert kind
+#[utoipa::path(
+    get,
+    path = "/api/usage",
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "Aggregated LLM usage", body = UsageReport),
+        (status = 400, description = "Invalid period"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "usage"
+)]
+pub async fn get_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, StatusCode> {
+    let days = query.days.unwrap_or(DEFAULT_USAGE_DAYS);
+    if days == 0 || days > MAX_USAGE_DAYS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let since = (Utc::now() - Duration::days(i64::from(days) - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let rows = db::usage_summary(&state.db_pool, &since)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(build_report(since, rows, &state.config.llm_pricing)))
+}
+
+/// Attach cost estimates to the aggregated rows and compute totals
+fn build_report(
+    since: String,
+    mut rows: Vec<UsageSummary>,
+    pricing: &HashMap<String, ModelPricing>,
+) -> UsageReport {
+    let mut unpriced_models = BTreeSet::new();
+    let mut total_estimated_cost_usd = 0.0;
+
+    for row in &mut rows {
+        match pricing.get(&row.model) {
+            Some(price) => {
+                let cost = price.cost(row.input_tokens, row.output_tokens);
+                total_estimated_cost_usd += cost;
+                row.estimated_cost_usd = Some(cost);
+            }
+            None => {
+                unpriced_models.insert(row.model.clone());
+            }
+        }
+    }
+
+    UsageReport {
+        since,
+        total_calls: rows.iter().map(|r| r.calls).sum(),
+        total_input_tokens: rows.iter().map(|r| r.input_tokens).sum(),
+        total_output_tokens: rows.iter().map(|r| r.output_tokens).sum(),
+        total_estimated_cost_usd,
+        unpriced_models: unpriced_models.into_iter().collect(),
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(model: &str, input_tokens: i64, output_tokens: i64) -> UsageSummary {
+        UsageSummary {
+            day: "2025-01-15".to_string(),
+            model: model.to_string(),
+            alert_kind: Some("bgp_alerter".to_string()),
+            calls: 1,
+            input_tokens,
+            output_tokens,
+            turns: 1,
+            duration_ms: 1000,
+            estimated_cost_usd: None,
+        }
+    }
+
+    #[test]
+    fn test_build_report_estimates_cost() {
+        let pricing = HashMap::from([(
+            "priced".to_string(),
+            ModelPricing {
+                input_per_mtok: 3.0,
+                output_per_mtok: 15.0,
+            },
+        )]);
+        let rows = vec![
+            row("priced", 1_000_000, 100_000),
+            row("local", 500, 500),
+            row("priced", 0, 0),
+        ];
+
+        let report = build_report("2025-01-01".to_string(), rows, &pricing);
+        assert_eq!(report.total_calls, 3);
+        assert_eq!(report.total_input_tokens, 1_000_500);
+        assert_eq!(report.total_output_tokens, 100_500);
+        assert_eq!(report.rows[0].estimated_cost_usd, Some(4.5));
+        assert_eq!(report.rows[1].estimated_cost_usd, None);
+        assert!((report.total_estimated_cost_usd - 4.5).abs() < 1e-9);
+        assert_eq!(report.unpriced_models, vec!["local"]);
+    }
+}
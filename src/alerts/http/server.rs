@@ -4,16 +4,28 @@ use axum::{
     Router,
     http::{StatusCode, Uri},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, patch, post, put},
 };
 use color_eyre::Result;
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::{Notify, broadcast};
 use utoipa::ToSchema;
 
-use crate::config::{AppConfig, PrefixesConfig};
+use crate::alerts::analysis_queue;
+use crate::alerts::budget::{BudgetPeriod, BudgetWarnings};
+use crate::alerts::notify;
+use crate::alerts::retention;
+use crate::alerts::ris_live;
+use crate::alerts::snmp;
+use crate::alerts::syslog;
+use crate::config::AppConfig;
+use crate::database::DbPool;
+use crate::database::models::{AlertStatus, AnalysisStatus};
+use crate::groups::GroupRegistry;
+use crate::mcp_manager::McpManager;
+use crate::prefixes::{self, PrefixesStore};
+use crate::rpki::{self, VrpStore};
 
 use super::openapi::ApiDoc;
 use super::routes;
@@ -24,41 +36,196 @@ use utoipa_swagger_ui::SwaggerUi;
 #[serde(tag = "type")]
 pub enum SseEvent {
     #[serde(rename = "new_alert")]
-    NewAlert { alert_id: i64 },
+    NewAlert {
+        alert_id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    #[serde(rename = "analysis_status")]
+    AnalysisStatus {
+        alert_id: i64,
+        status: AnalysisStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    #[serde(rename = "incident_updated")]
+    IncidentUpdated {
+        incident_id: i64,
+        alert_id: i64,
+        occurrence_count: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    /// An operator moved an alert to another status
+    #[serde(rename = "alert_status_changed")]
+    AlertStatusChanged {
+        alert_id: i64,
+        from_status: AlertStatus,
+        status: AlertStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        changed_by: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
+    /// An alert was assigned to an operator, or unassigned
+    #[serde(rename = "alert_assigned")]
+    AlertAssigned {
+        alert_id: i64,
+        assignee: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        changed_by: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
     #[serde(rename = "chat_message")]
-    ChatMessage { alert_id: i64, message_id: i64 },
+    ChatMessage {
+        alert_id: i64,
+        message_id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
     #[serde(rename = "alert_deleted")]
-    AlertDeleted { alert_id: i64 },
+    AlertDeleted {
+        alert_id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<String>,
+    },
     #[serde(rename = "health_check")]
     HealthCheck { status: String },
+    /// An LLM budget crossed a warning threshold (80 or 100 percent)
+    #[serde(rename = "budget_warning")]
+    BudgetWarning {
+        period: BudgetPeriod,
+        percent: u8,
+        message: String,
+    },
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+impl SseEvent {
+    /// Group of the alert the event is about; None for events about no alert of a group
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            SseEvent::NewAlert { group, .. }
+            | SseEvent::AnalysisStatus { group, .. }
+            | SseEvent::IncidentUpdated { group, .. }
+            | SseEvent::AlertStatusChanged { group, .. }
+            | SseEvent::AlertAssigned { group, .. }
+            | SseEvent::ChatMessage { group, .. }
+            | SseEvent::AlertDeleted { group, .. } => group.as_deref(),
+            SseEvent::HealthCheck { .. }
+            | SseEvent::BudgetWarning { .. }
+            | SseEvent::Error { .. } => None,
+        }
+    }
+}
+
+/// A serialized `SseEvent` as broadcast to the stream subscribers, with its group so that
+/// they filter events without decoding them
+#[derive(Debug, Clone)]
+pub struct SseMessage {
+    pub group: Option<String>,
+    pub data: String,
+}
+
+impl From<&SseEvent> for SseMessage {
+    fn from(event: &SseEvent) -> Self {
+        Self {
+            group: event.group().map(str::to_string),
+            data: serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string()),
+        }
+    }
+}
+
+/// Events sent on a streaming chat response
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+#[serde(tag = "type")]
+pub enum ChatStreamEvent {
+    #[serde(rename = "tool_call")]
+    ToolCall { name: String },
+    #[serde(rename = "token")]
+    Token { text: String },
+    #[serde(rename = "done")]
+    Done { message_id: i64, response: String },
     #[serde(rename = "error")]
     Error { message: String },
 }
 
 #[derive(Clone)]
 pub struct AppState {
-    pub tx: broadcast::Sender<String>,
+    pub tx: broadcast::Sender<SseMessage>,
     pub config: Arc<AppConfig>,
-    pub prefixes_config: PrefixesConfig,
-    pub db_pool: Arc<SqlitePool>,
+    /// Monitored resources from prefixes.yml, reloaded when the file changes
+    pub prefixes: Arc<PrefixesStore>,
+    pub db_pool: Arc<DbPool>,
+    /// Wakes the analysis workers when a new job is queued
+    pub analysis_notify: Arc<Notify>,
+    /// Pooled connections to the enabled MCP servers
+    pub mcp_manager: Arc<McpManager>,
+    /// Budget warnings already sent to the UI
+    pub budget_warnings: Arc<BudgetWarnings>,
+    /// VRPs used for local RPKI origin validation
+    pub rpki: Arc<VrpStore>,
+    /// Routing and triage settings of the prefixes.yml groups
+    pub groups: Arc<GroupRegistry>,
+    /// Owner of the analysis jobs and deliveries this process works on, so that replicas
+    /// sharing the database leave them alone
+    pub replica_id: Arc<str>,
 }
 
-pub async fn start(tx: broadcast::Sender<String>, config: Arc<AppConfig>) -> Result<()> {
+pub async fn start(tx: broadcast::Sender<SseMessage>, config: Arc<AppConfig>) -> Result<()> {
     // Initialize database
-    let db_pool = db::init_database().await?;
+    let db_pool = db::init_database(&config.database_url).await?;
 
     // Load prefixes configuration
-    let prefixes_config = PrefixesConfig::load("prefixes.yml")
+    let prefixes = PrefixesStore::load("prefixes.yml")
         .map_err(|e| color_eyre::eyre::eyre!("Failed to load prefixes.yml: {}", e))?;
+    let groups = GroupRegistry::load(&config.groups_path)
+        .map_err(|e| color_eyre::eyre::eyre!("Failed to load {}: {}", config.groups_path, e))?;
 
     let port = config.server_port;
+    let mcp_manager = Arc::new(McpManager::new(db_pool.clone()));
     let state = AppState {
         tx,
         config,
-        prefixes_config,
+        prefixes: Arc::new(prefixes),
         db_pool,
+        analysis_notify: Arc::new(Notify::new()),
+        mcp_manager: mcp_manager.clone(),
+        budget_warnings: Arc::new(BudgetWarnings::default()),
+        rpki: Arc::new(VrpStore::default()),
+        groups: Arc::new(groups),
+        replica_id: db::new_replica_id().into(),
     };
 
+    // Apply edits to prefixes.yml without a restart
+    prefixes::watch(state.prefixes.clone());
+
+    // Keep MCP connections alive in the background
+    McpManager::start(mcp_manager);
+
+    // Start background analysis workers
+    analysis_queue::start(state.clone()).await?;
+
+    // Resume notification deliveries interrupted by a shutdown of this or another replica
+    notify::start(state.clone()).await?;
+
+    // Watch BGP updates from RIS Live when enabled
+    ris_live::start(state.clone());
+
+    // Receive SNMP traps from monitored devices when enabled
+    snmp::start(state.clone());
+
+    // Receive syslog from monitored devices when enabled
+    syslog::start(state.clone());
+
+    // Keep the VRP set used for RPKI validation fresh when a source is configured
+    rpki::start(state.clone());
+
+    // Purge expired alerts and chat messages when a retention period is configured
+    retention::start(state.clone());
+
     // build our application with routes
     // API routes must come before static file serving
     let app = Router::new()
@@ -76,6 +243,55 @@ pub async fn start(tx: broadcast::Sender<String>, config: Arc<AppConfig>) -> Res
             "/api/alerts/{id}/chat",
             post(routes::alerts::chat_with_alert),
         )
+        .route(
+            "/api/alerts/{id}/chat/stream",
+            post(routes::alerts::chat_with_alert_stream),
+        )
+        .route(
+            "/api/alerts/{id}/tool-calls",
+            get(routes::alerts::list_alert_tool_calls),
+        )
+        .route(
+            "/api/alerts/{id}/notifications",
+            get(routes::alerts::list_alert_notifications),
+        )
+        // Alert lifecycle: acknowledge, assign, resolve, reopen
+        .route(
+            "/api/alerts/{id}/status",
+            patch(routes::lifecycle::update_alert_status),
+        )
+        .route(
+            "/api/alerts/{id}/assignee",
+            patch(routes::lifecycle::update_alert_assignee),
+        )
+        .route(
+            "/api/alerts/{id}/history",
+            get(routes::lifecycle::list_alert_history),
+        )
+        .route("/api/usage", get(routes::usage::get_usage))
+        .route("/api/storage", get(routes::storage::get_storage))
+        .route("/api/rpki/validate", get(routes::rpki::validate))
+        .route("/api/rpki/coverage", get(routes::rpki::get_coverage))
+        // Monitored resources, stored in prefixes.yml
+        .route(
+            "/api/prefixes",
+            get(routes::prefixes::list_prefixes).post(routes::prefixes::create_prefix),
+        )
+        .route(
+            "/api/prefixes/{*prefix}",
+            get(routes::prefixes::get_prefix)
+                .put(routes::prefixes::update_prefix)
+                .delete(routes::prefixes::delete_prefix),
+        )
+        .route(
+            "/api/monitored-asns",
+            get(routes::prefixes::list_asns).post(routes::prefixes::create_asn),
+        )
+        .route(
+            "/api/monitored-asns/{asn}",
+            put(routes::prefixes::update_asn).delete(routes::prefixes::delete_asn),
+        )
+        .route("/api/groups", get(routes::groups::list_groups))
         // MCP server management routes
         .route(
             "/api/mcps",
@@ -195,105 +411,44 @@ pub struct Details {
 mod tests {
     use super::routes;
     use super::*;
+    use crate::alerts::analysis_queue::ANALYSIS_LEASE;
     use crate::database::models;
+    use crate::rpki::coverage::CoverageState;
     use axum::extract::{Path, Query, State};
     use axum::{Json, http::StatusCode};
+    use serde_json::json;
 
     async fn create_test_state() -> AppState {
-        // Create in-memory database
-        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-
-        // Run migrations
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS alerts (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                alert_data TEXT NOT NULL,
-                initial_response TEXT NOT NULL,
-                kind TEXT NOT NULL DEFAULT 'bgp_alerter',
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS chat_messages (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                alert_id INTEGER NOT NULL,
-                role TEXT NOT NULL,
-                content TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (alert_id) REFERENCES alerts(id) ON DELETE CASCADE
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS mcp_servers (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                description TEXT,
-                transport_type TEXT NOT NULL CHECK(transport_type IN ('http', 'stdio')),
-                url TEXT,
-                command TEXT,
-                args TEXT,
-                env TEXT,
-                enabled INTEGER NOT NULL DEFAULT 1,
-                is_native INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_chat_messages_alert_id ON chat_messages(alert_id)
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_mcp_servers_enabled ON mcp_servers(enabled)
-            "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
+        let pool = db::tests::create_test_db().await.unwrap();
 
         let (tx, _) = broadcast::channel(100);
-        let config = Arc::new(AppConfig {
-            server_port: 7654,
-            llm_model_name: "test-model".to_string(),
-        });
-        let prefixes_config = PrefixesConfig::load("prefixes.test.yml").unwrap();
+        let config = Arc::new(AppConfig::default());
+        let prefixes = PrefixesStore::load("prefixes.test.yml").unwrap();
+
+        let db_pool = Arc::new(pool);
 
         AppState {
             tx,
             config,
-            prefixes_config,
-            db_pool: Arc::new(pool),
+            prefixes: Arc::new(prefixes),
+            mcp_manager: Arc::new(McpManager::new(db_pool.clone())),
+            db_pool,
+            analysis_notify: Arc::new(Notify::new()),
+            budget_warnings: Arc::new(BudgetWarnings::default()),
+            rpki: Arc::new(VrpStore::default()),
+            groups: Arc::new(
+                GroupRegistry::from_str("groups:\n  test:\n    severity: low\n").unwrap(),
+            ),
+            replica_id: "test-replica".into(),
         }
     }
 
     #[test]
     fn test_sse_event_serialization() {
-        let event = SseEvent::NewAlert { alert_id: 123 };
+        let event = SseEvent::NewAlert {
+            alert_id: 123,
+            group: None,
+        };
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains("new_alert"));
         assert!(json.contains("123"));
@@ -301,13 +456,26 @@ mod tests {
         let event = SseEvent::ChatMessage {
             alert_id: 123,
             message_id: 456,
+            group: None,
         };
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains("chat_message"));
         assert!(json.contains("123"));
         assert!(json.contains("456"));
 
-        let event = SseEvent::AlertDeleted { alert_id: 123 };
+        let event = SseEvent::AnalysisStatus {
+            alert_id: 123,
+            status: AnalysisStatus::Analyzing,
+            group: None,
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""type":"analysis_status""#));
+        assert!(json.contains(r#""status":"analyzing""#));
+
+        let event = SseEvent::AlertDeleted {
+            alert_id: 123,
+            group: None,
+        };
         let json = serde_json::to_string(&event).unwrap();
         assert!(json.contains("alert_deleted"));
         assert!(json.contains("123"));
@@ -325,7 +493,7 @@ mod tests {
         let json = r#"{"type":"new_alert","alert_id":123}"#;
         let event: SseEvent = serde_json::from_str(json).unwrap();
         match event {
-            SseEvent::NewAlert { alert_id } => assert_eq!(alert_id, 123),
+            SseEvent::NewAlert { alert_id, .. } => assert_eq!(alert_id, 123),
             _ => panic!("Wrong event type"),
         }
 
@@ -335,6 +503,7 @@ mod tests {
             SseEvent::ChatMessage {
                 alert_id,
                 message_id,
+                ..
             } => {
                 assert_eq!(alert_id, 123);
                 assert_eq!(message_id, 456);
@@ -343,6 +512,964 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_chat_stream_event_serialization() {
+        let event = ChatStreamEvent::ToolCall {
+            name: "ripestat.prefix-overview".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"tool_call","name":"ripestat.prefix-overview"}"#
+        );
+
+        let event = ChatStreamEvent::Token {
+            text: "AS65000".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"type":"token","text":"AS65000"}"#);
+
+        let event = ChatStreamEvent::Done {
+            message_id: 7,
+            response: "answer".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(r#""type":"done""#));
+        assert!(json.contains(r#""message_id":7"#));
+    }
+
+    // ========================================================================
+    // Alert API Tests
+    // ========================================================================
+
+    #[tokio::test]
+    async fn test_chat_stream_unknown_alert() {
+        let state = create_test_state().await;
+
+        let result = routes::alerts::chat_with_alert_stream(
+            State(state.clone()),
+            Path(999),
+            Json(routes::alerts::ChatRequest {
+                message: "What happened?".to_string(),
+            }),
+        )
+        .await;
+
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+        assert!(
+            db::get_chat_history(&state.db_pool, 999)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_alert_tool_calls() {
+        let state = create_test_state().await;
+        let alert_id = db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+        db::insert_tool_calls(
+            &state.db_pool,
+            alert_id,
+            None,
+            &[models::NewToolCall {
+                server_name: Some("ripestat".to_string()),
+                tool_name: "as_overview".to_string(),
+                arguments: r#"{"resource":"AS65000"}"#.to_string(),
+                result: None,
+                error: Some("timed out".to_string()),
+                latency_ms: 30000,
+                created_at: models::get_current_timestamp(),
+            }],
+        )
+        .await
+        .unwrap();
+
+        let Json(calls) =
+            routes::alerts::list_alert_tool_calls(State(state.clone()), Path(alert_id))
+                .await
+                .unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].tool_name, "as_overview");
+        assert_eq!(calls[0].error.as_deref(), Some("timed out"));
+
+        let result = routes::alerts::list_alert_tool_calls(State(state), Path(999)).await;
+        assert!(matches!(result, Err(StatusCode::NOT_FOUND)));
+    }
+
+    #[tokio::test]
+    async fn test_alert_lifecycle_endpoints() {
+        use routes::lifecycle::{UpdateAlertAssignee, UpdateAlertStatus};
+
+        let state = create_test_state().await;
+        let mut rx = state.tx.subscribe();
+        let alert_id = db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert {
+                group: Some("noc"),
+                ..models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter)
+            },
+            3,
+        )
+        .await
+        .unwrap();
+        let status = |status, resolution_notes: Option<&str>| UpdateAlertStatus {
+            status,
+            resolution_notes: resolution_notes.map(str::to_string),
+            changed_by: Some("alice".to_string()),
+            note: None,
+        };
+
+        let Json(lifecycle) = routes::lifecycle::update_alert_assignee(
+            State(state.clone()),
+            Path(alert_id),
+            Json(UpdateAlertAssignee {
+                assignee: Some(" alice ".to_string()),
+                changed_by: None,
+                note: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(lifecycle.assignee.as_deref(), Some("alice"));
+        let event: serde_json::Value =
+            serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert_eq!(
+            event,
+            json!({"type": "alert_assigned", "alert_id": alert_id, "assignee": "alice", "group": "noc"})
+        );
+
+        let Json(lifecycle) = routes::lifecycle::update_alert_status(
+            State(state.clone()),
+            Path(alert_id),
+            Json(status(models::AlertStatus::Acknowledged, None)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(lifecycle.status, models::AlertStatus::Acknowledged);
+        let event: serde_json::Value =
+            serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert_eq!(event["type"], "alert_status_changed");
+        assert_eq!(event["from_status"], "new");
+        assert_eq!(event["status"], "acknowledged");
+        assert_eq!(event["changed_by"], "alice");
+
+        // Only closed alerts can be reopened
+        let result = routes::lifecycle::update_alert_status(
+            State(state.clone()),
+            Path(alert_id),
+            Json(status(models::AlertStatus::New, None)),
+        )
+        .await;
+        assert!(matches!(result, Err((StatusCode::CONFLICT, _))));
+
+        let Json(lifecycle) = routes::lifecycle::update_alert_status(
+            State(state.clone()),
+            Path(alert_id),
+            Json(status(
+                models::AlertStatus::FalsePositive,
+                Some("Planned migration"),
+            )),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            lifecycle.resolution_notes.as_deref(),
+            Some("Planned migration")
+        );
+
+        let Json(detail) = routes::alerts::get_alert(State(state.clone()), Path(alert_id))
+            .await
+            .unwrap();
+        assert_eq!(detail.status, AlertStatus::FalsePositive);
+        assert_eq!(detail.assignee.as_deref(), Some("alice"));
+        let Json(alerts) = routes::alerts::list_alerts(
+            State(state.clone()),
+            Query(routes::alerts::ListAlertsQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(alerts.alerts[0]["status"], "false_positive");
+
+        let Json(history) =
+            routes::lifecycle::list_alert_history(State(state.clone()), Path(alert_id))
+                .await
+                .unwrap();
+        let transitions: Vec<_> = history
+            .iter()
+            .map(|change| (change.from_status, change.to_status))
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (models::AlertStatus::New, models::AlertStatus::New),
+                (models::AlertStatus::New, models::AlertStatus::Acknowledged),
+                (
+                    models::AlertStatus::Acknowledged,
+                    models::AlertStatus::FalsePositive
+                ),
+            ]
+        );
+
+        let result = routes::lifecycle::update_alert_status(
+            State(state.clone()),
+            Path(999),
+            Json(status(models::AlertStatus::Resolved, None)),
+        )
+        .await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+        let result = routes::lifecycle::list_alert_history(State(state), Path(999)).await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+    }
+
+    #[tokio::test]
+    async fn test_get_usage() {
+        let state = create_test_state().await;
+        let alert_id = db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+        db::insert_llm_usage(
+            &state.db_pool,
+            alert_id,
+            None,
+            &[models::NewLlmUsage {
+                model: "test-model".to_string(),
+                input_tokens: 1500,
+                output_tokens: 300,
+                turns: 2,
+                duration_ms: 2500,
+                created_at: models::get_current_timestamp(),
+            }],
+        )
+        .await
+        .unwrap();
+
+        let Json(report) = routes::usage::get_usage(
+            State(state.clone()),
+            Query(routes::usage::UsageQuery { days: Some(1) }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].alert_kind.as_deref(), Some("bgp_alerter"));
+        assert_eq!(report.total_input_tokens, 1500);
+        assert_eq!(report.unpriced_models, vec!["test-model"]);
+
+        let result = routes::usage::get_usage(
+            State(state),
+            Query(routes::usage::UsageQuery { days: Some(0) }),
+        )
+        .await;
+        assert!(matches!(result, Err(StatusCode::BAD_REQUEST)));
+    }
+
+    #[tokio::test]
+    async fn test_get_storage() {
+        let state = create_test_state().await;
+        db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+
+        let Json(report) = routes::storage::get_storage(State(state.clone()))
+            .await
+            .unwrap();
+        assert!(report.database_bytes > 0);
+        assert_eq!(report.tables[0].table, "alerts");
+        assert_eq!(report.tables[0].rows, 1);
+        // Nothing expires without a retention period
+        assert_eq!(report.next_purge_at, None);
+        assert_eq!(report.last_purge, None);
+
+        let mut state = state;
+        state.config = Arc::new(AppConfig {
+            retention_chat_days: Some(30),
+            ..(*state.config).clone()
+        });
+        let Json(report) = routes::storage::get_storage(State(state)).await.unwrap();
+        assert_eq!(report.policy.chat_days, Some(30));
+        assert_eq!(report.expired_chat_messages, 0);
+        assert!(report.next_purge_at.is_some());
+    }
+
+    /// Test state whose daily token budget is already used up
+    async fn create_over_budget_state() -> AppState {
+        let mut state = create_test_state().await;
+        state.config = Arc::new(AppConfig {
+            budget_daily_tokens: Some(1000),
+            ..(*state.config).clone()
+        });
+
+        let alert_id = db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(r#"{"message":"earlier"}"#, models::AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+        db::insert_llm_usage(
+            &state.db_pool,
+            alert_id,
+            None,
+            &[models::NewLlmUsage {
+                model: "test-model".to_string(),
+                input_tokens: 900,
+                output_tokens: 200,
+                turns: 1,
+                duration_ms: 1000,
+                created_at: models::get_current_timestamp(),
+            }],
+        )
+        .await
+        .unwrap();
+        // Drop the job queued for the earlier alert
+        db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap();
+
+        state
+    }
+
+    #[tokio::test]
+    async fn test_process_alert_skips_analysis_over_budget() {
+        let state = create_over_budget_state().await;
+        let mut rx = state.tx.subscribe();
+
+        let (status, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["analysis_queued"], false);
+        assert_eq!(body["budget_exceeded"], true);
+        assert_eq!(body["analysis_status"], "analysis_skipped_budget");
+
+        let alert_id = body["alert_id"].as_i64().unwrap();
+        let alert = db::get_alert_by_id(&state.db_pool, alert_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.analysis_status, AnalysisStatus::SkippedBudget);
+        assert!(
+            db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // The UI is warned once that the budget is exhausted
+        let event: SseEvent = serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert!(matches!(
+            event,
+            SseEvent::BudgetWarning {
+                period: BudgetPeriod::Day,
+                percent: 100,
+                ..
+            }
+        ));
+        let event: SseEvent = serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert!(matches!(event, SseEvent::NewAlert { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_repeat_analyzes_alert_skipped_over_budget() {
+        let mut state = create_over_budget_state().await;
+        let (_, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(body["analysis_status"], "analysis_skipped_budget");
+        let alert_id = body["alert_id"].as_i64().unwrap();
+
+        // The budget was raised; an identical repeat is not a material change
+        state.config = Arc::new(AppConfig {
+            budget_daily_tokens: None,
+            ..(*state.config).clone()
+        });
+        let (_, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(body["alert_id"], alert_id);
+        assert_eq!(body["analysis_queued"], true);
+
+        let alert = db::get_alert_by_id(&state.db_pool, alert_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.analysis_status, AnalysisStatus::Pending);
+        let job = db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.alert_id, alert_id);
+    }
+
+    #[tokio::test]
+    async fn test_chat_over_budget_is_rejected() {
+        let state = create_over_budget_state().await;
+        let alert_data = serde_json::to_string(&create_test_alert("10.1.0.0/16", "65000")).unwrap();
+        let alert_id = db::insert_unanalyzed_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(&alert_data, models::AlertKind::BgpAlerter),
+            AnalysisStatus::Done,
+        )
+        .await
+        .unwrap();
+
+        let result = routes::alerts::chat_with_alert(
+            State(state.clone()),
+            Path(alert_id),
+            Json(routes::alerts::ChatRequest {
+                message: "What happened?".to_string(),
+            }),
+        )
+        .await;
+
+        let Err((status, Json(body))) = result else {
+            panic!("Expected the chat to be rejected");
+        };
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(body["error"].as_str().unwrap().contains("budget"));
+        // The question is not stored when it cannot be answered
+        assert!(
+            db::get_chat_history(&state.db_pool, alert_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    fn create_test_alert(prefix: &str, asn: &str) -> BGPAlerterAlert {
+        BGPAlerterAlert {
+            message: format!("Possible hijack of {prefix}"),
+            description: "hijack".to_string(),
+            details: Details {
+                prefix: prefix.to_string(),
+                newprefix: None,
+                neworigin: Some("64512".to_string()),
+                summary: "Test summary".to_string(),
+                earliest: "2025-01-15T10:00:00Z".to_string(),
+                latest: "2025-01-15T10:30:00Z".to_string(),
+                kind: "hijack".to_string(),
+                asn: asn.to_string(),
+                paths: "3".to_string(),
+                peers: "2".to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_alert_queues_analysis() {
+        let state = create_test_state().await;
+        let mut rx = state.tx.subscribe();
+
+        let result = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await;
+
+        let (status, Json(body)) = result.unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["analysis_status"], "pending");
+        let alert_id = body["alert_id"].as_i64().unwrap();
+
+        // The alert is stored immediately, before any analysis has run
+        let alert = db::get_alert_by_id(&state.db_pool, alert_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.analysis_status, AnalysisStatus::Pending);
+        assert_eq!(alert.alert["details"]["prefix"], "10.1.0.0/16");
+
+        // The alert is routed to the group of the matched prefix, with its default severity
+        assert_eq!(alert.group.as_deref(), Some("test"));
+        assert_eq!(alert.severity, Some(models::Severity::Low));
+        let list = |group: &str| {
+            routes::alerts::list_alerts(
+                State(state.clone()),
+                Query(routes::alerts::ListAlertsQuery {
+                    group: Some(group.to_string()),
+                    ..Default::default()
+                }),
+            )
+        };
+        assert_eq!(list("test").await.unwrap().0.alerts.len(), 1);
+        assert!(list("peering").await.unwrap().0.alerts.is_empty());
+
+        // The monitored entry that made it relevant is returned and stored with the alert
+        assert_eq!(body["relevance"]["reason"], "covering_prefix");
+        assert_eq!(alert.alert["relevance"]["monitored"], "10.0.0.0/8");
+        assert_eq!(
+            alert.alert["relevance"]["prefix_info"]["asn"],
+            json!([65000, 65001])
+        );
+
+        // A job is waiting in the queue for the workers
+        let job = db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.alert_id, alert_id);
+        assert_eq!(job.max_attempts, 3);
+
+        let event: SseEvent = serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert!(matches!(
+            event,
+            SseEvent::NewAlert { alert_id: id, group: Some(ref group) } if id == alert_id && group == "test"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rpki_validation_endpoint_and_alert() {
+        let state = create_test_state().await;
+        let query = |prefix: &str, asn: &str| {
+            Query(routes::rpki::ValidateQuery {
+                prefix: prefix.to_string(),
+                asn: asn.to_string(),
+            })
+        };
+
+        let result =
+            routes::rpki::validate(State(state.clone()), query("10.1.0.0/16", "65000")).await;
+        assert!(matches!(result, Err(StatusCode::SERVICE_UNAVAILABLE)));
+
+        state.rpki.replace(rpki::VrpSet::new(
+            [rpki::Vrp {
+                prefix: "10.1.0.0/16".parse().unwrap(),
+                max_length: 16,
+                asn: 65000,
+            }],
+            "test",
+        ));
+        let Json(validation) =
+            routes::rpki::validate(State(state.clone()), query("10.1.0.0/16", "AS65000"))
+                .await
+                .unwrap();
+        assert_eq!(validation.state, rpki::RpkiState::Valid);
+        let result = routes::rpki::validate(State(state.clone()), query("10.1.0.0", "65000")).await;
+        assert!(matches!(result, Err(StatusCode::BAD_REQUEST)));
+
+        // The validation of the hijacking origin is stored with the alert
+        let (_, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+        let alert = db::get_alert_by_id(&state.db_pool, body["alert_id"].as_i64().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.alert["rpki"]["state"], "invalid");
+        assert_eq!(alert.alert["rpki"]["reason"], "as");
+        assert_eq!(alert.alert["rpki"]["asn"], 64512);
+    }
+
+    #[tokio::test]
+    async fn test_rpki_coverage_endpoint() {
+        let state = create_test_state().await;
+        let result = routes::rpki::get_coverage(State(state.clone())).await;
+        assert!(matches!(result, Err(StatusCode::SERVICE_UNAVAILABLE)));
+
+        state.rpki.replace(rpki::VrpSet::new(
+            [rpki::Vrp {
+                prefix: "10.0.0.0/8".parse().unwrap(),
+                max_length: 8,
+                asn: 65000,
+            }],
+            "test",
+        ));
+        let Json(report) = routes::rpki::get_coverage(State(state.clone()))
+            .await
+            .unwrap();
+        assert_eq!(report.vrp_source, "test");
+        // The ignored prefix is left out
+        let states: Vec<_> = report
+            .prefixes
+            .iter()
+            .map(|p| (p.prefix.as_str(), p.state))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("10.0.0.0/8", CoverageState::WrongOrigin),
+                ("172.16.0.0/12", CoverageState::NoRoa),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roa_coverage_changes_are_persisted() {
+        let state = create_test_state().await;
+        let vrps = |asn| {
+            rpki::VrpSet::new(
+                [rpki::Vrp {
+                    prefix: "172.16.0.0/12".parse().unwrap(),
+                    max_length: 12,
+                    asn,
+                }],
+                "test",
+            )
+        };
+        let alert_count = || async {
+            db::list_alerts(&state.db_pool, &Default::default())
+                .await
+                .unwrap()
+                .alerts
+                .len()
+        };
+
+        // The first check only sets the baseline
+        rpki::apply(&state, vrps(65099)).await;
+        assert_eq!(alert_count().await, 0);
+        let stored = db::get_roa_coverage(&state.db_pool).await.unwrap();
+        assert_eq!(stored["172.16.0.0/12"], "wrong_origin");
+
+        // Compared against the stored coverage, as after a restart
+        rpki::apply(&state, vrps(65002)).await;
+        assert_eq!(alert_count().await, 1);
+        rpki::apply(&state, vrps(65002)).await;
+        assert_eq!(alert_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pending_notifications_are_resumed() {
+        let (url, received) = crate::alerts::notify::webhook::tests::receiver().await;
+        let mut state = create_test_state().await;
+        state.groups = Arc::new(
+            GroupRegistry::from_str(&format!(
+                "channels:\n  noc-slack:\n    type: slack\n    url: {url}\n"
+            ))
+            .unwrap(),
+        );
+        let alert_id = db::insert_pending_alert(
+            &mut state.db_pool.acquire().await.unwrap(),
+            &models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+        let payload = json!({
+            "alert_id": alert_id,
+            "kind": "bgp_alerter",
+            "group": null,
+            "title": "Possible hijack of 203.0.113.0/24",
+            "resource": "203.0.113.0/24",
+            "severity": "High",
+            "summary": "AS64666 announces the prefix.",
+            "immediate_actions": ["Contact AS64666"],
+            "url": "http://localhost:7654/api/alerts/1"
+        })
+        .to_string();
+
+        // Left pending by a replica that stopped, so its leases ran out
+        for channel in ["noc-slack", "removed-channel"] {
+            let id = db::insert_notification(
+                &state.db_pool,
+                alert_id,
+                channel,
+                &payload,
+                "stopped-replica",
+                std::time::Duration::ZERO,
+            )
+            .await
+            .unwrap();
+            db::record_notification_attempt(
+                &state.db_pool,
+                id,
+                "stopped-replica",
+                models::DeliveryStatus::Pending,
+                Some("connection refused"),
+                std::time::Duration::ZERO,
+            )
+            .await
+            .unwrap();
+        }
+
+        notify::resume(&state).await.unwrap();
+        let mut deliveries = Vec::new();
+        for _ in 0..100 {
+            deliveries = db::list_notifications_for_alert(&state.db_pool, alert_id)
+                .await
+                .unwrap();
+            if deliveries
+                .iter()
+                .all(|d| d.status != models::DeliveryStatus::Pending)
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(deliveries[0].status, models::DeliveryStatus::Sent);
+        assert_eq!(deliveries[0].attempts, 2);
+        assert!(received.lock().unwrap()[0].1.contains("Contact AS64666"));
+        assert_eq!(deliveries[1].status, models::DeliveryStatus::Failed);
+        assert_eq!(
+            deliveries[1].last_error.as_deref(),
+            Some("channel is no longer configured")
+        );
+        assert!(
+            db::claim_stale_notifications(
+                &state.db_pool,
+                &state.replica_id,
+                std::time::Duration::ZERO
+            )
+            .await
+            .unwrap()
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefix_endpoints_update_relevance() {
+        let path = std::env::temp_dir().join(format!("agentnoc-api-{}.yml", std::process::id()));
+        std::fs::copy("prefixes.test.yml", &path).unwrap();
+        let mut state = create_test_state().await;
+        state.prefixes = Arc::new(PrefixesStore::load(&path).unwrap());
+
+        let (_, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("203.0.113.0/24", "64499")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(body["ignored"], true);
+
+        let payload: routes::prefixes::MonitoredPrefix = serde_json::from_value(json!({
+            "prefix": "203.0.113.0/24",
+            "description": "New customer",
+            "asn": [64499],
+            "ignoreMorespecifics": false,
+            "ignore": false,
+            "group": "noc"
+        }))
+        .unwrap();
+        let (status, Json(created)) =
+            routes::prefixes::create_prefix(State(state.clone()), Json(payload))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created.prefix, "203.0.113.0/24");
+
+        let prefix_path = |prefix: &str| {
+            Path(routes::prefixes::PrefixPath {
+                prefix: prefix.to_string(),
+            })
+        };
+        let Json(found) =
+            routes::prefixes::get_prefix(State(state.clone()), prefix_path("203.0.113.0/24"))
+                .await
+                .unwrap();
+        assert_eq!(found.info.description, "New customer");
+
+        // Alerts for the new prefix are relevant without a restart
+        let (status, Json(body)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("203.0.113.0/24", "64499")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert!(body["alert_id"].as_i64().is_some());
+
+        let payload: routes::prefixes::MonitoredAsn =
+            serde_json::from_value(json!({ "asn": "AS65000", "group": "noc" })).unwrap();
+        let result = routes::prefixes::create_asn(State(state.clone()), Json(payload)).await;
+        assert!(matches!(result, Err((StatusCode::CONFLICT, _))));
+
+        let result =
+            routes::prefixes::delete_prefix(State(state.clone()), prefix_path("198.51.100.0/24"))
+                .await;
+        assert!(matches!(result, Err((StatusCode::NOT_FOUND, _))));
+        let status =
+            routes::prefixes::delete_prefix(State(state.clone()), prefix_path("203.0.113.0/24"))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let Json(prefixes) = routes::prefixes::list_prefixes(State(state.clone())).await;
+        assert!(!prefixes.iter().any(|p| p.prefix == "203.0.113.0/24"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_process_alert_folds_repeats_into_incident() {
+        let state = create_test_state().await;
+
+        let (_, Json(first)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+        db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut rx = state.tx.subscribe();
+        let (status, Json(repeat)) = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "AS65000")),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(repeat["deduplicated"], true);
+        assert_eq!(repeat["analysis_queued"], false);
+        assert_eq!(repeat["alert_id"], first["alert_id"]);
+        assert_eq!(repeat["incident_id"], first["incident_id"]);
+        assert_eq!(repeat["occurrence_count"], 2);
+
+        // No second alert row and no second analysis
+        assert_eq!(
+            db::list_alerts(&state.db_pool, &Default::default())
+                .await
+                .unwrap()
+                .alerts
+                .len(),
+            1
+        );
+        assert!(
+            db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let event: SseEvent = serde_json::from_str(&rx.recv().await.unwrap().data).unwrap();
+        assert!(matches!(
+            event,
+            SseEvent::IncidentUpdated {
+                occurrence_count: 2,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_repeats_open_one_incident() {
+        let state = create_test_state().await;
+
+        let results = futures::future::join_all((0..8).map(|_| {
+            routes::alerts::process_alert(
+                State(state.clone()),
+                Json(create_test_alert("10.1.0.0/16", "65000")),
+            )
+        }))
+        .await;
+
+        let incident_ids: Vec<_> = results
+            .into_iter()
+            .map(|result| result.unwrap().1.0["incident_id"].clone())
+            .collect();
+        assert!(incident_ids.iter().all(|id| *id == incident_ids[0]));
+        let page = db::list_alerts(&state.db_pool, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(page.alerts.len(), 1);
+        let incident =
+            db::get_incident_by_alert_id(&state.db_pool, page.alerts[0]["id"].as_i64().unwrap())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(incident.occurrence_count, 8);
+    }
+
+    #[tokio::test]
+    async fn test_process_alert_reanalyzes_on_material_change() {
+        let state = create_test_state().await;
+
+        let _ = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("10.1.0.0/16", "65000")),
+        )
+        .await
+        .unwrap();
+        db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut spreading = create_test_alert("10.1.0.0/16", "65000");
+        spreading.details.peers = "20".to_string();
+        let (status, Json(body)) =
+            routes::alerts::process_alert(State(state.clone()), Json(spreading))
+                .await
+                .unwrap();
+
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["deduplicated"], true);
+        assert_eq!(body["analysis_queued"], true);
+
+        let alert_id = body["alert_id"].as_i64().unwrap();
+        let alert = db::get_alert_by_id(&state.db_pool, alert_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.analysis_status, AnalysisStatus::Pending);
+        assert_eq!(alert.alert["details"]["peers"], "20");
+        assert_eq!(alert.incident.as_ref().unwrap().peers, 20);
+        assert_eq!(alert.incident.as_ref().unwrap().analyzed_peers, 20);
+
+        let job = db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.alert_id, alert_id);
+    }
+
+    #[tokio::test]
+    async fn test_process_alert_ignores_irrelevant_alert() {
+        let state = create_test_state().await;
+
+        let result = routes::alerts::process_alert(
+            State(state.clone()),
+            Json(create_test_alert("198.51.100.0/24", "64999")),
+        )
+        .await;
+
+        let (status, Json(body)) = result.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["ignored"], true);
+
+        let alerts = db::list_alerts(&state.db_pool, &Default::default())
+            .await
+            .unwrap()
+            .alerts;
+        assert!(alerts.is_empty());
+        assert!(
+            db::claim_next_analysis_job(&state.db_pool, &state.replica_id, ANALYSIS_LEASE)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
     // ========================================================================
     // MCP Server API Tests
     // ========================================================================
@@ -616,4 +1743,72 @@ mod tests {
         assert_eq!(servers[0].name(), "alpha");
         assert_eq!(servers[1].name(), "beta");
     }
+
+    #[tokio::test]
+    async fn test_list_alerts_query() {
+        let state = create_test_state().await;
+        for _ in 0..3 {
+            db::insert_pending_alert(
+                &mut state.db_pool.acquire().await.unwrap(),
+                &models::NewAlert::new(r#"{"message":"test"}"#, models::AlertKind::BgpAlerter),
+                3,
+            )
+            .await
+            .unwrap();
+        }
+        let list = |query: routes::alerts::ListAlertsQuery| {
+            routes::alerts::list_alerts(State(state.clone()), Query(query))
+        };
+
+        let Json(page) = list(routes::alerts::ListAlertsQuery {
+            kind: Some("bgp_alerter, syslog".to_string()),
+            status: Some("new".to_string()),
+            since: Some("2000-01-01T02:00:00+02:00".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.alerts.len(), 2);
+        let Json(last) = list(routes::alerts::ListAlertsQuery {
+            cursor: page.next_cursor,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(last.alerts.len(), 1);
+        assert_eq!(last.next_cursor, None);
+
+        for query in [
+            routes::alerts::ListAlertsQuery {
+                kind: Some("fax".to_string()),
+                ..Default::default()
+            },
+            routes::alerts::ListAlertsQuery {
+                severity: Some("urgent".to_string()),
+                ..Default::default()
+            },
+            routes::alerts::ListAlertsQuery {
+                since: Some("yesterday".to_string()),
+                ..Default::default()
+            },
+            routes::alerts::ListAlertsQuery {
+                prefix: Some("10.0.0.0/33".to_string()),
+                ..Default::default()
+            },
+            routes::alerts::ListAlertsQuery {
+                asn: Some("AS-FOO".to_string()),
+                ..Default::default()
+            },
+            routes::alerts::ListAlertsQuery {
+                cursor: Some("garbage".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let (status, Json(body)) = list(query).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body["error"].is_string());
+        }
+    }
 }
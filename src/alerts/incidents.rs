@@ -0,0 +1,277 @@
+use chrono::{Duration, Utc};
+use color_eyre::Result;
+use ipnet::IpNet;
+
+use crate::alerts::sources::AlertEnvelope;
+use crate::config::{AppConfig, parse_asn};
+use crate::database::DbPool;
+use crate::database::db;
+use crate::database::models::{AnalysisStatus, Incident, NewAlert, NewIncident, Severity};
+
+/// What happened to an incoming alert after deduplication
+#[derive(Debug)]
+pub enum IncidentOutcome {
+    /// First alert with this fingerprint inside the window; analysis is queued unless over budget
+    New {
+        incident: Incident,
+        analysis_queued: bool,
+    },
+    /// Repeat of an open incident; analysis is queued again only on material change
+    Repeat {
+        incident: Incident,
+        reanalysis_queued: bool,
+    },
+}
+
+impl IncidentOutcome {
+    pub fn incident(&self) -> &Incident {
+        match self {
+            IncidentOutcome::New { incident, .. } => incident,
+            IncidentOutcome::Repeat { incident, .. } => incident,
+        }
+    }
+
+    /// Whether an analysis job was queued for this alert
+    pub fn analysis_queued(&self) -> bool {
+        match self {
+            IncidentOutcome::New {
+                analysis_queued, ..
+            } => *analysis_queued,
+            IncidentOutcome::Repeat {
+                reanalysis_queued, ..
+            } => *reanalysis_queued,
+        }
+    }
+}
+
+/// Build the deduplication fingerprint of an alert from its kind, prefix, ASN and new origin
+pub fn fingerprint(alert: &AlertEnvelope) -> String {
+    format!(
+        "{}|{}|{}|{}",
+        alert.category.trim().to_lowercase(),
+        normalize_prefix(&alert.resource),
+        alert.asn.as_deref().map(normalize_asn).unwrap_or_default(),
+        alert
+            .observed_asn
+            .as_deref()
+            .map(normalize_asn)
+            .unwrap_or_default()
+    )
+}
+
+fn normalize_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim();
+    prefix
+        .parse::<IpNet>()
+        .map(|net| net.trunc().to_string())
+        .unwrap_or_else(|_| prefix.to_lowercase())
+}
+
+fn normalize_asn(asn: &str) -> String {
+    parse_asn(asn)
+        .map(|asn| asn.to_string())
+        .unwrap_or_else(|_| asn.trim().to_string())
+}
+
+/// Parse a peer or path count reported by BGPAlerter, treating garbage as zero
+pub fn parse_count(value: &str) -> i64 {
+    value.trim().parse::<i64>().unwrap_or(0).max(0)
+}
+
+/// Whether a repeated alert differs enough from the analyzed one to warrant a new analysis
+pub fn is_material_change(incident: &Incident, alert: &AlertEnvelope, ratio: f64) -> bool {
+    let newprefix = alert.related_resource.as_deref().map(normalize_prefix);
+    if newprefix != incident.newprefix.as_deref().map(normalize_prefix) {
+        return true;
+    }
+
+    let grew = |analyzed: i64, current: i64| {
+        current > analyzed && (current - analyzed) as f64 >= analyzed.max(1) as f64 * ratio
+    };
+
+    grew(incident.analyzed_peers, alert.peers.max(incident.peers))
+        || grew(incident.analyzed_paths, alert.paths.max(incident.paths))
+}
+
+/// Store an alert, folding it into an open incident when its fingerprint was seen recently
+///
+/// With `analysis_allowed` unset (LLM budget exhausted) nothing is queued for analysis and
+/// new alerts are marked `analysis_skipped_budget`; the next repeat within budget queues them.
+/// New alerts get `severity`, their group's default, until the analysis assigns one.
+pub async fn record_alert(
+    pool: &DbPool,
+    config: &AppConfig,
+    alert: &AlertEnvelope,
+    alert_data: &str,
+    severity: Option<Severity>,
+    analysis_allowed: bool,
+) -> Result<IncidentOutcome> {
+    let fingerprint = fingerprint(alert);
+    let window = Duration::seconds(config.dedup_window_secs.min(i64::MAX as u64) as i64);
+    let since = (Utc::now() - window).to_rfc3339();
+    let peers = alert.peers;
+    let paths = alert.paths;
+
+    // Held until commit so that concurrent repeats cannot each open an incident
+    let mut tx = db::begin_incident_dedup(pool, &fingerprint).await?;
+
+    if let Some(open) = db::find_recent_incident(&mut tx, &fingerprint, &since).await? {
+        let incident = db::record_incident_occurrence(&mut tx, open.id, peers, paths).await?;
+
+        // An alert left unanalyzed (budget exhausted, or failed) is picked up by any repeat
+        let unanalyzed = matches!(
+            db::get_alert_analysis_status(&mut tx, incident.alert_id).await?,
+            Some(AnalysisStatus::SkippedBudget | AnalysisStatus::Failed)
+        );
+        let reanalysis_queued = analysis_allowed
+            && (unanalyzed
+                || is_material_change(&incident, alert, config.dedup_material_change_ratio));
+        if reanalysis_queued {
+            db::requeue_incident_analysis(
+                &mut tx,
+                &incident,
+                alert_data,
+                alert.related_resource.as_deref(),
+                config.analysis_max_attempts,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        return Ok(IncidentOutcome::Repeat {
+            incident,
+            reanalysis_queued,
+        });
+    }
+
+    let new_alert = NewAlert {
+        alert_data,
+        kind: alert.kind,
+        group: alert.group(),
+        severity,
+    };
+    let alert_id = if analysis_allowed {
+        db::insert_pending_alert(&mut tx, &new_alert, config.analysis_max_attempts).await?
+    } else {
+        db::insert_unanalyzed_alert(&mut tx, &new_alert, AnalysisStatus::SkippedBudget).await?
+    };
+
+    let new_incident = NewIncident {
+        fingerprint,
+        kind: alert.category.clone(),
+        prefix: alert.resource.clone(),
+        asn: alert.asn.clone().unwrap_or_default(),
+        neworigin: alert.observed_asn.clone(),
+        newprefix: alert.related_resource.clone(),
+        peers,
+        paths,
+    };
+    let incident = db::create_incident(&mut tx, &new_incident, alert_id).await?;
+    tx.commit().await?;
+
+    Ok(IncidentOutcome::New {
+        incident,
+        analysis_queued: analysis_allowed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::http::server::{BGPAlerterAlert, Details};
+    use crate::database::models::AlertKind;
+
+    fn alert(prefix: &str, asn: &str, neworigin: Option<&str>, peers: &str) -> AlertEnvelope {
+        BGPAlerterAlert {
+            message: "hijack".to_string(),
+            description: "Possible hijack".to_string(),
+            details: Details {
+                prefix: prefix.to_string(),
+                newprefix: None,
+                neworigin: neworigin.map(str::to_string),
+                summary: "summary".to_string(),
+                earliest: "2025-01-01T00:00:00Z".to_string(),
+                latest: "2025-01-01T00:05:00Z".to_string(),
+                kind: "hijack".to_string(),
+                asn: asn.to_string(),
+                paths: "4".to_string(),
+                peers: peers.to_string(),
+            },
+        }
+        .envelope(AlertKind::BgpAlerter)
+        .unwrap()
+    }
+
+    fn incident(analyzed_peers: i64, analyzed_paths: i64) -> Incident {
+        Incident {
+            id: 1,
+            fingerprint: "hijack|10.0.0.0/8|65000|65999".to_string(),
+            alert_id: 1,
+            kind: "hijack".to_string(),
+            prefix: "10.0.0.0/8".to_string(),
+            asn: "65000".to_string(),
+            neworigin: Some("65999".to_string()),
+            newprefix: None,
+            occurrence_count: 2,
+            first_seen: "2025-01-01T00:00:00+00:00".to_string(),
+            last_seen: "2025-01-01T00:05:00+00:00".to_string(),
+            peers: analyzed_peers,
+            paths: analyzed_paths,
+            analyzed_peers,
+            analyzed_paths,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_normalizes_prefix_and_asn() {
+        let a = alert("10.0.0.0/8", "65000", Some("AS65999"), "3");
+        let b = alert(" 10.1.2.3/8 ", "AS65000", Some("65999"), "10");
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_eq!(fingerprint(&a), "hijack|10.0.0.0/8|65000|65999");
+    }
+
+    #[test]
+    fn test_fingerprint_distinguishes_new_origin() {
+        let a = alert("10.0.0.0/8", "65000", Some("65999"), "3");
+        let b = alert("10.0.0.0/8", "65000", Some("65998"), "3");
+        let c = alert("10.0.0.0/8", "65000", None, "3");
+        assert_ne!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&c));
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("12"), 12);
+        assert_eq!(parse_count(" 7 "), 7);
+        assert_eq!(parse_count("many"), 0);
+        assert_eq!(parse_count("-3"), 0);
+    }
+
+    #[test]
+    fn test_small_growth_is_not_material() {
+        let incident = incident(10, 4);
+        assert!(!is_material_change(
+            &incident,
+            &alert("10.0.0.0/8", "65000", Some("65999"), "12"),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn test_peer_growth_is_material() {
+        let incident = incident(10, 4);
+        assert!(is_material_change(
+            &incident,
+            &alert("10.0.0.0/8", "65000", Some("65999"), "15"),
+            0.5
+        ));
+    }
+
+    #[test]
+    fn test_new_more_specific_is_material() {
+        let incident = incident(10, 4);
+        let mut repeat = alert("10.0.0.0/8", "65000", Some("65999"), "10");
+        repeat.related_resource = Some("10.1.0.0/16".to_string());
+        assert!(is_material_change(&incident, &repeat, 0.5));
+    }
+}
@@ -0,0 +1,80 @@
+use color_eyre::Result;
+
+use crate::alerts::budget;
+use crate::alerts::http::server::{AppState, SseEvent, SseMessage};
+use crate::alerts::incidents::{self, IncidentOutcome};
+use crate::alerts::sources::{self, AlertEnvelope};
+
+/// Result of feeding a relevant alert into the pipeline
+#[derive(Debug)]
+pub struct Ingested {
+    pub outcome: IncidentOutcome,
+    /// The LLM budget was exhausted, so no analysis was queued
+    pub budget_exceeded: bool,
+}
+
+/// Store a relevant alert with its RPKI validation and relevance match, queue its analysis and
+/// notify connected clients
+///
+/// Shared by every alert source so that deduplication, budgets and SSE updates behave the
+/// same regardless of where an alert came from. Sources that did not check relevance
+/// themselves, like RIS Live and ROA coverage, get the match looked up here.
+pub async fn ingest(state: &AppState, alert: &AlertEnvelope) -> Result<Ingested> {
+    let mut alert = alert.clone();
+    alert.rpki = state.rpki.validate_alert(&alert);
+    if alert.relevance.is_none() {
+        alert.relevance = sources::source_for(alert.kind).relevance(&alert, &state.prefixes.get());
+    }
+    let alert_data_json = serde_json::to_string(&alert.stored_payload())?;
+
+    // Store the alert without analysis once the LLM budget is exhausted
+    let budget_exceeded = budget::check_and_warn(state)
+        .await
+        .is_some_and(|status| status.exceeded());
+    if budget_exceeded {
+        tracing::warn!("LLM budget exhausted, alert will not be analyzed");
+    }
+
+    let group = alert.group().map(str::to_string);
+    let outcome = incidents::record_alert(
+        &state.db_pool,
+        &state.config,
+        &alert,
+        &alert_data_json,
+        state.groups.default_severity(group.as_deref()),
+        !budget_exceeded,
+    )
+    .await?;
+
+    let incident = outcome.incident();
+    let alert_id = incident.alert_id;
+
+    if outcome.analysis_queued() {
+        // Wake an analysis worker
+        state.analysis_notify.notify_one();
+    }
+
+    // Broadcast SSE notification
+    let event = match outcome {
+        IncidentOutcome::New { .. } => SseEvent::NewAlert { alert_id, group },
+        IncidentOutcome::Repeat { .. } => {
+            tracing::info!(
+                "Alert folded into incident {} (occurrence {})",
+                incident.id,
+                incident.occurrence_count
+            );
+            SseEvent::IncidentUpdated {
+                incident_id: incident.id,
+                alert_id,
+                occurrence_count: incident.occurrence_count,
+                group,
+            }
+        }
+    };
+    let _ = state.tx.send(SseMessage::from(&event));
+
+    Ok(Ingested {
+        outcome,
+        budget_exceeded,
+    })
+}
@@ -1 +1,11 @@
+pub mod analysis_queue;
+pub mod budget;
 pub mod http;
+pub mod incidents;
+pub mod ingest;
+pub mod notify;
+pub mod retention;
+pub mod ris_live;
+pub mod snmp;
+pub mod sources;
+pub mod syslog;
@@ -0,0 +1,152 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Deserialize;
+
+use super::{Notification, PermanentFailure};
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTls {
+    /// Upgrade a plain connection with STARTTLS (port 587)
+    #[default]
+    Starttls,
+    /// TLS from the start (port 465)
+    Tls,
+    /// No encryption, for local relays only
+    None,
+}
+
+/// Email sent through an SMTP relay
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    /// Defaults to the usual port of the TLS mode
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub tls: EmailTls,
+    /// SMTP user; no authentication without it
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Environment variable holding the SMTP password
+    #[serde(default)]
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl EmailConfig {
+    /// Check the addresses, so that a misconfigured channel is reported when groups.yml loads
+    pub fn validate(&self) -> Result<()> {
+        self.from
+            .parse::<Mailbox>()
+            .map_err(|e| eyre!("Invalid from address '{}': {}", self.from, e))?;
+        if self.to.is_empty() {
+            return Err(eyre!("No to address"));
+        }
+        for to in &self.to {
+            to.parse::<Mailbox>()
+                .map_err(|e| eyre!("Invalid to address '{}': {}", to, e))?;
+        }
+        Ok(())
+    }
+
+    /// Environment variable read for the SMTP password, when authenticating
+    pub fn password_var(&self) -> Option<&str> {
+        self.username.as_ref().and(self.password_env.as_deref())
+    }
+
+    fn message(&self, notification: &Notification) -> Result<Message> {
+        let mut builder = Message::builder()
+            .from(self.from.parse()?)
+            .subject(notification.subject())
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.parse()?);
+        }
+        Ok(builder.body(notification.text())?)
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        let mut builder = match self.tls {
+            EmailTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp_host)?
+            }
+            EmailTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.smtp_host)?,
+            EmailTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.smtp_host)
+            }
+        };
+        if let Some(port) = self.smtp_port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.username {
+            let password = match self.password_var() {
+                Some(var) => std::env::var(var).map_err(|_| {
+                    PermanentFailure(format!("SMTP password variable {var} is not set"))
+                })?,
+                None => String::new(),
+            };
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        Ok(builder.build())
+    }
+
+    pub async fn send(&self, notification: &Notification) -> Result<()> {
+        let message = self
+            .message(notification)
+            .map_err(|e| PermanentFailure(format!("Invalid email: {e}")))?;
+        self.transport()?.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::{AlertKind, Severity};
+
+    #[test]
+    fn test_message() {
+        let config: EmailConfig = serde_yaml::from_str(
+            "smtp_host: smtp.example.net\nfrom: AgentNOC <agentnoc@example.net>\nto: [noc@example.net, oncall@example.net]\n",
+        )
+        .unwrap();
+        assert_eq!(config.tls, EmailTls::Starttls);
+
+        let notification = Notification {
+            alert_id: 7,
+            kind: AlertKind::SnmpTrap,
+            group: None,
+            title: "linkDown on core1".to_string(),
+            resource: "core1".to_string(),
+            severity: Severity::Medium,
+            summary: "Uplink to transit is down.".to_string(),
+            immediate_actions: Vec::new(),
+            url: "http://localhost:7654/api/alerts/7".to_string(),
+        };
+        let message =
+            String::from_utf8(config.message(&notification).unwrap().formatted()).unwrap();
+        assert!(message.contains("Subject: [AgentNOC][Medium] linkDown on core1"));
+        assert!(message.contains("To: noc@example.net, oncall@example.net"));
+        assert!(message.contains("Uplink to transit is down."));
+
+        assert!(config.validate().is_ok());
+        let no_recipient = EmailConfig {
+            to: Vec::new(),
+            ..config.clone()
+        };
+        assert!(no_recipient.validate().is_err());
+        let invalid = EmailConfig {
+            from: "not an address".to_string(),
+            ..config
+        };
+        assert!(invalid.message(&notification).is_err());
+        assert!(invalid.validate().is_err());
+    }
+}
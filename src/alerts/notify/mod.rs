@@ -0,0 +1,665 @@
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::alerts::analysis_queue::retry_delay;
+use crate::alerts::http::server::AppState;
+use crate::alerts::sources::AlertEnvelope;
+use crate::database::DbPool;
+use crate::database::db;
+use crate::database::models::{AlertKind, DeliveryStatus, IncidentReport, Severity};
+use crate::groups::{DEFAULT_GROUP, GroupRegistry};
+
+pub mod email;
+pub mod webhook;
+
+use email::EmailConfig;
+use webhook::{SlackConfig, WebhookConfig};
+
+/// Delivery attempts per channel before a notification is marked failed
+const MAX_ATTEMPTS: i64 = 5;
+
+/// Base delay in seconds between two delivery attempts (doubled on every attempt)
+const RETRY_BASE_SECS: u64 = 10;
+
+/// Timeout of a single webhook request
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a replica holds a delivery for one attempt; the retry delay is added on top
+const DELIVERY_LEASE: Duration = Duration::from_secs(120);
+
+/// How often deliveries abandoned by a stopped replica are looked for
+const TAKEOVER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A delivery error that retrying cannot fix, such as a rejected request or a secret that
+/// is not set
+#[derive(Debug)]
+pub struct PermanentFailure(pub String);
+
+impl fmt::Display for PermanentFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PermanentFailure {}
+
+/// Where a channel delivers notifications
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelTarget {
+    /// JSON POST of the notification, optionally HMAC-signed
+    Webhook(WebhookConfig),
+    /// Slack or Mattermost incoming webhook
+    Slack(SlackConfig),
+    /// Plain text email over SMTP
+    Email(EmailConfig),
+}
+
+/// A notification channel of groups.yml
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ChannelConfig {
+    #[serde(flatten)]
+    pub target: ChannelTarget,
+    /// Only alerts analyzed at this severity or worse are sent
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    /// Only alerts of these groups are sent; every group when empty
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl ChannelConfig {
+    fn accepts(&self, group: &str, severity: Severity) -> bool {
+        self.min_severity
+            .is_none_or(|min_severity| severity.is_at_least(min_severity))
+            && (self.groups.is_empty() || self.groups.iter().any(|g| g == group))
+    }
+}
+
+/// Structured incident summary pushed to the channels once an alert is analyzed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub alert_id: i64,
+    pub kind: AlertKind,
+    pub group: Option<String>,
+    pub title: String,
+    pub resource: String,
+    pub severity: Severity,
+    pub summary: String,
+    pub immediate_actions: Vec<String>,
+    /// Link to the alert in the API
+    pub url: String,
+}
+
+impl Notification {
+    pub fn new(alert_id: i64, alert: &AlertEnvelope, report: &IncidentReport, url: String) -> Self {
+        Self {
+            alert_id,
+            kind: alert.kind,
+            group: alert.group().map(str::to_string),
+            title: alert.title.clone(),
+            resource: alert.resource.clone(),
+            severity: report.severity,
+            summary: report.summary.clone(),
+            immediate_actions: report.immediate_actions.clone(),
+            url,
+        }
+    }
+
+    /// One-line subject for emails and chat messages
+    pub fn subject(&self) -> String {
+        format!("[AgentNOC][{}] {}", self.severity.as_str(), self.title)
+    }
+
+    /// Plain text body for emails
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{}\n\nResource: {}\nSeverity: {}\n",
+            self.title,
+            self.resource,
+            self.severity.as_str()
+        );
+        if let Some(group) = &self.group {
+            text.push_str(&format!("Group: {group}\n"));
+        }
+        text.push_str(&format!("\n{}\n", self.summary));
+        if !self.immediate_actions.is_empty() {
+            text.push_str("\nImmediate actions:\n");
+            for action in &self.immediate_actions {
+                text.push_str(&format!("- {action}\n"));
+            }
+        }
+        text.push_str(&format!("\n{}\n", self.url));
+        text
+    }
+}
+
+impl ChannelTarget {
+    /// Check the parts of the channel that do not depend on the environment
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ChannelTarget::Email(config) => config.validate(),
+            ChannelTarget::Webhook(_) | ChannelTarget::Slack(_) => Ok(()),
+        }
+    }
+
+    /// Environment variables the channel reads its secrets from
+    pub fn secret_vars(&self) -> Vec<&str> {
+        match self {
+            ChannelTarget::Webhook(config) => config.secret_env.as_deref().into_iter().collect(),
+            ChannelTarget::Email(config) => config.password_var().into_iter().collect(),
+            ChannelTarget::Slack(_) => Vec::new(),
+        }
+    }
+
+    async fn send(&self, client: &reqwest::Client, notification: &Notification) -> Result<()> {
+        match self {
+            ChannelTarget::Webhook(config) => config.send(client, notification).await,
+            ChannelTarget::Slack(config) => config.send(client, notification).await,
+            ChannelTarget::Email(config) => config.send(notification).await,
+        }
+    }
+}
+
+/// Channels an analyzed alert of `group` is sent to
+pub fn targets<'a>(
+    groups: &'a GroupRegistry,
+    group: Option<&str>,
+    severity: Severity,
+) -> Vec<(&'a str, &'a ChannelConfig)> {
+    let group = group.unwrap_or(DEFAULT_GROUP);
+    let Some(config) = groups.get(group) else {
+        return Vec::new();
+    };
+    config
+        .channels
+        .iter()
+        .filter_map(|name| Some((name.as_str(), groups.channels.get(name)?)))
+        .filter(|(_, channel)| channel.accepts(group, severity))
+        .collect()
+}
+
+/// Send the analysis of an alert to the channels of its group
+///
+/// Each delivery is recorded and retried in the background, so a slow or unreachable
+/// channel never holds up the analysis workers.
+pub async fn dispatch(
+    state: &AppState,
+    alert_id: i64,
+    alert: &AlertEnvelope,
+    report: &IncidentReport,
+) {
+    let url = format!("{}/api/alerts/{}", state.config.public_url, alert_id);
+    let notification = Notification::new(alert_id, alert, report, url);
+    let payload = match serde_json::to_string(&notification) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::error!(
+                "Failed to serialize notification of alert {}: {}",
+                alert_id,
+                e
+            );
+            return;
+        }
+    };
+
+    for (name, channel) in targets(&state.groups, alert.group(), report.severity) {
+        let id = match db::insert_notification(
+            &state.db_pool,
+            alert_id,
+            name,
+            &payload,
+            &state.replica_id,
+            DELIVERY_LEASE,
+        )
+        .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to record notification of alert {} to {}: {}",
+                    alert_id,
+                    name,
+                    e
+                );
+                continue;
+            }
+        };
+        let delivery = Delivery {
+            id,
+            channel: name.to_string(),
+            target: channel.target.clone(),
+            notification: notification.clone(),
+            attempts: 0,
+        };
+        tokio::spawn(deliver(
+            state.db_pool.clone(),
+            state.replica_id.clone(),
+            delivery,
+            RETRY_BASE_SECS,
+        ));
+    }
+}
+
+/// Resume the pending deliveries of stopped replicas now, then keep looking for them
+pub async fn start(state: AppState) -> Result<()> {
+    resume(&state).await?;
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(TAKEOVER_INTERVAL).await;
+            if let Err(e) = resume(&state).await {
+                tracing::error!("Failed to resume pending notifications: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Take over the pending deliveries whose lease expired, because the replica delivering
+/// them stopped (or this process, before a restart)
+///
+/// Deliveries to channels no longer in groups.yml, or recorded without their
+/// notification, are marked failed.
+pub async fn resume(state: &AppState) -> Result<()> {
+    let pending =
+        db::claim_stale_notifications(&state.db_pool, &state.replica_id, DELIVERY_LEASE).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let count = pending.len();
+    let give_up = |id, error| give_up(&state.db_pool, &state.replica_id, id, error);
+    for delivery in pending {
+        let Some(channel) = state.groups.channels.get(&delivery.channel) else {
+            give_up(delivery.id, "channel is no longer configured".to_string()).await;
+            continue;
+        };
+        let notification = match delivery
+            .payload
+            .as_deref()
+            .map(serde_json::from_str::<Notification>)
+        {
+            Some(Ok(notification)) => notification,
+            Some(Err(e)) => {
+                give_up(delivery.id, format!("invalid notification: {e}")).await;
+                continue;
+            }
+            None => {
+                give_up(delivery.id, "notification was not stored".to_string()).await;
+                continue;
+            }
+        };
+        let delivery = Delivery {
+            id: delivery.id,
+            channel: delivery.channel,
+            target: channel.target.clone(),
+            notification,
+            attempts: delivery.attempts,
+        };
+        tokio::spawn(deliver(
+            state.db_pool.clone(),
+            state.replica_id.clone(),
+            delivery,
+            RETRY_BASE_SECS,
+        ));
+    }
+    tracing::warn!("Resumed {} interrupted notification delivery(ies)", count);
+
+    Ok(())
+}
+
+/// Mark a pending delivery failed without attempting it
+async fn give_up(pool: &DbPool, owner: &str, id: i64, error: String) {
+    tracing::warn!("Dropping pending notification {}: {}", id, error);
+    if let Err(e) = db::record_notification_attempt(
+        pool,
+        id,
+        owner,
+        DeliveryStatus::Failed,
+        Some(&error),
+        Duration::ZERO,
+    )
+    .await
+    {
+        tracing::error!("Failed to record notification {}: {}", id, e);
+    }
+}
+
+/// A notification to send to one channel
+struct Delivery {
+    id: i64,
+    channel: String,
+    target: ChannelTarget,
+    notification: Notification,
+    /// Attempts already made, when resuming a delivery
+    attempts: i64,
+}
+
+/// Send a notification until it succeeds or runs out of attempts, recording every attempt
+///
+/// A `PermanentFailure` fails the delivery right away. `owner` stops once another replica
+/// has taken the delivery over, and the delivery is then reported as still pending.
+async fn deliver(
+    pool: Arc<DbPool>,
+    owner: Arc<str>,
+    delivery: Delivery,
+    retry_base_secs: u64,
+) -> DeliveryStatus {
+    let Delivery {
+        id,
+        channel,
+        target,
+        notification,
+        attempts,
+    } = delivery;
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    for attempt in attempts + 1..=MAX_ATTEMPTS {
+        let result = target.send(&client, &notification).await;
+        let status = match &result {
+            Ok(()) => DeliveryStatus::Sent,
+            Err(e) if e.downcast_ref::<PermanentFailure>().is_some() => DeliveryStatus::Failed,
+            Err(_) if attempt == MAX_ATTEMPTS => DeliveryStatus::Failed,
+            Err(_) => DeliveryStatus::Pending,
+        };
+        let error = result.err().map(|e| e.to_string());
+        if let Some(error) = &error {
+            tracing::warn!(
+                "Notification of alert {} to {} failed (attempt {}/{}): {}",
+                notification.alert_id,
+                channel,
+                attempt,
+                MAX_ATTEMPTS,
+                error
+            );
+        }
+        // Held through the wait for the next attempt, and that attempt itself
+        let delay = retry_delay(retry_base_secs, attempt);
+        match db::record_notification_attempt(
+            &pool,
+            id,
+            &owner,
+            status,
+            error.as_deref(),
+            delay + DELIVERY_LEASE,
+        )
+        .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!(
+                    "Notification {} to {} was taken over by another replica",
+                    id,
+                    channel
+                );
+                return DeliveryStatus::Pending;
+            }
+            Err(e) => tracing::error!("Failed to record notification {}: {}", id, e),
+        }
+
+        if status != DeliveryStatus::Pending {
+            return status;
+        }
+        tokio::time::sleep(delay).await;
+    }
+    DeliveryStatus::Failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::NewAlert;
+
+    const GROUPS: &str = r#"
+channels:
+  noc-slack:
+    type: slack
+    url: https://hooks.slack.com/services/T000/B000/XXXX
+  noc-email:
+    type: email
+    smtp_host: smtp.example.net
+    from: agentnoc@example.net
+    to: [noc@example.net]
+    min_severity: high
+  customer-webhook:
+    type: webhook
+    url: https://tickets.example.net/hooks/agentnoc
+    secret_env: CUSTOMER_WEBHOOK_SECRET
+    groups: [customers]
+groups:
+  noc:
+    channels: [noc-slack, noc-email]
+  customers:
+    channels: [customer-webhook]
+  default:
+    channels: [noc-email, customer-webhook]
+"#;
+
+    fn names(targets: Vec<(&str, &ChannelConfig)>) -> Vec<String> {
+        targets
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    fn notification() -> Notification {
+        Notification {
+            alert_id: 7,
+            kind: AlertKind::BgpAlerter,
+            group: Some("noc".to_string()),
+            title: "Possible hijack of 203.0.113.0/24".to_string(),
+            resource: "203.0.113.0/24".to_string(),
+            severity: Severity::High,
+            summary: "AS64666 announces the prefix.".to_string(),
+            immediate_actions: vec!["Contact AS64666".to_string()],
+            url: "http://localhost:7654/api/alerts/7".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_targets_filter_by_severity_and_group() {
+        let groups = GroupRegistry::from_str(GROUPS).unwrap();
+
+        assert_eq!(
+            names(targets(&groups, Some("noc"), Severity::Critical)),
+            vec!["noc-slack", "noc-email"]
+        );
+        assert_eq!(
+            names(targets(&groups, Some("noc"), Severity::Medium)),
+            vec!["noc-slack"]
+        );
+        assert_eq!(
+            names(targets(&groups, Some("customers"), Severity::Info)),
+            vec!["customer-webhook"]
+        );
+
+        // Unlisted groups use the default group's channels, minus those limited to other groups
+        assert_eq!(
+            names(targets(&groups, Some("peering"), Severity::High)),
+            vec!["noc-email"]
+        );
+        assert!(targets(&groups, None, Severity::Low).is_empty());
+        assert!(targets(&GroupRegistry::default(), Some("noc"), Severity::Critical).is_empty());
+    }
+
+    #[test]
+    fn test_notification_text() {
+        let text = notification().text();
+        assert!(text.starts_with("Possible hijack of 203.0.113.0/24\n"));
+        assert!(text.contains("Severity: High\nGroup: noc\n"));
+        assert!(text.contains("Immediate actions:\n- Contact AS64666\n"));
+        assert!(text.ends_with("http://localhost:7654/api/alerts/7\n"));
+        assert_eq!(
+            notification().subject(),
+            "[AgentNOC][High] Possible hijack of 203.0.113.0/24"
+        );
+    }
+
+    const OWNER: &str = "test-replica";
+
+    fn delivery(id: i64, channel: &str, target: ChannelTarget) -> Delivery {
+        Delivery {
+            id,
+            channel: channel.to_string(),
+            target,
+            notification: notification(),
+            attempts: 0,
+        }
+    }
+
+    async fn create_alert(pool: &DbPool) -> i64 {
+        db::insert_pending_alert(
+            &mut pool.acquire().await.unwrap(),
+            &NewAlert::new(r#"{"message":"test"}"#, AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delivery_status_is_recorded() {
+        let pool = Arc::new(db::tests::create_test_db().await.unwrap());
+        let alert_id = create_alert(&pool).await;
+
+        let (url, received) = webhook::tests::receiver().await;
+        let target = ChannelTarget::Slack(SlackConfig { url });
+        let payload = serde_json::to_string(&notification()).unwrap();
+        let id = db::insert_notification(
+            &pool,
+            alert_id,
+            "noc-slack",
+            &payload,
+            OWNER,
+            DELIVERY_LEASE,
+        )
+        .await
+        .unwrap();
+        let status = deliver(
+            pool.clone(),
+            OWNER.into(),
+            delivery(id, "noc-slack", target),
+            0,
+        )
+        .await;
+        assert_eq!(status, DeliveryStatus::Sent);
+        assert!(received.lock().unwrap()[0].1.contains("Contact AS64666"));
+
+        // Nothing listens on the discard port, so every attempt fails
+        let target = ChannelTarget::Webhook(WebhookConfig {
+            url: "http://127.0.0.1:9/".to_string(),
+            secret_env: None,
+        });
+        let id =
+            db::insert_notification(&pool, alert_id, "broken", &payload, OWNER, DELIVERY_LEASE)
+                .await
+                .unwrap();
+        let status = deliver(
+            pool.clone(),
+            OWNER.into(),
+            delivery(id, "broken", target),
+            0,
+        )
+        .await;
+        assert_eq!(status, DeliveryStatus::Failed);
+
+        let deliveries = db::list_notifications_for_alert(&pool, alert_id)
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries[0].channel, "noc-slack");
+        assert_eq!(deliveries[0].status, DeliveryStatus::Sent);
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(deliveries[0].last_error, None);
+        assert_eq!(deliveries[1].status, DeliveryStatus::Failed);
+        assert_eq!(deliveries[1].attempts, MAX_ATTEMPTS);
+        assert!(deliveries[1].last_error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failure_is_not_retried() {
+        let pool = Arc::new(db::tests::create_test_db().await.unwrap());
+        let alert_id = create_alert(&pool).await;
+        let payload = serde_json::to_string(&notification()).unwrap();
+
+        // The receiver answers 404 outside of its hook
+        let (url, _) = webhook::tests::receiver().await;
+        let targets = [
+            ChannelTarget::Webhook(WebhookConfig {
+                url: format!("{url}/missing"),
+                secret_env: None,
+            }),
+            ChannelTarget::Webhook(WebhookConfig {
+                url,
+                secret_env: Some("AGENT_NOC_TEST_UNSET_SECRET".to_string()),
+            }),
+        ];
+        for target in targets {
+            let id = db::insert_notification(
+                &pool,
+                alert_id,
+                "rejected",
+                &payload,
+                OWNER,
+                DELIVERY_LEASE,
+            )
+            .await
+            .unwrap();
+            let status = deliver(
+                pool.clone(),
+                OWNER.into(),
+                delivery(id, "rejected", target),
+                3600,
+            )
+            .await;
+            assert_eq!(status, DeliveryStatus::Failed);
+        }
+
+        let deliveries = db::list_notifications_for_alert(&pool, alert_id)
+            .await
+            .unwrap();
+        assert!(deliveries.iter().all(|delivery| delivery.attempts == 1));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_stops_once_taken_over() {
+        let pool = Arc::new(db::tests::create_test_db().await.unwrap());
+        let alert_id = create_alert(&pool).await;
+        let payload = serde_json::to_string(&notification()).unwrap();
+
+        // The lease ran out, as if this replica had stalled, and another replica claimed it
+        let id =
+            db::insert_notification(&pool, alert_id, "broken", &payload, OWNER, Duration::ZERO)
+                .await
+                .unwrap();
+        let claimed = db::claim_stale_notifications(&pool, "other-replica", DELIVERY_LEASE)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+
+        let target = ChannelTarget::Webhook(WebhookConfig {
+            url: "http://127.0.0.1:9/".to_string(),
+            secret_env: None,
+        });
+        let status = deliver(
+            pool.clone(),
+            OWNER.into(),
+            delivery(id, "broken", target),
+            0,
+        )
+        .await;
+        assert_eq!(status, DeliveryStatus::Pending);
+
+        let deliveries = db::list_notifications_for_alert(&pool, alert_id)
+            .await
+            .unwrap();
+        assert_eq!(deliveries[0].status, DeliveryStatus::Pending);
+        assert_eq!(deliveries[0].attempts, 0);
+    }
+}
@@ -0,0 +1,207 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use super::{Notification, PermanentFailure};
+
+/// Header carrying the HMAC-SHA256 of the request body, as `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-AgentNOC-Signature";
+
+/// Generic webhook receiving the notification as JSON
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Environment variable holding the HMAC key; requests are unsigned without it
+    #[serde(default)]
+    pub secret_env: Option<String>,
+}
+
+/// Slack (or Mattermost) incoming webhook
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SlackConfig {
+    pub url: String,
+}
+
+/// Signature of `body` for the signature header
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+impl WebhookConfig {
+    pub async fn send(&self, client: &reqwest::Client, notification: &Notification) -> Result<()> {
+        let secret = match &self.secret_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| {
+                PermanentFailure(format!("Webhook secret variable {var} is not set"))
+            })?),
+            None => None,
+        };
+        post_json(
+            client,
+            &self.url,
+            serde_json::to_vec(notification)?,
+            secret.as_deref(),
+        )
+        .await
+    }
+}
+
+impl SlackConfig {
+    pub async fn send(&self, client: &reqwest::Client, notification: &Notification) -> Result<()> {
+        let body = json!({ "text": slack_text(notification) });
+        post_json(client, &self.url, serde_json::to_vec(&body)?, None).await
+    }
+}
+
+async fn post_json(
+    client: &reqwest::Client,
+    url: &str,
+    body: Vec<u8>,
+    secret: Option<&str>,
+) -> Result<()> {
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = secret {
+        request = request.header(SIGNATURE_HEADER, sign(secret.as_bytes(), &body));
+    }
+    let response = request.body(body).send().await?;
+    let status = response.status();
+    // Other client errors mean the request itself is refused, sending it again will not help
+    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(PermanentFailure(format!("{url} answered {status}")).into());
+    }
+    if !status.is_success() {
+        return Err(eyre!("{} answered {}", url, status));
+    }
+    Ok(())
+}
+
+/// Message in Slack mrkdwn, which Mattermost also renders
+fn slack_text(notification: &Notification) -> String {
+    let mut text = format!(
+        "*[{}] {}*\n{}",
+        notification.severity.as_str(),
+        notification.title,
+        notification.summary
+    );
+    if !notification.immediate_actions.is_empty() {
+        text.push_str("\n*Immediate actions:*");
+        for action in &notification.immediate_actions {
+            text.push_str(&format!("\n• {action}"));
+        }
+    }
+    text.push_str(&format!(
+        "\n<{}|View alert {}>",
+        notification.url, notification.alert_id
+    ));
+    text
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::database::models::{AlertKind, Severity};
+    use axum::Router;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    /// Requests received by a test webhook: signature header and body
+    pub type Received = Arc<Mutex<Vec<(Option<String>, String)>>>;
+
+    /// Start a webhook receiver on a local port and return its URL
+    pub async fn receiver() -> (String, Received) {
+        let received = Received::default();
+        let store = received.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: String| async move {
+                let signature = headers
+                    .get(SIGNATURE_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string);
+                store.lock().unwrap().push((signature, body));
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, received)
+    }
+
+    fn notification() -> Notification {
+        Notification {
+            alert_id: 7,
+            kind: AlertKind::BgpAlerter,
+            group: None,
+            title: "Possible hijack of 203.0.113.0/24".to_string(),
+            resource: "203.0.113.0/24".to_string(),
+            severity: Severity::Critical,
+            summary: "AS64666 announces the prefix.".to_string(),
+            immediate_actions: vec![
+                "Contact AS64666".to_string(),
+                "Filter the route".to_string(),
+            ],
+            url: "http://localhost:7654/api/alerts/7".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sign_matches_rfc_4231() {
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_slack_text() {
+        assert_eq!(
+            slack_text(&notification()),
+            "*[Critical] Possible hijack of 203.0.113.0/24*\n\
+             AS64666 announces the prefix.\n\
+             *Immediate actions:*\n\
+             • Contact AS64666\n\
+             • Filter the route\n\
+             <http://localhost:7654/api/alerts/7|View alert 7>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_is_signed() {
+        let (url, received) = receiver().await;
+        let client = reqwest::Client::new();
+        let body = serde_json::to_vec(&notification()).unwrap();
+        post_json(&client, &url, body.clone(), Some("s3cret"))
+            .await
+            .unwrap();
+        post_json(&client, &url, body.clone(), None).await.unwrap();
+
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received[0].0, Some(sign(b"s3cret", &body)));
+            assert_eq!(received[1].0, None);
+            let payload: serde_json::Value = serde_json::from_str(&received[0].1).unwrap();
+            assert_eq!(payload["severity"], "Critical");
+            assert_eq!(payload["immediate_actions"][1], "Filter the route");
+        }
+
+        let missing = WebhookConfig {
+            url: url.clone(),
+            secret_env: Some("AGENT_NOC_TEST_UNSET_SECRET".to_string()),
+        };
+        let err = missing.send(&client, &notification()).await.unwrap_err();
+        assert!(err.to_string().contains("AGENT_NOC_TEST_UNSET_SECRET"));
+        assert!(
+            post_json(&client, &format!("{url}/missing"), body, None)
+                .await
+                .is_err()
+        );
+    }
+}
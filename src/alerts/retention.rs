@@ -0,0 +1,601 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::Result;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+use crate::alerts::http::server::AppState;
+use crate::config::AppConfig;
+use crate::database::DbPool;
+use crate::database::db;
+use crate::database::models::{RetentionRun, TableUsage};
+
+/// Longest interval between two purges
+const MAX_INTERVAL_SECS: u64 = 366 * 24 * 3600;
+
+/// Records read and written at a time when archiving
+const ARCHIVE_BATCH: usize = 100;
+
+/// How long alerts and chat messages are kept, from the configuration
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RetentionPolicy {
+    /// Days resolved and false-positive alerts are kept after their last change
+    pub resolved_alert_days: Option<u64>,
+    /// Days chat messages are kept
+    pub chat_days: Option<u64>,
+    /// Directory expired records are exported to before they are deleted
+    pub archive_dir: Option<String>,
+    /// Seconds between two purges
+    pub interval_secs: u64,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            resolved_alert_days: config.retention_resolved_alert_days,
+            chat_days: config.retention_chat_days,
+            archive_dir: config.retention_archive_dir.clone(),
+            interval_secs: config.retention_interval_secs.max(1),
+        }
+    }
+
+    /// Whether anything ever expires
+    pub fn is_enabled(&self) -> bool {
+        self.resolved_alert_days.is_some() || self.chat_days.is_some()
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::seconds(self.interval_secs.min(MAX_INTERVAL_SECS) as i64)
+    }
+}
+
+/// Records changed before `days` ago are expired
+fn cutoff(days: Option<u64>, now: DateTime<Utc>) -> Option<String> {
+    let days = i64::try_from(days?).ok()?;
+    Some((now - Duration::try_days(days)?).to_rfc3339())
+}
+
+/// Records a purge at `now` would delete
+struct Expired {
+    /// Alerts last changed before this expire
+    alerts_before: Option<String>,
+    alert_ids: Vec<i64>,
+    /// Messages of the alerts that are kept; the others go with their alert
+    chat_message_ids: Vec<i64>,
+}
+
+impl Expired {
+    async fn find(pool: &DbPool, policy: &RetentionPolicy, now: DateTime<Utc>) -> Result<Self> {
+        let alerts_before = cutoff(policy.resolved_alert_days, now);
+        let alert_ids = match &alerts_before {
+            Some(before) => db::list_expired_alert_ids(pool, before).await?,
+            None => Vec::new(),
+        };
+        let chat_message_ids = match cutoff(policy.chat_days, now) {
+            Some(before) => {
+                let expired_alerts: HashSet<i64> = alert_ids.iter().copied().collect();
+                db::list_expired_chat_message_ids(pool, &before)
+                    .await?
+                    .into_iter()
+                    .filter(|(_, alert_id)| !expired_alerts.contains(alert_id))
+                    .map(|(id, _)| id)
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(Self {
+            alerts_before,
+            alert_ids,
+            chat_message_ids,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.alert_ids.is_empty() && self.chat_message_ids.is_empty()
+    }
+}
+
+/// One line of an archive file
+fn archive_row(kind: &str, id: i64, record: &impl Serialize) -> serde_json::Value {
+    serde_json::json!({ "type": kind, "id": id, "record": record })
+}
+
+/// Create a new archive file in `dir`, adding a counter to the name when an archive was
+/// already written within the same second
+fn create_archive_file(dir: &Path, now: DateTime<Utc>) -> std::io::Result<(PathBuf, File)> {
+    let stem = format!("agent_noc-{}", now.format("%Y%m%dT%H%M%SZ"));
+    let mut attempt = 0u32;
+    loop {
+        let path = match attempt {
+            0 => dir.join(format!("{stem}.jsonl.gz")),
+            n => dir.join(format!("{stem}-{n}.jsonl.gz")),
+        };
+        match File::create_new(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Export the expired records to a new gzipped JSONL file in `dir`, one record per line
+///
+/// Alerts are exported whole, with their chat messages, analysis and incident. Records are
+/// read and written `ARCHIVE_BATCH` at a time, and the file is synced to disk before
+/// returning, so the records can be deleted safely.
+async fn archive(
+    pool: &DbPool,
+    dir: &Path,
+    now: DateTime<Utc>,
+    expired: &Expired,
+) -> Result<PathBuf> {
+    let (batches, mut received) = mpsc::channel::<Vec<serde_json::Value>>(2);
+    let dir = dir.to_path_buf();
+    // Compressing and syncing would otherwise hold up the runtime's worker thread
+    let writer = tokio::task::spawn_blocking(move || -> Result<PathBuf> {
+        std::fs::create_dir_all(&dir)?;
+        let (path, file) = create_archive_file(&dir, now)?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        while let Some(rows) = received.blocking_recv() {
+            for row in &rows {
+                serde_json::to_writer(&mut encoder, row)?;
+                encoder.write_all(b"\n")?;
+            }
+        }
+
+        let file = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        Ok(path)
+    });
+
+    // A failed send means the writer stopped, its error is returned below
+    let exported: Result<()> = async {
+        for ids in expired.alert_ids.chunks(ARCHIVE_BATCH) {
+            let mut rows = Vec::with_capacity(ids.len());
+            for &id in ids {
+                // Alerts deleted in the meantime are simply not exported
+                if let Some(alert) = db::get_alert_by_id(pool, id).await? {
+                    rows.push(archive_row("alert", id, &alert));
+                }
+            }
+            if batches.send(rows).await.is_err() {
+                return Ok(());
+            }
+        }
+        for ids in expired.chat_message_ids.chunks(ARCHIVE_BATCH) {
+            // Messages deleted in the meantime are simply not exported
+            let rows = db::get_chat_messages_by_ids(pool, ids)
+                .await?
+                .iter()
+                .map(|message| archive_row("chat_message", message.id, message))
+                .collect();
+            if batches.send(rows).await.is_err() {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+    .await;
+    drop(batches);
+
+    let path = writer.await??;
+    if let Err(e) = exported {
+        // An incomplete archive must not pass for the export of the records left in place
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Archive then delete the expired records; returns the counts deleted and the archive
+async fn purge_expired(
+    pool: &DbPool,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<(u64, u64, Option<PathBuf>)> {
+    let expired = Expired::find(pool, policy, now).await?;
+    if expired.is_empty() {
+        return Ok((0, 0, None));
+    }
+
+    let archive_path = match &policy.archive_dir {
+        Some(dir) => Some(archive(pool, Path::new(dir), now, &expired).await?),
+        None => None,
+    };
+    let (alerts, chat_messages) = db::delete_expired(
+        pool,
+        &expired.alert_ids,
+        expired.alerts_before.as_deref(),
+        &expired.chat_message_ids,
+    )
+    .await?;
+    Ok((alerts, chat_messages, archive_path))
+}
+
+/// Purge the records expired at `now` and record the run
+///
+/// A failed export deletes nothing; the failure is recorded in the run and the records are
+/// retried at the next purge.
+pub async fn purge(
+    pool: &DbPool,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<RetentionRun> {
+    let started_at = now.to_rfc3339();
+    let result = purge_expired(pool, policy, now).await;
+    let finished_at = Utc::now().to_rfc3339();
+    let run = match result {
+        Ok((alerts, chat_messages, archive_path)) => RetentionRun {
+            started_at,
+            finished_at,
+            alerts_deleted: alerts as i64,
+            chat_messages_deleted: chat_messages as i64,
+            archive_path: archive_path.map(|path| path.display().to_string()),
+            error: None,
+        },
+        Err(e) => RetentionRun {
+            started_at,
+            finished_at,
+            alerts_deleted: 0,
+            chat_messages_deleted: 0,
+            archive_path: None,
+            error: Some(e.to_string()),
+        },
+    };
+    db::insert_retention_run(pool, &run).await?;
+    Ok(run)
+}
+
+/// When the next purge is due: an interval after the last one, by any replica sharing the
+/// database, or right away; `None` when nothing expires
+pub fn next_purge_at(
+    policy: &RetentionPolicy,
+    last: Option<&RetentionRun>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if !policy.is_enabled() {
+        return None;
+    }
+    let next = last
+        .and_then(|run| DateTime::parse_from_rfc3339(&run.started_at).ok())
+        .map(|started_at| started_at.with_timezone(&Utc) + policy.interval());
+    Some(next.map_or(now, |next| next.max(now)))
+}
+
+/// Response of the storage endpoint
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StorageReport {
+    /// Size of the database in bytes
+    pub database_bytes: i64,
+    pub tables: Vec<TableUsage>,
+    pub policy: RetentionPolicy,
+    /// Alerts already expired, deleted by the next purge
+    pub expired_alerts: i64,
+    /// Chat messages already expired, besides those of the expired alerts
+    pub expired_chat_messages: i64,
+    pub last_purge: Option<RetentionRun>,
+    /// When the next purge is due; `None` when nothing expires
+    pub next_purge_at: Option<String>,
+}
+
+/// Storage used, retention policy and state of the purges
+pub async fn report(pool: &DbPool, config: &AppConfig) -> Result<StorageReport> {
+    let now = Utc::now();
+    let policy = RetentionPolicy::from_config(config);
+    let (database_bytes, tables) = db::storage_usage(pool).await?;
+    let alerts_before = cutoff(policy.resolved_alert_days, now);
+    let expired_alerts = match &alerts_before {
+        Some(before) => db::count_expired_alerts(pool, before).await?,
+        None => 0,
+    };
+    let expired_chat_messages = match cutoff(policy.chat_days, now) {
+        Some(before) => {
+            db::count_expired_chat_messages(pool, &before, alerts_before.as_deref()).await?
+        }
+        None => 0,
+    };
+    let last_purge = db::get_last_retention_run(pool).await?;
+    let next_purge_at = next_purge_at(&policy, last_purge.as_ref(), now).map(|t| t.to_rfc3339());
+
+    Ok(StorageReport {
+        database_bytes,
+        tables,
+        expired_alerts,
+        expired_chat_messages,
+        policy,
+        last_purge,
+        next_purge_at,
+    })
+}
+
+/// Purge expired records in the background when a retention period is configured
+pub fn start(state: AppState) {
+    let policy = RetentionPolicy::from_config(&state.config);
+    if !policy.is_enabled() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let interval = policy.interval().to_std().unwrap_or_default();
+        loop {
+            let now = Utc::now();
+            let last = db::get_last_retention_run(&state.db_pool)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to get the last purge: {}", e);
+                    None
+                });
+            let next = next_purge_at(&policy, last.as_ref(), now).unwrap_or(now);
+            if next > now {
+                tokio::time::sleep((next - now).to_std().unwrap_or(interval)).await;
+                continue;
+            }
+
+            match purge(&state.db_pool, &policy, now).await {
+                Ok(RetentionRun {
+                    error: Some(error), ..
+                }) => tracing::error!("Purge of expired records failed: {}", error),
+                Ok(run) => tracing::info!(
+                    "Purged {} expired alerts and {} chat messages",
+                    run.alerts_deleted,
+                    run.chat_messages_deleted
+                ),
+                Err(e) => tracing::error!("Failed to record the purge: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db::tests::create_test_db;
+    use crate::database::models::{AlertKind, NewAlert};
+    use flate2::read::GzDecoder;
+    use std::io::{BufRead, BufReader};
+
+    const OLD: &str = "2025-01-01T00:00:00+00:00";
+
+    fn policy(archive_dir: Option<&Path>) -> RetentionPolicy {
+        RetentionPolicy {
+            resolved_alert_days: Some(90),
+            chat_days: Some(30),
+            archive_dir: archive_dir.map(|dir| dir.display().to_string()),
+            interval_secs: 3600,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("agent_noc_{}_{}", name, std::process::id()))
+    }
+
+    /// Alert in `status`, last changed at `updated_at`, with one chat message sent then
+    async fn insert_alert(pool: &DbPool, status: &str, updated_at: &str) -> (i64, i64) {
+        let id = db::insert_pending_alert(
+            &mut pool.acquire().await.unwrap(),
+            &NewAlert::new(r#"{"message":"test"}"#, AlertKind::BgpAlerter),
+            3,
+        )
+        .await
+        .unwrap();
+        let message_id = db::insert_chat_message(pool, id, "user", "Is it a leak?")
+            .await
+            .unwrap();
+        sqlx::query("UPDATE alerts SET status = $1, updated_at = $2 WHERE id = $3")
+            .bind(status)
+            .bind(updated_at)
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE chat_messages SET created_at = $1 WHERE id = $2")
+            .bind(updated_at)
+            .bind(message_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        (id, message_id)
+    }
+
+    async fn count(pool: &DbPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_purge_archives_then_deletes() {
+        let pool = create_test_db().await.unwrap();
+        let now = Utc::now();
+        let recent = (now - Duration::days(1)).to_rfc3339();
+        let (expired, _) = insert_alert(&pool, "resolved", OLD).await;
+        let (false_positive, _) = insert_alert(&pool, "false_positive", OLD).await;
+        // Kept: recently resolved, or still open; only the old chat message of the open one goes
+        insert_alert(&pool, "resolved", &recent).await;
+        let (open, old_message) = insert_alert(&pool, "investigating", OLD).await;
+
+        let dir = temp_dir("retention_archive");
+        let run = purge(&pool, &policy(Some(&dir)), now).await.unwrap();
+        assert_eq!(run.error, None);
+        assert_eq!((run.alerts_deleted, run.chat_messages_deleted), (2, 1));
+        assert_eq!(count(&pool, "alerts").await, 2);
+        assert_eq!(count(&pool, "chat_messages").await, 1);
+        assert!(db::get_alert_by_id(&pool, open).await.unwrap().is_some());
+
+        let path = PathBuf::from(run.archive_path.clone().unwrap());
+        let lines: Vec<serde_json::Value> =
+            BufReader::new(GzDecoder::new(std::fs::File::open(&path).unwrap()))
+                .lines()
+                .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+                .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "alert");
+        assert_eq!(lines[0]["id"], expired);
+        assert_eq!(
+            lines[0]["record"]["chat_messages"][0]["content"],
+            "Is it a leak?"
+        );
+        assert_eq!(lines[1]["id"], false_positive);
+        assert_eq!(lines[2]["type"], "chat_message");
+        assert_eq!(lines[2]["id"], old_message);
+        assert_eq!(lines[2]["record"]["content"], "Is it a leak?");
+
+        assert_eq!(db::get_last_retention_run(&pool).await.unwrap(), Some(run));
+        // Nothing left to purge
+        let run = purge(&pool, &policy(Some(&dir)), now).await.unwrap();
+        assert_eq!((run.alerts_deleted, run.archive_path), (0, None));
+    }
+
+    #[tokio::test]
+    async fn test_archives_within_the_same_second() {
+        let pool = create_test_db().await.unwrap();
+        let now = Utc::now();
+        let dir = temp_dir("retention_same_second");
+        let expired = Expired {
+            alerts_before: None,
+            alert_ids: Vec::new(),
+            chat_message_ids: Vec::new(),
+        };
+
+        let first = archive(&pool, &dir, now, &expired).await.unwrap();
+        let second = archive(&pool, &dir, now, &expired).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_failed_archive_deletes_nothing() {
+        let pool = create_test_db().await.unwrap();
+        insert_alert(&pool, "resolved", OLD).await;
+
+        // A file where the archive directory should be
+        let file = temp_dir("retention_not_a_dir");
+        std::fs::write(&file, b"").unwrap();
+        let run = purge(&pool, &policy(Some(&file)), Utc::now())
+            .await
+            .unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(run.error.is_some());
+        assert_eq!(run.alerts_deleted, 0);
+        assert_eq!(count(&pool, "alerts").await, 1);
+        assert_eq!(count(&pool, "chat_messages").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_purge_without_archive() {
+        let pool = create_test_db().await.unwrap();
+        insert_alert(&pool, "resolved", OLD).await;
+
+        let chat_only = RetentionPolicy {
+            resolved_alert_days: None,
+            ..policy(None)
+        };
+        let run = purge(&pool, &chat_only, Utc::now()).await.unwrap();
+        assert_eq!((run.alerts_deleted, run.chat_messages_deleted), (0, 1));
+        assert_eq!(run.archive_path, None);
+        assert_eq!(count(&pool, "alerts").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reopened_alert_is_kept() {
+        let pool = create_test_db().await.unwrap();
+        let now = Utc::now();
+        let (id, _) = insert_alert(&pool, "resolved", OLD).await;
+        let expired = Expired::find(&pool, &policy(None), now).await.unwrap();
+        assert_eq!(expired.alert_ids, vec![id]);
+
+        // Reopened between listing and deleting
+        sqlx::query("UPDATE alerts SET status = 'investigating' WHERE id = $1")
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let deleted = db::delete_expired(
+            &pool,
+            &expired.alert_ids,
+            expired.alerts_before.as_deref(),
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(deleted, (0, 0));
+        assert_eq!(count(&pool, "alerts").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_count_expired() {
+        let pool = create_test_db().await.unwrap();
+        let now = Utc::now();
+        insert_alert(&pool, "resolved", OLD).await;
+        insert_alert(&pool, "investigating", OLD).await;
+        insert_alert(&pool, "resolved", &(now - Duration::days(1)).to_rfc3339()).await;
+
+        let alerts_before = cutoff(Some(90), now).unwrap();
+        let chat_before = cutoff(Some(30), now).unwrap();
+        assert_eq!(
+            db::count_expired_alerts(&pool, &alerts_before)
+                .await
+                .unwrap(),
+            1
+        );
+        // The message of the expired alert goes with it
+        assert_eq!(
+            db::count_expired_chat_messages(&pool, &chat_before, Some(&alerts_before))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            db::count_expired_chat_messages(&pool, &chat_before, None)
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_next_purge_at() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T12:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let run = |started_at: &str| RetentionRun {
+            started_at: started_at.to_string(),
+            finished_at: started_at.to_string(),
+            alerts_deleted: 0,
+            chat_messages_deleted: 0,
+            archive_path: None,
+            error: None,
+        };
+        let policy = policy(None);
+
+        assert_eq!(next_purge_at(&policy, None, now), Some(now));
+        assert_eq!(
+            next_purge_at(&policy, Some(&run("2025-06-01T11:30:00+00:00")), now)
+                .unwrap()
+                .to_rfc3339(),
+            "2025-06-01T12:30:00+00:00"
+        );
+        // Overdue purges are due right away
+        assert_eq!(
+            next_purge_at(&policy, Some(&run("2025-05-01T00:00:00+00:00")), now),
+            Some(now)
+        );
+
+        let disabled = RetentionPolicy {
+            resolved_alert_days: None,
+            chat_days: None,
+            ..policy
+        };
+        assert_eq!(next_purge_at(&disabled, None, now), None);
+    }
+}
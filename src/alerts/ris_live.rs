@@ -0,0 +1,689 @@
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use futures::{SinkExt, StreamExt};
+use ipnet::IpNet;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::alerts::http::server::{AppState, BGPAlerterAlert, Details};
+use crate::alerts::ingest;
+use crate::config::{AppConfig, PrefixInfo, PrefixesConfig};
+use crate::database::models::AlertKind;
+use crate::prefixes::PrefixesStore;
+
+/// Delay before the first reconnect attempt
+const BASE_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound for the reconnect backoff
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Alerts waiting to be stored before the websocket reader is slowed down
+const ALERT_BUFFER: usize = 256;
+
+/// Message sent by RIS Live, e.g. `{"type": "ris_message", "data": {...}}`
+#[derive(Debug, Deserialize)]
+struct RisEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// A BGP UPDATE seen by a RIS peer
+#[derive(Debug, Deserialize)]
+pub struct RisUpdate {
+    /// Seconds since the epoch
+    pub timestamp: f64,
+    pub peer: String,
+    #[serde(default)]
+    pub peer_asn: String,
+    /// Route collector, e.g. `rrc00`
+    #[serde(default)]
+    pub host: String,
+    /// AS path; AS sets are nested arrays
+    #[serde(default)]
+    pub path: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub announcements: Vec<RisAnnouncement>,
+    #[serde(default)]
+    pub withdrawals: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RisAnnouncement {
+    pub prefixes: Vec<String>,
+}
+
+impl RisUpdate {
+    /// Origin ASNs of the path (several when the path ends in an AS set)
+    fn origins(&self) -> Vec<u32> {
+        let as_number = |value: &serde_json::Value| value.as_u64().map(|asn| asn as u32);
+        match self.path.last() {
+            Some(serde_json::Value::Array(set)) => set.iter().filter_map(as_number).collect(),
+            Some(value) => as_number(value).into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn path_string(&self) -> String {
+        self.path
+            .iter()
+            .map(|hop| match hop {
+                serde_json::Value::Array(set) => format!(
+                    "{{{}}}",
+                    set.iter()
+                        .map(|asn| asn.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                asn => asn.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn seen_at(&self) -> String {
+        rfc3339(self.timestamp)
+    }
+}
+
+/// Distinct RIS peers that recently withdrew each monitored prefix
+///
+/// A single peer withdrawing a prefix usually means its own session flapped, so withdrawals
+/// are only alerted on once `min_peers` peers withdrew the prefix within `window`, as
+/// BGPAlerter's `thresholdMinPeers`.
+#[derive(Debug)]
+pub struct WithdrawalTracker {
+    min_peers: usize,
+    window: Duration,
+    /// Monitored prefix, then peer address and time of its withdrawal in seconds since the epoch
+    withdrawn: HashMap<String, HashMap<String, f64>>,
+}
+
+impl WithdrawalTracker {
+    pub fn new(min_peers: usize, window: Duration) -> Self {
+        Self {
+            min_peers: min_peers.max(1),
+            window,
+            withdrawn: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self::new(
+            config.ris_live_withdrawal_min_peers,
+            Duration::from_secs(config.ris_live_withdrawal_window_secs),
+        )
+    }
+
+    /// Record that `peer` withdrew `monitored` at `timestamp`
+    ///
+    /// Returns the number of peers and the time of the first withdrawal once enough peers
+    /// withdrew the prefix; counting then starts over.
+    fn withdraw(&mut self, monitored: &str, peer: &str, timestamp: f64) -> Option<(usize, f64)> {
+        let since = timestamp - self.window.as_secs_f64();
+        let peers = self.withdrawn.entry(monitored.to_string()).or_default();
+        peers.retain(|_, withdrawn_at| *withdrawn_at >= since);
+        peers.insert(peer.to_string(), timestamp);
+        if peers.len() < self.min_peers {
+            return None;
+        }
+
+        let first = peers.values().copied().fold(timestamp, f64::min);
+        let count = peers.len();
+        self.withdrawn.remove(monitored);
+        Some((count, first))
+    }
+
+    /// Forget the withdrawal of `monitored` by `peer`, which announces it again
+    fn announce(&mut self, monitored: &str, peer: &str) {
+        if let Some(peers) = self.withdrawn.get_mut(monitored) {
+            peers.remove(peer);
+        }
+    }
+}
+
+/// `timestamp` in seconds since the epoch as RFC 3339
+fn rfc3339(timestamp: f64) -> String {
+    let secs = timestamp.trunc() as i64;
+    let nanos = (timestamp.fract() * 1e9) as u32;
+    DateTime::<Utc>::from_timestamp(secs, nanos)
+        .unwrap_or_else(Utc::now)
+        .to_rfc3339()
+}
+
+/// Subscription requests for every monitored prefix, including more-specifics
+fn subscriptions(prefixes: &PrefixesConfig) -> Vec<String> {
+    let mut monitored: Vec<&String> = prefixes
+        .prefixes
+        .iter()
+        .filter(|(_, info)| !info.ignore)
+        .map(|(prefix, _)| prefix)
+        .collect();
+    monitored.sort();
+
+    monitored
+        .into_iter()
+        .map(|prefix| {
+            serde_json::json!({
+                "type": "ris_subscribe",
+                "data": {
+                    "type": "UPDATE",
+                    "prefix": prefix,
+                    "moreSpecific": true
+                }
+            })
+            .to_string()
+        })
+        .collect()
+}
+
+/// The monitored prefix covering `prefix`, ignoring less-specific announcements
+fn covering_prefix<'a>(
+    prefixes: &'a PrefixesConfig,
+    prefix: &str,
+) -> Option<(&'a str, &'a PrefixInfo, bool)> {
+    let net = prefix.parse::<IpNet>().ok()?;
+    let (monitored, info) = prefixes.find_matching_prefix(prefix)?;
+    let monitored_net = monitored.parse::<IpNet>().ok()?;
+    if info.ignore || !monitored_net.contains(&net) {
+        return None;
+    }
+    Some((
+        monitored,
+        info,
+        net.prefix_len() > monitored_net.prefix_len(),
+    ))
+}
+
+fn expected_asns(info: &PrefixInfo) -> String {
+    info.asn
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Turn a RIS update into alerts for the monitored prefixes it affects
+///
+/// Detects announcements from an unexpected origin, new more-specifics of monitored
+/// prefixes and withdrawals of monitored prefixes by enough peers (see `WithdrawalTracker`).
+/// Alerts are shaped like BGPAlerter's so they share the deduplication and analysis pipeline.
+pub fn detect(
+    update: &RisUpdate,
+    prefixes: &PrefixesConfig,
+    withdrawals: &mut WithdrawalTracker,
+) -> Vec<BGPAlerterAlert> {
+    let seen_at = update.seen_at();
+    let origins = update.origins();
+    let path = update.path_string();
+    let observer = format!(
+        "peer {} (AS{}) on {}",
+        update.peer, update.peer_asn, update.host
+    );
+    let mut alerts = Vec::new();
+
+    let alert = |monitored: &str,
+                 info: &PrefixInfo,
+                 kind: &str,
+                 message: String,
+                 summary: String,
+                 newprefix: Option<&str>,
+                 neworigin: Option<String>| BGPAlerterAlert {
+        message: message.clone(),
+        description: info.description.clone(),
+        details: Details {
+            prefix: monitored.to_string(),
+            newprefix: newprefix.map(str::to_string),
+            neworigin,
+            summary: format!("{message}. {summary}"),
+            earliest: seen_at.clone(),
+            latest: seen_at.clone(),
+            kind: kind.to_string(),
+            asn: expected_asns(info),
+            paths: "1".to_string(),
+            peers: "1".to_string(),
+        },
+    };
+
+    for prefix in update
+        .announcements
+        .iter()
+        .flat_map(|announcement| &announcement.prefixes)
+    {
+        let Some((monitored, info, more_specific)) = covering_prefix(prefixes, prefix) else {
+            continue;
+        };
+        if !more_specific {
+            withdrawals.announce(monitored, &update.peer);
+        }
+        let newprefix = more_specific.then_some(prefix.as_str());
+
+        if !origins.is_empty() && !origins.iter().any(|origin| info.asn.contains(origin)) {
+            let origin = origins
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            alerts.push(alert(
+                monitored,
+                info,
+                "hijack",
+                format!(
+                    "Possible hijack of {monitored}: {prefix} announced by AS{origin} instead of AS{}",
+                    expected_asns(info)
+                ),
+                format!("Seen by {observer} with path {path}"),
+                newprefix,
+                Some(origin),
+            ));
+        } else if more_specific && !info.ignore_morespecifics {
+            alerts.push(alert(
+                monitored,
+                info,
+                "newprefix",
+                format!("New more-specific {prefix} of {monitored} announced"),
+                format!("Seen by {observer} with path {path}"),
+                newprefix,
+                None,
+            ));
+        }
+    }
+
+    for prefix in &update.withdrawals {
+        let Some((monitored, info, false)) = covering_prefix(prefixes, prefix) else {
+            continue;
+        };
+        let Some((peers, first)) = withdrawals.withdraw(monitored, &update.peer, update.timestamp)
+        else {
+            continue;
+        };
+        let mut alert = alert(
+            monitored,
+            info,
+            "visibility",
+            format!("{monitored} withdrawn by {peers} RIS peers"),
+            format!("Last withdrawal seen by {observer}"),
+            None,
+            None,
+        );
+        alert.details.earliest = rfc3339(first);
+        alert.details.peers = peers.to_string();
+        alerts.push(alert);
+    }
+
+    alerts
+}
+
+/// How a RIS Live session ended
+#[derive(Debug)]
+pub enum SessionOutcome {
+    /// The websocket could not be opened
+    Unreachable(color_eyre::Report),
+    /// The session was open, then closed, failed or was dropped to resubscribe
+    Ended(Result<()>),
+}
+
+/// Subscribe to RIS Live and forward detected alerts until the connection closes or the
+/// monitored prefixes change
+pub async fn run_session(
+    url: &str,
+    prefixes: &PrefixesStore,
+    alerts: &mpsc::Sender<BGPAlerterAlert>,
+    withdrawals: &mut WithdrawalTracker,
+) -> SessionOutcome {
+    let changes = prefixes.subscribe();
+    let ws = match tokio_tungstenite::connect_async(url).await {
+        Ok((ws, _)) => ws,
+        Err(e) => return SessionOutcome::Unreachable(e.into()),
+    };
+    tracing::info!("Connected to RIS Live at {}", url);
+
+    SessionOutcome::Ended(forward_updates(ws, prefixes, changes, alerts, withdrawals).await)
+}
+
+async fn forward_updates(
+    mut ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    prefixes: &PrefixesStore,
+    mut changes: watch::Receiver<Arc<PrefixesConfig>>,
+    alerts: &mpsc::Sender<BGPAlerterAlert>,
+    withdrawals: &mut WithdrawalTracker,
+) -> Result<()> {
+    for subscription in subscriptions(&prefixes.get()) {
+        ws.send(Message::text(subscription)).await?;
+    }
+
+    loop {
+        let message = tokio::select! {
+            message = ws.next() => message,
+            _ = changes.changed() => {
+                tracing::info!("Monitored prefixes changed, resubscribing to RIS Live");
+                return Ok(());
+            }
+        };
+        let Some(message) = message else {
+            break;
+        };
+        let text = match message? {
+            Message::Text(text) => text,
+            Message::Close(frame) => {
+                tracing::info!("RIS Live closed the connection: {:?}", frame);
+                break;
+            }
+            _ => continue,
+        };
+
+        let envelope: RisEnvelope = match serde_json::from_str(&text) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::warn!("Ignoring malformed RIS Live message: {}", e);
+                continue;
+            }
+        };
+
+        match envelope.kind.as_str() {
+            "ris_message" => {
+                let update: RisUpdate = match serde_json::from_value(envelope.data) {
+                    Ok(update) => update,
+                    Err(e) => {
+                        tracing::warn!("Ignoring malformed RIS Live update: {}", e);
+                        continue;
+                    }
+                };
+                for alert in detect(&update, &prefixes.get(), withdrawals) {
+                    alerts.send(alert).await?;
+                }
+            }
+            "ris_error" => tracing::warn!("RIS Live error: {}", envelope.data),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+async fn store(state: &AppState, alert: &BGPAlerterAlert) -> Result<()> {
+    ingest::ingest(state, &alert.envelope(AlertKind::RisLive)?).await?;
+    Ok(())
+}
+
+/// Consume RIS Live in the background when enabled, reconnecting with exponential backoff
+pub fn start(state: AppState) {
+    if !state.config.ris_live_enabled {
+        return;
+    }
+
+    // Several TLS backends are linked in, so rustls needs to be told which one to use
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let (tx, mut rx) = mpsc::channel(ALERT_BUFFER);
+
+    let ingest_state = state.clone();
+    tokio::spawn(async move {
+        while let Some(alert) = rx.recv().await {
+            if let Err(e) = store(&ingest_state, &alert).await {
+                tracing::error!("Failed to store RIS Live alert: {}", e);
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        // Kept across sessions, so that withdrawals around a reconnect still add up
+        let mut withdrawals = WithdrawalTracker::from_config(&state.config);
+        let mut delay = BASE_RECONNECT_DELAY;
+        loop {
+            let outcome = run_session(
+                &state.config.ris_live_url,
+                &state.prefixes,
+                &tx,
+                &mut withdrawals,
+            )
+            .await;
+            let unreachable = match outcome {
+                SessionOutcome::Unreachable(e) => {
+                    tracing::warn!("RIS Live connection failed: {}", e);
+                    true
+                }
+                SessionOutcome::Ended(result) => {
+                    if let Err(e) = result {
+                        tracing::warn!("RIS Live connection failed: {}", e);
+                    }
+                    delay = BASE_RECONNECT_DELAY;
+                    false
+                }
+            };
+            tracing::info!("Reconnecting to RIS Live in {}s", delay.as_secs());
+            tokio::time::sleep(delay).await;
+            // Only back off further while RIS Live cannot be reached at all
+            if unreachable {
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchReason;
+    use tokio::net::TcpListener;
+
+    const PREFIXES: &str = r#"
+10.1.0.0/16:
+  description: Test network
+  asn:
+    - 65000
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+10.2.0.0/16:
+  description: Anycast
+  asn:
+    - 65000
+  ignoreMorespecifics: true
+  ignore: false
+  group: noc
+"#;
+
+    fn update(path: serde_json::Value, announced: &[&str], withdrawn: &[&str]) -> RisUpdate {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": 1736935200.5,
+            "peer": "192.0.2.1",
+            "peer_asn": "64496",
+            "host": "rrc00",
+            "path": path,
+            "announcements": [{"next_hop": "192.0.2.1", "prefixes": announced}],
+            "withdrawals": withdrawn
+        }))
+        .unwrap()
+    }
+
+    fn withdrawal(peer: &str, timestamp: f64, withdrawn: &[&str]) -> RisUpdate {
+        RisUpdate {
+            peer: peer.to_string(),
+            timestamp,
+            ..update(serde_json::json!([]), &[], withdrawn)
+        }
+    }
+
+    fn tracker() -> WithdrawalTracker {
+        WithdrawalTracker::new(1, Duration::from_secs(300))
+    }
+
+    #[test]
+    fn test_detect_origin_change() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let alerts = detect(
+            &update(serde_json::json!([64496, 64512]), &["10.1.0.0/16"], &[]),
+            &prefixes,
+            &mut tracker(),
+        );
+
+        assert_eq!(alerts.len(), 1);
+        let details = &alerts[0].details;
+        assert_eq!(details.kind, "hijack");
+        assert_eq!(details.prefix, "10.1.0.0/16");
+        assert_eq!(details.newprefix, None);
+        assert_eq!(details.neworigin.as_deref(), Some("64512"));
+        assert_eq!(details.asn, "65000");
+        assert_eq!(details.earliest, "2025-01-15T10:00:00.500+00:00");
+        assert!(details.summary.contains("rrc00"));
+        let relevance = prefixes
+            .alert_relevance(&alerts[0].envelope(AlertKind::RisLive).unwrap())
+            .unwrap();
+        assert_eq!(relevance.reason, MatchReason::ExactPrefix);
+    }
+
+    #[test]
+    fn test_detect_more_specifics() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let alerts = detect(
+            &update(
+                serde_json::json!([64496, [65000, 65001]]),
+                &["10.1.1.0/24", "10.2.1.0/24", "10.1.0.0/16"],
+                &[],
+            ),
+            &prefixes,
+            &mut tracker(),
+        );
+
+        // Legitimate origin: only the more-specific of the prefix that does not ignore them
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].details.kind, "newprefix");
+        assert_eq!(alerts[0].details.prefix, "10.1.0.0/16");
+        assert_eq!(alerts[0].details.newprefix.as_deref(), Some("10.1.1.0/24"));
+    }
+
+    #[test]
+    fn test_detect_withdrawals() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let alerts = detect(
+            &update(
+                serde_json::json!([]),
+                &[],
+                &["10.1.0.0/16", "10.1.5.0/24", "192.0.2.0/24"],
+            ),
+            &prefixes,
+            &mut tracker(),
+        );
+
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].details.kind, "visibility");
+        assert_eq!(alerts[0].details.prefix, "10.1.0.0/16");
+    }
+
+    #[test]
+    fn test_withdrawals_need_enough_peers() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let mut tracker = WithdrawalTracker::new(2, Duration::from_secs(300));
+        let mut withdraw = |peer: &str, timestamp: f64| {
+            detect(
+                &withdrawal(peer, timestamp, &["10.1.0.0/16"]),
+                &prefixes,
+                &mut tracker,
+            )
+        };
+
+        // One flapping peer is not enough, however often it withdraws
+        assert!(withdraw("192.0.2.1", 1736935200.0).is_empty());
+        assert!(withdraw("192.0.2.1", 1736935260.0).is_empty());
+        let alerts = withdraw("198.51.100.1", 1736935290.0);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].details.kind, "visibility");
+        assert_eq!(alerts[0].details.peers, "2");
+        assert_eq!(alerts[0].details.earliest, "2025-01-15T10:01:00+00:00");
+
+        // Counting starts over, and withdrawals outside the window do not add up
+        assert!(withdraw("192.0.2.1", 1736935300.0).is_empty());
+        assert!(withdraw("198.51.100.1", 1736935700.0).is_empty());
+
+        // A peer announcing the prefix again no longer counts
+        let announced = RisUpdate {
+            peer: "198.51.100.1".to_string(),
+            ..update(serde_json::json!([64496, 65000]), &["10.1.0.0/16"], &[])
+        };
+        assert!(detect(&announced, &prefixes, &mut tracker).is_empty());
+        assert!(
+            detect(
+                &withdrawal("203.0.113.1", 1736935710.0, &["10.1.0.0/16"]),
+                &prefixes,
+                &mut tracker
+            )
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_against_mock_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let mut subscribed = Vec::new();
+            for _ in 0..2 {
+                let Some(Ok(Message::Text(text))) = ws.next().await else {
+                    panic!("expected a subscription");
+                };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(request["type"], "ris_subscribe");
+                subscribed.push(request["data"]["prefix"].as_str().unwrap().to_string());
+            }
+
+            let messages = [
+                serde_json::json!({"type": "ris_error", "data": {"message": "test"}}),
+                serde_json::json!({"type": "ris_message", "data": {
+                    "timestamp": 1736935200.0,
+                    "peer": "192.0.2.1",
+                    "peer_asn": "64496",
+                    "host": "rrc21",
+                    "type": "UPDATE",
+                    "path": [64496, 64512],
+                    "announcements": [{"next_hop": "192.0.2.1", "prefixes": ["10.2.0.0/16"]}]
+                }}),
+            ];
+            for message in messages {
+                ws.send(Message::text(message.to_string())).await.unwrap();
+            }
+            ws.close(None).await.unwrap();
+            subscribed
+        });
+
+        let prefixes =
+            PrefixesStore::new("prefixes.yml", PrefixesConfig::from_str(PREFIXES).unwrap());
+        let (tx, mut rx) = mpsc::channel(8);
+        let outcome = run_session(&url, &prefixes, &tx, &mut tracker()).await;
+        assert!(matches!(outcome, SessionOutcome::Ended(Ok(()))));
+
+        assert_eq!(
+            server.await.unwrap(),
+            vec!["10.1.0.0/16".to_string(), "10.2.0.0/16".to_string()]
+        );
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.details.kind, "hijack");
+        assert_eq!(alert.details.prefix, "10.2.0.0/16");
+        assert_eq!(alert.details.neworigin.as_deref(), Some("64512"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_reports_failed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let prefixes =
+            PrefixesStore::new("prefixes.yml", PrefixesConfig::from_str(PREFIXES).unwrap());
+        let (tx, _rx) = mpsc::channel(8);
+        let outcome = run_session(&url, &prefixes, &tx, &mut tracker()).await;
+        assert!(matches!(outcome, SessionOutcome::Unreachable(_)));
+    }
+}
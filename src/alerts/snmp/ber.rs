@@ -0,0 +1,393 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+pub const IP_ADDRESS: u8 = 0x40;
+pub const COUNTER32: u8 = 0x41;
+pub const GAUGE32: u8 = 0x42;
+pub const TIMETICKS: u8 = 0x43;
+pub const OPAQUE: u8 = 0x44;
+pub const COUNTER64: u8 = 0x46;
+pub const NO_SUCH_OBJECT: u8 = 0x80;
+pub const NO_SUCH_INSTANCE: u8 = 0x81;
+pub const END_OF_MIB_VIEW: u8 = 0x82;
+
+/// Reads consecutive BER elements from a buffer
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Next element as its tag and contents
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8])> {
+        let truncated = || eyre!("Truncated BER element");
+        let (&tag, rest) = self.buf.split_first().ok_or_else(truncated)?;
+        let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+
+        let len = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let octets = (first & 0x7f) as usize;
+            if octets == 0 || octets > 4 || rest.len() < octets {
+                return Err(eyre!("Unsupported BER length encoding"));
+            }
+            let (len, after) = rest.split_at(octets);
+            rest = after;
+            len.iter().fold(0usize, |len, b| (len << 8) | *b as usize)
+        };
+
+        if rest.len() < len {
+            return Err(truncated());
+        }
+        let (contents, rest) = rest.split_at(len);
+        self.buf = rest;
+        Ok((tag, contents))
+    }
+
+    /// Contents of the next element, which must have the given tag
+    pub fn read(&mut self, expected: u8) -> Result<&'a [u8]> {
+        let (tag, contents) = self.read_any()?;
+        if tag != expected {
+            return Err(eyre!(
+                "Expected BER tag {:#04x}, found {:#04x}",
+                expected,
+                tag
+            ));
+        }
+        Ok(contents)
+    }
+
+    pub fn read_integer(&mut self) -> Result<i64> {
+        decode_integer(self.read(INTEGER)?)
+    }
+
+    pub fn read_octets(&mut self) -> Result<&'a [u8]> {
+        self.read(OCTET_STRING)
+    }
+
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>> {
+        Ok(Reader::new(self.read(SEQUENCE)?))
+    }
+}
+
+fn decode_integer(contents: &[u8]) -> Result<i64> {
+    if contents.is_empty() || contents.len() > 8 {
+        return Err(eyre!("Invalid BER integer length {}", contents.len()));
+    }
+    let sign = if contents[0] & 0x80 != 0 { -1 } else { 0 };
+    Ok(contents
+        .iter()
+        .fold(sign, |value, b| (value << 8) | *b as i64))
+}
+
+fn decode_unsigned(contents: &[u8]) -> Result<u64> {
+    // A leading zero octet keeps the high bit of 64-bit values from reading as a sign
+    let contents = match contents {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => contents,
+    };
+    if contents.is_empty() || contents.len() > 8 {
+        return Err(eyre!("Invalid BER unsigned length {}", contents.len()));
+    }
+    Ok(contents.iter().fold(0, |value, b| (value << 8) | *b as u64))
+}
+
+fn decode_u32(contents: &[u8]) -> Result<u32> {
+    u32::try_from(decode_unsigned(contents)?).map_err(|_| eyre!("BER value exceeds 32 bits"))
+}
+
+/// An object identifier, e.g. `1.3.6.1.6.3.1.1.5.3`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oid(pub Vec<u32>);
+
+impl Oid {
+    pub fn decode(contents: &[u8]) -> Result<Self> {
+        let (&first, rest) = contents
+            .split_first()
+            .ok_or_else(|| eyre!("Empty object identifier"))?;
+        let (x, y) = match first {
+            0..=39 => (0, first),
+            40..=79 => (1, first - 40),
+            _ => (2, first - 80),
+        };
+        let mut arcs = vec![x, y as u32];
+
+        let mut arc: u32 = 0;
+        for (i, b) in rest.iter().enumerate() {
+            arc = arc
+                .checked_mul(128)
+                .ok_or_else(|| eyre!("Object identifier arc overflows"))?
+                | (b & 0x7f) as u32;
+            if b & 0x80 == 0 {
+                arcs.push(arc);
+                arc = 0;
+            } else if i == rest.len() - 1 {
+                return Err(eyre!("Truncated object identifier"));
+            }
+        }
+
+        Ok(Self(arcs))
+    }
+
+    /// The arcs following `prefix`, if this OID lies under it
+    pub fn suffix(&self, prefix: &[u32]) -> Option<&[u32]> {
+        self.0.strip_prefix(prefix)
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arcs: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        f.write_str(&arcs.join("."))
+    }
+}
+
+/// Value of a variable binding
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Oid),
+    IpAddress(Ipv4Addr),
+    Counter32(u32),
+    Gauge32(u32),
+    TimeTicks(u32),
+    Opaque(Vec<u8>),
+    Counter64(u64),
+    NoSuchObject,
+    NoSuchInstance,
+    EndOfMibView,
+}
+
+impl Value {
+    pub fn decode(tag: u8, contents: &[u8]) -> Result<Self> {
+        Ok(match tag {
+            INTEGER => Value::Integer(decode_integer(contents)?),
+            OCTET_STRING => Value::OctetString(contents.to_vec()),
+            NULL => Value::Null,
+            OBJECT_IDENTIFIER => Value::Oid(Oid::decode(contents)?),
+            IP_ADDRESS => {
+                let octets: [u8; 4] = contents
+                    .try_into()
+                    .map_err(|_| eyre!("Invalid IpAddress length {}", contents.len()))?;
+                Value::IpAddress(Ipv4Addr::from(octets))
+            }
+            COUNTER32 => Value::Counter32(decode_u32(contents)?),
+            GAUGE32 => Value::Gauge32(decode_u32(contents)?),
+            TIMETICKS => Value::TimeTicks(decode_u32(contents)?),
+            OPAQUE => Value::Opaque(contents.to_vec()),
+            COUNTER64 => Value::Counter64(decode_unsigned(contents)?),
+            NO_SUCH_OBJECT => Value::NoSuchObject,
+            NO_SUCH_INSTANCE => Value::NoSuchInstance,
+            END_OF_MIB_VIEW => Value::EndOfMibView,
+            other => return Err(eyre!("Unsupported SNMP value type {:#04x}", other)),
+        })
+    }
+
+    /// Numeric value of integer-like types
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            Value::Counter32(v) | Value::Gauge32(v) | Value::TimeTicks(v) => Some(*v as i64),
+            Value::Counter64(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    /// Printable contents of an octet string
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Value::OctetString(bytes) => std::str::from_utf8(bytes)
+                .ok()
+                .map(|s| s.trim_end_matches('\0'))
+                .filter(|s| s.chars().all(|c| !c.is_control() || c.is_whitespace()))
+                .map(str::to_string),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(v) => write!(f, "{v}"),
+            Value::OctetString(bytes) | Value::Opaque(bytes) => match self.as_text() {
+                Some(text) => f.write_str(&text),
+                None => write!(f, "0x{}", hex::encode(bytes)),
+            },
+            Value::Null => f.write_str("null"),
+            Value::Oid(oid) => write!(f, "{oid}"),
+            Value::IpAddress(ip) => write!(f, "{ip}"),
+            Value::Counter32(v) | Value::Gauge32(v) | Value::TimeTicks(v) => write!(f, "{v}"),
+            Value::Counter64(v) => write!(f, "{v}"),
+            Value::NoSuchObject => f.write_str("noSuchObject"),
+            Value::NoSuchInstance => f.write_str("noSuchInstance"),
+            Value::EndOfMibView => f.write_str("endOfMibView"),
+        }
+    }
+}
+
+/// BER encoding of the subset needed to craft trap packets in tests
+#[cfg(test)]
+pub mod encode {
+    use super::*;
+
+    pub fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => out.push(len as u8),
+            len @ 0x80..=0xff => out.extend([0x81, len as u8]),
+            len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(contents);
+        out
+    }
+
+    pub fn integer(value: i64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let mut start = 0;
+        while start < 7
+            && ((bytes[start] == 0 && bytes[start + 1] & 0x80 == 0)
+                || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+        {
+            start += 1;
+        }
+        tlv(INTEGER, &bytes[start..])
+    }
+
+    pub fn unsigned(tag: u8, value: u32) -> Vec<u8> {
+        let mut bytes = vec![0];
+        bytes.extend(value.to_be_bytes());
+        let start = bytes
+            .windows(2)
+            .position(|w| w[0] != 0 || w[1] & 0x80 != 0)
+            .unwrap_or(bytes.len() - 1);
+        tlv(tag, &bytes[start..])
+    }
+
+    pub fn octets(value: &[u8]) -> Vec<u8> {
+        tlv(OCTET_STRING, value)
+    }
+
+    pub fn oid(dotted: &str) -> Vec<u8> {
+        let arcs: Vec<u32> = dotted.split('.').map(|a| a.parse().unwrap()).collect();
+        let mut contents = vec![(arcs[0] * 40 + arcs[1]) as u8];
+        for &arc in &arcs[2..] {
+            let mut chunk = vec![(arc & 0x7f) as u8];
+            let mut rest = arc >> 7;
+            while rest > 0 {
+                chunk.insert(0, (rest & 0x7f) as u8 | 0x80);
+                rest >>= 7;
+            }
+            contents.extend(chunk);
+        }
+        tlv(OBJECT_IDENTIFIER, &contents)
+    }
+
+    pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+        tlv(SEQUENCE, &items.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_integers() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            255,
+            256,
+            -1,
+            -128,
+            -129,
+            1_000_000,
+            i64::MIN,
+        ] {
+            let bytes = encode::integer(value);
+            assert_eq!(Reader::new(&bytes).read_integer().unwrap(), value);
+        }
+        assert!(Reader::new(&[INTEGER, 0]).read_integer().is_err());
+    }
+
+    #[test]
+    fn test_decode_oid() {
+        let bytes = encode::oid("1.3.6.1.4.1.2636.4.5.0.1");
+        let oid = Oid::decode(Reader::new(&bytes).read(OBJECT_IDENTIFIER).unwrap()).unwrap();
+        assert_eq!(oid.to_string(), "1.3.6.1.4.1.2636.4.5.0.1");
+        assert_eq!(
+            oid.suffix(&[1, 3, 6, 1, 4, 1]),
+            Some(&[2636, 4, 5, 0, 1][..])
+        );
+        assert!(oid.suffix(&[1, 3, 6, 2]).is_none());
+
+        assert!(Oid::decode(&[0x2b, 0x86]).is_err());
+    }
+
+    #[test]
+    fn test_decode_values() {
+        assert_eq!(
+            Value::decode(TIMETICKS, &[0x00, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+            Value::TimeTicks(u32::MAX)
+        );
+        assert_eq!(
+            Value::decode(IP_ADDRESS, &[192, 0, 2, 1])
+                .unwrap()
+                .to_string(),
+            "192.0.2.1"
+        );
+        assert_eq!(
+            Value::decode(OCTET_STRING, b"ge-0/0/1")
+                .unwrap()
+                .as_text()
+                .as_deref(),
+            Some("ge-0/0/1")
+        );
+        assert_eq!(
+            Value::decode(OCTET_STRING, &[0x06, 0x04])
+                .unwrap()
+                .to_string(),
+            "0x0604"
+        );
+        assert!(Value::decode(IP_ADDRESS, &[192, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_truncated_data() {
+        let bytes = encode::sequence(&[encode::integer(1), encode::octets(b"public")]);
+        assert!(
+            Reader::new(&bytes[..bytes.len() - 1])
+                .read_sequence()
+                .is_err()
+        );
+
+        let mut long = encode::tlv(OCTET_STRING, &[b'a'; 300]);
+        assert_eq!(Reader::new(&long).read_octets().unwrap().len(), 300);
+        long.truncate(100);
+        assert!(Reader::new(&long).read_octets().is_err());
+    }
+}
@@ -0,0 +1,967 @@
+use chrono::Utc;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+use crate::alerts::http::server::AppState;
+use crate::alerts::ingest;
+use crate::alerts::sources::{self, AlertEnvelope};
+use crate::config::{AppConfig, PrefixesConfig};
+use crate::database::models::AlertKind;
+use crate::prefixes::PrefixesStore;
+
+pub mod ber;
+pub mod usm;
+
+use ber::{Oid, Reader, Value};
+
+/// Alerts waiting to be stored before the listener stops reading packets
+const ALERT_BUFFER: usize = 256;
+
+const INFORM_REQUEST: u8 = 0xa6;
+const SNMPV2_TRAP: u8 = 0xa7;
+const RESPONSE: u8 = 0xa2;
+
+const USM_SECURITY_MODEL: i64 = 3;
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_PRIV: u8 = 0x02;
+
+/// Seconds an authenticated SNMPv3 message may lag behind its engine's clock (RFC 3414 2.2.3)
+const TIME_WINDOW: i64 = 150;
+/// snmpEngineBoots value of an engine that must be reconfigured before it is trusted again
+const MAX_ENGINE_BOOTS: i64 = 2_147_483_647;
+
+const SYS_UPTIME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 3, 0];
+const SNMP_TRAP_OID: &[u32] = &[1, 3, 6, 1, 6, 3, 1, 1, 4, 1, 0];
+/// Original sender of a notification relayed by a proxy
+const SNMP_TRAP_ADDRESS: &[u32] = &[1, 3, 6, 1, 6, 3, 18, 1, 3, 0];
+/// IF-MIB ifEntry: 1 ifIndex, 2 ifDescr, 7 ifAdminStatus, 8 ifOperStatus
+const IF_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 2, 2, 1];
+/// IF-MIB ifXEntry: 1 ifName, 18 ifAlias
+const IF_X_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1];
+/// BGP4-MIB bgpPeerEntry, indexed by the IPv4 peer address: 2 state, 9 remote AS, 14 last error
+const BGP_PEER_ENTRY: &[u32] = &[1, 3, 6, 1, 2, 1, 15, 3, 1];
+
+/// Standard notifications worth naming in alerts
+const TRAP_NAMES: &[(&[u32], &str)] = &[
+    (&[1, 3, 6, 1, 6, 3, 1, 1, 5, 1], "coldStart"),
+    (&[1, 3, 6, 1, 6, 3, 1, 1, 5, 2], "warmStart"),
+    (&[1, 3, 6, 1, 6, 3, 1, 1, 5, 3], "linkDown"),
+    (&[1, 3, 6, 1, 6, 3, 1, 1, 5, 4], "linkUp"),
+    (&[1, 3, 6, 1, 6, 3, 1, 1, 5, 5], "authenticationFailure"),
+    (&[1, 3, 6, 1, 2, 1, 15, 0, 1], "bgpEstablished"),
+    (&[1, 3, 6, 1, 2, 1, 15, 0, 2], "bgpBackwardTransition"),
+    // RFC 1657 placed the BGP notifications under bgpTraps
+    (&[1, 3, 6, 1, 2, 1, 15, 7, 1], "bgpEstablished"),
+    (&[1, 3, 6, 1, 2, 1, 15, 7, 2], "bgpBackwardTransition"),
+    (&[1, 3, 6, 1, 4, 1, 9, 9, 187, 0, 1], "cbgpFsmStateChange"),
+    (
+        &[1, 3, 6, 1, 4, 1, 9, 9, 187, 0, 2],
+        "cbgpBackwardTransition",
+    ),
+    (&[1, 3, 6, 1, 2, 1, 14, 16, 2, 2], "ospfNbrStateChange"),
+    (&[1, 3, 6, 1, 2, 1, 14, 16, 2, 16], "ospfIfStateChange"),
+];
+
+/// Communities and users the listener accepts notifications from
+pub struct Credentials {
+    communities: Vec<String>,
+    users: Vec<usm::User>,
+    /// Latest boots and time of each SNMPv3 engine, to refuse replayed messages
+    engines: Mutex<HashMap<Vec<u8>, EngineClock>>,
+}
+
+/// What the listener knows of a sending engine's clock (RFC 3414 2.3)
+struct EngineClock {
+    boots: i64,
+    latest_time: i64,
+    /// When `latest_time` was received, to estimate the engine's current time
+    received_at: Instant,
+}
+
+impl EngineClock {
+    fn time(&self) -> i64 {
+        self.latest_time + self.received_at.elapsed().as_secs() as i64
+    }
+}
+
+impl Credentials {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            communities: config.snmp_trap_communities.clone(),
+            users: config
+                .snmp_trap_v3_users
+                .iter()
+                .map(usm::User::new)
+                .collect(),
+            engines: Mutex::default(),
+        }
+    }
+
+    /// Refuse an authenticated message from outside its engine's time window (RFC 3414 3.2
+    /// step 7b), and move the engine's clock forward otherwise
+    fn check_timeliness(&self, engine_id: &[u8], boots: i64, time: i64) -> Result<()> {
+        let mut engines = self.engines.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(clock) = engines.get(engine_id) {
+            if clock.boots == MAX_ENGINE_BOOTS
+                || boots < clock.boots
+                || (boots == clock.boots && time < clock.time() - TIME_WINDOW)
+            {
+                return Err(eyre!(
+                    "SNMPv3 message outside the time window of its engine"
+                ));
+            }
+            if boots == clock.boots && time <= clock.latest_time {
+                return Ok(());
+            }
+        }
+
+        engines.insert(
+            engine_id.to_vec(),
+            EngineClock {
+                boots,
+                latest_time: time,
+                received_at: Instant::now(),
+            },
+        );
+        Ok(())
+    }
+}
+
+/// An authenticated notification, before it is matched to monitored resources
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub version: &'static str,
+    /// USM user of SNMPv3 notifications
+    pub user: Option<String>,
+    /// Acknowledgement to send back for SNMPv2c informs
+    pub response: Option<Vec<u8>>,
+    pub varbinds: Vec<(Oid, Value)>,
+}
+
+/// An SNMP notification as stored and shown to the analysis agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnmpTrap {
+    /// Notification name, e.g. `linkDown`, or its OID when unknown
+    pub trap: String,
+    pub trap_oid: String,
+    pub message: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub device: TrapDevice,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<TrapInterface>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bgp_peer: Option<TrapBgpPeer>,
+    /// Device uptime in seconds when the notification was sent
+    #[serde(default)]
+    pub uptime_secs: Option<u64>,
+    pub received_at: String,
+    #[serde(default)]
+    pub varbinds: Vec<TrapVarBind>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrapDevice {
+    pub address: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrapInterface {
+    pub index: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub admin_status: Option<String>,
+    #[serde(default)]
+    pub oper_status: Option<String>,
+    /// ASN of the neighbor behind the interface, from prefixes.yml
+    #[serde(default)]
+    pub neighbor_asn: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrapBgpPeer {
+    pub address: String,
+    #[serde(default)]
+    pub remote_as: Option<u32>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrapVarBind {
+    pub oid: String,
+    pub value: String,
+}
+
+/// Decode and authenticate an SNMPv2c or SNMPv3 notification
+pub fn decode(packet: &[u8], credentials: &Credentials) -> Result<Notification> {
+    let mut message = Reader::new(packet).read_sequence()?;
+    match message.read_integer()? {
+        1 => decode_v2c(packet, message, &credentials.communities),
+        3 => decode_v3(packet, message, credentials),
+        0 => Err(eyre!("SNMPv1 traps are not supported")),
+        other => Err(eyre!("Unknown SNMP version {}", other)),
+    }
+}
+
+fn decode_v2c(packet: &[u8], mut message: Reader, communities: &[String]) -> Result<Notification> {
+    let community = message.read_octets()?;
+    if !communities.iter().any(|c| c.as_bytes() == community) {
+        return Err(eyre!("Unknown SNMPv2c community"));
+    }
+
+    let pdu_offset = packet.len() - message.remaining().len();
+    let (inform, varbinds) = read_pdu(&mut message)?;
+
+    // The response to an inform carries the same request ID and bindings
+    let response = inform.then(|| {
+        let mut response = packet.to_vec();
+        response[pdu_offset] = RESPONSE;
+        response
+    });
+
+    Ok(Notification {
+        version: "v2c",
+        user: None,
+        response,
+        varbinds,
+    })
+}
+
+fn decode_v3(
+    packet: &[u8],
+    mut message: Reader,
+    credentials: &Credentials,
+) -> Result<Notification> {
+    let mut global = message.read_sequence()?;
+    let _msg_id = global.read_integer()?;
+    let _max_size = global.read_integer()?;
+    let flags = global.read_octets()?.first().copied().unwrap_or(0);
+    if global.read_integer()? != USM_SECURITY_MODEL {
+        return Err(eyre!("Unsupported SNMPv3 security model"));
+    }
+
+    let mut security = Reader::new(message.read_octets()?).read_sequence()?;
+    let engine_id = security.read_octets()?;
+    let engine_boots = security.read_integer()?;
+    let engine_time = security.read_integer()?;
+    let name = String::from_utf8_lossy(security.read_octets()?).to_string();
+    let auth_params = security.read_octets()?;
+
+    let user = credentials
+        .users
+        .iter()
+        .find(|user| user.name == name)
+        .ok_or_else(|| eyre!("Unknown SNMPv3 user {}", name))?;
+    if flags & FLAG_PRIV != 0 {
+        return Err(eyre!("Encrypted SNMPv3 notifications are not supported"));
+    }
+    match (flags & FLAG_AUTH != 0, user.requires_auth()) {
+        (true, true) => user.verify(engine_id, packet, auth_params)?,
+        (false, false) => {}
+        (true, false) => {
+            return Err(eyre!(
+                "No passphrase configured to authenticate user {}",
+                name
+            ));
+        }
+        (false, true) => {
+            return Err(eyre!("Unauthenticated notification for user {}", name));
+        }
+    }
+
+    let mut scoped = message.read_sequence()?;
+    let _context_engine_id = scoped.read_octets()?;
+    let _context_name = scoped.read_octets()?;
+    let (inform, varbinds) = read_pdu(&mut scoped)?;
+    // An inform is addressed to the receiver's own engine, which the listener does not run
+    if inform {
+        return Err(eyre!(
+            "SNMPv3 inform from user {} refused, only traps are supported",
+            name
+        ));
+    }
+    if flags & FLAG_AUTH != 0 {
+        credentials.check_timeliness(engine_id, engine_boots, engine_time)?;
+    }
+
+    Ok(Notification {
+        version: "v3",
+        user: Some(name),
+        response: None,
+        varbinds,
+    })
+}
+
+/// Read a trap or inform PDU, returning whether it is an inform and its bindings
+fn read_pdu(reader: &mut Reader) -> Result<(bool, Vec<(Oid, Value)>)> {
+    let (tag, contents) = reader.read_any()?;
+    let inform = match tag {
+        SNMPV2_TRAP => false,
+        INFORM_REQUEST => true,
+        other => return Err(eyre!("Unexpected SNMP PDU type {:#04x}", other)),
+    };
+
+    let mut pdu = Reader::new(contents);
+    let _request_id = pdu.read_integer()?;
+    let _error_status = pdu.read_integer()?;
+    let _error_index = pdu.read_integer()?;
+
+    let mut list = pdu.read_sequence()?;
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        let mut varbind = list.read_sequence()?;
+        let oid = Oid::decode(varbind.read(ber::OBJECT_IDENTIFIER)?)?;
+        let (tag, contents) = varbind.read_any()?;
+        varbinds.push((oid, Value::decode(tag, contents)?));
+    }
+
+    Ok((inform, varbinds))
+}
+
+/// Turn a notification from `source` into a trap naming the monitored device and interface
+pub fn interpret(
+    notification: &Notification,
+    source: IpAddr,
+    prefixes: &PrefixesConfig,
+) -> Result<SnmpTrap> {
+    let trap_oid = notification
+        .varbinds
+        .iter()
+        .find_map(|(oid, value)| match value {
+            Value::Oid(trap_oid) if oid.0 == SNMP_TRAP_OID => Some(trap_oid),
+            _ => None,
+        })
+        .ok_or_else(|| eyre!("Notification without snmpTrapOID.0"))?;
+    let trap = TRAP_NAMES
+        .iter()
+        .find(|(oid, _)| trap_oid.0 == *oid)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| trap_oid.to_string());
+
+    let mut address = source.to_string();
+    let mut uptime_secs = None;
+    let mut interface: Option<TrapInterface> = None;
+    let mut bgp_peer: Option<TrapBgpPeer> = None;
+    let mut varbinds = Vec::new();
+
+    for (oid, value) in &notification.varbinds {
+        if oid.0 == SNMP_TRAP_OID {
+            continue;
+        }
+        if oid.0 == SYS_UPTIME {
+            uptime_secs = value.as_i64().map(|ticks| ticks as u64 / 100);
+            continue;
+        }
+        varbinds.push(TrapVarBind {
+            oid: oid.to_string(),
+            value: value.to_string(),
+        });
+
+        if oid.0 == SNMP_TRAP_ADDRESS
+            && let Value::IpAddress(ip) = value
+        {
+            address = ip.to_string();
+        } else if let Some(&[column, index]) = oid.suffix(IF_ENTRY) {
+            let interface = interface.get_or_insert_with(|| TrapInterface {
+                index,
+                ..Default::default()
+            });
+            match column {
+                2 => interface.name = interface.name.take().or_else(|| value.as_text()),
+                7 => interface.admin_status = value.as_i64().map(if_status),
+                8 => interface.oper_status = value.as_i64().map(if_status),
+                _ => {}
+            }
+        } else if let Some(&[column, index]) = oid.suffix(IF_X_ENTRY) {
+            let interface = interface.get_or_insert_with(|| TrapInterface {
+                index,
+                ..Default::default()
+            });
+            match column {
+                // ifName is preferred over ifDescr
+                1 => interface.name = value.as_text().or(interface.name.take()),
+                18 => interface.description = value.as_text().filter(|a| !a.is_empty()),
+                _ => {}
+            }
+        } else if let Some(&[column, a, b, c, d]) = oid.suffix(BGP_PEER_ENTRY) {
+            let peer = bgp_peer.get_or_insert_with(|| TrapBgpPeer {
+                address: format!("{a}.{b}.{c}.{d}"),
+                ..Default::default()
+            });
+            match column {
+                2 => peer.state = value.as_i64().map(bgp_state),
+                9 => peer.remote_as = value.as_i64().and_then(|asn| u32::try_from(asn).ok()),
+                14 => peer.last_error = bgp_error(value),
+                _ => {}
+            }
+        }
+    }
+
+    let device = prefixes.find_device(&address);
+    if let (Some(interface), Some(device)) = (interface.as_mut(), device)
+        && let Some(info) = device.interfaces.get(&interface.index.to_string())
+    {
+        interface.name = interface.name.take().or_else(|| info.name.clone());
+        interface.description = info.description.clone().or(interface.description.take());
+        interface.neighbor_asn = info.asn;
+    }
+
+    let device = TrapDevice {
+        address,
+        name: device.map(|d| d.name.clone()),
+        group: device.map(|d| d.group.clone()).filter(|g| !g.is_empty()),
+    };
+    let message = describe(&trap, &device, interface.as_ref(), bgp_peer.as_ref());
+
+    Ok(SnmpTrap {
+        trap,
+        trap_oid: trap_oid.to_string(),
+        message,
+        version: notification.version.to_string(),
+        user: notification.user.clone(),
+        device,
+        interface,
+        bgp_peer,
+        uptime_secs,
+        received_at: Utc::now().to_rfc3339(),
+        varbinds,
+    })
+}
+
+fn describe(
+    trap: &str,
+    device: &TrapDevice,
+    interface: Option<&TrapInterface>,
+    bgp_peer: Option<&TrapBgpPeer>,
+) -> String {
+    let device = device.name.as_deref().unwrap_or(&device.address);
+
+    if let Some(peer) = bgp_peer {
+        let remote_as = peer
+            .remote_as
+            .map(|asn| format!(" (AS{asn})"))
+            .unwrap_or_default();
+        let state = peer
+            .state
+            .as_deref()
+            .map(|state| format!(", now {state}"))
+            .unwrap_or_default();
+        return format!(
+            "{trap} on {device}: BGP peer {}{remote_as}{state}",
+            peer.address
+        );
+    }
+
+    if let Some(interface) = interface {
+        let name = interface
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("ifIndex {}", interface.index));
+        let description = interface
+            .description
+            .as_deref()
+            .map(|d| format!(" ({d})"))
+            .unwrap_or_default();
+        return format!("{trap} on {device} {name}{description}");
+    }
+
+    format!("{trap} from {device}")
+}
+
+fn if_status(status: i64) -> String {
+    match status {
+        1 => "up",
+        2 => "down",
+        3 => "testing",
+        4 => "unknown",
+        5 => "dormant",
+        6 => "notPresent",
+        7 => "lowerLayerDown",
+        _ => return status.to_string(),
+    }
+    .to_string()
+}
+
+fn bgp_state(state: i64) -> String {
+    match state {
+        1 => "idle",
+        2 => "connect",
+        3 => "active",
+        4 => "openSent",
+        5 => "openConfirm",
+        6 => "established",
+        _ => return state.to_string(),
+    }
+    .to_string()
+}
+
+/// Last BGP NOTIFICATION as its error code name and code/subcode
+fn bgp_error(value: &Value) -> Option<String> {
+    let Value::OctetString(bytes) = value else {
+        return None;
+    };
+    let &[code, subcode] = bytes.as_slice() else {
+        return None;
+    };
+    let name = match code {
+        0 => return None,
+        1 => "Message Header Error",
+        2 => "OPEN Message Error",
+        3 => "UPDATE Message Error",
+        4 => "Hold Timer Expired",
+        5 => "Finite State Machine Error",
+        6 => "Cease",
+        _ => "Unknown error",
+    };
+    Some(format!("{name} ({code}/{subcode})"))
+}
+
+/// Receive notifications on `socket` and forward the relevant ones as alerts
+pub async fn run_listener(
+    socket: &UdpSocket,
+    credentials: &Credentials,
+    prefixes: &PrefixesStore,
+    alerts: &mpsc::Sender<AlertEnvelope>,
+) -> Result<()> {
+    let source = sources::source_for(AlertKind::SnmpTrap);
+    let mut buf = vec![0u8; 65_535];
+
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf).await?;
+
+        let notification = match decode(&buf[..len], credentials) {
+            Ok(notification) => notification,
+            Err(e) => {
+                tracing::warn!("Ignoring SNMP packet from {}: {}", peer, e);
+                continue;
+            }
+        };
+        if let Some(response) = &notification.response
+            && let Err(e) = socket.send_to(response, peer).await
+        {
+            tracing::warn!("Failed to acknowledge SNMP inform from {}: {}", peer, e);
+        }
+
+        let prefixes = prefixes.get();
+        let mut alert =
+            match interpret(&notification, peer.ip(), &prefixes).and_then(|trap| trap.envelope()) {
+                Ok(alert) => alert,
+                Err(e) => {
+                    tracing::warn!("Ignoring SNMP notification from {}: {}", peer, e);
+                    continue;
+                }
+            };
+        alert.relevance = source.relevance(&alert, &prefixes);
+        if alert.relevance.is_none() {
+            tracing::debug!(
+                "SNMP {} from {} is not relevant to monitored resources",
+                alert.category,
+                alert.resource
+            );
+            continue;
+        }
+
+        alerts.send(alert).await?;
+    }
+}
+
+/// Listen for SNMP traps in the background when enabled
+pub fn start(state: AppState) {
+    if !state.config.snmp_trap_enabled {
+        return;
+    }
+
+    let (tx, mut rx) = mpsc::channel::<AlertEnvelope>(ALERT_BUFFER);
+
+    let ingest_state = state.clone();
+    tokio::spawn(async move {
+        while let Some(alert) = rx.recv().await {
+            if let Err(e) = ingest::ingest(&ingest_state, &alert).await {
+                tracing::error!("Failed to store SNMP trap alert: {}", e);
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let socket = match UdpSocket::bind(&state.config.snmp_trap_bind).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to bind SNMP trap listener to {}: {}",
+                    state.config.snmp_trap_bind,
+                    e
+                );
+                return;
+            }
+        };
+        tracing::info!(
+            "Listening for SNMP traps on {}",
+            state.config.snmp_trap_bind
+        );
+
+        let credentials = Credentials::from_config(&state.config);
+        if let Err(e) = run_listener(&socket, &credentials, &state.prefixes, &tx).await {
+            tracing::error!("SNMP trap listener stopped: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SnmpAuthProtocol, SnmpV3User};
+    use ber::encode;
+    use std::time::Duration;
+
+    const PREFIXES: &str = r#"
+192.0.2.0/24:
+  description: Customer network
+  asn:
+    - 65000
+  group: noc
+options:
+  monitorDevices:
+    '198.51.100.1':
+      name: edge1
+      group: noc
+      interfaces:
+        '3':
+          description: Transit to AS64496
+          asn: 64496
+    '127.0.0.1':
+      name: lab
+"#;
+
+    const LINK_DOWN: &str = "1.3.6.1.6.3.1.1.5.3";
+    const BGP_BACKWARD_TRANSITION: &str = "1.3.6.1.2.1.15.0.2";
+
+    fn pdu(tag: u8, trap_oid: &str, extra: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut varbinds = vec![
+            encode::sequence(&[
+                encode::oid("1.3.6.1.2.1.1.3.0"),
+                encode::unsigned(ber::TIMETICKS, 123_456),
+            ]),
+            encode::sequence(&[encode::oid("1.3.6.1.6.3.1.1.4.1.0"), encode::oid(trap_oid)]),
+        ];
+        varbinds.extend(
+            extra
+                .iter()
+                .map(|(oid, value)| encode::sequence(&[encode::oid(oid), value.clone()])),
+        );
+        encode::tlv(
+            tag,
+            &[
+                encode::integer(42),
+                encode::integer(0),
+                encode::integer(0),
+                encode::sequence(&varbinds),
+            ]
+            .concat(),
+        )
+    }
+
+    fn link_down() -> Vec<u8> {
+        pdu(
+            SNMPV2_TRAP,
+            LINK_DOWN,
+            &[
+                ("1.3.6.1.2.1.2.2.1.1.3", encode::integer(3)),
+                ("1.3.6.1.2.1.2.2.1.7.3", encode::integer(1)),
+                ("1.3.6.1.2.1.2.2.1.8.3", encode::integer(2)),
+                ("1.3.6.1.2.1.31.1.1.1.1.3", encode::octets(b"ge-0/0/3")),
+            ],
+        )
+    }
+
+    fn v2c(community: &str, pdu: Vec<u8>) -> Vec<u8> {
+        encode::sequence(&[
+            encode::integer(1),
+            encode::octets(community.as_bytes()),
+            pdu,
+        ])
+    }
+
+    fn v3(user: &SnmpV3User, flags: u8, pdu: Vec<u8>) -> Vec<u8> {
+        v3_at(user, flags, pdu, 1, 100)
+    }
+
+    /// SNMPv3 message sent at `boots` and `time` of the sending engine
+    fn v3_at(user: &SnmpV3User, flags: u8, pdu: Vec<u8>, boots: i64, time: i64) -> Vec<u8> {
+        let engine_id = b"\x80\x00\x1f\x88\x04edge1";
+        let message = |auth_params: &[u8]| {
+            let security = encode::sequence(&[
+                encode::octets(engine_id),
+                encode::integer(boots),
+                encode::integer(time),
+                encode::octets(user.name.as_bytes()),
+                encode::octets(auth_params),
+                encode::octets(b""),
+            ]);
+            encode::sequence(&[
+                encode::integer(3),
+                encode::sequence(&[
+                    encode::integer(7),
+                    encode::integer(65_507),
+                    encode::octets(&[flags]),
+                    encode::integer(USM_SECURITY_MODEL),
+                ]),
+                encode::octets(&security),
+                encode::sequence(&[encode::octets(engine_id), encode::octets(b""), pdu.clone()]),
+            ])
+        };
+
+        if flags & FLAG_AUTH == 0 {
+            return message(b"");
+        }
+        let tag = usm::sign(user, engine_id, &message(&[0; 12]));
+        message(&tag)
+    }
+
+    fn credentials(users: &[SnmpV3User]) -> Credentials {
+        Credentials {
+            communities: vec!["s3cret".to_string()],
+            users: users.iter().map(usm::User::new).collect(),
+            engines: Mutex::default(),
+        }
+    }
+
+    fn sha_user() -> SnmpV3User {
+        SnmpV3User {
+            name: "monitor".to_string(),
+            auth: Some((SnmpAuthProtocol::Sha, "authpass123".to_string())),
+        }
+    }
+
+    #[test]
+    fn test_decode_v2c_link_down() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let notification = decode(&v2c("s3cret", link_down()), &credentials(&[])).unwrap();
+        assert_eq!(notification.version, "v2c");
+        assert!(notification.response.is_none());
+
+        let trap = interpret(&notification, "198.51.100.1".parse().unwrap(), &prefixes).unwrap();
+        assert_eq!(trap.trap, "linkDown");
+        assert_eq!(trap.uptime_secs, Some(1234));
+        assert_eq!(trap.device.name.as_deref(), Some("edge1"));
+        let interface = trap.interface.as_ref().unwrap();
+        assert_eq!(interface.index, 3);
+        assert_eq!(interface.name.as_deref(), Some("ge-0/0/3"));
+        assert_eq!(interface.description.as_deref(), Some("Transit to AS64496"));
+        assert_eq!(interface.admin_status.as_deref(), Some("up"));
+        assert_eq!(interface.oper_status.as_deref(), Some("down"));
+        assert_eq!(interface.neighbor_asn, Some(64496));
+        assert_eq!(
+            trap.message,
+            "linkDown on edge1 ge-0/0/3 (Transit to AS64496)"
+        );
+
+        assert!(decode(&v2c("public", link_down()), &credentials(&[])).is_err());
+    }
+
+    #[test]
+    fn test_interpret_bgp_backward_transition() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let packet = v2c(
+            "s3cret",
+            pdu(
+                SNMPV2_TRAP,
+                BGP_BACKWARD_TRANSITION,
+                &[
+                    ("1.3.6.1.2.1.15.3.1.14.192.0.2.9", encode::octets(&[4, 0])),
+                    ("1.3.6.1.2.1.15.3.1.2.192.0.2.9", encode::integer(1)),
+                    ("1.3.6.1.2.1.15.3.1.9.192.0.2.9", encode::integer(65000)),
+                    (
+                        "1.3.6.1.6.3.18.1.3.0",
+                        encode::tlv(ber::IP_ADDRESS, &[203, 0, 113, 5]),
+                    ),
+                ],
+            ),
+        );
+
+        let notification = decode(&packet, &credentials(&[])).unwrap();
+        let trap = interpret(&notification, "127.0.0.1".parse().unwrap(), &prefixes).unwrap();
+        assert_eq!(trap.trap, "bgpBackwardTransition");
+        assert_eq!(trap.device.address, "203.0.113.5");
+        assert!(trap.device.name.is_none());
+        assert_eq!(
+            trap.bgp_peer,
+            Some(TrapBgpPeer {
+                address: "192.0.2.9".to_string(),
+                remote_as: Some(65000),
+                state: Some("idle".to_string()),
+                last_error: Some("Hold Timer Expired (4/0)".to_string()),
+            })
+        );
+        assert_eq!(
+            trap.message,
+            "bgpBackwardTransition on 203.0.113.5: BGP peer 192.0.2.9 (AS65000), now idle"
+        );
+    }
+
+    #[test]
+    fn test_decode_v3() {
+        let user = sha_user();
+        let lab = SnmpV3User {
+            name: "lab".to_string(),
+            auth: None,
+        };
+        let creds = credentials(&[user.clone(), lab.clone()]);
+
+        let notification = decode(&v3(&user, FLAG_AUTH, link_down()), &creds).unwrap();
+        assert_eq!(notification.version, "v3");
+        assert_eq!(notification.user.as_deref(), Some("monitor"));
+        assert_eq!(notification.varbinds.len(), 6);
+        assert!(decode(&v3(&lab, 0, link_down()), &creds).is_ok());
+
+        // Wrong passphrase, missing authentication, encryption and unknown users are refused
+        let forged = SnmpV3User {
+            auth: Some((SnmpAuthProtocol::Sha, "wrongpass1".to_string())),
+            ..user.clone()
+        };
+        assert!(decode(&v3(&forged, FLAG_AUTH, link_down()), &creds).is_err());
+        assert!(decode(&v3(&user, 0, link_down()), &creds).is_err());
+        assert!(decode(&v3(&user, FLAG_AUTH | FLAG_PRIV, link_down()), &creds).is_err());
+        assert!(decode(&v3(&user, FLAG_AUTH, link_down()), &credentials(&[])).is_err());
+
+        let mut tampered = v3(&user, FLAG_AUTH, link_down());
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decode(&tampered, &creds).is_err());
+    }
+
+    #[test]
+    fn test_decode_v3_refuses_replays() {
+        let user = sha_user();
+        let creds = credentials(&[sha_user()]);
+        let at = |boots, time| decode(&v3_at(&user, FLAG_AUTH, link_down(), boots, time), &creds);
+
+        assert!(at(2, 1000).is_ok());
+        // Late messages are accepted within 150 seconds of the latest engine time
+        assert!(at(2, 900).is_ok());
+        assert!(at(2, 1000).is_ok());
+        assert!(at(2, 849).is_err());
+        // A message from before the engine rebooted is a replay
+        assert!(at(1, 5000).is_err());
+        assert!(at(3, 10).is_ok());
+        assert!(at(2, 1000).is_err());
+
+        // Unauthenticated messages carry no trustworthy clock to check
+        let lab = SnmpV3User {
+            name: "lab".to_string(),
+            auth: None,
+        };
+        let creds = credentials(std::slice::from_ref(&lab));
+        assert!(decode(&v3_at(&lab, 0, link_down(), 3, 10), &creds).is_ok());
+        assert!(decode(&v3_at(&lab, 0, link_down(), 0, 0), &creds).is_ok());
+    }
+
+    #[test]
+    fn test_decode_v3_refuses_informs() {
+        let user = sha_user();
+        let creds = credentials(&[sha_user()]);
+        let inform = pdu(INFORM_REQUEST, LINK_DOWN, &[]);
+
+        let err = decode(&v3(&user, FLAG_AUTH, inform), &creds).unwrap_err();
+        assert!(err.to_string().contains("inform"));
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        let creds = credentials(&[]);
+        assert!(decode(b"", &creds).is_err());
+        assert!(decode(b"\x30\x03\x02\x01", &creds).is_err());
+        assert!(decode(&v2c("s3cret", encode::tlv(0xa4, &[])), &creds).is_err());
+        assert!(
+            decode(
+                &v2c("s3cret", pdu(SNMPV2_TRAP, LINK_DOWN, &[]))[..20],
+                &creds
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listener_receives_traps_on_localhost() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let prefixes =
+            PrefixesStore::new("prefixes.yml", PrefixesConfig::from_str(PREFIXES).unwrap());
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let listener = tokio::spawn(async move {
+            let creds = credentials(&[sha_user()]);
+            run_listener(&socket, &creds, &prefixes, &tx).await
+        });
+
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let relayed = pdu(
+            SNMPV2_TRAP,
+            "1.3.6.1.6.3.1.1.5.1",
+            &[(
+                "1.3.6.1.6.3.18.1.3.0",
+                encode::tlv(ber::IP_ADDRESS, &[203, 0, 113, 77]),
+            )],
+        );
+        let bgp = pdu(
+            INFORM_REQUEST,
+            BGP_BACKWARD_TRANSITION,
+            &[("1.3.6.1.2.1.15.3.1.2.192.0.2.9", encode::integer(3))],
+        );
+        for packet in [
+            b"not snmp".to_vec(),
+            v2c("s3cret", relayed),
+            v3(&sha_user(), FLAG_AUTH, link_down()),
+            v2c("s3cret", bgp.clone()),
+        ] {
+            sender.send_to(&packet, address).await.unwrap();
+        }
+
+        // The inform is acknowledged with a response carrying the same bindings
+        let mut buf = [0u8; 1500];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(5), sender.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        let mut response = bgp;
+        response[0] = RESPONSE;
+        assert_eq!(&buf[..len], v2c("s3cret", response).as_slice());
+
+        let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.kind, AlertKind::SnmpTrap);
+        assert_eq!(alert.category, "linkDown");
+        assert_eq!(alert.resource, "127.0.0.1");
+        assert_eq!(alert.related_resource.as_deref(), Some("ge-0/0/3"));
+
+        // Relayed on behalf of an unknown device, the cold start is dropped, while the BGP
+        // peer of the inform sits in a monitored prefix
+        let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.category, "bgpBackwardTransition");
+        assert_eq!(alert.related_resource.as_deref(), Some("192.0.2.9"));
+        assert!(rx.try_recv().is_err());
+
+        listener.abort();
+    }
+}
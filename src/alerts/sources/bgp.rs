@@ -0,0 +1,219 @@
+use color_eyre::Result;
+use serde_json::Value;
+
+use crate::alerts::http::server::BGPAlerterAlert;
+use crate::alerts::incidents::parse_count;
+use crate::alerts::sources::{self, AlertEnvelope, AlertSource};
+use crate::database::models::AlertKind;
+
+const ANALYSIS_ROLE: &str = r#"
+You are a BGP security analyst for busy NOC operators who need FAST, ACTIONABLE insights.
+
+CRITICAL: Use your available tools to PROACTIVELY gather enrichment data.
+The operator should NOT need to run queries themselves - you do the lookups and include relevant
+context directly in your report (ownership info, ASN details, historical patterns, etc.)."#;
+
+const CHAT_ROLE: &str = r#"
+You are a BGP security analyst assistant helping NOC operators with follow-up questions about BGP alerts.
+You are answering questions about a BGP alert that has already been analyzed."#;
+
+const INSTRUCTIONS: &str = r#"1. USE YOUR TOOLS: Query WHOIS/RIPEstat for ASN ownership, prefix registration, and historical data
+2. ENRICH YOUR RESPONSE: Include context the operator would need (who owns the ASNs, legitimacy indicators, etc.)
+3. SAVE OPERATOR TIME: They should NOT need to run additional queries - you provide all relevant context
+4. BE SPECIFIC: Include actual organization names, registration details, and concrete evidence in your assessment
+5. KEY FACTS: affected_prefix is the prefix from the alert, expected_asn and observed_asn come with organization names (e.g. 'AS9999 (Unknown Operator)'), duration runs from earliest to latest and peer_count is the count from the alert
+
+EXAMPLES of enriched responses:
+- Good: "AS9999 (Suspicious Networks Inc.) announcing prefix registered to AS3333 (RIPE NCC)"
+- Bad: "AS9999 announcing prefix expected from AS3333""#;
+
+/// Note added to the prompt of alerts detected from RIS Live updates
+const RIS_LIVE_NOTE: &str = "This alert was raised from BGP UPDATEs seen by RIPE RIS peers: a single \
+UPDATE for announcements, withdrawals by peer_count distinct peers for visibility alerts. Check current \
+visibility with your tools before judging how widespread the event is.";
+
+impl BGPAlerterAlert {
+    /// Common view of the alert, attributed to `kind`
+    pub fn envelope(&self, kind: AlertKind) -> Result<AlertEnvelope> {
+        let details = &self.details;
+        Ok(AlertEnvelope {
+            kind,
+            category: details.kind.clone(),
+            title: self.message.clone(),
+            resource: details.prefix.clone(),
+            related_resource: details.newprefix.clone(),
+            asn: Some(details.asn.clone()),
+            observed_asn: details.neworigin.clone(),
+            earliest: details.earliest.clone(),
+            latest: details.latest.clone(),
+            peers: parse_count(&details.peers),
+            paths: parse_count(&details.paths),
+            payload: serde_json::to_value(self)?,
+            rpki: None,
+            relevance: None,
+        })
+    }
+}
+
+/// Alerts posted by BGPAlerter to `/api/alerts`
+pub struct BgpAlerterSource;
+
+/// Alerts detected by the built-in RIS Live consumer, stored in BGPAlerter's format
+pub struct RisLiveSource;
+
+impl AlertSource for BgpAlerterSource {
+    fn kind(&self) -> AlertKind {
+        AlertKind::BgpAlerter
+    }
+
+    fn label(&self) -> &'static str {
+        "BGP alert"
+    }
+
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope> {
+        serde_json::from_value::<BGPAlerterAlert>(payload)?.envelope(self.kind())
+    }
+
+    fn analysis_preamble(&self) -> String {
+        sources::analysis_preamble(ANALYSIS_ROLE)
+    }
+
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String> {
+        let alert_json = serde_json::to_string_pretty(&alert.payload)?;
+        Ok(build_analysis_prompt(&alert_json))
+    }
+
+    fn chat_preamble(&self) -> String {
+        sources::chat_preamble(CHAT_ROLE)
+    }
+}
+
+impl AlertSource for RisLiveSource {
+    fn kind(&self) -> AlertKind {
+        AlertKind::RisLive
+    }
+
+    fn label(&self) -> &'static str {
+        "BGP alert"
+    }
+
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope> {
+        serde_json::from_value::<BGPAlerterAlert>(payload)?.envelope(self.kind())
+    }
+
+    fn analysis_preamble(&self) -> String {
+        sources::analysis_preamble(ANALYSIS_ROLE)
+    }
+
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String> {
+        let alert_json = serde_json::to_string_pretty(&alert.payload)?;
+        Ok(build_analysis_prompt(&format!(
+            "{alert_json}\n\n{RIS_LIVE_NOTE}"
+        )))
+    }
+
+    fn chat_preamble(&self) -> String {
+        sources::chat_preamble(CHAT_ROLE)
+    }
+}
+
+fn build_analysis_prompt(alert_json: &str) -> String {
+    sources::report_prompt("BGP alert", "BGP Alert", alert_json, INSTRUCTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sources::{normalize, source_for};
+    use crate::config::{MatchReason, RelevanceMatch};
+    use crate::rpki::{RpkiState, Vrp, VrpSet};
+
+    const ALERT: &str = r#"{
+        "message": "Possible hijack of 10.1.0.0/16",
+        "description": "hijack",
+        "details": {
+            "prefix": "10.1.0.0/16",
+            "newprefix": "10.1.1.0/24",
+            "neworigin": "64512",
+            "summary": "summary",
+            "earliest": "2025-01-15T10:00:00Z",
+            "latest": "2025-01-15T10:30:00Z",
+            "kind": "hijack",
+            "asn": "65000",
+            "paths": "3",
+            "peers": "many"
+        }
+    }"#;
+
+    #[test]
+    fn test_normalize_bgp_alerter_payload() {
+        let alert = normalize(AlertKind::BgpAlerter, ALERT).unwrap();
+        assert_eq!(alert.kind, AlertKind::BgpAlerter);
+        assert_eq!(alert.category, "hijack");
+        assert_eq!(alert.resource, "10.1.0.0/16");
+        assert_eq!(alert.related_resource.as_deref(), Some("10.1.1.0/24"));
+        assert_eq!(alert.asn.as_deref(), Some("65000"));
+        assert_eq!(alert.observed_asn.as_deref(), Some("64512"));
+        assert_eq!(alert.paths, 3);
+        assert_eq!(alert.peers, 0);
+        assert_eq!(alert.payload["details"]["peers"], "many");
+
+        assert!(normalize(AlertKind::BgpAlerter, r#"{"message":"test"}"#).is_err());
+    }
+
+    #[test]
+    fn test_rpki_validation_and_relevance_are_stored_with_payload() {
+        let mut alert = normalize(AlertKind::BgpAlerter, ALERT).unwrap();
+        assert_eq!(alert.rpki, None);
+        assert_eq!(alert.relevance, None);
+        assert_eq!(alert.stored_payload(), alert.payload);
+
+        let vrps = VrpSet::new(
+            [Vrp {
+                prefix: "10.1.0.0/16".parse().unwrap(),
+                max_length: 24,
+                asn: 65000,
+            }],
+            "test",
+        );
+        alert.rpki = Some(vrps.validate(&"10.1.1.0/24".parse().unwrap(), 64512));
+        alert.relevance = Some(RelevanceMatch {
+            reason: MatchReason::CoveringPrefix,
+            value: "10.1.1.0/24".to_string(),
+            monitored: "10.1.0.0/16".to_string(),
+            group: "noc".to_string(),
+            prefix_info: None,
+            device_name: None,
+        });
+
+        let stored = serde_json::to_string(&alert.stored_payload()).unwrap();
+        let restored = normalize(AlertKind::BgpAlerter, &stored).unwrap();
+        assert_eq!(restored, alert);
+        assert_eq!(restored.rpki.unwrap().state, RpkiState::Invalid);
+        assert_eq!(restored.relevance.unwrap().monitored, "10.1.0.0/16");
+    }
+
+    #[test]
+    fn test_sources_dispatch_on_kind() {
+        for kind in [
+            AlertKind::BgpAlerter,
+            AlertKind::RisLive,
+            AlertKind::SnmpTrap,
+            AlertKind::Syslog,
+            AlertKind::RoaCoverage,
+        ] {
+            assert_eq!(source_for(kind).kind(), kind);
+        }
+
+        let alert = normalize(AlertKind::RisLive, ALERT).unwrap();
+        let prompt = source_for(AlertKind::RisLive)
+            .analysis_prompt(&alert)
+            .unwrap();
+        assert!(prompt.contains(RIS_LIVE_NOTE));
+        assert!(prompt.contains("10.1.1.0/24"));
+        let prompt = source_for(AlertKind::BgpAlerter)
+            .analysis_prompt(&alert)
+            .unwrap();
+        assert!(!prompt.contains(RIS_LIVE_NOTE));
+    }
+}
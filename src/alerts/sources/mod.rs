@@ -0,0 +1,198 @@
+use color_eyre::Result;
+use serde_json::Value;
+
+use crate::config::{PrefixesConfig, RelevanceMatch};
+use crate::database::models::AlertKind;
+use crate::rpki::RpkiValidation;
+
+pub mod bgp;
+pub mod rpki;
+pub mod snmp;
+pub mod syslog;
+
+/// Common view of an alert, whatever system produced it
+///
+/// The original payload is kept untouched for storage and prompts; the other fields are
+/// what deduplication, relevance checks and the UI need.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEnvelope {
+    pub kind: AlertKind,
+    /// What happened, e.g. `hijack` or `visibility`
+    pub category: String,
+    /// One-line description for logs and notifications
+    pub title: String,
+    /// Primary affected resource, e.g. the monitored prefix
+    pub resource: String,
+    /// Secondary resource involved, e.g. a more-specific prefix
+    pub related_resource: Option<String>,
+    /// Expected ASN(s) of the resource
+    pub asn: Option<String>,
+    /// Unexpected ASN that was observed, if any
+    pub observed_asn: Option<String>,
+    pub earliest: String,
+    pub latest: String,
+    /// Number of peers reporting the event
+    pub peers: i64,
+    /// Number of paths reporting the event
+    pub paths: i64,
+    /// The alert as received
+    pub payload: Value,
+    /// RFC 6811 validity of the announced route against the local VRP set, attached at ingest
+    pub rpki: Option<RpkiValidation>,
+    /// The monitored entry that made the alert relevant, attached at ingest
+    pub relevance: Option<RelevanceMatch>,
+}
+
+/// Key of the RPKI validation stored alongside the payload
+const RPKI_KEY: &str = "rpki";
+/// Key of the relevance match stored alongside the payload
+const RELEVANCE_KEY: &str = "relevance";
+
+impl AlertEnvelope {
+    /// The payload as stored, with the RPKI validation and relevance match attached
+    pub fn stored_payload(&self) -> Value {
+        let mut payload = self.payload.clone();
+        if let Some(object) = payload.as_object_mut() {
+            if let Some(Ok(rpki)) = self.rpki.as_ref().map(serde_json::to_value) {
+                object.insert(RPKI_KEY.to_string(), rpki);
+            }
+            if let Some(Ok(relevance)) = self.relevance.as_ref().map(serde_json::to_value) {
+                object.insert(RELEVANCE_KEY.to_string(), relevance);
+            }
+        }
+        payload
+    }
+
+    /// Group of the monitored entry the alert matched, which decides where it is routed
+    pub fn group(&self) -> Option<&str> {
+        self.relevance
+            .as_ref()
+            .map(|relevance| relevance.group.as_str())
+            .filter(|group| !group.is_empty())
+    }
+}
+
+/// Rules closing the system prompt of every analysis agent
+const COMMUNICATION_RULES: &str = r#"COMMUNICATION RULES:
+- Be extremely concise - every word must add value
+- Lead with the most critical information
+- Assume the operator is an experienced network engineer
+- Focus on "what to do" over "what happened"
+- No emojis, minimal formatting
+- If tools fail, provide analysis based solely on the alert data and mention tool failures briefly
+
+Your reports should take 30 seconds to read and act upon, not 5 minutes."#;
+
+/// Rules closing the system prompt of every chat agent
+const CHAT_RULES: &str = r#"You have access to tools to gather information about IP prefixes, ASNs, and routing announcements.
+Provide clear, concise answers based on the original alert data and your access to current routing information.
+Do not use emojis in your responses. Use plain text formatting only."#;
+
+/// JSON structure of the `IncidentReport` every analysis must answer with
+const INCIDENT_REPORT_FORMAT: &str = r#"{
+  "summary": "2-3 sentence executive summary with enriched context (owner names, device, peer) and the likely impact",
+  "severity": "Critical|High|Medium|Low|Info",
+  "key_facts": {
+    "affected_prefix": "affected prefix with its registration owner if found, otherwise null",
+    "expected_asn": "expected or neighbor ASN with organization name (e.g. 'AS3333 (RIPE NCC)'), otherwise null",
+    "observed_asn": "unexpected ASN observed, with organization name, otherwise null",
+    "duration": "human readable duration of the event if known, otherwise null",
+    "peer_count": number of peers reporting the event, or null
+  },
+  "immediate_actions": [
+    "First action with specific commands, contact info or validation method",
+    "Second action with concrete steps based on enrichment data",
+    "Third action informed by historical patterns, registration info or device state"
+  ],
+  "risk_assessment": "1-2 sentence analysis of the routing and traffic impact, informed by tool lookups",
+  "tool_notes": "Brief summary of enrichment data gathered or any tool failures"
+}"#;
+
+/// System prompt of an analysis agent: its `role`, then the rules shared by every source
+pub fn analysis_preamble(role: &str) -> String {
+    format!("{role}\n\n{COMMUNICATION_RULES}")
+}
+
+/// System prompt of a chat agent: its `role`, then the rules shared by every source
+pub fn chat_preamble(role: &str) -> String {
+    format!("{role}\n{CHAT_RULES}")
+}
+
+/// Prompt asking for an `IncidentReport` about a `subject`, e.g. "SNMP notification"
+///
+/// The payload is shown under `heading`, followed by the source's numbered `instructions`.
+pub fn report_prompt(subject: &str, heading: &str, payload: &str, instructions: &str) -> String {
+    format!(
+        r#"Analyze this {subject} and respond with ONLY a valid JSON object. NO markdown, NO explanations, JUST the JSON.
+
+{heading}:
+{payload}
+
+CRITICAL INSTRUCTIONS:
+{instructions}
+
+Required JSON structure:
+{INCIDENT_REPORT_FORMAT}
+
+CRITICAL: Output ONLY valid JSON. No markdown code blocks, no extra text."#
+    )
+}
+
+/// A system alerts come from, with everything needed to triage and analyze its alerts
+pub trait AlertSource: Send + Sync {
+    fn kind(&self) -> AlertKind;
+
+    /// Name of the alerts in prompts, e.g. "BGP alert"
+    fn label(&self) -> &'static str;
+
+    /// Build the envelope for a payload of this source
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope>;
+
+    /// The monitored entry the alert concerns, or `None` when it is not relevant
+    fn relevance(
+        &self,
+        alert: &AlertEnvelope,
+        prefixes: &PrefixesConfig,
+    ) -> Option<RelevanceMatch> {
+        prefixes.alert_relevance(alert)
+    }
+
+    /// System prompt of the analysis agent, usually built with `analysis_preamble`
+    fn analysis_preamble(&self) -> String;
+
+    /// Prompt asking for an `IncidentReport` about the alert, usually built with `report_prompt`
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String>;
+
+    /// System prompt of the chat agent, usually built with `chat_preamble`
+    fn chat_preamble(&self) -> String;
+}
+
+/// The source handling alerts of `kind`
+pub fn source_for(kind: AlertKind) -> &'static dyn AlertSource {
+    match kind {
+        AlertKind::BgpAlerter => &bgp::BgpAlerterSource,
+        AlertKind::RisLive => &bgp::RisLiveSource,
+        AlertKind::SnmpTrap => &snmp::SnmpTrapSource,
+        AlertKind::Syslog => &syslog::SyslogSource,
+        AlertKind::RoaCoverage => &rpki::RoaCoverageSource,
+    }
+}
+
+/// Parse a stored alert payload of the given kind
+pub fn normalize(kind: AlertKind, alert_data: &str) -> Result<AlertEnvelope> {
+    let mut payload: Value = serde_json::from_str(alert_data)?;
+    let mut take = |key: &str| {
+        payload
+            .as_object_mut()
+            .and_then(|object| object.remove(key))
+    };
+    let rpki = take(RPKI_KEY).map(serde_json::from_value).transpose()?;
+    let relevance = take(RELEVANCE_KEY)
+        .map(serde_json::from_value)
+        .transpose()?;
+
+    let mut alert = source_for(kind).normalize(payload)?;
+    alert.rpki = rpki;
+    alert.relevance = relevance;
+    Ok(alert)
+}
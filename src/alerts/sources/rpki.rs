@@ -0,0 +1,133 @@
+use color_eyre::Result;
+use serde_json::Value;
+
+use crate::alerts::sources::{self, AlertEnvelope, AlertSource};
+use crate::database::models::AlertKind;
+use crate::rpki::coverage::RoaCoverageAlert;
+
+const ANALYSIS_ROLE: &str = r#"
+You are a network operations analyst for busy NOC operators who need FAST, ACTIONABLE insights.
+You review changes in the RPKI ROA coverage of the operator's own prefixes: prefixes losing their
+ROA, ROAs authorizing the wrong origin, and maxLength settings loose enough to allow forged-origin
+more-specific hijacks.
+
+CRITICAL: Use your available tools to PROACTIVELY gather enrichment data.
+Check how the prefix is currently announced and by which origin, and whether the change makes
+current announcements RPKI invalid, so the operator knows whether traffic is at risk of being dropped."#;
+
+const CHAT_ROLE: &str = r#"
+You are a network operations assistant helping NOC operators with follow-up questions about RPKI
+ROA coverage changes for their prefixes (missing ROAs, wrong origins, loose maxLength).
+You are answering questions about a change that has already been analyzed."#;
+
+/// What the coverage states in the payload mean
+const STATES: &str = r#"States: no_roa (no VRP covers the prefix), wrong_origin (VRPs exist but do not authorize every expected origin),
+loose_max_length (a VRP also authorizes more-specifics that are not announced), covered (fully covered)."#;
+
+const INSTRUCTIONS: &str = r#"1. USE YOUR TOOLS: Check current announcements of the prefix and its more-specifics and their origins
+2. ASSESS IMPACT: Say whether current announcements are now RPKI invalid and likely dropped by validating networks
+3. SAVE OPERATOR TIME: They should NOT need to run additional queries - you provide all relevant context
+4. BE SPECIFIC: Name the exact ROA to create, fix or tighten (prefix, maxLength, origin ASN)
+5. KEY FACTS: affected_prefix is the monitored prefix, expected_asn its expected origins and observed_asn the origin currently seen announcing it; duration and peer_count stay null"#;
+
+impl RoaCoverageAlert {
+    /// Common view of the change, keyed on the monitored prefix
+    pub fn envelope(&self) -> Result<AlertEnvelope> {
+        let asn = (!self.expected_asns.is_empty()).then(|| {
+            self.expected_asns
+                .iter()
+                .map(|asn| asn.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+
+        Ok(AlertEnvelope {
+            kind: AlertKind::RoaCoverage,
+            category: self.state.as_str().to_string(),
+            title: self.message.clone(),
+            resource: self.prefix.clone(),
+            related_resource: None,
+            asn,
+            observed_asn: None,
+            earliest: self.detected_at.clone(),
+            latest: self.detected_at.clone(),
+            peers: 0,
+            paths: 0,
+            payload: serde_json::to_value(self)?,
+            rpki: None,
+            relevance: None,
+        })
+    }
+}
+
+/// Coverage changes found by the periodic ROA coverage check
+pub struct RoaCoverageSource;
+
+impl AlertSource for RoaCoverageSource {
+    fn kind(&self) -> AlertKind {
+        AlertKind::RoaCoverage
+    }
+
+    fn label(&self) -> &'static str {
+        "ROA coverage change"
+    }
+
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope> {
+        serde_json::from_value::<RoaCoverageAlert>(payload)?.envelope()
+    }
+
+    fn analysis_preamble(&self) -> String {
+        sources::analysis_preamble(ANALYSIS_ROLE)
+    }
+
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String> {
+        let change_json = serde_json::to_string_pretty(&alert.payload)?;
+        Ok(sources::report_prompt(
+            "ROA coverage change",
+            "Coverage Change",
+            &format!("{change_json}\n\n{STATES}"),
+            INSTRUCTIONS,
+        ))
+    }
+
+    fn chat_preamble(&self) -> String {
+        sources::chat_preamble(CHAT_ROLE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sources::{normalize, source_for};
+    use crate::rpki::coverage::CoverageState;
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let alert = RoaCoverageAlert {
+            message: "ROA coverage of 192.0.2.0/24 changed from fully covered to no ROA"
+                .to_string(),
+            prefix: "192.0.2.0/24".to_string(),
+            expected_asns: vec![64500, 64501],
+            previous_state: CoverageState::Covered,
+            state: CoverageState::NoRoa,
+            detail: Some("No ROA covers 192.0.2.0/24".to_string()),
+            covering_vrps: Vec::new(),
+            vrp_source: "rtr://127.0.0.1:3323".to_string(),
+            detected_at: "2025-01-15T10:00:00+00:00".to_string(),
+        }
+        .envelope()
+        .unwrap();
+        assert_eq!(alert.category, "no_roa");
+        assert_eq!(alert.resource, "192.0.2.0/24");
+        assert_eq!(alert.asn.as_deref(), Some("64500,64501"));
+
+        let stored = serde_json::to_string(&alert.payload).unwrap();
+        assert_eq!(normalize(AlertKind::RoaCoverage, &stored).unwrap(), alert);
+
+        let prompt = source_for(AlertKind::RoaCoverage)
+            .analysis_prompt(&alert)
+            .unwrap();
+        assert!(prompt.contains("Coverage Change:"));
+        assert!(prompt.contains("\"previous_state\": \"covered\""));
+    }
+}
@@ -0,0 +1,216 @@
+use color_eyre::Result;
+use serde_json::Value;
+
+use crate::alerts::snmp::SnmpTrap;
+use crate::alerts::sources::{self, AlertEnvelope, AlertSource};
+use crate::config::{MatchReason, PrefixesConfig, RelevanceMatch};
+use crate::database::models::AlertKind;
+
+const ANALYSIS_ROLE: &str = r#"
+You are a network operations analyst for busy NOC operators who need FAST, ACTIONABLE insights.
+You triage SNMP notifications from routers and switches: link state changes, BGP session
+transitions, reboots and similar device events.
+
+CRITICAL: Use your available tools to PROACTIVELY gather enrichment data.
+Check whether the routing impact is visible from the outside (prefix visibility, peer ASN
+ownership, recent announcements) so the operator knows how serious the event is."#;
+
+const CHAT_ROLE: &str = r#"
+You are a network operations assistant helping NOC operators with follow-up questions about SNMP
+notifications from their devices (link state changes, BGP session transitions, reboots).
+You are answering questions about a trap that has already been analyzed."#;
+
+const INSTRUCTIONS: &str = r#"1. USE YOUR TOOLS: Look up the BGP peer or neighbor ASN and check visibility of prefixes that may depend on this link or session
+2. ASSESS IMPACT: Say whether traffic or routing is likely affected (redundant paths, single transit, customer session, etc.)
+3. SAVE OPERATOR TIME: They should NOT need to run additional queries - you provide all relevant context
+4. BE SPECIFIC: Name the device, interface or peer, and the likely cause when the trap data points to one (e.g. hold timer expiry, admin shutdown)
+5. KEY FACTS: affected_prefix holds the prefixes behind the link or session, expected_asn the neighbor or BGP peer ASN, duration the time since the event or the device uptime; observed_asn and peer_count stay null"#;
+
+impl SnmpTrap {
+    /// Common view of the trap, keyed on the device that sent it
+    pub fn envelope(&self) -> Result<AlertEnvelope> {
+        let related_resource = match (&self.bgp_peer, &self.interface) {
+            (Some(peer), _) => Some(peer.address.clone()),
+            (None, Some(interface)) => Some(
+                interface
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("ifIndex {}", interface.index)),
+            ),
+            (None, None) => None,
+        };
+        let observed_asn = self
+            .bgp_peer
+            .as_ref()
+            .and_then(|peer| peer.remote_as)
+            .or_else(|| self.interface.as_ref().and_then(|i| i.neighbor_asn))
+            .map(|asn| asn.to_string());
+
+        Ok(AlertEnvelope {
+            kind: AlertKind::SnmpTrap,
+            category: self.trap.clone(),
+            title: self.message.clone(),
+            resource: self.device.address.clone(),
+            related_resource,
+            asn: None,
+            observed_asn,
+            earliest: self.received_at.clone(),
+            latest: self.received_at.clone(),
+            peers: 0,
+            paths: 0,
+            payload: serde_json::to_value(self)?,
+            rpki: None,
+            relevance: None,
+        })
+    }
+}
+
+/// Notifications received by the built-in SNMP trap listener
+pub struct SnmpTrapSource;
+
+impl AlertSource for SnmpTrapSource {
+    fn kind(&self) -> AlertKind {
+        AlertKind::SnmpTrap
+    }
+
+    fn label(&self) -> &'static str {
+        "SNMP trap"
+    }
+
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope> {
+        serde_json::from_value::<SnmpTrap>(payload)?.envelope()
+    }
+
+    /// Traps matter when they come from a monitored device or concern a BGP peer or
+    /// neighbor ASN we monitor
+    fn relevance(
+        &self,
+        alert: &AlertEnvelope,
+        prefixes: &PrefixesConfig,
+    ) -> Option<RelevanceMatch> {
+        prefixes
+            .device_match(&alert.resource)
+            .or_else(|| {
+                alert
+                    .related_resource
+                    .as_deref()
+                    .and_then(|peer| prefixes.address_match(peer))
+            })
+            .or_else(|| {
+                alert
+                    .observed_asn
+                    .as_deref()
+                    .and_then(|asn| prefixes.asn_match(asn, MatchReason::MonitoredAsn))
+            })
+    }
+
+    fn analysis_preamble(&self) -> String {
+        sources::analysis_preamble(ANALYSIS_ROLE)
+    }
+
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String> {
+        let trap_json = serde_json::to_string_pretty(&alert.payload)?;
+        Ok(sources::report_prompt(
+            "SNMP notification",
+            "SNMP Trap",
+            &trap_json,
+            INSTRUCTIONS,
+        ))
+    }
+
+    fn chat_preamble(&self) -> String {
+        sources::chat_preamble(CHAT_ROLE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::snmp::{TrapBgpPeer, TrapDevice, TrapInterface};
+    use crate::alerts::sources::{normalize, source_for};
+
+    const PREFIXES: &str = r#"
+192.0.2.0/24:
+  description: Customer network
+  asn:
+    - 65000
+  group: noc
+options:
+  monitorASns:
+    '64496':
+      group: noc
+  monitorDevices:
+    '198.51.100.1':
+      name: edge1
+"#;
+
+    fn trap(address: &str) -> SnmpTrap {
+        SnmpTrap {
+            trap: "linkDown".to_string(),
+            trap_oid: "1.3.6.1.6.3.1.1.5.3".to_string(),
+            message: "linkDown on edge1 ifIndex 7".to_string(),
+            version: "v2c".to_string(),
+            user: None,
+            device: TrapDevice {
+                address: address.to_string(),
+                name: None,
+                group: None,
+            },
+            interface: Some(TrapInterface {
+                index: 7,
+                ..Default::default()
+            }),
+            bgp_peer: None,
+            uptime_secs: Some(60),
+            received_at: "2025-01-15T10:00:00+00:00".to_string(),
+            varbinds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let alert = trap("198.51.100.1").envelope().unwrap();
+        assert_eq!(alert.category, "linkDown");
+        assert_eq!(alert.resource, "198.51.100.1");
+        assert_eq!(alert.related_resource.as_deref(), Some("ifIndex 7"));
+        assert_eq!(alert.observed_asn, None);
+
+        let stored = serde_json::to_string(&alert.payload).unwrap();
+        assert_eq!(normalize(AlertKind::SnmpTrap, &stored).unwrap(), alert);
+        assert!(normalize(AlertKind::SnmpTrap, r#"{"trap":"linkDown"}"#).is_err());
+    }
+
+    #[test]
+    fn test_relevance() {
+        let prefixes = PrefixesConfig::from_str(PREFIXES).unwrap();
+        let source = source_for(AlertKind::SnmpTrap);
+
+        let relevance = |alert: &AlertEnvelope| source.relevance(alert, &prefixes);
+        let device = relevance(&trap("198.51.100.1").envelope().unwrap()).unwrap();
+        assert_eq!(device.reason, MatchReason::MonitoredDevice);
+        assert!(relevance(&trap("198.51.100.2").envelope().unwrap()).is_none());
+
+        let mut peer_in_prefix = trap("198.51.100.2");
+        peer_in_prefix.bgp_peer = Some(TrapBgpPeer {
+            address: "192.0.2.9".to_string(),
+            ..Default::default()
+        });
+        let peer = relevance(&peer_in_prefix.envelope().unwrap()).unwrap();
+        assert_eq!(peer.reason, MatchReason::CoveringPrefix);
+        assert_eq!(peer.value, "192.0.2.9");
+
+        let mut monitored_neighbor = trap("198.51.100.2");
+        monitored_neighbor.interface.as_mut().unwrap().neighbor_asn = Some(64496);
+        let alert = monitored_neighbor.envelope().unwrap();
+        assert_eq!(alert.observed_asn.as_deref(), Some("64496"));
+        let neighbor = relevance(&alert).unwrap();
+        assert_eq!(neighbor.reason, MatchReason::MonitoredAsn);
+        assert_eq!(neighbor.monitored, "64496");
+
+        let prompt = source.analysis_prompt(&alert).unwrap();
+        assert!(prompt.contains("SNMP Trap:"));
+        assert!(prompt.contains("198.51.100.2"));
+        assert!(prompt.contains(r#""immediate_actions": ["#));
+        assert!(source.analysis_preamble().contains("COMMUNICATION RULES:"));
+    }
+}
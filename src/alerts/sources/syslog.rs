@@ -0,0 +1,179 @@
+use color_eyre::Result;
+use serde_json::Value;
+
+use crate::alerts::sources::{self, AlertEnvelope, AlertSource};
+use crate::alerts::syslog::SyslogAlert;
+use crate::config::{MatchReason, PrefixesConfig, RelevanceMatch};
+use crate::database::models::AlertKind;
+
+const ANALYSIS_ROLE: &str = r#"
+You are a network operations analyst for busy NOC operators who need FAST, ACTIONABLE insights.
+You triage syslog events from routers and switches: BGP session and prefix limit events, OSPF
+adjacency changes, interface state changes and hardware alarms.
+
+CRITICAL: Use your available tools to PROACTIVELY gather enrichment data.
+Check whether the routing impact is visible from the outside (prefix visibility, peer ASN
+ownership, recent announcements) so the operator knows how serious the event is."#;
+
+const CHAT_ROLE: &str = r#"
+You are a network operations assistant helping NOC operators with follow-up questions about syslog
+events from their devices (BGP session flaps, prefix limits, OSPF adjacency changes, link state).
+You are answering questions about an event that has already been analyzed."#;
+
+const INSTRUCTIONS: &str = r#"1. USE YOUR TOOLS: Look up the BGP or OSPF neighbor and its ASN, and check visibility of prefixes that may depend on this session or link
+2. ASSESS IMPACT: Say whether traffic or routing is likely affected (redundant paths, single transit, customer session, etc.)
+3. SAVE OPERATOR TIME: They should NOT need to run additional queries - you provide all relevant context
+4. BE SPECIFIC: Name the device, interface or neighbor, and the likely cause when the message points to one (e.g. hold timer expiry, prefix limit, dead timer)
+5. KEY FACTS: affected_prefix holds the prefixes behind the session or link, expected_asn the neighbor ASN, duration the time since the event; observed_asn and peer_count stay null"#;
+
+impl SyslogAlert {
+    /// Common view of the message, keyed on the device that logged it
+    pub fn envelope(&self) -> Result<AlertEnvelope> {
+        let category = self
+            .event
+            .as_ref()
+            .map(|event| event.name().to_string())
+            .unwrap_or_else(|| self.rule.clone());
+
+        Ok(AlertEnvelope {
+            kind: AlertKind::Syslog,
+            category,
+            title: self.message.clone(),
+            resource: self.device.address.clone(),
+            related_resource: self.event.as_ref().map(|e| e.subject().to_string()),
+            asn: None,
+            observed_asn: self
+                .event
+                .as_ref()
+                .and_then(|e| e.remote_as())
+                .map(|asn| asn.to_string()),
+            earliest: self.received_at.clone(),
+            latest: self.received_at.clone(),
+            peers: 0,
+            paths: 0,
+            payload: serde_json::to_value(self)?,
+            rpki: None,
+            relevance: None,
+        })
+    }
+}
+
+/// Messages received by the built-in syslog listener
+pub struct SyslogSource;
+
+impl AlertSource for SyslogSource {
+    fn kind(&self) -> AlertKind {
+        AlertKind::Syslog
+    }
+
+    fn label(&self) -> &'static str {
+        "Syslog event"
+    }
+
+    fn normalize(&self, payload: Value) -> Result<AlertEnvelope> {
+        serde_json::from_value::<SyslogAlert>(payload)?.envelope()
+    }
+
+    /// Messages matter when they come from a monitored device or concern a neighbor or
+    /// neighbor ASN we monitor
+    fn relevance(
+        &self,
+        alert: &AlertEnvelope,
+        prefixes: &PrefixesConfig,
+    ) -> Option<RelevanceMatch> {
+        prefixes
+            .device_match(&alert.resource)
+            .or_else(|| {
+                alert
+                    .related_resource
+                    .as_deref()
+                    .and_then(|neighbor| prefixes.address_match(neighbor))
+            })
+            .or_else(|| {
+                alert
+                    .observed_asn
+                    .as_deref()
+                    .and_then(|asn| prefixes.asn_match(asn, MatchReason::MonitoredAsn))
+            })
+    }
+
+    fn analysis_preamble(&self) -> String {
+        sources::analysis_preamble(ANALYSIS_ROLE)
+    }
+
+    fn analysis_prompt(&self, alert: &AlertEnvelope) -> Result<String> {
+        let event_json = serde_json::to_string_pretty(&alert.payload)?;
+        Ok(sources::report_prompt(
+            "syslog event",
+            "Syslog Event",
+            &event_json,
+            INSTRUCTIONS,
+        ))
+    }
+
+    fn chat_preamble(&self) -> String {
+        sources::chat_preamble(CHAT_ROLE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::sources::{normalize, source_for};
+    use crate::alerts::syslog::SyslogDevice;
+    use crate::alerts::syslog::events::SyslogEvent;
+
+    fn alert(event: Option<SyslogEvent>) -> SyslogAlert {
+        SyslogAlert {
+            message: "BGP session to 192.0.2.1 down on edge1".to_string(),
+            rule: "bgp".to_string(),
+            device: SyslogDevice {
+                address: "198.51.100.1".to_string(),
+                name: Some("edge1".to_string()),
+                ..Default::default()
+            },
+            event,
+            facility: "local7".to_string(),
+            severity: "notice".to_string(),
+            app: None,
+            timestamp: None,
+            received_at: "2025-01-15T10:00:00+00:00".to_string(),
+            text: "%BGP-5-ADJCHANGE: neighbor 192.0.2.1 Down".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let alert = alert(Some(SyslogEvent::BgpSession {
+            neighbor: "192.0.2.1".to_string(),
+            remote_as: Some(64496),
+            from_state: None,
+            to_state: "Down".to_string(),
+            reason: None,
+        }))
+        .envelope()
+        .unwrap();
+        assert_eq!(alert.category, "bgp_session");
+        assert_eq!(alert.resource, "198.51.100.1");
+        assert_eq!(alert.related_resource.as_deref(), Some("192.0.2.1"));
+        assert_eq!(alert.observed_asn.as_deref(), Some("64496"));
+
+        let stored = serde_json::to_string(&alert.payload).unwrap();
+        assert_eq!(normalize(AlertKind::Syslog, &stored).unwrap(), alert);
+        assert!(normalize(AlertKind::Syslog, r#"{"message":"x"}"#).is_err());
+
+        let prompt = source_for(AlertKind::Syslog)
+            .analysis_prompt(&alert)
+            .unwrap();
+        assert!(prompt.contains("Syslog Event:"));
+        assert!(prompt.contains("bgp_session"));
+    }
+
+    #[test]
+    fn test_unparsed_messages_are_keyed_on_rule() {
+        let alert = alert(None).envelope().unwrap();
+        assert_eq!(alert.category, "bgp");
+        assert_eq!(alert.related_resource, None);
+        assert_eq!(alert.observed_asn, None);
+    }
+}
@@ -0,0 +1,440 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// Routing and link events recognised in vendor syslog messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SyslogEvent {
+    /// BGP session going up or down, e.g. Cisco `%BGP-5-ADJCHANGE`
+    BgpSession {
+        neighbor: String,
+        #[serde(default)]
+        remote_as: Option<u32>,
+        #[serde(default)]
+        from_state: Option<String>,
+        to_state: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Prefixes received from a neighbor crossing the warning threshold or the limit
+    BgpPrefixLimit {
+        neighbor: String,
+        #[serde(default)]
+        remote_as: Option<u32>,
+        #[serde(default)]
+        received: Option<u64>,
+        #[serde(default)]
+        limit: Option<u64>,
+        /// False when only the warning threshold was crossed
+        exceeded: bool,
+    },
+    OspfNeighbor {
+        neighbor: String,
+        interface: String,
+        from_state: String,
+        to_state: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    LinkState {
+        interface: String,
+        state: String,
+    },
+}
+
+impl SyslogEvent {
+    /// Event name used by rules and as the alert category
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BgpSession { .. } => "bgp_session",
+            Self::BgpPrefixLimit { .. } => "bgp_prefix_limit",
+            Self::OspfNeighbor { .. } => "ospf_neighbor",
+            Self::LinkState { .. } => "link_state",
+        }
+    }
+
+    /// Normalised state rules match on: `up`, `down`, `exceeded`, `threshold` or the
+    /// OSPF state the neighbor moved to
+    pub fn state(&self) -> String {
+        match self {
+            Self::BgpSession { to_state, .. } => {
+                if to_state.eq_ignore_ascii_case("up")
+                    || to_state.eq_ignore_ascii_case("established")
+                {
+                    "up"
+                } else {
+                    "down"
+                }
+            }
+            .to_string(),
+            Self::BgpPrefixLimit { exceeded, .. } => {
+                if *exceeded { "exceeded" } else { "threshold" }.to_string()
+            }
+            Self::OspfNeighbor { to_state, .. } => match to_state.to_lowercase().as_str() {
+                "full" => "up".to_string(),
+                "down" => "down".to_string(),
+                other => other.to_string(),
+            },
+            Self::LinkState { state, .. } => {
+                if state.eq_ignore_ascii_case("up") {
+                    "up"
+                } else {
+                    "down"
+                }
+            }
+            .to_string(),
+        }
+    }
+
+    pub fn remote_as(&self) -> Option<u32> {
+        match self {
+            Self::BgpSession { remote_as, .. } | Self::BgpPrefixLimit { remote_as, .. } => {
+                *remote_as
+            }
+            _ => None,
+        }
+    }
+
+    /// Neighbor address or interface name the event is about
+    pub fn subject(&self) -> &str {
+        match self {
+            Self::LinkState { interface, .. } => interface,
+            Self::BgpSession { neighbor, .. }
+            | Self::BgpPrefixLimit { neighbor, .. }
+            | Self::OspfNeighbor { neighbor, .. } => neighbor,
+        }
+    }
+
+    /// One line summary, e.g. `BGP session to 192.0.2.1 (AS64496) down: Hold timer expired`
+    pub fn describe(&self) -> String {
+        let remote_as = self
+            .remote_as()
+            .map(|asn| format!(" (AS{asn})"))
+            .unwrap_or_default();
+        match self {
+            Self::BgpSession {
+                neighbor, reason, ..
+            } => {
+                let reason = reason
+                    .as_deref()
+                    .map(|r| format!(": {r}"))
+                    .unwrap_or_default();
+                format!(
+                    "BGP session to {neighbor}{remote_as} {}{reason}",
+                    self.state()
+                )
+            }
+            Self::BgpPrefixLimit {
+                neighbor,
+                received,
+                limit,
+                exceeded,
+                ..
+            } => {
+                let what = if *exceeded {
+                    "exceeded its prefix limit"
+                } else {
+                    "reached its prefix threshold"
+                };
+                let counts = match (received, limit) {
+                    (Some(received), Some(limit)) => format!(" ({received} of {limit})"),
+                    (Some(received), None) => format!(" ({received} received)"),
+                    _ => String::new(),
+                };
+                format!("BGP neighbor {neighbor}{remote_as} {what}{counts}")
+            }
+            Self::OspfNeighbor {
+                neighbor,
+                interface,
+                from_state,
+                to_state,
+                ..
+            } => format!(
+                "OSPF neighbor {neighbor} on {interface} changed from {from_state} to {to_state}"
+            ),
+            Self::LinkState { interface, state } => format!("Interface {interface} {state}"),
+        }
+    }
+}
+
+type Parser = fn(&Captures) -> Option<SyslogEvent>;
+
+fn pattern(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("built-in syslog pattern must compile")
+}
+
+fn text(captures: &Captures, name: &str) -> Option<String> {
+    captures
+        .name(name)
+        .map(|m| m.as_str().trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn number<T: std::str::FromStr>(captures: &Captures, name: &str) -> Option<T> {
+    captures.name(name)?.as_str().parse().ok()
+}
+
+/// Vendor message formats, tried in order
+static PATTERNS: LazyLock<Vec<(Regex, Parser)>> = LazyLock::new(|| {
+    vec![
+        // Cisco IOS/IOS-XE/IOS-XR, e.g.
+        // %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Down BGP Notification sent
+        (
+            pattern(
+                r"%(?:ROUTING-)?BGP-\d-ADJCHANGE\s*:\s*neighbor\s+(?P<neighbor>\S+)(?:\s+vpn vrf \S+)?\s+(?P<state>Up|Down)\b\s*-?\s*(?P<reason>.*)$",
+            ),
+            |c| {
+                Some(SyslogEvent::BgpSession {
+                    neighbor: text(c, "neighbor")?,
+                    remote_as: None,
+                    from_state: None,
+                    to_state: text(c, "state")?,
+                    reason: text(c, "reason"),
+                })
+            },
+        ),
+        // Junos, e.g.
+        // RPD_BGP_NEIGHBOR_STATE_CHANGED: BGP peer 192.0.2.1 (External AS 64496) changed state from Established to Idle (event HoldTime)
+        (
+            pattern(
+                r"RPD_BGP_NEIGHBOR_STATE_CHANGED:\s*BGP peer (?P<neighbor>[^\s+]+)(?:\+\d+)? \((?:External|Internal) AS (?P<asn>\d+)\) changed state from (?P<from>\w+) to (?P<to>\w+)(?: \(event (?P<reason>\w+)\))?",
+            ),
+            |c| {
+                Some(SyslogEvent::BgpSession {
+                    neighbor: text(c, "neighbor")?,
+                    remote_as: number(c, "asn"),
+                    from_state: text(c, "from"),
+                    to_state: text(c, "to")?,
+                    reason: text(c, "reason"),
+                })
+            },
+        ),
+        // Junos, e.g.
+        // BGP_PREFIX_THRESH_EXCEEDED: 192.0.2.1 (External AS 64496): Configured maximum prefix-limit threshold(80) exceeded for inet-unicast nlri: 850 (instance master)
+        (
+            pattern(
+                r"BGP_PREFIX_(?P<kind>THRESH|LIMIT)_EXCEEDED:\s*(?P<neighbor>[^\s+]+)(?:\+\d+)? \((?:External|Internal) AS (?P<asn>\d+)\): Configured maximum prefix-limit(?: threshold)?\((?P<limit>\d+)\) exceeded for \S+ nlri: (?P<received>\d+)",
+            ),
+            |c| {
+                let exceeded = &c["kind"] == "LIMIT";
+                Some(SyslogEvent::BgpPrefixLimit {
+                    neighbor: text(c, "neighbor")?,
+                    remote_as: number(c, "asn"),
+                    received: number(c, "received"),
+                    // The threshold is a percentage of the limit, not a prefix count
+                    limit: if exceeded { number(c, "limit") } else { None },
+                    exceeded,
+                })
+            },
+        ),
+        // Cisco, e.g.
+        // %BGP-4-MAXPFX: No. of IPv4 Unicast prefix received from 192.0.2.1 reaches 80, max 100
+        (
+            pattern(
+                r"%BGP-\d-MAXPFX\s*:\s*No\. of .*?prefix(?:es)? received from (?P<neighbor>[^\s:]+)(?:\s*\(afi \d+\))?:?\s*reaches? (?P<received>\d+), max (?P<limit>\d+)",
+            ),
+            |c| {
+                Some(SyslogEvent::BgpPrefixLimit {
+                    neighbor: text(c, "neighbor")?,
+                    remote_as: None,
+                    received: number(c, "received"),
+                    limit: number(c, "limit"),
+                    exceeded: false,
+                })
+            },
+        ),
+        // Cisco, e.g.
+        // %BGP-3-MAXPFXEXCEED: No. of IPv4 Unicast prefix received from 192.0.2.1: 101 exceed limit 100
+        (
+            pattern(
+                r"%BGP-\d-MAXPFXEXCEED\s*:\s*No\. of .*?prefix(?:es)? received from (?P<neighbor>[^\s:]+)(?:\s*\(afi \d+\))?:?\s*(?P<received>\d+) exceeds? (?:the )?limit (?P<limit>\d+)",
+            ),
+            |c| {
+                Some(SyslogEvent::BgpPrefixLimit {
+                    neighbor: text(c, "neighbor")?,
+                    remote_as: None,
+                    received: number(c, "received"),
+                    limit: number(c, "limit"),
+                    exceeded: true,
+                })
+            },
+        ),
+        // Cisco, e.g.
+        // %OSPF-5-ADJCHG: Process 1, Nbr 10.0.0.2 on GigabitEthernet0/1 from FULL to DOWN, Neighbor Down: Dead timer expired
+        (
+            pattern(
+                r"%OSPF(?:V3)?-\d-ADJCHG\s*:\s*Process \S+,\s*Nbr (?P<neighbor>\S+) on (?P<interface>\S+) from (?P<from>\S+) to (?P<to>[^\s,]+),?\s*(?P<reason>.*)$",
+            ),
+            |c| {
+                Some(SyslogEvent::OspfNeighbor {
+                    neighbor: text(c, "neighbor")?,
+                    interface: text(c, "interface")?,
+                    from_state: text(c, "from")?,
+                    to_state: text(c, "to")?,
+                    reason: text(c, "reason"),
+                })
+            },
+        ),
+        // Junos, e.g.
+        // RPD_OSPF_NBRDOWN: OSPF neighbor 10.0.0.2 (realm ospf-v2 ge-0/0/1.0 area 0.0.0.0) state changed from Full to Down due to InActiveTimer (event reason: BFD session timed out and neighbor was declared dead)
+        (
+            pattern(
+                r"RPD_OSPF_NBR(?:UP|DOWN):\s*OSPF neighbor (?P<neighbor>\S+) \(realm \S+ (?P<interface>\S+) area [^)]+\) state changed from (?P<from>\S+) to (?P<to>\S+)(?: due to (?P<reason>.*))?$",
+            ),
+            |c| {
+                Some(SyslogEvent::OspfNeighbor {
+                    neighbor: text(c, "neighbor")?,
+                    interface: text(c, "interface")?,
+                    from_state: text(c, "from")?,
+                    to_state: text(c, "to")?,
+                    reason: text(c, "reason"),
+                })
+            },
+        ),
+        // Cisco, e.g.
+        // %LINK-3-UPDOWN: Interface GigabitEthernet0/1, changed state to down
+        (
+            pattern(
+                r"%LINK-\d-UPDOWN\s*:\s*Interface (?P<interface>[^,]+), changed state to (?P<state>administratively down|\w+)",
+            ),
+            |c| {
+                Some(SyslogEvent::LinkState {
+                    interface: text(c, "interface")?,
+                    state: text(c, "state")?,
+                })
+            },
+        ),
+        // Junos, e.g.
+        // SNMP_TRAP_LINK_DOWN: ifIndex 527, ifAdminStatus up(1), ifOperStatus down(2), ifName ge-0/0/1
+        (
+            pattern(
+                r"SNMP_TRAP_LINK_(?P<state>UP|DOWN):\s*ifIndex \d+,.*ifName (?P<interface>\S+)",
+            ),
+            |c| {
+                Some(SyslogEvent::LinkState {
+                    interface: text(c, "interface")?,
+                    state: text(c, "state")?.to_lowercase(),
+                })
+            },
+        ),
+    ]
+});
+
+/// Recognise a routing or link event in the text of a syslog message
+pub fn parse(text: &str) -> Option<SyslogEvent> {
+    PATTERNS
+        .iter()
+        .find_map(|(regex, parser)| regex.captures(text).and_then(|captures| parser(&captures)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bgp_sessions() {
+        let event = parse(
+            "*Mar  1 18:46:11.123: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Down BGP Notification sent",
+        )
+        .unwrap();
+        assert_eq!(event.name(), "bgp_session");
+        assert_eq!(event.state(), "down");
+        assert_eq!(event.subject(), "192.0.2.1");
+        assert_eq!(
+            event.describe(),
+            "BGP session to 192.0.2.1 down: BGP Notification sent"
+        );
+
+        let event = parse("%BGP-5-ADJCHANGE: neighbor 2001:db8::1 vpn vrf CUST Up").unwrap();
+        assert_eq!(event.state(), "up");
+        assert_eq!(event.subject(), "2001:db8::1");
+
+        let event = parse(
+            "RPD_BGP_NEIGHBOR_STATE_CHANGED: BGP peer 192.0.2.1+179 (External AS 64496) changed state from Established to Idle (event HoldTime) (instance master)",
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            SyslogEvent::BgpSession {
+                neighbor: "192.0.2.1".to_string(),
+                remote_as: Some(64496),
+                from_state: Some("Established".to_string()),
+                to_state: "Idle".to_string(),
+                reason: Some("HoldTime".to_string()),
+            }
+        );
+        assert_eq!(event.state(), "down");
+    }
+
+    #[test]
+    fn test_parse_prefix_limits() {
+        let event = parse(
+            "BGP_PREFIX_THRESH_EXCEEDED: 192.0.2.1 (External AS 64496): Configured maximum prefix-limit threshold(80) exceeded for inet-unicast nlri: 850 (instance master)",
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            SyslogEvent::BgpPrefixLimit {
+                neighbor: "192.0.2.1".to_string(),
+                remote_as: Some(64496),
+                received: Some(850),
+                limit: None,
+                exceeded: false,
+            }
+        );
+        assert_eq!(event.state(), "threshold");
+
+        let event = parse(
+            "%BGP-3-MAXPFXEXCEED: No. of IPv4 Unicast prefix received from 192.0.2.1: 101 exceed limit 100",
+        )
+        .unwrap();
+        assert_eq!(event.state(), "exceeded");
+        assert_eq!(
+            event.describe(),
+            "BGP neighbor 192.0.2.1 exceeded its prefix limit (101 of 100)"
+        );
+
+        let event = parse(
+            "%BGP-4-MAXPFX: No. of IPv4 Unicast prefix received from 192.0.2.1 reaches 80, max 100",
+        )
+        .unwrap();
+        assert_eq!(event.state(), "threshold");
+    }
+
+    #[test]
+    fn test_parse_ospf_and_links() {
+        let event = parse(
+            "%OSPF-5-ADJCHG: Process 1, Nbr 10.0.0.2 on GigabitEthernet0/1 from FULL to DOWN, Neighbor Down: Dead timer expired",
+        )
+        .unwrap();
+        assert_eq!(event.name(), "ospf_neighbor");
+        assert_eq!(event.state(), "down");
+        assert_eq!(event.subject(), "10.0.0.2");
+
+        let event = parse(
+            "RPD_OSPF_NBRUP: OSPF neighbor 10.0.0.2 (realm ospf-v2 ge-0/0/1.0 area 0.0.0.0) state changed from Loading to Full due to LoadDone (event reason: OSPF loading completed)",
+        )
+        .unwrap();
+        assert_eq!(event.state(), "up");
+
+        let event =
+            parse("%LINK-3-UPDOWN: Interface GigabitEthernet0/1, changed state to down").unwrap();
+        assert_eq!(
+            event,
+            SyslogEvent::LinkState {
+                interface: "GigabitEthernet0/1".to_string(),
+                state: "down".to_string(),
+            }
+        );
+
+        let event = parse(
+            "SNMP_TRAP_LINK_DOWN: ifIndex 527, ifAdminStatus up(1), ifOperStatus down(2), ifName ge-0/0/1",
+        )
+        .unwrap();
+        assert_eq!(event.subject(), "ge-0/0/1");
+        assert_eq!(event.state(), "down");
+
+        assert_eq!(parse("%SYS-5-CONFIG_I: Configured from console"), None);
+    }
+}
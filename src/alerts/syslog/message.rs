@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+
+const SEVERITIES: [&str; 8] = [
+    "emergency",
+    "alert",
+    "critical",
+    "error",
+    "warning",
+    "notice",
+    "informational",
+    "debug",
+];
+
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/// Numeric severity of a name such as `warning` or `err`
+pub fn severity_from_name(name: &str) -> Option<u8> {
+    let name = name.trim().to_lowercase();
+    let name = match name.as_str() {
+        "emerg" | "panic" => "emergency",
+        "crit" => "critical",
+        "err" => "error",
+        "warn" => "warning",
+        "info" => "informational",
+        other => other,
+    };
+    SEVERITIES.iter().position(|s| *s == name).map(|s| s as u8)
+}
+
+pub fn severity_name(severity: u8) -> &'static str {
+    SEVERITIES
+        .get(severity as usize)
+        .copied()
+        .unwrap_or("debug")
+}
+
+/// A syslog message split into its RFC 5424 or RFC 3164 header fields
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyslogMessage {
+    pub facility: String,
+    /// 0 (emergency) to 7 (debug)
+    pub severity: u8,
+    /// Timestamp as written by the sender
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    /// APP-NAME, or the tag of RFC 3164 messages, e.g. `rpd`
+    pub app: Option<String>,
+    pub msg_id: Option<String>,
+    pub text: String,
+}
+
+impl SyslogMessage {
+    /// Parse a message, falling back to treating unrecognised headers as message text
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim_end_matches(['\r', '\n', '\0']);
+        let (pri, rest) = parse_pri(raw).unwrap_or((13, raw));
+        let facility = FACILITIES
+            .get((pri >> 3) as usize)
+            .copied()
+            .unwrap_or("unknown")
+            .to_string();
+        let severity = (pri & 7) as u8;
+
+        let mut message = match rest.strip_prefix("1 ") {
+            Some(rest) => parse_rfc5424(rest),
+            None => parse_rfc3164(rest),
+        };
+        message.facility = facility;
+        message.severity = severity;
+        message
+    }
+
+    pub fn severity_name(&self) -> &'static str {
+        severity_name(self.severity)
+    }
+}
+
+/// `<PRI>` prefix, e.g. `<189>`
+fn parse_pri(raw: &str) -> Option<(u16, &str)> {
+    let rest = raw.strip_prefix('<')?;
+    let end = rest.find('>').filter(|end| (1..=3).contains(end))?;
+    let pri = rest[..end].parse::<u16>().ok().filter(|pri| *pri < 192)?;
+    Some((pri, &rest[end + 1..]))
+}
+
+fn nil(field: &str) -> Option<String> {
+    (field != "-" && !field.is_empty()).then(|| field.to_string())
+}
+
+/// `TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG`
+fn parse_rfc5424(rest: &str) -> SyslogMessage {
+    let mut fields = rest.splitn(6, ' ');
+    let timestamp = fields.next().and_then(nil);
+    let hostname = fields.next().and_then(nil);
+    let app = fields.next().and_then(nil);
+    let _proc_id = fields.next();
+    let msg_id = fields.next().and_then(nil);
+    let rest = fields.next().unwrap_or_default();
+
+    let text = skip_structured_data(rest)
+        .trim_start_matches('\u{feff}')
+        .to_string();
+
+    SyslogMessage {
+        facility: String::new(),
+        severity: 0,
+        timestamp,
+        hostname,
+        app,
+        msg_id,
+        text,
+    }
+}
+
+/// Skip `-` or a run of `[id param="value"]` elements, honouring escaped `]`
+fn skip_structured_data(rest: &str) -> &str {
+    if let Some(text) = rest.strip_prefix('-') {
+        return text.strip_prefix(' ').unwrap_or(text);
+    }
+
+    let mut in_element = false;
+    let mut in_value = false;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_value => escaped = true,
+            '"' if in_element => in_value = !in_value,
+            '[' if !in_element => in_element = true,
+            ']' if in_element && !in_value => in_element = false,
+            ' ' if !in_element => return &rest[i + 1..],
+            _ if !in_element => return &rest[i..],
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// `Mmm dd hh:mm:ss HOSTNAME TAG: MSG`, where every part of the header is optional
+fn parse_rfc3164(rest: &str) -> SyslogMessage {
+    let mut timestamp = None;
+    let mut hostname = None;
+    let mut rest = rest.trim_start();
+
+    if rest.len() >= 16 && is_bsd_timestamp(&rest[..15]) && rest.as_bytes()[15] == b' ' {
+        timestamp = Some(rest[..15].to_string());
+        rest = &rest[16..];
+
+        // The hostname follows the timestamp unless the sender went straight to its tag
+        if let Some((host, after)) = rest.split_once(' ')
+            && !host.ends_with(':')
+            && !host.contains('[')
+        {
+            hostname = Some(host.to_string());
+            rest = after;
+        }
+    }
+
+    let (app, text) = match rest.split_once(": ") {
+        Some((tag, text)) if is_tag(tag) => {
+            let app = tag.split('[').next().unwrap_or(tag);
+            (Some(app.to_string()), text)
+        }
+        _ => (None, rest),
+    };
+
+    SyslogMessage {
+        facility: String::new(),
+        severity: 0,
+        timestamp,
+        hostname,
+        app,
+        msg_id: None,
+        text: text.to_string(),
+    }
+}
+
+fn is_bsd_timestamp(value: &str) -> bool {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let bytes = value.as_bytes();
+    value.is_char_boundary(3)
+        && MONTHS.contains(&&value[..3])
+        && bytes[3] == b' '
+        && bytes[6] == b' '
+        && bytes[9] == b':'
+        && bytes[12] == b':'
+}
+
+/// A program name with an optional `[pid]`, e.g. `rpd[1552]`
+fn is_tag(tag: &str) -> bool {
+    let name = match tag.split_once('[') {
+        Some((name, pid)) if pid.ends_with(']') => name,
+        Some(_) => return false,
+        None => tag,
+    };
+    !name.is_empty()
+        && name.len() <= 48
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let message = SyslogMessage::parse(
+            "<165>1 2025-01-15T10:00:00.003Z edge1 rpd 1552 BGP [origin ip=\"192.0.2.1\"][meta note=\"a \\] b\"] \u{feff}BGP peer down\n",
+        );
+        assert_eq!(message.facility, "local4");
+        assert_eq!(message.severity, 5);
+        assert_eq!(message.severity_name(), "notice");
+        assert_eq!(
+            message.timestamp.as_deref(),
+            Some("2025-01-15T10:00:00.003Z")
+        );
+        assert_eq!(message.hostname.as_deref(), Some("edge1"));
+        assert_eq!(message.app.as_deref(), Some("rpd"));
+        assert_eq!(message.msg_id.as_deref(), Some("BGP"));
+        assert_eq!(message.text, "BGP peer down");
+
+        let message = SyslogMessage::parse("<14>1 - - - - - - hello");
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.text, "hello");
+    }
+
+    #[test]
+    fn test_parse_rfc3164() {
+        let message = SyslogMessage::parse(
+            "<28>Jan 15 10:00:00 edge1 rpd[1552]: BGP_PREFIX_THRESH_EXCEEDED: 192.0.2.1 (External AS 64496): limit",
+        );
+        assert_eq!(message.facility, "daemon");
+        assert_eq!(message.severity, 4);
+        assert_eq!(message.timestamp.as_deref(), Some("Jan 15 10:00:00"));
+        assert_eq!(message.hostname.as_deref(), Some("edge1"));
+        assert_eq!(message.app.as_deref(), Some("rpd"));
+        assert!(message.text.starts_with("BGP_PREFIX_THRESH_EXCEEDED"));
+
+        // Cisco IOS without timestamp or hostname
+        let message = SyslogMessage::parse(
+            "<189>123: *Mar  1 18:46:11.123: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Down",
+        );
+        assert_eq!(message.facility, "local7");
+        assert_eq!(message.hostname, None);
+        assert!(message.text.contains("%BGP-5-ADJCHANGE"));
+
+        let message = SyslogMessage::parse("no header at all");
+        assert_eq!(message.facility, "user");
+        assert_eq!(message.severity, 5);
+        assert_eq!(message.text, "no header at all");
+    }
+
+    #[test]
+    fn test_severity_names() {
+        assert_eq!(severity_from_name("warning"), Some(4));
+        assert_eq!(severity_from_name("CRIT"), Some(2));
+        assert_eq!(severity_from_name("loud"), None);
+        assert_eq!(severity_name(3), "error");
+    }
+}
@@ -0,0 +1,496 @@
+use chrono::Utc;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+
+use crate::alerts::http::server::AppState;
+use crate::alerts::ingest;
+use crate::alerts::sources::{self, AlertEnvelope};
+use crate::config::PrefixesConfig;
+use crate::database::models::AlertKind;
+use crate::prefixes::PrefixesStore;
+
+pub mod events;
+pub mod message;
+pub mod rules;
+
+use events::SyslogEvent;
+use message::SyslogMessage;
+use rules::{RuleAction, SyslogRules};
+
+/// Alerts waiting to be stored before the listeners stop reading messages
+const ALERT_BUFFER: usize = 256;
+
+/// Longest message accepted over TCP; UDP is bounded by the datagram size
+const MAX_MESSAGE_LEN: usize = 65_535;
+
+/// A syslog message that matched an alerting rule, as stored and shown to the analysis agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyslogAlert {
+    /// One line summary, e.g. `BGP session to 192.0.2.1 (AS64496) down on edge1`
+    pub message: String,
+    /// Name of the rule that raised the alert
+    pub rule: String,
+    pub device: SyslogDevice,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<SyslogEvent>,
+    pub facility: String,
+    pub severity: String,
+    #[serde(default)]
+    pub app: Option<String>,
+    /// Timestamp as written by the device
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    pub received_at: String,
+    /// Message text after the syslog header
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyslogDevice {
+    pub address: String,
+    /// Hostname the device put in the message
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// Turns raw messages into alerts for monitored resources
+pub struct Pipeline {
+    rules: SyslogRules,
+    prefixes: Arc<PrefixesStore>,
+}
+
+impl Pipeline {
+    pub fn new(rules: SyslogRules, prefixes: Arc<PrefixesStore>) -> Self {
+        Self { rules, prefixes }
+    }
+
+    /// Parse a message and return its alert if an alerting rule matches and it concerns a
+    /// monitored resource
+    pub fn process(&self, raw: &str, sender: IpAddr) -> Option<AlertEnvelope> {
+        let message = SyslogMessage::parse(raw);
+        let event = events::parse(&message.text);
+
+        let rule = self.rules.evaluate(&message, event.as_ref())?;
+        if rule.action == RuleAction::Ignore {
+            tracing::debug!(
+                "Syslog message from {} ignored by rule {}",
+                sender,
+                rule.name
+            );
+            return None;
+        }
+
+        let prefixes = self.prefixes.get();
+        let alert = interpret(message, event, &rule.name, sender, &prefixes);
+        let mut alert = match alert.envelope() {
+            Ok(alert) => alert,
+            Err(e) => {
+                tracing::warn!("Ignoring syslog message from {}: {}", sender, e);
+                return None;
+            }
+        };
+
+        alert.relevance = sources::source_for(AlertKind::Syslog).relevance(&alert, &prefixes);
+        if alert.relevance.is_none() {
+            tracing::debug!(
+                "Syslog {} from {} is not relevant to monitored resources",
+                alert.category,
+                alert.resource
+            );
+            return None;
+        }
+        Some(alert)
+    }
+}
+
+/// Attach the sending device to a matched message
+pub fn interpret(
+    message: SyslogMessage,
+    event: Option<SyslogEvent>,
+    rule: &str,
+    sender: IpAddr,
+    prefixes: &PrefixesConfig,
+) -> SyslogAlert {
+    let sender = sender.to_string();
+
+    // Relays forward messages from other devices, so fall back to the hostname they carry
+    let known = prefixes.find_device_by_host(&sender).or_else(|| {
+        message
+            .hostname
+            .as_deref()
+            .and_then(|host| prefixes.find_device_by_host(host))
+    });
+    let device = match known {
+        Some((address, info)) => SyslogDevice {
+            address: address.to_string(),
+            hostname: message.hostname.clone(),
+            name: Some(info.name.clone()),
+            group: Some(info.group.clone()).filter(|g| !g.is_empty()),
+        },
+        None => SyslogDevice {
+            address: sender,
+            hostname: message.hostname.clone(),
+            name: None,
+            group: None,
+        },
+    };
+
+    let device_name = device
+        .name
+        .as_deref()
+        .or(device.hostname.as_deref())
+        .unwrap_or(&device.address);
+    let summary = match &event {
+        Some(event) => format!("{} on {device_name}", event.describe()),
+        None => format!("{device_name}: {}", message.text),
+    };
+
+    SyslogAlert {
+        message: summary,
+        rule: rule.to_string(),
+        device,
+        event,
+        severity: message.severity_name().to_string(),
+        facility: message.facility,
+        app: message.app,
+        timestamp: message.timestamp,
+        received_at: Utc::now().to_rfc3339(),
+        text: message.text,
+    }
+}
+
+/// Receive one message per datagram on `socket` and forward the relevant ones as alerts
+pub async fn run_udp(
+    socket: &UdpSocket,
+    pipeline: &Pipeline,
+    alerts: &mpsc::Sender<AlertEnvelope>,
+) -> Result<()> {
+    let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf).await?;
+        let raw = String::from_utf8_lossy(&buf[..len]);
+        if let Some(alert) = pipeline.process(&raw, peer.ip())
+            && alerts.send(alert).await.is_err()
+        {
+            return Err(eyre!("Alert channel closed"));
+        }
+    }
+}
+
+/// Accept syslog connections on `listener` and forward the relevant messages as alerts
+pub async fn run_tcp(
+    listener: &TcpListener,
+    pipeline: Arc<Pipeline>,
+    alerts: &mpsc::Sender<AlertEnvelope>,
+) -> Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let pipeline = pipeline.clone();
+        let alerts = alerts.clone();
+
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stream);
+            loop {
+                let raw = match read_frame(&mut reader).await {
+                    Ok(Some(raw)) => raw,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!("Closing syslog connection from {}: {}", peer, e);
+                        break;
+                    }
+                };
+                if let Some(alert) = pipeline.process(&raw, peer.ip())
+                    && alerts.send(alert).await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Next message of a TCP stream, framed by octet counting or newlines (RFC 6587)
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<Option<String>> {
+    let first = match reader.fill_buf().await?.first() {
+        Some(byte) => *byte,
+        None => return Ok(None),
+    };
+
+    let mut frame = Vec::new();
+    if first.is_ascii_digit() {
+        let mut len = Vec::new();
+        (&mut *reader).take(8).read_until(b' ', &mut len).await?;
+        let len = std::str::from_utf8(&len)
+            .ok()
+            .and_then(|l| l.trim_end().parse::<usize>().ok())
+            .filter(|l| *l <= MAX_MESSAGE_LEN)
+            .ok_or_else(|| eyre!("Invalid octet count"))?;
+        frame.resize(len, 0);
+        reader.read_exact(&mut frame).await?;
+    } else {
+        (&mut *reader)
+            .take(MAX_MESSAGE_LEN as u64)
+            .read_until(b'\n', &mut frame)
+            .await?;
+    }
+    Ok(Some(String::from_utf8_lossy(&frame).into_owned()))
+}
+
+/// Listen for syslog over UDP and TCP when enabled and store relevant messages as alerts
+pub fn start(state: AppState) {
+    if !state.config.syslog_enabled {
+        return;
+    }
+
+    let rules = match SyslogRules::load(&state.config.syslog_rules_path) {
+        Ok(rules) => rules,
+        Err(e) => {
+            tracing::error!(
+                "Failed to load syslog rules from {}: {}",
+                state.config.syslog_rules_path,
+                e
+            );
+            return;
+        }
+    };
+    let pipeline = Arc::new(Pipeline::new(rules, state.prefixes.clone()));
+    let bind = state.config.syslog_bind.clone();
+
+    let (tx, mut rx) = mpsc::channel::<AlertEnvelope>(ALERT_BUFFER);
+
+    tokio::spawn(async move {
+        while let Some(alert) = rx.recv().await {
+            if let Err(e) = ingest::ingest(&state, &alert).await {
+                tracing::error!("Failed to store syslog alert: {}", e);
+            }
+        }
+    });
+
+    let udp_pipeline = pipeline.clone();
+    let udp_bind = bind.clone();
+    let udp_tx = tx.clone();
+    tokio::spawn(async move {
+        let socket = match UdpSocket::bind(&udp_bind).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::error!("Failed to bind syslog UDP listener to {}: {}", udp_bind, e);
+                return;
+            }
+        };
+        tracing::info!("Listening for syslog on udp://{}", udp_bind);
+
+        if let Err(e) = run_udp(&socket, &udp_pipeline, &udp_tx).await {
+            tracing::error!("Syslog UDP listener stopped: {}", e);
+        }
+    });
+
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&bind).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("Failed to bind syslog TCP listener to {}: {}", bind, e);
+                return;
+            }
+        };
+        tracing::info!("Listening for syslog on tcp://{}", bind);
+
+        if let Err(e) = run_tcp(&listener, pipeline, &tx).await {
+            tracing::error!("Syslog TCP listener stopped: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpStream;
+
+    const PREFIXES: &str = r#"
+192.0.2.0/24:
+  description: Customer network
+  asn:
+    - 65000
+  group: noc
+options:
+  monitorASns:
+    '64496':
+      group: noc
+  monitorDevices:
+    '198.51.100.1':
+      name: edge1
+      group: noc
+    '127.0.0.1':
+      name: lab
+"#;
+
+    const RULES: &str = r#"
+rules:
+  - name: ignore-core
+    host: '^core'
+    action: ignore
+  - name: bgp
+    event: bgp_session
+  - name: links
+    event: link_state
+    state: down
+"#;
+
+    fn pipeline() -> Pipeline {
+        Pipeline::new(
+            SyslogRules::from_str(RULES).unwrap(),
+            Arc::new(PrefixesStore::new(
+                "prefixes.yml",
+                PrefixesConfig::from_str(PREFIXES).unwrap(),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_process_relayed_junos_message() {
+        let alert = pipeline()
+            .process(
+                "<28>Jan 15 10:00:00 edge1 rpd[1552]: RPD_BGP_NEIGHBOR_STATE_CHANGED: BGP peer 203.0.113.9 (External AS 64496) changed state from Established to Idle (event HoldTime)",
+                "10.9.9.9".parse().unwrap(),
+            )
+            .unwrap();
+
+        // Resolved from the hostname, as the relay itself is not a monitored device
+        assert_eq!(alert.kind, AlertKind::Syslog);
+        assert_eq!(alert.category, "bgp_session");
+        assert_eq!(alert.resource, "198.51.100.1");
+        assert_eq!(alert.related_resource.as_deref(), Some("203.0.113.9"));
+        assert_eq!(alert.observed_asn.as_deref(), Some("64496"));
+        assert_eq!(
+            alert.title,
+            "BGP session to 203.0.113.9 (AS64496) down: HoldTime on edge1"
+        );
+
+        let payload: SyslogAlert = serde_json::from_value(alert.payload).unwrap();
+        assert_eq!(payload.rule, "bgp");
+        assert_eq!(payload.severity, "warning");
+        assert_eq!(payload.app.as_deref(), Some("rpd"));
+        assert_eq!(payload.device.group.as_deref(), Some("noc"));
+    }
+
+    #[test]
+    fn test_process_filters_messages() {
+        let pipeline = pipeline();
+        let unknown: IpAddr = "10.9.9.9".parse().unwrap();
+
+        // Unknown device, but the neighbor sits in a monitored prefix
+        assert!(
+            pipeline
+                .process(
+                    "<189>Jan 15 10:00:00 pe9 55: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Up",
+                    unknown
+                )
+                .is_some()
+        );
+        // Unknown device and neighbor
+        assert!(
+            pipeline
+                .process(
+                    "<189>Jan 15 10:00:00 pe9 55: %BGP-5-ADJCHANGE: neighbor 203.0.113.1 Up",
+                    unknown
+                )
+                .is_none()
+        );
+        // Ignored by rule, even though the neighbor is monitored
+        assert!(
+            pipeline
+                .process(
+                    "<189>Jan 15 10:00:00 core1 55: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Up",
+                    unknown
+                )
+                .is_none()
+        );
+        // No rule matches a link coming up
+        let edge1 = "198.51.100.1".parse().unwrap();
+        assert!(
+            pipeline
+                .process(
+                    "<189>55: %LINK-3-UPDOWN: Interface Gi0/1, changed state to up",
+                    edge1
+                )
+                .is_none()
+        );
+        let alert = pipeline
+            .process(
+                "<187>55: %LINK-3-UPDOWN: Interface Gi0/1, changed state to down",
+                edge1,
+            )
+            .unwrap();
+        assert_eq!(alert.category, "link_state");
+        assert_eq!(alert.title, "Interface Gi0/1 down on edge1");
+    }
+
+    #[tokio::test]
+    async fn test_listeners_receive_messages_on_localhost() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let udp_address = socket.local_addr().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_address = listener.local_addr().unwrap();
+        let pipeline = Arc::new(pipeline());
+        let (tx, mut rx) = mpsc::channel(8);
+
+        let udp_pipeline = pipeline.clone();
+        let udp_tx = tx.clone();
+        let udp = tokio::spawn(async move { run_udp(&socket, &udp_pipeline, &udp_tx).await });
+        let tcp = tokio::spawn(async move { run_tcp(&listener, pipeline, &tx).await });
+
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        sender
+            .send_to(
+                b"<187>1 2025-01-15T10:00:00Z lab - - - - %LINK-3-UPDOWN: Interface Gi0/2, changed state to down",
+                udp_address,
+            )
+            .await
+            .unwrap();
+        let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.resource, "127.0.0.1");
+        assert_eq!(alert.related_resource.as_deref(), Some("Gi0/2"));
+
+        // One octet-counted frame followed by a newline-delimited one
+        let frame = "<189>55: %BGP-5-ADJCHANGE: neighbor 192.0.2.5 Down Peer closed the session";
+        let mut stream = TcpStream::connect(tcp_address).await.unwrap();
+        stream
+            .write_all(format!("{} {frame}", frame.len()).as_bytes())
+            .await
+            .unwrap();
+        stream
+            .write_all(b"<189>56: %BGP-5-ADJCHANGE: neighbor 192.0.2.5 Up\n")
+            .await
+            .unwrap();
+
+        let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.category, "bgp_session");
+        assert!(alert.title.contains("down: Peer closed the session"));
+        let alert = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(alert.title, "BGP session to 192.0.2.5 up on lab");
+
+        udp.abort();
+        tcp.abort();
+    }
+}
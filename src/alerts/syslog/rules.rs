@@ -0,0 +1,247 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use super::events::SyslogEvent;
+use super::message::{SyslogMessage, severity_from_name};
+
+/// Rules used when no rules file exists
+const DEFAULT_RULES: &str = include_str!("../../../syslog_rules.yml.example");
+
+const EVENT_NAMES: [&str; 4] = [
+    "bgp_session",
+    "bgp_prefix_limit",
+    "ospf_neighbor",
+    "link_state",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Alert,
+    Ignore,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleConfig {
+    name: String,
+    #[serde(default)]
+    action: RuleAction,
+    #[serde(default)]
+    event: Option<String>,
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    host: Option<String>,
+    #[serde(default)]
+    app: Option<String>,
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+/// A rule with its conditions validated and regular expressions compiled
+#[derive(Debug)]
+pub struct Rule {
+    pub name: String,
+    pub action: RuleAction,
+    event: Option<String>,
+    state: Option<String>,
+    max_severity: Option<u8>,
+    host: Option<Regex>,
+    app: Option<Regex>,
+    pattern: Option<Regex>,
+}
+
+impl Rule {
+    fn from_config(config: RuleConfig) -> Result<Self> {
+        let regex = |field: &str, value: Option<String>| {
+            value
+                .map(|v| {
+                    Regex::new(&v).map_err(|e| {
+                        eyre!("Invalid {} in syslog rule {}: {}", field, config.name, e)
+                    })
+                })
+                .transpose()
+        };
+
+        if let Some(event) = &config.event
+            && !EVENT_NAMES.contains(&event.as_str())
+        {
+            return Err(eyre!(
+                "Unknown event '{}' in syslog rule {}, expected one of {}",
+                event,
+                config.name,
+                EVENT_NAMES.join(", ")
+            ));
+        }
+        let max_severity = config
+            .severity
+            .as_deref()
+            .map(|s| {
+                severity_from_name(s)
+                    .ok_or_else(|| eyre!("Unknown severity '{}' in syslog rule {}", s, config.name))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            host: regex("host", config.host.clone())?,
+            app: regex("app", config.app.clone())?,
+            pattern: regex("pattern", config.pattern.clone())?,
+            name: config.name,
+            action: config.action,
+            event: config.event,
+            state: config.state.map(|s| s.trim().to_lowercase()),
+            max_severity,
+        })
+    }
+
+    fn matches(&self, message: &SyslogMessage, event: Option<&SyslogEvent>) -> bool {
+        if let Some(name) = &self.event
+            && event.is_none_or(|e| e.name() != name)
+        {
+            return false;
+        }
+        if let Some(state) = &self.state
+            && event.is_none_or(|e| e.state() != *state)
+        {
+            return false;
+        }
+        if self.max_severity.is_some_and(|max| message.severity > max) {
+            return false;
+        }
+
+        let field_matches = |regex: &Option<Regex>, value: Option<&str>| match regex {
+            Some(regex) => value.is_some_and(|v| regex.is_match(v)),
+            None => true,
+        };
+        field_matches(&self.host, message.hostname.as_deref())
+            && field_matches(&self.app, message.app.as_deref())
+            && field_matches(&self.pattern, Some(&message.text))
+    }
+}
+
+/// Ordered rules deciding which syslog messages become alerts
+#[derive(Debug)]
+pub struct SyslogRules {
+    pub rules: Vec<Rule>,
+}
+
+impl SyslogRules {
+    /// Load the rules file, falling back to the built-in rules when it does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            tracing::info!(
+                "{} not found, using the default syslog rules",
+                path.display()
+            );
+            return Self::from_str(DEFAULT_RULES);
+        }
+        let content = fs::read_to_string(path)?;
+        Self::from_str(&content)
+    }
+
+    pub fn from_str(content: &str) -> Result<Self> {
+        let file: RulesFile = serde_yaml::from_str(content)?;
+        let rules = file
+            .rules
+            .into_iter()
+            .map(Rule::from_config)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// First rule matching the message, if any
+    pub fn evaluate(&self, message: &SyslogMessage, event: Option<&SyslogEvent>) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(message, event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::syslog::events;
+
+    fn evaluate<'a>(rules: &'a SyslogRules, raw: &str) -> Option<&'a Rule> {
+        let message = SyslogMessage::parse(raw);
+        let event = events::parse(&message.text);
+        rules.evaluate(&message, event.as_ref())
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = SyslogRules::from_str(DEFAULT_RULES).unwrap();
+
+        let rule = evaluate(
+            &rules,
+            "<189>Jan 15 10:00:00 edge1 123: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Up",
+        )
+        .unwrap();
+        assert_eq!(rule.name, "bgp-session-changes");
+        assert_eq!(rule.action, RuleAction::Alert);
+
+        let rule = evaluate(
+            &rules,
+            "<189>Jan 15 10:00:00 lab-r1 123: %BGP-5-ADJCHANGE: neighbor 192.0.2.1 Down",
+        )
+        .unwrap();
+        assert_eq!(rule.action, RuleAction::Ignore);
+
+        // OSPF coming up and informational chatter match no rule
+        assert!(
+            evaluate(
+                &rules,
+                "<189>Jan 15 10:00:00 edge1 123: %OSPF-5-ADJCHG: Process 1, Nbr 10.0.0.2 on Gi0/1 from LOADING to FULL, Loading Done",
+            )
+            .is_none()
+        );
+        assert!(
+            evaluate(
+                &rules,
+                "<189>Jan 15 10:00:00 edge1 123: %SYS-5-CONFIG_I: Configured from console"
+            )
+            .is_none()
+        );
+
+        let rule = evaluate(
+            &rules,
+            "<186>Jan 15 10:00:00 edge1 chassisd: Power Supply 1 failed",
+        )
+        .unwrap();
+        assert_eq!(rule.name, "hardware-failures");
+        // Same text at a lower severity is not a hardware alarm
+        assert!(
+            evaluate(
+                &rules,
+                "<189>Jan 15 10:00:00 edge1 chassisd: Power Supply 1 failed"
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(SyslogRules::from_str("rules:\n  - name: a\n    event: bgp\n").is_err());
+        assert!(SyslogRules::from_str("rules:\n  - name: a\n    severity: loud\n").is_err());
+        assert!(SyslogRules::from_str("rules:\n  - name: a\n    pattern: '('\n").is_err());
+        assert!(SyslogRules::from_str("rules:\n  - name: a\n    action: page\n").is_err());
+        assert!(
+            SyslogRules::from_str("rules: []\n")
+                .unwrap()
+                .rules
+                .is_empty()
+        );
+    }
+}
@@ -1,41 +1,244 @@
 use color_eyre::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::prefix_trie::PrefixTrie;
+
+/// Default maximum tokens for LLM completion requests
+pub const DEFAULT_LLM_MAX_TOKENS: u64 = 4096;
+
+/// LLM backend used by every agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProvider {
+    /// Anthropic Messages API
+    Anthropic,
+    /// OpenAI Responses API
+    #[serde(rename = "openai")]
+    OpenAi,
+    /// Any server speaking the OpenAI Chat Completions API (vLLM, llama.cpp, LM Studio, ...)
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible,
+    /// Local Ollama server
+    Ollama,
+}
 
-/// Default maximum tokens for Anthropic API requests
-pub const ANTHROPIC_MAX_TOKENS: u64 = 4096;
+impl LlmProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LlmProvider::Anthropic => "anthropic",
+            LlmProvider::OpenAi => "openai",
+            LlmProvider::OpenAiCompatible => "openai_compatible",
+            LlmProvider::Ollama => "ollama",
+        }
+    }
+}
 
-#[derive(Debug, Clone, Deserialize)]
+impl TryFrom<&str> for LlmProvider {
+    type Error = String;
+
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "anthropic" => Ok(LlmProvider::Anthropic),
+            "openai" => Ok(LlmProvider::OpenAi),
+            "openai_compatible" | "openai-compatible" => Ok(LlmProvider::OpenAiCompatible),
+            "ollama" => Ok(LlmProvider::Ollama),
+            other => Err(format!("Unknown LLM provider: {other}")),
+        }
+    }
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPricing {
+    /// Estimated cost in USD of the given token counts
+    pub fn cost(&self, input_tokens: i64, output_tokens: i64) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Parse `LLM_PRICING`, e.g. `claude-sonnet-4-5-20250929=3:15,gpt-4o=2.5:10`
+///
+/// Each entry maps a model name to its input and output price in USD per million tokens.
+pub fn parse_llm_pricing(value: &str) -> Result<HashMap<String, ModelPricing>> {
+    let mut pricing = HashMap::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry.rsplit_once('=').and_then(|(model, prices)| {
+            let (input, output) = prices.split_once(':')?;
+            let price = |p: &str| {
+                p.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|p| p.is_finite() && *p >= 0.0)
+            };
+            Some((
+                model.trim().to_string(),
+                ModelPricing {
+                    input_per_mtok: price(input)?,
+                    output_per_mtok: price(output)?,
+                },
+            ))
+        });
+
+        match parsed {
+            Some((model, prices)) if !model.is_empty() => {
+                pricing.insert(model, prices);
+            }
+            _ => {
+                return Err(color_eyre::eyre::eyre!(
+                    "Invalid LLM_PRICING entry '{}', expected model=input:output",
+                    entry
+                ));
+            }
+        }
+    }
+
+    Ok(pricing)
+}
+
+/// Authentication protocol of an SNMPv3 user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpAuthProtocol {
+    Md5,
+    Sha,
+}
+
+/// SNMPv3 user allowed to send traps
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SnmpV3User {
+    pub name: String,
+    /// Protocol and passphrase; `None` for noAuthNoPriv users
+    pub auth: Option<(SnmpAuthProtocol, String)>,
+}
+
+/// Parse `SNMP_TRAP_V3_USERS`, e.g. `monitor:sha:secret123,lab`
+///
+/// Each entry is a user name, optionally followed by an authentication protocol (`md5` or
+/// `sha`) and passphrase. Users without one are accepted with noAuthNoPriv only.
+pub fn parse_snmp_v3_users(value: &str) -> Result<Vec<SnmpV3User>> {
+    let mut users = Vec::new();
+
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.splitn(3, ':');
+        let name = parts.next().unwrap_or_default().trim().to_string();
+        let auth = match (parts.next(), parts.next()) {
+            (None, _) => None,
+            (Some(protocol), Some(passphrase)) if passphrase.len() >= 8 => {
+                let protocol = match protocol.trim().to_lowercase().as_str() {
+                    "md5" => SnmpAuthProtocol::Md5,
+                    "sha" | "sha1" => SnmpAuthProtocol::Sha,
+                    other => {
+                        return Err(color_eyre::eyre::eyre!(
+                            "Unsupported SNMPv3 authentication protocol '{}' for user {}",
+                            other,
+                            name
+                        ));
+                    }
+                };
+                Some((protocol, passphrase.to_string()))
+            }
+            _ => {
+                return Err(color_eyre::eyre::eyre!(
+                    "Invalid SNMP_TRAP_V3_USERS entry for user {}, expected \
+                     user[:md5|sha:passphrase] with a passphrase of at least 8 characters",
+                    name
+                ));
+            }
+        };
+
+        if name.is_empty() {
+            return Err(color_eyre::eyre::eyre!(
+                "Invalid SNMP_TRAP_V3_USERS entry '{}', missing user name",
+                entry
+            ));
+        }
+        users.push(SnmpV3User { name, auth });
+    }
+
+    Ok(users)
+}
+
+/// Parse an ASN written as `64500`, `AS64500` or `as64500`
+///
+/// Anything else, such as a sign, a repeated `AS` or trailing characters, is rejected.
+pub fn parse_asn(asn: &str) -> Result<u32> {
+    let trimmed = asn.trim();
+    let digits = match trimmed.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("AS") => &trimmed[2..],
+        _ => trimmed,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(color_eyre::eyre::eyre!("Invalid ASN '{}'", asn));
+    }
+    digits
+        .parse()
+        .map_err(|_| color_eyre::eyre::eyre!("Invalid ASN '{}'", asn))
+}
+
+/// A monitored prefix entry, as BGPAlerter reads it from prefixes.yml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct PrefixInfo {
-    #[allow(dead_code)]
     pub description: String,
-    #[allow(dead_code)]
     pub asn: Vec<u32>,
     #[serde(default)]
     #[serde(rename = "ignoreMorespecifics")]
     pub ignore_morespecifics: bool,
     #[serde(default)]
     pub ignore: bool,
-    #[allow(dead_code)]
     pub group: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// A monitored ASN entry under `options.monitorASns`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AsnInfo {
-    #[allow(dead_code)]
     pub group: String,
 }
 
+/// Network device sending SNMP traps or syslog, keyed by its management address
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    #[serde(default)]
+    pub group: String,
+    /// Interfaces worth naming in alerts, keyed by ifIndex
+    #[serde(default)]
+    pub interfaces: HashMap<String, InterfaceInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InterfaceInfo {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// ASN of the neighbor behind the interface, e.g. a transit provider
+    #[serde(default)]
+    pub asn: Option<u32>,
+}
+
 #[derive(Debug, Clone)]
 pub struct PrefixesConfig {
     pub prefixes: HashMap<String, PrefixInfo>,
     pub monitored_asns: HashMap<String, AsnInfo>,
+    pub monitored_devices: HashMap<String, DeviceInfo>,
+    /// `prefixes` keyed by network, compiled when the config is built
+    index: PrefixTrie<String>,
 }
 
 impl PrefixesConfig {
     /// Load and parse the prefixes.yml file
+    #[allow(dead_code)] // Used in tests
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path)?;
         Self::from_str(&content)
@@ -48,22 +251,37 @@ impl PrefixesConfig {
 
         let mut prefixes = HashMap::new();
         let mut monitored_asns = HashMap::new();
+        let mut monitored_devices = HashMap::new();
 
         if let serde_yaml::Value::Mapping(mapping) = value {
             for (key, val) in mapping {
                 if let Some(key_str) = key.as_str() {
                     if key_str == "options" {
                         // Parse the options section
-                        if let serde_yaml::Value::Mapping(options_map) = val
-                            && let Some(serde_yaml::Value::Mapping(asns_map)) = options_map
+                        if let serde_yaml::Value::Mapping(options_map) = val {
+                            if let Some(serde_yaml::Value::Mapping(asns_map)) = options_map
                                 .get(serde_yaml::Value::String("monitorASns".to_string()))
-                        {
-                            for (asn_key, asn_val) in asns_map {
-                                if let Some(asn_str) = asn_key.as_str()
-                                    && let Ok(asn_info) =
-                                        serde_yaml::from_value::<AsnInfo>(asn_val.clone())
-                                {
-                                    monitored_asns.insert(asn_str.to_string(), asn_info);
+                            {
+                                for (asn_key, asn_val) in asns_map {
+                                    if let Some(asn_str) = asn_key.as_str()
+                                        && let Ok(asn_info) =
+                                            serde_yaml::from_value::<AsnInfo>(asn_val.clone())
+                                    {
+                                        monitored_asns.insert(asn_str.to_string(), asn_info);
+                                    }
+                                }
+                            }
+
+                            if let Some(serde_yaml::Value::Mapping(devices_map)) = options_map
+                                .get(serde_yaml::Value::String("monitorDevices".to_string()))
+                            {
+                                for (address, device_val) in devices_map {
+                                    if let Some(address) = address.as_str()
+                                        && let Ok(device_info) =
+                                            serde_yaml::from_value::<DeviceInfo>(device_val.clone())
+                                    {
+                                        monitored_devices.insert(address.to_string(), device_info);
+                                    }
                                 }
                             }
                         }
@@ -77,10 +295,27 @@ impl PrefixesConfig {
             }
         }
 
-        Ok(Self {
+        Ok(Self::new(prefixes, monitored_asns, monitored_devices))
+    }
+
+    pub fn new(
+        prefixes: HashMap<String, PrefixInfo>,
+        monitored_asns: HashMap<String, AsnInfo>,
+        monitored_devices: HashMap<String, DeviceInfo>,
+    ) -> Self {
+        let mut index = PrefixTrie::default();
+        for prefix in prefixes.keys() {
+            match prefix.trim().parse::<ipnet::IpNet>() {
+                Ok(net) => index.insert(&net, prefix.clone()),
+                Err(_) => tracing::warn!("Ignoring invalid monitored prefix {}", prefix),
+            }
+        }
+        Self {
             prefixes,
             monitored_asns,
-        })
+            monitored_devices,
+            index,
+        }
     }
 
     /// Check if a prefix is monitored
@@ -90,81 +325,238 @@ impl PrefixesConfig {
     }
 
     /// Check if an ASN is monitored
+    #[allow(dead_code)] // Used in tests
     pub fn is_asn_monitored(&self, asn: &str) -> bool {
         self.monitored_asns.contains_key(asn)
     }
 
-    /// Find the matching prefix info for a given alert prefix
-    /// Returns the prefix info if the alert prefix matches or is contained within a monitored prefix
-    fn find_matching_prefix_info(&self, alert_prefix: &str) -> Option<&PrefixInfo> {
+    /// Find the monitored device with this management address
+    pub fn find_device(&self, address: &str) -> Option<&DeviceInfo> {
+        self.monitored_devices.get(address.trim())
+    }
+
+    /// Find the monitored device with this management address or configured name
+    pub fn find_device_by_host(&self, host: &str) -> Option<(&str, &DeviceInfo)> {
+        let host = host.trim();
+        self.monitored_devices
+            .get_key_value(host)
+            .or_else(|| {
+                self.monitored_devices
+                    .iter()
+                    .find(|(_, device)| device.name.eq_ignore_ascii_case(host))
+            })
+            .map(|(address, device)| (address.as_str(), device))
+    }
+
+    /// The monitored prefix an IP address falls inside
+    pub fn address_match(&self, address: &str) -> Option<RelevanceMatch> {
+        let ip = address.trim().parse::<std::net::IpAddr>().ok()?;
+        let mut relevance = self.prefix_match(&ipnet::IpNet::from(ip).to_string())?;
+        relevance.value = address.trim().to_string();
+        Some(relevance)
+    }
+
+    /// Find the monitored prefix matching a given alert prefix
+    /// Returns the monitored prefix and its info if the alert prefix matches, is contained
+    /// within, or covers a monitored prefix. The most specific covering prefix wins, then the
+    /// least specific covered one.
+    pub fn find_matching_prefix(&self, alert_prefix: &str) -> Option<(&str, &PrefixInfo)> {
         // First check exact match
-        if let Some(prefix_info) = self.prefixes.get(alert_prefix) {
-            return Some(prefix_info);
+        if let Some((prefix, prefix_info)) = self.prefixes.get_key_value(alert_prefix) {
+            return Some((prefix, prefix_info));
         }
 
-        // Parse the alert prefix to check containment
-        if let Ok(alert_net) = alert_prefix.parse::<ipnet::IpNet>() {
-            // Check if alert prefix is contained in any monitored prefix
-            for (monitored_prefix, prefix_info) in &self.prefixes {
+        let alert_net = alert_prefix.parse::<ipnet::IpNet>().ok()?;
+        let entry = |prefix: &String| {
+            self.prefixes
+                .get_key_value(prefix)
+                .map(|(prefix, info)| (prefix.as_str(), info))
                 // Skip ignored prefixes
-                if prefix_info.ignore {
-                    continue;
-                }
+                .filter(|(_, info)| !info.ignore)
+        };
+
+        // Monitored prefixes containing the alert prefix, unless they ignore more-specifics
+        let covering = self
+            .index
+            .covering(&alert_net)
+            .into_iter()
+            .filter_map(|(monitored_net, prefix)| {
+                entry(prefix).filter(|(_, info)| {
+                    !info.ignore_morespecifics
+                        || alert_net.prefix_len() <= monitored_net.prefix_len()
+                })
+            })
+            .next();
+
+        // Also check if a monitored prefix is contained in the alert prefix
+        covering.or_else(|| {
+            self.index
+                .covered(&alert_net)
+                .into_iter()
+                .find_map(|(_, prefix)| entry(prefix))
+        })
+    }
 
-                if let Ok(monitored_net) = monitored_prefix.parse::<ipnet::IpNet>() {
-                    // Check if alert prefix is contained in monitored prefix
-                    if monitored_net.contains(&alert_net) {
-                        // If ignoreMorespecifics is true, skip more specific prefixes
-                        if prefix_info.ignore_morespecifics
-                            && alert_net.prefix_len() > monitored_net.prefix_len()
-                        {
-                            continue;
-                        }
-                        return Some(prefix_info);
-                    }
-                    // Also check if monitored prefix is contained in alert prefix
-                    if alert_net.contains(&monitored_net) {
-                        return Some(prefix_info);
-                    }
+    /// Why an alert prefix is relevant, if it matches, is contained within, or covers a
+    /// monitored prefix
+    pub fn prefix_match(&self, alert_prefix: &str) -> Option<RelevanceMatch> {
+        let (monitored, info) = self.find_matching_prefix(alert_prefix)?;
+        let nets = (
+            monitored.parse::<ipnet::IpNet>(),
+            alert_prefix.parse::<ipnet::IpNet>(),
+        );
+        let reason = match nets {
+            (Ok(monitored_net), Ok(alert_net)) if monitored_net.trunc() != alert_net.trunc() => {
+                if monitored_net.contains(&alert_net) {
+                    MatchReason::CoveringPrefix
+                } else {
+                    MatchReason::CoveredMoreSpecific
                 }
             }
-        }
+            _ => MatchReason::ExactPrefix,
+        };
+        Some(RelevanceMatch {
+            reason,
+            value: alert_prefix.to_string(),
+            monitored: monitored.to_string(),
+            group: info.group.clone(),
+            prefix_info: Some(info.clone()),
+            device_name: None,
+        })
+    }
 
-        None
+    /// Why an ASN is relevant, if it is monitored; `reason` says which alert field it came from
+    pub fn asn_match(&self, asn: &str, reason: MatchReason) -> Option<RelevanceMatch> {
+        let (monitored, info) = self.monitored_asns.get_key_value(asn)?;
+        Some(RelevanceMatch {
+            reason,
+            value: asn.to_string(),
+            monitored: monitored.clone(),
+            group: info.group.clone(),
+            prefix_info: None,
+            device_name: None,
+        })
     }
 
-    /// Check if a prefix matches or is contained within any monitored prefix
-    pub fn is_prefix_relevant(&self, alert_prefix: &str) -> bool {
-        self.find_matching_prefix_info(alert_prefix).is_some()
+    /// Why an alert from this management address is relevant, if it is a monitored device
+    pub fn device_match(&self, address: &str) -> Option<RelevanceMatch> {
+        let (monitored, device) = self.monitored_devices.get_key_value(address.trim())?;
+        Some(RelevanceMatch {
+            reason: MatchReason::MonitoredDevice,
+            value: address.to_string(),
+            monitored: monitored.clone(),
+            group: device.group.clone(),
+            prefix_info: None,
+            device_name: Some(device.name.clone()),
+        })
     }
 
-    /// Check if an alert is relevant to our monitored resources
-    pub fn is_alert_relevant(&self, alert: &crate::alerts::http::server::BGPAlerterAlert) -> bool {
-        // Check main prefix
-        if self.is_prefix_relevant(&alert.details.prefix) {
-            return true;
-        }
+    /// Why an alert is relevant to our monitored resources, or `None` when it is not
+    ///
+    /// Checked in order: the alert prefix, the new prefix, the expected ASN, then the new
+    /// origin ASN; the first match wins.
+    pub fn alert_relevance(
+        &self,
+        alert: &crate::alerts::sources::AlertEnvelope,
+    ) -> Option<RelevanceMatch> {
+        self.prefix_match(&alert.resource)
+            .or_else(|| {
+                alert
+                    .related_resource
+                    .as_deref()
+                    .and_then(|newprefix| self.prefix_match(newprefix))
+            })
+            .or_else(|| {
+                alert
+                    .asn
+                    .as_deref()
+                    .and_then(|asn| self.asn_match(asn, MatchReason::MonitoredAsn))
+            })
+            .or_else(|| {
+                alert.observed_asn.as_deref().and_then(|neworigin| {
+                    self.asn_match(neworigin, MatchReason::MonitoredNeworigin)
+                })
+            })
+    }
+}
 
-        // Check new prefix if present
-        if let Some(ref newprefix) = alert.details.newprefix
-            && self.is_prefix_relevant(newprefix)
-        {
-            return true;
-        }
+/// Which kind of monitored entry made an alert relevant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchReason {
+    /// The alert prefix is a monitored prefix
+    ExactPrefix,
+    /// The alert prefix is a more-specific of a monitored prefix
+    CoveringPrefix,
+    /// The alert prefix covers a monitored more-specific
+    CoveredMoreSpecific,
+    /// The expected origin ASN is monitored
+    MonitoredAsn,
+    /// The unexpected origin ASN that was observed is monitored
+    MonitoredNeworigin,
+    /// The alert comes from a monitored device
+    MonitoredDevice,
+}
 
-        // Check ASN in monitored ASNs list
-        if self.is_asn_monitored(&alert.details.asn) {
-            return true;
-        }
+/// The monitored entry an alert matched, stored with the alert to explain why it was kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelevanceMatch {
+    pub reason: MatchReason,
+    /// The alert value that matched, e.g. the announced prefix or origin ASN
+    pub value: String,
+    /// Key of the matched entry in prefixes.yml: a prefix, ASN or device address
+    pub monitored: String,
+    /// Group of the matched entry
+    pub group: String,
+    /// The matched prefix entry, with its expected origin ASNs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_info: Option<PrefixInfo>,
+    /// Name of the matched device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+}
+
+impl RelevanceMatch {
+    /// One sentence for prompts, e.g. "10.1.2.0/24 is inside the monitored prefix 10.1.0.0/16
+    /// (Customer, group noc, expected origins AS65000)"
+    pub fn describe(&self) -> String {
+        let what = match self.reason {
+            MatchReason::ExactPrefix => format!("{} is the monitored prefix", self.value),
+            MatchReason::CoveringPrefix => {
+                format!("{} is inside the monitored prefix", self.value)
+            }
+            MatchReason::CoveredMoreSpecific => {
+                format!("{} covers the monitored more-specific", self.value)
+            }
+            MatchReason::MonitoredAsn => format!("ASN {} is the monitored ASN", self.value),
+            MatchReason::MonitoredNeworigin => {
+                format!("the new origin {} is the monitored ASN", self.value)
+            }
+            MatchReason::MonitoredDevice => format!("{} is the monitored device", self.value),
+        };
 
-        // Check new origin ASN if present
-        if let Some(ref neworigin) = alert.details.neworigin
-            && self.is_asn_monitored(neworigin)
+        let mut details = Vec::new();
+        if let Some(name) = &self.device_name {
+            details.push(name.clone());
+        }
+        if let Some(info) = &self.prefix_info
+            && !info.description.is_empty()
         {
-            return true;
+            details.push(info.description.clone());
+        }
+        if !self.group.is_empty() {
+            details.push(format!("group {}", self.group));
+        }
+        if let Some(info) = &self.prefix_info {
+            let origins: Vec<String> = info.asn.iter().map(|asn| format!("AS{asn}")).collect();
+            details.push(format!("expected origins {}", origins.join(", ")));
         }
 
-        false
+        if details.is_empty() {
+            format!("{what} {}", self.monitored)
+        } else {
+            format!("{what} {} ({})", self.monitored, details.join(", "))
+        }
     }
 }
 
@@ -172,18 +564,197 @@ impl PrefixesConfig {
 pub struct AppConfig {
     #[serde(default = "default_server_port")]
     pub server_port: u16,
+    /// Where alerts, chat history and MCP servers are stored: `sqlite://<file>`, or
+    /// `postgres://...` in builds with the `postgres` feature
+    #[serde(default = "default_database_url")]
+    pub database_url: String,
+    #[serde(default = "default_llm_provider")]
+    pub llm_provider: LlmProvider,
+    /// Overrides the provider's default API endpoint
+    #[serde(default)]
+    pub llm_base_url: Option<String>,
     #[serde(default = "default_llm_model_name")]
     pub llm_model_name: String,
+    #[serde(default = "default_llm_max_tokens")]
+    pub llm_max_tokens: u64,
+    /// Sampling temperature; the provider default is used when unset
+    #[serde(default)]
+    pub llm_temperature: Option<f64>,
+    /// Number of background workers draining the analysis queue
+    #[serde(default = "default_analysis_workers")]
+    pub analysis_workers: usize,
+    /// Maximum number of analysis attempts per alert before it is marked failed
+    #[serde(default = "default_analysis_max_attempts")]
+    pub analysis_max_attempts: u32,
+    /// Base delay in seconds between analysis retries (doubled on every attempt)
+    #[serde(default = "default_analysis_retry_base_secs")]
+    pub analysis_retry_base_secs: u64,
+    /// Window in seconds during which repeated alerts fold into the same incident
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+    /// Relative growth in peers or paths that triggers a fresh analysis of an incident
+    #[serde(default = "default_dedup_material_change_ratio")]
+    pub dedup_material_change_ratio: f64,
+    /// Per-model prices used to estimate spend; models without an entry have no cost estimate
+    #[serde(default)]
+    pub llm_pricing: HashMap<String, ModelPricing>,
+    /// Maximum input + output tokens per UTC day
+    #[serde(default)]
+    pub budget_daily_tokens: Option<u64>,
+    /// Maximum input + output tokens per UTC hour
+    #[serde(default)]
+    pub budget_hourly_tokens: Option<u64>,
+    /// Maximum estimated spend in USD per UTC day (needs `llm_pricing`)
+    #[serde(default)]
+    pub budget_daily_cost_usd: Option<f64>,
+    /// Maximum estimated spend in USD per UTC hour (needs `llm_pricing`)
+    #[serde(default)]
+    pub budget_hourly_cost_usd: Option<f64>,
+    /// Consume BGP updates from RIPE RIS Live in addition to the BGPAlerter webhook
+    #[serde(default)]
+    pub ris_live_enabled: bool,
+    /// RIS Live websocket endpoint
+    #[serde(default = "default_ris_live_url")]
+    pub ris_live_url: String,
+    /// Distinct RIS peers that must withdraw a monitored prefix before it is alerted on
+    #[serde(default = "default_ris_live_withdrawal_min_peers")]
+    pub ris_live_withdrawal_min_peers: usize,
+    /// Seconds within which withdrawals by different peers are counted together
+    #[serde(default = "default_ris_live_withdrawal_window_secs")]
+    pub ris_live_withdrawal_window_secs: u64,
+    /// Listen for SNMP traps from monitored devices
+    #[serde(default)]
+    pub snmp_trap_enabled: bool,
+    /// UDP address of the SNMP trap listener
+    #[serde(default = "default_snmp_trap_bind")]
+    pub snmp_trap_bind: String,
+    /// SNMPv2c communities accepted by the trap listener
+    #[serde(default = "default_snmp_trap_communities")]
+    pub snmp_trap_communities: Vec<String>,
+    /// SNMPv3 users accepted by the trap listener
+    #[serde(default)]
+    pub snmp_trap_v3_users: Vec<SnmpV3User>,
+    /// Listen for syslog messages from monitored devices
+    #[serde(default)]
+    pub syslog_enabled: bool,
+    /// Address of the syslog listener, used for both UDP and TCP
+    #[serde(default = "default_syslog_bind")]
+    pub syslog_bind: String,
+    /// Rules file deciding which syslog messages become alerts
+    #[serde(default = "default_syslog_rules_path")]
+    pub syslog_rules_path: String,
+    /// Routinator or rpki-client JSON export of VRPs used for RPKI validation
+    #[serde(default)]
+    pub rpki_vrp_file: Option<String>,
+    /// RTR cache (`host:port`) to fetch VRPs from; takes precedence over the JSON export
+    #[serde(default)]
+    pub rpki_rtr_server: Option<String>,
+    /// Seconds between two VRP refreshes
+    #[serde(default = "default_rpki_refresh_secs")]
+    pub rpki_refresh_secs: u64,
+    /// Group registry mapping prefixes.yml groups to channels, severities and prompt profiles
+    #[serde(default = "default_groups_path")]
+    pub groups_path: String,
+    /// Base URL of the server as reachable by the people notified, used in alert links
+    #[serde(default)]
+    pub public_url: String,
+    /// Days resolved and false-positive alerts are kept after their last change; forever when unset
+    #[serde(default)]
+    pub retention_resolved_alert_days: Option<u64>,
+    /// Days chat messages are kept; forever when unset
+    #[serde(default)]
+    pub retention_chat_days: Option<u64>,
+    /// Directory receiving expired records as gzipped JSONL before they are deleted
+    #[serde(default)]
+    pub retention_archive_dir: Option<String>,
+    /// Seconds between two purges of expired records
+    #[serde(default = "default_retention_interval_secs")]
+    pub retention_interval_secs: u64,
 }
 
 fn default_server_port() -> u16 {
     7654
 }
 
+fn default_database_url() -> String {
+    "sqlite://agent_noc.db".to_string()
+}
+
+fn default_llm_provider() -> LlmProvider {
+    LlmProvider::Anthropic
+}
+
 fn default_llm_model_name() -> String {
     "claude-sonnet-4-5-20250929".to_string()
 }
 
+fn default_llm_max_tokens() -> u64 {
+    DEFAULT_LLM_MAX_TOKENS
+}
+
+fn default_analysis_workers() -> usize {
+    2
+}
+
+fn default_analysis_max_attempts() -> u32 {
+    3
+}
+
+fn default_analysis_retry_base_secs() -> u64 {
+    30
+}
+
+fn default_dedup_window_secs() -> u64 {
+    3600
+}
+
+fn default_dedup_material_change_ratio() -> f64 {
+    0.5
+}
+
+fn default_ris_live_url() -> String {
+    "wss://ris-live.ripe.net/v1/ws/?client=agent-noc".to_string()
+}
+
+fn default_ris_live_withdrawal_min_peers() -> usize {
+    // BGPAlerter's default thresholdMinPeers for withdrawals
+    3
+}
+
+fn default_ris_live_withdrawal_window_secs() -> u64 {
+    300
+}
+
+fn default_snmp_trap_bind() -> String {
+    // 162 is privileged, so listen on the unprivileged port commonly used instead
+    "0.0.0.0:1162".to_string()
+}
+
+fn default_snmp_trap_communities() -> Vec<String> {
+    vec!["public".to_string()]
+}
+
+fn default_syslog_bind() -> String {
+    // 514 is privileged, so listen on the unprivileged port commonly used instead
+    "0.0.0.0:1514".to_string()
+}
+
+fn default_syslog_rules_path() -> String {
+    "syslog_rules.yml".to_string()
+}
+
+fn default_groups_path() -> String {
+    "groups.yml".to_string()
+}
+
+fn default_rpki_refresh_secs() -> u64 {
+    600
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
 impl AppConfig {
     pub fn from_env() -> Result<Self> {
         dotenv::dotenv().ok();
@@ -193,16 +764,286 @@ impl AppConfig {
             .and_then(|p| p.parse().ok())
             .unwrap_or_else(default_server_port);
 
-        let llm_model_name =
-            std::env::var("LLM_MODEL_NAME").unwrap_or_else(|_| default_llm_model_name());
+        let database_url = std::env::var("DATABASE_URL")
+            .ok()
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .unwrap_or_else(default_database_url);
+
+        let llm_provider = match std::env::var("LLM_PROVIDER") {
+            Ok(p) => LlmProvider::try_from(p.as_str()).map_err(|e| color_eyre::eyre::eyre!(e))?,
+            Err(_) => default_llm_provider(),
+        };
+
+        let llm_base_url = std::env::var("LLM_BASE_URL")
+            .ok()
+            .filter(|u| !u.trim().is_empty());
+
+        // The default model only exists on Anthropic
+        let llm_model_name = match std::env::var("LLM_MODEL_NAME") {
+            Ok(name) => name,
+            Err(_) if llm_provider == LlmProvider::Anthropic => default_llm_model_name(),
+            Err(_) => {
+                return Err(color_eyre::eyre::eyre!(
+                    "LLM_MODEL_NAME must be set when LLM_PROVIDER is {}",
+                    llm_provider.as_str()
+                ));
+            }
+        };
+
+        let llm_max_tokens = std::env::var("LLM_MAX_TOKENS")
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .filter(|t| *t > 0)
+            .unwrap_or_else(default_llm_max_tokens);
+
+        let llm_temperature = std::env::var("LLM_TEMPERATURE")
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .filter(|t: &f64| (0.0..=2.0).contains(t));
+
+        let analysis_workers = std::env::var("ANALYSIS_WORKERS")
+            .ok()
+            .and_then(|w| w.parse().ok())
+            .filter(|w| *w > 0)
+            .unwrap_or_else(default_analysis_workers);
+
+        let analysis_max_attempts = std::env::var("ANALYSIS_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|a| a.parse().ok())
+            .filter(|a| *a > 0)
+            .unwrap_or_else(default_analysis_max_attempts);
+
+        let analysis_retry_base_secs = std::env::var("ANALYSIS_RETRY_BASE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_analysis_retry_base_secs);
+
+        let dedup_window_secs = std::env::var("DEDUP_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_dedup_window_secs);
+
+        let dedup_material_change_ratio = std::env::var("DEDUP_MATERIAL_CHANGE_RATIO")
+            .ok()
+            .and_then(|r| r.parse().ok())
+            .filter(|r: &f64| r.is_finite() && *r >= 0.0)
+            .unwrap_or_else(default_dedup_material_change_ratio);
+
+        let llm_pricing = match std::env::var("LLM_PRICING") {
+            Ok(value) => parse_llm_pricing(&value)?,
+            Err(_) => HashMap::new(),
+        };
+
+        let token_budget = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|t| t.parse::<u64>().ok())
+                .filter(|t| *t > 0)
+        };
+        let cost_budget = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|c| c.parse::<f64>().ok())
+                .filter(|c| c.is_finite() && *c > 0.0)
+        };
+        let budget_daily_tokens = token_budget("LLM_BUDGET_DAILY_TOKENS");
+        let budget_hourly_tokens = token_budget("LLM_BUDGET_HOURLY_TOKENS");
+        let budget_daily_cost_usd = cost_budget("LLM_BUDGET_DAILY_USD");
+        let budget_hourly_cost_usd = cost_budget("LLM_BUDGET_HOURLY_USD");
+
+        let ris_live_enabled = std::env::var("RIS_LIVE_ENABLED")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let ris_live_url = std::env::var("RIS_LIVE_URL")
+            .ok()
+            .filter(|u| !u.trim().is_empty())
+            .unwrap_or_else(default_ris_live_url);
+
+        let ris_live_withdrawal_min_peers = std::env::var("RIS_LIVE_WITHDRAWAL_MIN_PEERS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|peers: &usize| *peers > 0)
+            .unwrap_or_else(default_ris_live_withdrawal_min_peers);
+
+        let ris_live_withdrawal_window_secs = std::env::var("RIS_LIVE_WITHDRAWAL_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_else(default_ris_live_withdrawal_window_secs);
+
+        let snmp_trap_enabled = std::env::var("SNMP_TRAP_ENABLED")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let snmp_trap_bind = std::env::var("SNMP_TRAP_BIND")
+            .ok()
+            .filter(|b| !b.trim().is_empty())
+            .unwrap_or_else(default_snmp_trap_bind);
+
+        let snmp_trap_communities = std::env::var("SNMP_TRAP_COMMUNITIES")
+            .ok()
+            .map(|c| {
+                c.split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_else(default_snmp_trap_communities);
+
+        let snmp_trap_v3_users = match std::env::var("SNMP_TRAP_V3_USERS") {
+            Ok(value) => parse_snmp_v3_users(&value)?,
+            Err(_) => Vec::new(),
+        };
+
+        let syslog_enabled = std::env::var("SYSLOG_ENABLED")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let syslog_bind = std::env::var("SYSLOG_BIND")
+            .ok()
+            .filter(|b| !b.trim().is_empty())
+            .unwrap_or_else(default_syslog_bind);
+
+        let syslog_rules_path = std::env::var("SYSLOG_RULES_PATH")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(default_syslog_rules_path);
+
+        let rpki_vrp_file = std::env::var("RPKI_VRP_FILE")
+            .ok()
+            .filter(|p| !p.trim().is_empty());
+
+        let rpki_rtr_server = std::env::var("RPKI_RTR_SERVER")
+            .ok()
+            .filter(|s| !s.trim().is_empty());
+
+        let rpki_refresh_secs = std::env::var("RPKI_REFRESH_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|s| *s > 0)
+            .unwrap_or_else(default_rpki_refresh_secs);
+
+        let groups_path = std::env::var("GROUPS_PATH")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(default_groups_path);
+
+        let public_url = std::env::var("PUBLIC_URL")
+            .ok()
+            .map(|u| u.trim().trim_end_matches('/').to_string())
+            .filter(|u| !u.is_empty())
+            .unwrap_or_else(|| format!("http://localhost:{server_port}"));
+
+        let retention_resolved_alert_days = std::env::var("RETENTION_RESOLVED_ALERT_DAYS")
+            .ok()
+            .and_then(|d| d.parse().ok())
+            .filter(|d| *d > 0);
+
+        let retention_chat_days = std::env::var("RETENTION_CHAT_DAYS")
+            .ok()
+            .and_then(|d| d.parse().ok())
+            .filter(|d| *d > 0);
+
+        let retention_archive_dir = std::env::var("RETENTION_ARCHIVE_DIR")
+            .ok()
+            .filter(|d| !d.trim().is_empty());
+
+        let retention_interval_secs = std::env::var("RETENTION_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|s| *s > 0)
+            .unwrap_or_else(default_retention_interval_secs);
 
         Ok(Self {
             server_port,
+            database_url,
+            llm_provider,
+            llm_base_url,
             llm_model_name,
+            llm_max_tokens,
+            llm_temperature,
+            analysis_workers,
+            analysis_max_attempts,
+            analysis_retry_base_secs,
+            dedup_window_secs,
+            dedup_material_change_ratio,
+            llm_pricing,
+            budget_daily_tokens,
+            budget_hourly_tokens,
+            budget_daily_cost_usd,
+            budget_hourly_cost_usd,
+            ris_live_enabled,
+            ris_live_url,
+            ris_live_withdrawal_min_peers,
+            ris_live_withdrawal_window_secs,
+            snmp_trap_enabled,
+            snmp_trap_bind,
+            snmp_trap_communities,
+            snmp_trap_v3_users,
+            syslog_enabled,
+            syslog_bind,
+            syslog_rules_path,
+            rpki_vrp_file,
+            rpki_rtr_server,
+            rpki_refresh_secs,
+            groups_path,
+            public_url,
+            retention_resolved_alert_days,
+            retention_chat_days,
+            retention_archive_dir,
+            retention_interval_secs,
         })
     }
 }
 
+/// Configuration for tests: in-memory database, no listeners, nothing read from the environment
+#[cfg(test)]
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            server_port: 7654,
+            database_url: "sqlite::memory:".to_string(),
+            llm_provider: LlmProvider::Anthropic,
+            llm_base_url: None,
+            llm_model_name: "test-model".to_string(),
+            llm_max_tokens: 4096,
+            llm_temperature: None,
+            analysis_workers: 1,
+            analysis_max_attempts: 3,
+            analysis_retry_base_secs: 30,
+            dedup_window_secs: 3600,
+            dedup_material_change_ratio: 0.5,
+            llm_pricing: Default::default(),
+            budget_daily_tokens: None,
+            budget_hourly_tokens: None,
+            budget_daily_cost_usd: None,
+            budget_hourly_cost_usd: None,
+            ris_live_enabled: false,
+            ris_live_url: String::new(),
+            ris_live_withdrawal_min_peers: 3,
+            ris_live_withdrawal_window_secs: 300,
+            snmp_trap_enabled: false,
+            snmp_trap_bind: String::new(),
+            snmp_trap_communities: Vec::new(),
+            snmp_trap_v3_users: Vec::new(),
+            syslog_enabled: false,
+            syslog_bind: String::new(),
+            syslog_rules_path: String::new(),
+            rpki_vrp_file: None,
+            rpki_rtr_server: None,
+            rpki_refresh_secs: 600,
+            groups_path: String::new(),
+            public_url: "http://localhost:7654".to_string(),
+            retention_resolved_alert_days: None,
+            retention_chat_days: None,
+            retention_archive_dir: None,
+            retention_interval_secs: 3600,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -403,7 +1244,7 @@ options:
         let config = PrefixesConfig::from_str(yaml).unwrap();
 
         assert!(!config.is_prefix_monitored("192.0.2.0/24"));
-        assert!(config.prefixes.get("192.0.2.0/24").is_none());
+        assert!(!config.prefixes.contains_key("192.0.2.0/24"));
     }
 
     #[test]
@@ -452,6 +1293,45 @@ options:
         assert!(prefix2.description.contains("Expected prefix"));
     }
 
+    #[test]
+    fn test_parse_monitored_devices() {
+        let yaml = r#"
+192.0.2.0/24:
+  description: Test prefix
+  asn:
+    - 3333
+  group: noc
+options:
+  monitorDevices:
+    '198.51.100.1':
+      name: edge1
+      group: noc
+      interfaces:
+        '3':
+          name: ge-0/0/3
+          description: Transit to AS64496
+          asn: 64496
+"#;
+
+        let config = PrefixesConfig::from_str(yaml).unwrap();
+
+        let device = config.find_device("198.51.100.1").unwrap();
+        assert_eq!(device.name, "edge1");
+        let interface = &device.interfaces["3"];
+        assert_eq!(interface.name.as_deref(), Some("ge-0/0/3"));
+        assert_eq!(interface.asn, Some(64496));
+        assert!(config.find_device("198.51.100.2").is_none());
+
+        let (address, _) = config.find_device_by_host("EDGE1").unwrap();
+        assert_eq!(address, "198.51.100.1");
+        assert!(config.find_device_by_host("198.51.100.1").is_some());
+        assert!(config.find_device_by_host("edge2").is_none());
+
+        assert!(config.address_match("192.0.2.7").is_some());
+        assert!(config.address_match("198.51.100.1").is_none());
+        assert!(config.address_match("ge-0/0/3").is_none());
+    }
+
     #[test]
     fn test_invalid_yaml() {
         let yaml = "invalid: yaml: content: [";
@@ -616,4 +1496,323 @@ options:
         assert!(prefix_info.asn.contains(&65000));
         assert!(prefix_info.asn.contains(&65001));
     }
+
+    #[test]
+    fn test_llm_provider_parsing() {
+        assert_eq!(
+            LlmProvider::try_from("anthropic"),
+            Ok(LlmProvider::Anthropic)
+        );
+        assert_eq!(LlmProvider::try_from("OpenAI"), Ok(LlmProvider::OpenAi));
+        assert_eq!(
+            LlmProvider::try_from("openai-compatible"),
+            Ok(LlmProvider::OpenAiCompatible)
+        );
+        assert_eq!(LlmProvider::try_from(" ollama "), Ok(LlmProvider::Ollama));
+        assert!(LlmProvider::try_from("gemini").is_err());
+
+        for provider in [
+            LlmProvider::Anthropic,
+            LlmProvider::OpenAi,
+            LlmProvider::OpenAiCompatible,
+            LlmProvider::Ollama,
+        ] {
+            assert_eq!(LlmProvider::try_from(provider.as_str()), Ok(provider));
+        }
+    }
+
+    #[test]
+    fn test_parse_llm_pricing() {
+        let pricing = parse_llm_pricing("claude-sonnet-4-5=3:15, gpt-4o = 2.5 : 10 ,").unwrap();
+        assert_eq!(pricing.len(), 2);
+        assert_eq!(
+            pricing["claude-sonnet-4-5"],
+            ModelPricing {
+                input_per_mtok: 3.0,
+                output_per_mtok: 15.0
+            }
+        );
+        assert_eq!(pricing["gpt-4o"].input_per_mtok, 2.5);
+        assert!(parse_llm_pricing("").unwrap().is_empty());
+
+        assert!(parse_llm_pricing("gpt-4o=2.5").is_err());
+        assert!(parse_llm_pricing("gpt-4o=cheap:10").is_err());
+        assert!(parse_llm_pricing("=1:2").is_err());
+        assert!(parse_llm_pricing("gpt-4o=-1:2").is_err());
+    }
+
+    #[test]
+    fn test_parse_asn() {
+        assert_eq!(parse_asn("64500").unwrap(), 64500);
+        assert_eq!(parse_asn(" AS64500 ").unwrap(), 64500);
+        assert_eq!(parse_asn("as4200000000").unwrap(), 4_200_000_000);
+
+        for invalid in [
+            "",
+            "AS",
+            "SASA64500",
+            "sas1",
+            "ASAS1",
+            "+1",
+            "AS-1",
+            "64500x",
+            "4294967296",
+        ] {
+            assert!(parse_asn(invalid).is_err(), "{invalid:?} accepted");
+        }
+    }
+
+    #[test]
+    fn test_parse_snmp_v3_users() {
+        let users =
+            parse_snmp_v3_users("monitor:sha:pass:word1, lab ,legacy:MD5:secret123").unwrap();
+        assert_eq!(
+            users,
+            vec![
+                SnmpV3User {
+                    name: "monitor".to_string(),
+                    auth: Some((SnmpAuthProtocol::Sha, "pass:word1".to_string())),
+                },
+                SnmpV3User {
+                    name: "lab".to_string(),
+                    auth: None,
+                },
+                SnmpV3User {
+                    name: "legacy".to_string(),
+                    auth: Some((SnmpAuthProtocol::Md5, "secret123".to_string())),
+                },
+            ]
+        );
+        assert!(parse_snmp_v3_users("").unwrap().is_empty());
+
+        assert!(parse_snmp_v3_users("monitor:sha").is_err());
+        assert!(parse_snmp_v3_users("monitor:sha:short").is_err());
+        assert!(parse_snmp_v3_users("monitor:sha256:secret123").is_err());
+        assert!(parse_snmp_v3_users(":sha:secret123").is_err());
+    }
+
+    #[test]
+    fn test_model_pricing_cost() {
+        let pricing = ModelPricing {
+            input_per_mtok: 3.0,
+            output_per_mtok: 15.0,
+        };
+        assert!((pricing.cost(1_000_000, 0) - 3.0).abs() < 1e-9);
+        assert!((pricing.cost(2_000, 1_000) - 0.021).abs() < 1e-9);
+    }
+
+    /// Matches the former linear scan accepted, in any order: the exact match alone, or every
+    /// non-ignored prefix covering the alert prefix (unless it ignores more-specifics) or
+    /// covered by it
+    fn linear_matches(config: &PrefixesConfig, alert_prefix: &str) -> Vec<String> {
+        if config.prefixes.contains_key(alert_prefix) {
+            return vec![alert_prefix.to_string()];
+        }
+        let Ok(alert_net) = alert_prefix.parse::<ipnet::IpNet>() else {
+            return Vec::new();
+        };
+        config
+            .prefixes
+            .iter()
+            .filter(|(_, info)| !info.ignore)
+            .filter(|(prefix, info)| {
+                let monitored_net = prefix.parse::<ipnet::IpNet>().unwrap();
+                (monitored_net.contains(&alert_net)
+                    && !(info.ignore_morespecifics
+                        && alert_net.prefix_len() > monitored_net.prefix_len()))
+                    || alert_net.contains(&monitored_net)
+            })
+            .map(|(prefix, _)| prefix.clone())
+            .collect()
+    }
+
+    fn prefix_info(ignore: bool, ignore_morespecifics: bool) -> PrefixInfo {
+        PrefixInfo {
+            description: String::new(),
+            asn: vec![65000],
+            ignore_morespecifics,
+            ignore,
+            group: "noc".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_matching_prefix_prefers_most_specific() {
+        let prefixes = [
+            ("10.0.0.0/8", prefix_info(false, false)),
+            ("10.1.0.0/16", prefix_info(false, true)),
+            ("10.1.2.0/24", prefix_info(true, false)),
+            ("10.2.0.0/16", prefix_info(false, false)),
+        ]
+        .into_iter()
+        .map(|(prefix, info)| (prefix.to_string(), info))
+        .collect();
+        let config = PrefixesConfig::new(prefixes, HashMap::new(), HashMap::new());
+        let matched = |prefix: &str| config.find_matching_prefix(prefix).map(|(p, _)| p);
+
+        assert_eq!(matched("10.2.3.0/24"), Some("10.2.0.0/16"));
+        assert_eq!(matched("10.1.0.0/16"), Some("10.1.0.0/16"));
+        // 10.1.0.0/16 ignores more-specifics and 10.1.2.0/24 is ignored
+        assert_eq!(matched("10.1.2.0/25"), Some("10.0.0.0/8"));
+        // Exact matches are returned even when ignored
+        assert_eq!(matched("10.1.2.0/24"), Some("10.1.2.0/24"));
+        assert_eq!(matched("10.0.0.0/7"), Some("10.0.0.0/8"));
+        assert_eq!(matched("11.0.0.0/8"), None);
+        assert_eq!(matched("not a prefix"), None);
+    }
+
+    #[test]
+    fn test_alert_relevance_explains_match() {
+        let prefixes = [
+            ("10.1.0.0/16", prefix_info(false, false)),
+            ("10.2.1.0/24", prefix_info(false, false)),
+        ]
+        .into_iter()
+        .map(|(prefix, info)| (prefix.to_string(), info))
+        .collect();
+        let asns = [(
+            "64512".to_string(),
+            AsnInfo {
+                group: "peering".to_string(),
+            },
+        )]
+        .into();
+        let config = PrefixesConfig::new(prefixes, asns, HashMap::new());
+        let reason = |prefix: &str| config.prefix_match(prefix).map(|m| m.reason);
+
+        assert_eq!(reason("10.1.0.0/16"), Some(MatchReason::ExactPrefix));
+        assert_eq!(reason("10.1.2.0/24"), Some(MatchReason::CoveringPrefix));
+        assert_eq!(
+            reason("10.2.0.0/16"),
+            Some(MatchReason::CoveredMoreSpecific)
+        );
+        assert_eq!(reason("10.3.0.0/16"), None);
+
+        let relevance = config.prefix_match("10.1.2.0/24").unwrap();
+        assert_eq!(relevance.monitored, "10.1.0.0/16");
+        assert_eq!(relevance.group, "noc");
+        assert_eq!(
+            relevance.describe(),
+            "10.1.2.0/24 is inside the monitored prefix 10.1.0.0/16 (group noc, expected \
+             origins AS65000)"
+        );
+
+        let mut alert = crate::alerts::sources::AlertEnvelope {
+            kind: crate::database::models::AlertKind::BgpAlerter,
+            category: "hijack".to_string(),
+            title: String::new(),
+            resource: "192.0.2.0/24".to_string(),
+            related_resource: None,
+            asn: Some("65000".to_string()),
+            observed_asn: Some("64512".to_string()),
+            earliest: String::new(),
+            latest: String::new(),
+            peers: 0,
+            paths: 0,
+            payload: serde_json::json!({}),
+            rpki: None,
+            relevance: None,
+        };
+        let relevance = config.alert_relevance(&alert).unwrap();
+        assert_eq!(relevance.reason, MatchReason::MonitoredNeworigin);
+        assert_eq!(relevance.group, "peering");
+        assert_eq!(relevance.prefix_info, None);
+
+        alert.related_resource = Some("10.1.0.0/17".to_string());
+        let relevance = config.alert_relevance(&alert).unwrap();
+        assert_eq!(relevance.reason, MatchReason::CoveringPrefix);
+        assert_eq!(relevance.value, "10.1.0.0/17");
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn arb_prefix() -> impl Strategy<Value = String> {
+            prop_oneof![
+                (0u32..16, 8u8..=28).prop_map(|(addr, len)| {
+                    let net = ipnet::Ipv4Net::new((0x0a00_0000 | addr << 18).into(), len).unwrap();
+                    net.trunc().to_string()
+                }),
+                (0u16..16, 32u8..=56).prop_map(|(addr, len)| {
+                    let addr = std::net::Ipv6Addr::new(0x2001, 0xdb8, addr << 10, 0, 0, 0, 0, 0);
+                    ipnet::Ipv6Net::new(addr, len).unwrap().trunc().to_string()
+                }),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn prop_find_matching_prefix_agrees_with_linear_scan(
+                entries in prop::collection::vec((arb_prefix(), any::<bool>(), any::<bool>()), 0..48),
+                queries in prop::collection::vec(arb_prefix(), 1..16),
+            ) {
+                let prefixes = entries
+                    .into_iter()
+                    .map(|(prefix, ignore, ims)| (prefix, prefix_info(ignore, ims)))
+                    .collect();
+                let config = PrefixesConfig::new(prefixes, HashMap::new(), HashMap::new());
+
+                for query in &queries {
+                    let expected = linear_matches(&config, query);
+                    match config.find_matching_prefix(query) {
+                        Some((prefix, _)) => prop_assert!(
+                            expected.iter().any(|p| p == prefix),
+                            "{} matched {} outside {:?}", query, prefix, expected
+                        ),
+                        None => prop_assert!(expected.is_empty(), "{} missed {:?}", query, expected),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compare with the linear scan on a transit-sized config:
+    /// `cargo test --release bench_find_matching_prefix -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_find_matching_prefix() {
+        let prefixes: HashMap<String, PrefixInfo> = (0u32..50_000)
+            .map(|i| {
+                let net = ipnet::Ipv4Net::new((0x0a00_0000 + (i << 8)).into(), 24).unwrap();
+                (net.to_string(), prefix_info(false, false))
+            })
+            .collect();
+        let started = std::time::Instant::now();
+        let config = PrefixesConfig::new(prefixes, HashMap::new(), HashMap::new());
+        println!(
+            "indexed {} prefixes in {:?}",
+            config.index.len(),
+            started.elapsed()
+        );
+
+        let queries: Vec<String> = (0u32..1_000)
+            .map(|i| {
+                let net = ipnet::Ipv4Net::new((0x0a00_0000 + ((i * 37) << 8)).into(), 25).unwrap();
+                net.to_string()
+            })
+            .collect();
+
+        let started = std::time::Instant::now();
+        let matched = queries
+            .iter()
+            .filter(|q| config.find_matching_prefix(q).is_some())
+            .count();
+        let trie = started.elapsed();
+
+        let started = std::time::Instant::now();
+        let linear_matched = queries
+            .iter()
+            .filter(|q| !linear_matches(&config, q).is_empty())
+            .count();
+        let linear = started.elapsed();
+
+        assert_eq!(matched, linear_matched);
+        println!(
+            "{} lookups: trie {:?}, linear scan {:?}",
+            queries.len(),
+            trie,
+            linear
+        );
+    }
 }
@@ -0,0 +1,531 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use sha2::{Digest, Sha256};
+use sqlx::Executor;
+
+use super::DbPool;
+use super::models::get_current_timestamp;
+
+/// A numbered schema change, applied in a transaction of its own
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub up: &'static str,
+    /// Reverts `up`, dropping the data it holds
+    pub down: &'static str,
+}
+
+impl Migration {
+    /// SHA-256 of the `up` script, recorded to detect migrations edited after release
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+/// Script of a migration, in the SQL dialect of the database this build uses
+#[cfg(not(feature = "postgres"))]
+macro_rules! script {
+    ($file:literal) => {
+        include_str!(concat!("../../migrations/sqlite/", $file))
+    };
+}
+#[cfg(feature = "postgres")]
+macro_rules! script {
+    ($file:literal) => {
+        include_str!(concat!("../../migrations/postgres/", $file))
+    };
+}
+
+/// Every migration, oldest first; released migrations must never be edited, add a new one
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        up: script!("0001_initial_schema.up.sql"),
+        down: script!("0001_initial_schema.down.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "full-text search",
+        up: script!("0002_full_text_search.up.sql"),
+        down: script!("0002_full_text_search.down.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "retention",
+        up: script!("0003_retention.up.sql"),
+        down: script!("0003_retention.down.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "ROA coverage",
+        up: script!("0004_roa_coverage.up.sql"),
+        down: script!("0004_roa_coverage.down.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "notification payload",
+        up: script!("0005_notification_payload.up.sql"),
+        down: script!("0005_notification_payload.down.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "leases",
+        up: script!("0006_leases.up.sql"),
+        down: script!("0006_leases.down.sql"),
+    },
+];
+
+/// Columns added to existing tables before versioned migrations, as `ALTER TABLE` used to
+#[cfg(not(feature = "postgres"))]
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("mcp_servers", "is_native", "INTEGER NOT NULL DEFAULT 0"),
+    ("alerts", "kind", "TEXT NOT NULL DEFAULT 'bgp_alerter'"),
+    ("alerts", "analysis_status", "TEXT NOT NULL DEFAULT 'done'"),
+    ("alerts", "severity", "TEXT"),
+    ("alerts", "affected_prefix", "TEXT"),
+    ("alerts", "expected_asn", "TEXT"),
+    ("alerts", "observed_asn", "TEXT"),
+    ("alerts", "duration", "TEXT"),
+    ("alerts", "peer_count", "INTEGER"),
+    ("alerts", "group_name", "TEXT"),
+    ("alerts", "status", "TEXT NOT NULL DEFAULT 'new'"),
+    ("alerts", "assignee", "TEXT"),
+    ("alerts", "resolution_notes", "TEXT"),
+    ("incidents", "prefix_family", "INTEGER"),
+    ("incidents", "prefix_start", "TEXT"),
+    ("incidents", "prefix_end", "TEXT"),
+    ("incidents", "asns", "TEXT"),
+];
+
+/// A migration recorded in `schema_version`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+async fn create_version_table(pool: &DbPool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version BIGINT PRIMARY KEY,
+            description TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Migrations recorded in the database, oldest first
+pub async fn applied(pool: &DbPool) -> Result<Vec<AppliedMigration>> {
+    create_version_table(pool).await?;
+    let rows = sqlx::query_as::<_, (i64, String, String, String)>(
+        "SELECT version, description, checksum, applied_at FROM schema_version ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(version, description, checksum, applied_at)| AppliedMigration {
+                version,
+                description,
+                checksum,
+                applied_at,
+            },
+        )
+        .collect())
+}
+
+/// Check that the recorded migrations are the ones this build ships, unmodified
+fn verify(applied: &[AppliedMigration]) -> Result<()> {
+    for record in applied {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == record.version)
+            .ok_or_else(|| {
+                eyre!(
+                    "Database schema version {} ({}) is newer than this build supports",
+                    record.version,
+                    record.description
+                )
+            })?;
+        if migration.checksum() != record.checksum {
+            return Err(eyre!(
+                "Migration {} ({}) was modified after it was applied",
+                record.version,
+                record.description
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn table_exists(pool: &DbPool, table: &str) -> Result<bool> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1",
+    )
+    .bind(table)
+    .fetch_one(pool)
+    .await?;
+    Ok(count > 0)
+}
+
+/// Bring a database created before versioned migrations up to the initial schema
+///
+/// Only columns are added here; missing tables and indexes are created by migration 1.
+/// Releases from before versioned migrations only stored their state in SQLite.
+#[cfg(not(feature = "postgres"))]
+async fn upgrade_legacy_schema(pool: &DbPool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for &(table, column, definition) in LEGACY_COLUMNS {
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info($1)")
+            .bind(table)
+            .fetch_all(&mut *tx)
+            .await?;
+        // Missing tables are created whole by migration 1
+        if columns.is_empty() || columns.iter().any(|c| c == column) {
+            continue;
+        }
+        tracing::info!("Adding column {}.{} to a legacy database", table, column);
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Apply the pending migrations, each in its own transaction
+///
+/// Fails without touching the schema when a recorded migration is unknown to this build or
+/// its script changed since it was applied.
+pub async fn run(pool: &DbPool) -> Result<()> {
+    let applied = applied(pool).await?;
+    verify(&applied)?;
+
+    #[cfg(not(feature = "postgres"))]
+    if applied.is_empty() && table_exists(pool, "alerts").await? {
+        upgrade_legacy_schema(pool).await?;
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .collect();
+    for migration in pending {
+        let mut tx = pool.begin().await?;
+        // Roll back before returning so the connection goes back to the pool unlocked
+        if let Err(e) = tx.execute(migration.up).await {
+            tx.rollback().await?;
+            return Err(eyre!(
+                "Migration {} ({}) failed: {}",
+                migration.version,
+                migration.description,
+                e
+            ));
+        }
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, checksum, applied_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(migration.checksum())
+        .bind(get_current_timestamp())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        tracing::info!(
+            "Applied migration {} ({})",
+            migration.version,
+            migration.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Revert the migrations newer than `target`, newest first, each in its own transaction
+///
+/// Returns the reverted versions. Reverting drops the tables and columns the migrations
+/// created, with their data.
+pub async fn rollback(pool: &DbPool, target: i64) -> Result<Vec<i64>> {
+    let applied = applied(pool).await?;
+    verify(&applied)?;
+
+    let newer: Vec<&Migration> = applied
+        .iter()
+        .rev()
+        .filter(|a| a.version > target)
+        .filter_map(|a| MIGRATIONS.iter().find(|m| m.version == a.version))
+        .collect();
+    let mut reverted = Vec::new();
+    for migration in newer {
+        let mut tx = pool.begin().await?;
+        // Roll back before returning so the connection goes back to the pool unlocked
+        if let Err(e) = tx.execute(migration.down).await {
+            tx.rollback().await?;
+            return Err(eyre!(
+                "Rollback of migration {} ({}) failed: {}",
+                migration.version,
+                migration.description,
+                e
+            ));
+        }
+        sqlx::query("DELETE FROM schema_version WHERE version = $1")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        tracing::info!(
+            "Rolled back migration {} ({})",
+            migration.version,
+            migration.description
+        );
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
+}
+
+/// `agent_noc migrate [status | up | rollback <version>]`
+pub async fn command(pool: &DbPool, args: &[String]) -> Result<()> {
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] | ["status"] => {
+            let applied = applied(pool).await?;
+            for migration in MIGRATIONS {
+                let state = match applied.iter().find(|a| a.version == migration.version) {
+                    Some(record) => format!("applied {}", record.applied_at),
+                    None => "pending".to_string(),
+                };
+                println!(
+                    "{:04} {:<20} {}",
+                    migration.version, migration.description, state
+                );
+            }
+            verify(&applied)
+        }
+        ["up"] => run(pool).await,
+        ["rollback", target] => {
+            let target = target
+                .parse()
+                .map_err(|_| eyre!("Invalid schema version: {}", target))?;
+            for version in rollback(pool, target).await? {
+                println!("Rolled back migration {version}");
+            }
+            Ok(())
+        }
+        _ => Err(eyre!(
+            "Usage: agent_noc migrate [status | up | rollback <version>]"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db::tests::test_pool;
+
+    async fn memory_pool() -> DbPool {
+        test_pool().await.unwrap()
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    async fn tables(pool: &DbPool) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+             AND name NOT LIKE '%_fts_%' ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn tables(pool: &DbPool) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT tablename::text FROM pg_tables WHERE schemaname = current_schema() \
+             ORDER BY tablename",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    /// Whether the full-text search of migration 2 is in place
+    #[cfg(not(feature = "postgres"))]
+    async fn has_search_index(pool: &DbPool) -> bool {
+        tables(pool).await.contains(&"alerts_fts".to_string())
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn has_search_index(pool: &DbPool) -> bool {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM pg_indexes WHERE schemaname = current_schema() \
+             AND indexname = 'idx_alerts_search'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn test_migrations_are_numbered_in_order() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(!migration.down.trim().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_is_idempotent() {
+        let pool = memory_pool().await;
+        run(&pool).await.unwrap();
+        run(&pool).await.unwrap();
+
+        let applied = applied(&pool).await.unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(applied[0].description, "initial schema");
+        assert_eq!(applied[0].checksum, MIGRATIONS[0].checksum());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_and_reapply() {
+        let pool = memory_pool().await;
+        run(&pool).await.unwrap();
+        let migrated = tables(&pool).await;
+        assert!(has_search_index(&pool).await);
+
+        assert_eq!(rollback(&pool, 1).await.unwrap(), vec![6, 5, 4, 3, 2]);
+        assert!(!has_search_index(&pool).await);
+        assert_eq!(applied(&pool).await.unwrap().len(), 1);
+
+        assert_eq!(rollback(&pool, 0).await.unwrap(), vec![1]);
+        assert_eq!(tables(&pool).await, vec!["schema_version"]);
+
+        run(&pool).await.unwrap();
+        assert_eq!(tables(&pool).await, migrated);
+        assert!(has_search_index(&pool).await);
+        assert!(rollback(&pool, 6).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_modified_or_unknown_migrations_are_rejected() {
+        let pool = memory_pool().await;
+        run(&pool).await.unwrap();
+
+        sqlx::query("UPDATE schema_version SET checksum = 'edited' WHERE version = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let err = run(&pool).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Migration 1 (initial schema) was modified")
+        );
+
+        sqlx::query("UPDATE schema_version SET checksum = $1 WHERE version = 1")
+            .bind(MIGRATIONS[0].checksum())
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO schema_version VALUES (999, 'from the future', 'x', '2999-01-01T00:00:00+00:00')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let err = run(&pool).await.unwrap_err();
+        assert!(err.to_string().contains("newer than this build"));
+        assert!(rollback(&pool, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back() {
+        let pool = memory_pool().await;
+        // A view where migration 1 expects a table makes it fail after creating its first tables
+        sqlx::query("CREATE VIEW alerts AS SELECT 1 AS id, '' AS created_at")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let err = run(&pool).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Migration 1 (initial schema) failed")
+        );
+        assert_eq!(tables(&pool).await, vec!["schema_version"]);
+        assert!(applied(&pool).await.unwrap().is_empty());
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[tokio::test]
+    async fn test_legacy_database_is_adopted() {
+        let pool = memory_pool().await;
+        // Schema of a release from before the analysis queue and versioned migrations
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE alerts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                alert_data TEXT NOT NULL,
+                initial_response TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE mcp_servers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                transport_type TEXT NOT NULL,
+                url TEXT,
+                command TEXT,
+                args TEXT,
+                env TEXT,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            INSERT INTO alerts (alert_data, initial_response, created_at, updated_at)
+            VALUES ('{}', 'Route leak through a customer', '2025-01-01T00:00:00+00:00',
+                    '2025-01-01T00:00:00+00:00');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        run(&pool).await.unwrap();
+
+        let (status, kind): (String, String) =
+            sqlx::query_as("SELECT status, kind FROM alerts WHERE id = 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((status.as_str(), kind.as_str()), ("new", "bgp_alerter"));
+        let found: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM alerts_fts WHERE alerts_fts MATCH 'leak'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(found, 1);
+        assert!(tables(&pool).await.contains(&"analysis_jobs".to_string()));
+    }
+}
@@ -1,2 +1,13 @@
 pub mod db;
+pub mod migrations;
 pub mod models;
+
+/// Database the build stores its state in: SQLite, or PostgreSQL with the `postgres` feature
+#[cfg(not(feature = "postgres"))]
+pub type Db = sqlx::Sqlite;
+#[cfg(feature = "postgres")]
+pub type Db = sqlx::Postgres;
+
+pub type DbPool = sqlx::Pool<Db>;
+pub type DbConnection = <Db as sqlx::Database>::Connection;
+pub type DbRow = <Db as sqlx::Database>::Row;
@@ -1,4 +1,7 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use chrono::Utc;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -194,12 +197,24 @@ pub struct UpdateMcpServer {
 #[serde(rename_all = "snake_case")]
 pub enum AlertKind {
     BgpAlerter,
+    /// Detected by the built-in RIPE RIS Live consumer
+    RisLive,
+    /// Received by the built-in SNMP trap listener
+    SnmpTrap,
+    /// Received by the built-in syslog listener
+    Syslog,
+    /// Raised by the periodic ROA coverage check
+    RoaCoverage,
 }
 
 impl AlertKind {
     pub fn as_str(&self) -> &'static str {
         match self {
             AlertKind::BgpAlerter => "bgp_alerter",
+            AlertKind::RisLive => "ris_live",
+            AlertKind::SnmpTrap => "snmp_trap",
+            AlertKind::Syslog => "syslog",
+            AlertKind::RoaCoverage => "roa_coverage",
         }
     }
 }
@@ -210,11 +225,551 @@ impl TryFrom<&str> for AlertKind {
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         match s {
             "bgp_alerter" => Ok(AlertKind::BgpAlerter),
+            "ris_live" => Ok(AlertKind::RisLive),
+            "snmp_trap" => Ok(AlertKind::SnmpTrap),
+            "syslog" => Ok(AlertKind::Syslog),
+            "roa_coverage" => Ok(AlertKind::RoaCoverage),
             _ => Err(format!("Unknown alert kind: {}", s)),
         }
     }
 }
 
+/// Progress of the background LLM analysis for an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisStatus {
+    Pending,
+    Analyzing,
+    Done,
+    Failed,
+    /// Not analyzed because the LLM budget was exhausted
+    #[serde(rename = "analysis_skipped_budget")]
+    SkippedBudget,
+}
+
+impl AnalysisStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnalysisStatus::Pending => "pending",
+            AnalysisStatus::Analyzing => "analyzing",
+            AnalysisStatus::Done => "done",
+            AnalysisStatus::Failed => "failed",
+            AnalysisStatus::SkippedBudget => "analysis_skipped_budget",
+        }
+    }
+}
+
+impl TryFrom<&str> for AnalysisStatus {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "pending" => Ok(AnalysisStatus::Pending),
+            "analyzing" => Ok(AnalysisStatus::Analyzing),
+            "done" => Ok(AnalysisStatus::Done),
+            "failed" => Ok(AnalysisStatus::Failed),
+            "analysis_skipped_budget" => Ok(AnalysisStatus::SkippedBudget),
+            _ => Err(format!("Unknown analysis status: {}", s)),
+        }
+    }
+}
+
+/// Where an alert is in its handling by the operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    New,
+    Acknowledged,
+    Investigating,
+    Resolved,
+    FalsePositive,
+}
+
+impl AlertStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertStatus::New => "new",
+            AlertStatus::Acknowledged => "acknowledged",
+            AlertStatus::Investigating => "investigating",
+            AlertStatus::Resolved => "resolved",
+            AlertStatus::FalsePositive => "false_positive",
+        }
+    }
+
+    /// Whether handling of the alert is over
+    pub fn is_closed(&self) -> bool {
+        matches!(self, AlertStatus::Resolved | AlertStatus::FalsePositive)
+    }
+
+    /// Whether an alert may move from this status to `to`; only closed alerts go back to new,
+    /// when they are reopened
+    pub fn can_transition_to(&self, to: AlertStatus) -> bool {
+        *self != to && (to != AlertStatus::New || self.is_closed())
+    }
+}
+
+impl TryFrom<&str> for AlertStatus {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "new" => Ok(AlertStatus::New),
+            "acknowledged" => Ok(AlertStatus::Acknowledged),
+            "investigating" => Ok(AlertStatus::Investigating),
+            "resolved" => Ok(AlertStatus::Resolved),
+            "false_positive" => Ok(AlertStatus::FalsePositive),
+            _ => Err(format!("Unknown alert status: {}", s)),
+        }
+    }
+}
+
+/// Lifecycle state of an alert
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AlertLifecycle {
+    pub status: AlertStatus,
+    /// Operator handling the alert
+    pub assignee: Option<String>,
+    /// How the alert was resolved, cleared when it is reopened
+    pub resolution_notes: Option<String>,
+}
+
+/// An entry of an alert's lifecycle history: a status transition or a change of assignee
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AlertStatusChange {
+    pub id: i64,
+    pub alert_id: i64,
+    pub from_status: AlertStatus,
+    /// Equal to `from_status` when only the assignee changed
+    pub to_status: AlertStatus,
+    /// Assignee after the change
+    pub assignee: Option<String>,
+    /// Operator who made the change
+    pub changed_by: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// A claimed entry from the analysis work queue
+#[derive(Debug, Clone)]
+pub struct AnalysisJob {
+    pub id: i64,
+    pub alert_id: i64,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    /// Replica that claimed the job
+    pub owner: String,
+}
+
+/// Severity assigned to an alert by the analysis agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Severity {
+    #[serde(alias = "critical", alias = "CRITICAL")]
+    Critical,
+    #[serde(alias = "high", alias = "HIGH")]
+    High,
+    #[serde(alias = "medium", alias = "MEDIUM")]
+    Medium,
+    #[serde(alias = "low", alias = "LOW")]
+    Low,
+    #[serde(alias = "info", alias = "INFO")]
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Critical => "Critical",
+            Severity::High => "High",
+            Severity::Medium => "Medium",
+            Severity::Low => "Low",
+            Severity::Info => "Info",
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Severity::Critical => 4,
+            Severity::High => 3,
+            Severity::Medium => 2,
+            Severity::Low => 1,
+            Severity::Info => 0,
+        }
+    }
+
+    /// Whether this severity is `other` or worse
+    pub fn is_at_least(&self, other: Severity) -> bool {
+        self.rank() >= other.rank()
+    }
+}
+
+impl TryFrom<&str> for Severity {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.trim().to_lowercase().as_str() {
+            "critical" => Ok(Severity::Critical),
+            "high" => Ok(Severity::High),
+            "medium" => Ok(Severity::Medium),
+            "low" => Ok(Severity::Low),
+            "info" => Ok(Severity::Info),
+            _ => Err(format!("Unknown severity: {}", s)),
+        }
+    }
+}
+
+/// Key facts extracted from an alert by the analysis agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct KeyFacts {
+    #[serde(default)]
+    pub affected_prefix: Option<String>,
+    #[serde(default)]
+    pub expected_asn: Option<String>,
+    #[serde(default)]
+    pub observed_asn: Option<String>,
+    #[serde(default)]
+    pub duration: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_lenient_count")]
+    pub peer_count: Option<i64>,
+}
+
+/// Structured incident report produced by the alert analysis agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IncidentReport {
+    pub summary: String,
+    pub severity: Severity,
+    #[serde(default)]
+    pub key_facts: KeyFacts,
+    #[serde(default)]
+    pub immediate_actions: Vec<String>,
+    #[serde(default)]
+    pub risk_assessment: String,
+    #[serde(default)]
+    pub tool_notes: String,
+}
+
+/// Accept counts given as a number or as a numeric string
+fn deserialize_lenient_count<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Some(Value::String(s)) => s.split_whitespace().next().and_then(|n| n.parse().ok()),
+        _ => None,
+    })
+}
+
+/// Response body of `GET /api/alerts/{id}`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AlertDetail {
+    /// The original alert payload
+    pub alert: Value,
+    /// Raw analysis output as stored
+    pub initial_response: String,
+    /// Typed analysis report, absent until the analysis has completed
+    pub report: Option<IncidentReport>,
+    pub severity: Option<Severity>,
+    pub kind: AlertKind,
+    /// Group of the monitored entry the alert matched
+    pub group: Option<String>,
+    pub analysis_status: AnalysisStatus,
+    /// Handling status set by the operators
+    pub status: AlertStatus,
+    pub assignee: Option<String>,
+    pub resolution_notes: Option<String>,
+    pub incident: Option<Incident>,
+    pub chat_messages: Vec<ChatMessage>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// How the prefix of an alert must relate to the prefix an alert listing is filtered on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefixMatch {
+    Exact,
+    /// The alert's prefix equals or covers the filter, e.g. a /16 for a /24 filter
+    Covering,
+    /// The alert's prefix equals or is covered by the filter
+    Covered,
+    /// Covering or covered
+    #[default]
+    Related,
+}
+
+/// Position after the last alert of a page; alerts are listed newest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlertCursor {
+    pub created_at: String,
+    pub id: i64,
+}
+
+impl AlertCursor {
+    /// Opaque, URL-safe form given to clients
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.id, self.created_at))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid cursor: {}", cursor);
+        let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (id, created_at) = decoded.split_once('|').ok_or_else(invalid)?;
+        Ok(Self {
+            created_at: created_at.to_string(),
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Filters and page size of an alert listing; empty filters match every alert
+#[derive(Debug, Clone, Default)]
+pub struct AlertFilter {
+    pub kinds: Vec<AlertKind>,
+    pub severities: Vec<Severity>,
+    pub statuses: Vec<AlertStatus>,
+    pub groups: Vec<String>,
+    /// Created at or after this RFC 3339 UTC timestamp
+    pub since: Option<String>,
+    /// Created before this RFC 3339 UTC timestamp
+    pub until: Option<String>,
+    pub prefix: Option<(IpNet, PrefixMatch)>,
+    /// Expected or observed origin ASN
+    pub asn: Option<u32>,
+    /// Full-text query over the analysis and the chat messages
+    pub search: Option<String>,
+    pub cursor: Option<AlertCursor>,
+    /// Maximum number of alerts returned; every alert when unset
+    pub limit: Option<u32>,
+}
+
+/// Response body of `GET /api/alerts`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AlertPage {
+    /// Alerts of the page, newest first
+    pub alerts: Vec<Value>,
+    /// Number of alerts matching the filters, over all pages
+    pub total: i64,
+    /// Cursor of the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A group of repeated alerts sharing the same fingerprint within the deduplication window
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Incident {
+    pub id: i64,
+    pub fingerprint: String,
+    /// The alert holding the payload and analysis for this incident
+    pub alert_id: i64,
+    pub kind: String,
+    pub prefix: String,
+    pub asn: String,
+    pub neworigin: Option<String>,
+    pub newprefix: Option<String>,
+    pub occurrence_count: i64,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// Highest peer count reported by any occurrence
+    pub peers: i64,
+    /// Highest path count reported by any occurrence
+    pub paths: i64,
+    /// Peer count the current analysis was based on
+    pub analyzed_peers: i64,
+    /// Path count the current analysis was based on
+    pub analyzed_paths: i64,
+}
+
+/// Fields needed to store a new alert
+#[derive(Debug, Clone)]
+pub struct NewAlert<'a> {
+    /// The alert payload as stored
+    pub alert_data: &'a str,
+    pub kind: AlertKind,
+    /// Group of the monitored entry the alert matched
+    pub group: Option<&'a str>,
+    /// Severity until the analysis assigns one
+    pub severity: Option<Severity>,
+}
+
+impl<'a> NewAlert<'a> {
+    #[allow(dead_code)] // Used in tests
+    pub fn new(alert_data: &'a str, kind: AlertKind) -> Self {
+        Self {
+            alert_data,
+            kind,
+            group: None,
+            severity: None,
+        }
+    }
+}
+
+/// Fields needed to open a new incident
+#[derive(Debug, Clone)]
+pub struct NewIncident {
+    pub fingerprint: String,
+    pub kind: String,
+    pub prefix: String,
+    pub asn: String,
+    pub neworigin: Option<String>,
+    pub newprefix: Option<String>,
+    pub peers: i64,
+    pub paths: i64,
+}
+
+/// An MCP tool invocation made by an agent while investigating an alert
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ToolCall {
+    pub id: i64,
+    pub alert_id: i64,
+    /// Assistant chat message the call was made for; `None` for the analysis run
+    pub chat_message_id: Option<i64>,
+    /// MCP server that provides the tool, if known
+    pub server_name: Option<String>,
+    pub tool_name: String,
+    /// Arguments as sent by the model (JSON)
+    pub arguments: String,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: i64,
+    pub created_at: String,
+}
+
+/// Delivery state of a notification to one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Not delivered yet; retried with backoff
+    Pending,
+    Sent,
+    /// Every attempt failed
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<&str> for DeliveryStatus {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "sent" => Ok(DeliveryStatus::Sent),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(format!("Unknown delivery status: {}", s)),
+        }
+    }
+}
+
+/// Delivery of an alert's notification to one channel
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationDelivery {
+    pub id: i64,
+    pub alert_id: i64,
+    /// Channel name in groups.yml
+    pub channel: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A notification still being delivered, as left by a previous process
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub alert_id: i64,
+    pub channel: String,
+    pub attempts: i64,
+    /// Notification as JSON; missing for rows recorded before it was stored
+    pub payload: Option<String>,
+}
+
+/// A tool invocation captured during an agent run, not yet stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewToolCall {
+    pub server_name: Option<String>,
+    pub tool_name: String,
+    pub arguments: String,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: i64,
+    pub created_at: String,
+}
+
+/// Token usage of one LLM prompt (possibly spanning several turns), not yet stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewLlmUsage {
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Completion requests sent to the model, one per tool-calling round
+    pub turns: i64,
+    pub duration_ms: i64,
+    pub created_at: String,
+}
+
+/// Aggregated LLM usage for one day, model and alert kind
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageSummary {
+    /// UTC date (YYYY-MM-DD)
+    pub day: String,
+    pub model: String,
+    /// Kind of the alert the calls were made for, if it still exists
+    pub alert_kind: Option<String>,
+    pub calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub turns: i64,
+    pub duration_ms: i64,
+    /// Estimated spend in USD; `None` when no price is configured for the model
+    pub estimated_cost_usd: Option<f64>,
+}
+
+/// Response of the usage endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageReport {
+    /// Start of the reported period (UTC date, inclusive)
+    pub since: String,
+    pub rows: Vec<UsageSummary>,
+    pub total_calls: i64,
+    pub total_input_tokens: i64,
+    pub total_output_tokens: i64,
+    /// Estimated spend in USD across all priced models
+    pub total_estimated_cost_usd: f64,
+    /// Models that were used but have no configured price
+    pub unpriced_models: Vec<String>,
+}
+
+/// A purge of expired alerts and chat messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RetentionRun {
+    pub started_at: String,
+    pub finished_at: String,
+    pub alerts_deleted: i64,
+    pub chat_messages_deleted: i64,
+    /// Archive the deleted records were exported to, if any
+    pub archive_path: Option<String>,
+    /// Why the purge failed; nothing was deleted then
+    pub error: Option<String>,
+}
+
+/// Number of rows of a table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TableUsage {
+    pub table: String,
+    pub rows: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Alert {
     pub id: i64,
@@ -642,4 +1197,38 @@ mod tests {
         assert!(timestamp.contains('T'));
         assert!(timestamp.contains('Z') || timestamp.contains('+') || timestamp.contains('-'));
     }
+
+    #[test]
+    fn test_alert_status_transitions() {
+        use AlertStatus::*;
+
+        assert!(New.can_transition_to(Acknowledged));
+        assert!(New.can_transition_to(Resolved));
+        assert!(Acknowledged.can_transition_to(Investigating));
+        assert!(Investigating.can_transition_to(FalsePositive));
+        assert!(Resolved.can_transition_to(FalsePositive));
+        // Reopening
+        assert!(Resolved.can_transition_to(New));
+        assert!(FalsePositive.can_transition_to(Investigating));
+
+        assert!(!New.can_transition_to(New));
+        assert!(!Acknowledged.can_transition_to(New));
+        assert!(!Investigating.can_transition_to(New));
+
+        for status in [New, Acknowledged, Investigating, Resolved, FalsePositive] {
+            assert_eq!(AlertStatus::try_from(status.as_str()), Ok(status));
+        }
+        assert!(AlertStatus::try_from("closed").is_err());
+    }
+
+    #[test]
+    fn test_alert_cursor_round_trip() {
+        let cursor = AlertCursor {
+            created_at: "2025-01-01T00:00:00.123+00:00".to_string(),
+            id: 42,
+        };
+        assert_eq!(AlertCursor::decode(&cursor.encode()), Ok(cursor));
+        assert!(AlertCursor::decode("not a cursor").is_err());
+        assert!(AlertCursor::decode(&URL_SAFE_NO_PAD.encode("x|2025")).is_err());
+    }
 }
@@ -0,0 +1,234 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::alerts::notify::ChannelConfig;
+use crate::database::models::Severity;
+
+/// Group whose settings apply to groups that are not listed, as in BGPAlerter
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Extra analysis instructions for the alerts of some groups, e.g. customer prefixes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PromptProfile {
+    /// Appended to the system prompt of the analysis agent
+    pub instructions: String,
+}
+
+/// How the alerts of one group are routed and triaged
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GroupConfig {
+    /// Names of the notification channels alerts of the group are sent to
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Severity of the group's alerts until the analysis assigns one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Prompt profile used to analyze the group's alerts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Contents of groups.yml
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GroupRegistry {
+    #[serde(default)]
+    pub profiles: HashMap<String, PromptProfile>,
+    #[serde(default)]
+    pub groups: HashMap<String, GroupConfig>,
+    /// Notification channels; not serialized, as they hold endpoints and credentials
+    #[serde(default, skip_serializing)]
+    #[schema(ignore)]
+    pub channels: HashMap<String, ChannelConfig>,
+}
+
+impl GroupRegistry {
+    /// Load the registry, with no groups when the file does not exist
+    ///
+    /// Fails when a channel reads a secret from an environment variable that is not set.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            tracing::info!(
+                "{} not found, alerts will not be routed by group",
+                path.display()
+            );
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        let registry = Self::from_str(&content)?;
+        registry.check_secrets()?;
+        Ok(registry)
+    }
+
+    /// Check that the secrets of every channel are set in the environment
+    pub fn check_secrets(&self) -> Result<()> {
+        for (name, channel) in &self.channels {
+            if let Some(var) = channel
+                .target
+                .secret_vars()
+                .into_iter()
+                .find(|var| std::env::var_os(var).is_none())
+            {
+                return Err(eyre!(
+                    "Notification channel {} reads {}, which is not set",
+                    name,
+                    var
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_str(content: &str) -> Result<Self> {
+        let registry: Self = serde_yaml::from_str(content)?;
+        for (name, channel) in &registry.channels {
+            channel
+                .target
+                .validate()
+                .map_err(|e| eyre!("Notification channel {}: {}", name, e))?;
+        }
+        for (name, group) in &registry.groups {
+            if let Some(profile) = &group.profile
+                && !registry.profiles.contains_key(profile)
+            {
+                return Err(eyre!(
+                    "Group {} uses unknown prompt profile '{}'",
+                    name,
+                    profile
+                ));
+            }
+            if let Some(channel) = group
+                .channels
+                .iter()
+                .find(|channel| !registry.channels.contains_key(*channel))
+            {
+                return Err(eyre!(
+                    "Group {} uses unknown notification channel '{}'",
+                    name,
+                    channel
+                ));
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Settings of a group, or of the default group when it is not listed
+    pub fn get(&self, group: &str) -> Option<&GroupConfig> {
+        self.groups
+            .get(group)
+            .or_else(|| self.groups.get(DEFAULT_GROUP))
+    }
+
+    /// Severity of a new alert of the group, before it is analyzed
+    pub fn default_severity(&self, group: Option<&str>) -> Option<Severity> {
+        self.get(group?)?.severity
+    }
+
+    /// Instructions of the group's prompt profile, if it has one
+    pub fn profile_instructions(&self, group: Option<&str>) -> Option<&str> {
+        let profile = self.get(group?)?.profile.as_ref()?;
+        self.profiles
+            .get(profile)
+            .map(|profile| profile.instructions.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUPS: &str = r#"
+channels:
+  noc-slack:
+    type: slack
+    url: https://hooks.slack.com/services/T000/B000/XXXX
+  noc-email:
+    type: email
+    smtp_host: smtp.example.net
+    from: agentnoc@example.net
+    to: [noc@example.net]
+  customer-webhook:
+    type: webhook
+    url: https://tickets.example.net/hooks/agentnoc
+profiles:
+  customer:
+    instructions: These prefixes belong to customers; name the customer in the summary.
+groups:
+  noc:
+    channels: [noc-slack, noc-email]
+    severity: high
+  customers:
+    channels: [customer-webhook]
+    severity: medium
+    profile: customer
+  default:
+    channels: [noc-email]
+"#;
+
+    #[test]
+    fn test_group_settings() {
+        let registry = GroupRegistry::from_str(GROUPS).unwrap();
+
+        assert_eq!(registry.get("noc").unwrap().channels.len(), 2);
+        assert_eq!(registry.default_severity(Some("noc")), Some(Severity::High));
+        assert_eq!(registry.profile_instructions(Some("noc")), None);
+        assert!(
+            registry
+                .profile_instructions(Some("customers"))
+                .unwrap()
+                .contains("name the customer")
+        );
+
+        // Unlisted groups fall back to the default group
+        assert_eq!(registry.get("peering").unwrap().channels, vec!["noc-email"]);
+        assert_eq!(registry.default_severity(Some("peering")), None);
+        assert_eq!(registry.default_severity(None), None);
+
+        assert!(GroupRegistry::default().get("noc").is_none());
+    }
+
+    #[test]
+    fn test_example_file_loads() {
+        // Its channels read secrets that are not set here
+        let content = fs::read_to_string("groups.yml.example").unwrap();
+        let registry = GroupRegistry::from_str(&content).unwrap();
+        assert!(registry.profile_instructions(Some("customers")).is_some());
+        let err = registry.check_secrets().unwrap_err();
+        assert!(err.to_string().contains("which is not set"));
+        assert!(
+            GroupRegistry::load("missing-groups.yml")
+                .unwrap()
+                .groups
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_unknown_profile_is_rejected() {
+        let err = GroupRegistry::from_str("groups:\n  noc:\n    profile: transit\n").unwrap_err();
+        assert!(err.to_string().contains("unknown prompt profile 'transit'"));
+        assert!(GroupRegistry::from_str("groups:\n  noc:\n    severity: urgent\n").is_err());
+
+        let err = GroupRegistry::from_str("groups:\n  noc:\n    channels: [pager]\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown notification channel 'pager'")
+        );
+        assert!(GroupRegistry::from_str("channels:\n  pager:\n    type: sms\n").is_err());
+    }
+
+    #[test]
+    fn test_invalid_email_address_is_rejected() {
+        let err = GroupRegistry::from_str(
+            "channels:\n  noc-email:\n    type: email\n    smtp_host: smtp.example.net\n    \
+             from: agentnoc@example.net\n    to: [noc]\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid to address 'noc'"));
+    }
+}
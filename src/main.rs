@@ -2,10 +2,16 @@ mod agents;
 mod alerts;
 mod config;
 mod database;
+mod groups;
 mod mcp_clients;
+mod mcp_manager;
 mod native_mcps;
+mod prefix_trie;
+mod prefixes;
+mod rpki;
 
 use alerts::http;
+use alerts::http::server::SseMessage;
 use std::fs::OpenOptions;
 use std::sync::Arc;
 use tokio::sync::broadcast;
@@ -30,11 +36,18 @@ async fn main() -> Result<()> {
 
     // Load configuration
     let config = config::AppConfig::from_env()?;
+
+    // Schema maintenance without starting the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let pool = database::db::connect(&config.database_url).await?;
+        return database::migrations::command(&pool, &args[1..]).await;
+    }
     let server_port = config.server_port;
     let server_url = format!("http://127.0.0.1:{server_port}");
 
     // Create broadcast channel for message streaming
-    let (tx, _) = broadcast::channel::<String>(100);
+    let (tx, _) = broadcast::channel::<SseMessage>(100);
 
     // Spawn server task
     let config_arc = Arc::new(config);
@@ -5,20 +5,20 @@ use rmcp::transport::streamable_http_client::{
     StreamableHttpClientTransport, StreamableHttpClientTransportConfig,
 };
 use rmcp::{Peer, RoleClient, ServiceExt};
-use sqlx::SqlitePool;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::database::db::get_enabled_mcp_servers;
 use crate::database::models::McpServer;
 
 /// Container for MCP client tools and peer information
-/// IMPORTANT: The Box<dyn ...> must be kept alive for the peer to work
+/// IMPORTANT: The service must be kept alive for the peer to work; clones share it
+#[derive(Clone)]
 pub struct MCPConnection {
     pub name: String,
     pub tools: Vec<Tool>,
     pub peer: Peer<RoleClient>,
     #[allow(dead_code)] // This field must exist to keep the service alive
-    _service: Box<dyn std::any::Any + Send>,
+    _service: Arc<dyn std::any::Any + Send + Sync>,
 }
 
 impl MCPConnection {
@@ -83,7 +83,7 @@ async fn connect_http(name: &str, client_info: ClientInfo, url: &str) -> Result<
         name: name.to_string(),
         tools: tools_result.tools,
         peer,
-        _service: Box::new(client),
+        _service: Arc::new(client),
     })
 }
 
@@ -132,60 +132,10 @@ async fn connect_stdio(
         name: name.to_string(),
         tools: tools_result.tools,
         peer,
-        _service: Box::new(client),
+        _service: Arc::new(client),
     })
 }
 
-/// Connect to all enabled MCP servers from the database
-///
-/// This function attempts to connect to all enabled MCP servers.
-/// If a server fails to connect, it logs the error and continues with the rest.
-/// Returns a vector of successfully connected servers.
-pub async fn connect_all_enabled(pool: &SqlitePool) -> Result<Vec<MCPConnection>> {
-    let servers = get_enabled_mcp_servers(pool).await?;
-
-    if servers.is_empty() {
-        tracing::warn!("No enabled MCP servers found in database");
-        return Ok(Vec::new());
-    }
-
-    tracing::info!("Connecting to {} enabled MCP server(s)...", servers.len());
-
-    let mut connections = Vec::new();
-    let mut failed_count = 0;
-
-    for server in servers {
-        match connect(&server).await {
-            Ok(conn) => {
-                tracing::info!(
-                    "Successfully connected to MCP server '{}' ({} tools)",
-                    conn.name,
-                    conn.tool_count()
-                );
-                connections.push(conn);
-            }
-            Err(e) => {
-                tracing::error!(
-                    "Failed to connect to MCP server '{}': {:?}",
-                    server.name(),
-                    e
-                );
-                failed_count += 1;
-            }
-        }
-    }
-
-    if failed_count > 0 {
-        tracing::warn!(
-            "{} MCP server(s) failed to connect, {} connected successfully",
-            failed_count,
-            connections.len()
-        );
-    }
-
-    Ok(connections)
-}
-
 /// Test connection to a specific MCP server
 ///
 /// Returns Ok(tool_count) if connection successful, Err if failed
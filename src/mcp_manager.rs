@@ -0,0 +1,326 @@
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::database::DbPool;
+use crate::database::db::get_enabled_mcp_servers;
+use crate::database::models::McpServer;
+use crate::mcp_clients::{self, MCPConnection};
+
+/// How often the background task checks connections and retries failed servers
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Delay before the first reconnect attempt (doubled on every failure)
+const BASE_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound for the delay between reconnect attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bound for establishing a connection and listing its tools
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connection state of a managed MCP server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McpServerStatus {
+    Connected { tool_count: usize },
+    Disconnected { error: String, retry_in: Duration },
+}
+
+struct ManagedServer {
+    server: McpServer,
+    /// Live connection with its cached tool list
+    connection: Option<MCPConnection>,
+    failures: u32,
+    retry_at: Instant,
+    last_error: Option<String>,
+}
+
+/// Long-lived pool holding one connection per enabled MCP server
+///
+/// Connections are reused across analyses, chats and health checks. Failed or
+/// closed connections are retried with exponential backoff, and the set of
+/// servers is reconciled with the database on every refresh.
+pub struct McpManager {
+    db_pool: Arc<DbPool>,
+    servers: RwLock<HashMap<i64, ManagedServer>>,
+    /// Serializes refreshes so a server is never connected twice
+    refresh_lock: Mutex<()>,
+}
+
+impl McpManager {
+    pub fn new(db_pool: Arc<DbPool>) -> Self {
+        Self {
+            db_pool,
+            servers: RwLock::new(HashMap::new()),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    /// Spawn the background task that keeps connections alive
+    pub fn start(manager: Arc<McpManager>) {
+        tokio::spawn(async move {
+            loop {
+                manager.refresh().await;
+                tokio::time::sleep(MAINTENANCE_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Refresh in the background, e.g. after a server was changed through the API
+    pub fn refresh_in_background(manager: &Arc<McpManager>) {
+        let manager = manager.clone();
+        tokio::spawn(async move { manager.refresh().await });
+    }
+
+    /// Reconcile with the enabled servers in the database and (re)connect where due
+    pub async fn refresh(&self) {
+        let _guard = self.refresh_lock.lock().await;
+
+        let enabled = match get_enabled_mcp_servers(&self.db_pool).await {
+            Ok(servers) => servers,
+            Err(e) => {
+                tracing::error!("Failed to load MCP servers: {}", e);
+                return;
+            }
+        };
+
+        let due = self.reconcile(enabled).await;
+        if due.is_empty() {
+            return;
+        }
+
+        let results = join_all(due.iter().map(|server| async move {
+            match tokio::time::timeout(CONNECT_TIMEOUT, mcp_clients::connect(server)).await {
+                Ok(result) => result,
+                Err(_) => Err(color_eyre::eyre::eyre!("connection timed out")),
+            }
+        }))
+        .await;
+
+        let mut servers = self.servers.write().await;
+        for (server, result) in due.iter().zip(results) {
+            let Some(managed) = servers.get_mut(&server.meta().id) else {
+                // Removed while we were connecting
+                continue;
+            };
+
+            match result {
+                Ok(connection) => {
+                    tracing::info!(
+                        "Connected to MCP server '{}' ({} tools)",
+                        server.name(),
+                        connection.tool_count()
+                    );
+                    managed.connection = Some(connection);
+                    managed.failures = 0;
+                    managed.last_error = None;
+                }
+                Err(e) => {
+                    managed.failures += 1;
+                    let delay = reconnect_delay(managed.failures);
+                    managed.retry_at = Instant::now() + delay;
+                    managed.last_error = Some(e.to_string());
+                    tracing::warn!(
+                        "Failed to connect to MCP server '{}' (attempt {}), retrying in {:?}: {}",
+                        server.name(),
+                        managed.failures,
+                        delay,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Live connections to all enabled servers
+    ///
+    /// Never blocks on (re)connecting; that is left to the background task.
+    pub async fn connections(&self) -> Vec<MCPConnection> {
+        let servers = self.servers.read().await;
+        servers
+            .values()
+            .filter_map(|managed| managed.connection.as_ref())
+            .filter(|connection| !connection.peer.is_transport_closed())
+            .cloned()
+            .collect()
+    }
+
+    /// Connection state of every enabled server, keyed by server name
+    pub async fn status(&self) -> Vec<(String, McpServerStatus)> {
+        let now = Instant::now();
+        let servers = self.servers.read().await;
+        let mut status: Vec<_> = servers
+            .values()
+            .map(|managed| {
+                let state = match &managed.connection {
+                    Some(connection) if !connection.peer.is_transport_closed() => {
+                        McpServerStatus::Connected {
+                            tool_count: connection.tool_count(),
+                        }
+                    }
+                    Some(_) => McpServerStatus::Disconnected {
+                        error: "connection closed".to_string(),
+                        retry_in: managed.retry_at.saturating_duration_since(now),
+                    },
+                    None => McpServerStatus::Disconnected {
+                        error: managed
+                            .last_error
+                            .clone()
+                            .unwrap_or_else(|| "not connected yet".to_string()),
+                        retry_in: managed.retry_at.saturating_duration_since(now),
+                    },
+                };
+                (managed.server.name().to_string(), state)
+            })
+            .collect();
+        status.sort_by(|a, b| a.0.cmp(&b.0));
+        status
+    }
+
+    /// Apply the current set of enabled servers and return those due for a connection attempt
+    async fn reconcile(&self, enabled: Vec<McpServer>) -> Vec<McpServer> {
+        let now = Instant::now();
+        let mut servers = self.servers.write().await;
+
+        let enabled: HashMap<i64, McpServer> = enabled
+            .into_iter()
+            .map(|server| (server.meta().id, server))
+            .collect();
+
+        // Drop servers that were deleted, disabled or reconfigured
+        servers.retain(|id, managed| {
+            let keep = enabled
+                .get(id)
+                .is_some_and(|server| same_config(server, &managed.server));
+            if !keep {
+                tracing::info!("Dropping MCP connection '{}'", managed.server.name());
+            }
+            keep
+        });
+
+        for (id, server) in enabled {
+            servers.entry(id).or_insert_with(|| ManagedServer {
+                server,
+                connection: None,
+                failures: 0,
+                retry_at: now,
+                last_error: None,
+            });
+        }
+
+        for managed in servers.values_mut() {
+            if managed
+                .connection
+                .as_ref()
+                .is_some_and(|c| c.peer.is_transport_closed())
+            {
+                tracing::warn!("MCP server '{}' disconnected", managed.server.name());
+                managed.connection = None;
+                managed.last_error = Some("connection closed".to_string());
+                managed.retry_at = now;
+            }
+        }
+
+        servers
+            .values()
+            .filter(|managed| managed.connection.is_none() && managed.retry_at <= now)
+            .map(|managed| managed.server.clone())
+            .collect()
+    }
+}
+
+fn same_config(a: &McpServer, b: &McpServer) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Delay before the next reconnect attempt after `failures` consecutive failures
+pub fn reconnect_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    BASE_RECONNECT_DELAY
+        .saturating_mul(1 << exponent)
+        .min(MAX_RECONNECT_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db;
+    use crate::database::models::CreateMcpServer;
+
+    async fn create_test_manager() -> (McpManager, Arc<DbPool>) {
+        let pool = Arc::new(db::tests::create_test_db().await.unwrap());
+        (McpManager::new(pool.clone()), pool)
+    }
+
+    fn broken_stdio_server(name: &str) -> CreateMcpServer {
+        CreateMcpServer::Stdio {
+            name: name.to_string(),
+            description: None,
+            command: "/nonexistent/agent-noc-mcp".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_reconnect_delay_backoff() {
+        assert_eq!(reconnect_delay(1), Duration::from_secs(5));
+        assert_eq!(reconnect_delay(2), Duration::from_secs(10));
+        assert_eq!(reconnect_delay(3), Duration::from_secs(20));
+        assert_eq!(reconnect_delay(50), MAX_RECONNECT_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_no_servers() {
+        let (manager, _pool) = create_test_manager().await;
+
+        assert!(manager.connections().await.is_empty());
+        assert!(manager.status().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_server_backs_off() {
+        let (manager, pool) = create_test_manager().await;
+        db::create_mcp_server(&pool, &broken_stdio_server("broken"))
+            .await
+            .unwrap();
+
+        manager.refresh().await;
+        assert!(manager.connections().await.is_empty());
+        let status = manager.status().await;
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].0, "broken");
+        let McpServerStatus::Disconnected { retry_in, .. } = &status[0].1 else {
+            panic!("Expected a disconnected server");
+        };
+        assert!(*retry_in > Duration::ZERO);
+
+        // Not retried again before the backoff expires
+        manager.refresh().await;
+        let servers = manager.servers.read().await;
+        assert_eq!(servers.values().next().unwrap().failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_removed_server_is_dropped() {
+        let (manager, pool) = create_test_manager().await;
+        let server = db::create_mcp_server(&pool, &broken_stdio_server("broken"))
+            .await
+            .unwrap();
+
+        manager.refresh().await;
+        assert_eq!(manager.status().await.len(), 1);
+
+        db::delete_mcp_server(&pool, server.meta().id)
+            .await
+            .unwrap();
+        manager.refresh().await;
+        assert!(manager.status().await.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// A path-compressed binary trie node; keys are left-aligned in a u128 so IPv4 and IPv6 share
+/// the same code, in separate tries
+#[derive(Clone)]
+struct Node<T> {
+    key: u128,
+    len: u8,
+    value: Option<T>,
+    children: [Option<Box<Node<T>>>; 2],
+}
+
+impl<T> Node<T> {
+    fn new(key: u128, len: u8, value: Option<T>) -> Box<Self> {
+        Box::new(Self {
+            key,
+            len,
+            value,
+            children: [None, None],
+        })
+    }
+}
+
+fn mask(key: u128, len: u8) -> u128 {
+    match u128::MAX.checked_shr(u32::from(len)) {
+        Some(host) => key & !host,
+        None => key,
+    }
+}
+
+fn bit_at(key: u128, index: u8) -> usize {
+    ((key >> (127 - u32::from(index))) & 1) as usize
+}
+
+/// Number of leading bits `a` and `b` share, capped at `max`
+fn common_len(a: u128, b: u128, max: u8) -> u8 {
+    ((a ^ b).leading_zeros() as u8).min(max)
+}
+
+fn encode(net: &IpNet) -> (u128, u8) {
+    let net = net.trunc();
+    let key = match net.addr() {
+        IpAddr::V4(addr) => u128::from(u32::from(addr)) << 96,
+        IpAddr::V6(addr) => u128::from(addr),
+    };
+    (key, net.prefix_len())
+}
+
+fn decode(key: u128, len: u8, v4: bool) -> IpNet {
+    if v4 {
+        let addr = Ipv4Addr::from((key >> 96) as u32);
+        IpNet::V4(Ipv4Net::new(addr, len).expect("trie keys have valid lengths"))
+    } else {
+        IpNet::V6(Ipv6Net::new(Ipv6Addr::from(key), len).expect("trie keys have valid lengths"))
+    }
+}
+
+fn insert<T>(slot: &mut Option<Box<Node<T>>>, key: u128, len: u8, value: T) {
+    let Some(node) = slot else {
+        *slot = Some(Node::new(key, len, Some(value)));
+        return;
+    };
+
+    let common = common_len(node.key, key, node.len.min(len));
+    if common == node.len && common == len {
+        node.value = Some(value);
+    } else if common == node.len {
+        insert(&mut node.children[bit_at(key, node.len)], key, len, value);
+    } else {
+        // The new key and the node diverge before the end of the node: put a node at the
+        // point where they diverge, or the new key itself when it is the shorter one
+        let old = slot.take().expect("slot holds a node");
+        let mut parent = if common == len {
+            Node::new(key, len, Some(value))
+        } else {
+            let mut branch = Node::new(mask(key, common), common, None);
+            branch.children[bit_at(key, common)] = Some(Node::new(key, len, Some(value)));
+            branch
+        };
+        let old_bit = bit_at(old.key, common);
+        parent.children[old_bit] = Some(old);
+        *slot = Some(parent);
+    }
+}
+
+fn collect<'a, T>(node: &'a Node<T>, v4: bool, out: &mut Vec<(IpNet, &'a T)>) {
+    if let Some(value) = &node.value {
+        out.push((decode(node.key, node.len, v4), value));
+    }
+    for child in node.children.iter().flatten() {
+        collect(child, v4, out);
+    }
+}
+
+/// Longest-prefix-match index of IP prefixes
+#[derive(Clone)]
+pub struct PrefixTrie<T> {
+    v4: Option<Box<Node<T>>>,
+    v6: Option<Box<Node<T>>>,
+    len: usize,
+}
+
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        Self {
+            v4: None,
+            v6: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for PrefixTrie<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefixTrie")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<T> PrefixTrie<T> {
+    fn root(&self, net: &IpNet) -> (&Option<Box<Node<T>>>, bool) {
+        match net {
+            IpNet::V4(_) => (&self.v4, true),
+            IpNet::V6(_) => (&self.v6, false),
+        }
+    }
+
+    /// Add `net`, replacing the value of an equal prefix
+    pub fn insert(&mut self, net: &IpNet, value: T) {
+        if self.get(net).is_none() {
+            self.len += 1;
+        }
+        let (key, len) = encode(net);
+        let root = match net {
+            IpNet::V4(_) => &mut self.v4,
+            IpNet::V6(_) => &mut self.v6,
+        };
+        insert(root, key, len, value);
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value stored for exactly `net`
+    pub fn get(&self, net: &IpNet) -> Option<&T> {
+        let (key, len) = encode(net);
+        self.covering(net)
+            .into_iter()
+            .next()
+            .filter(|(found, _)| encode(found) == (key, len))
+            .map(|(_, value)| value)
+    }
+
+    /// Prefixes equal to or covering `net`, most specific first
+    pub fn covering(&self, net: &IpNet) -> Vec<(IpNet, &T)> {
+        let (key, len) = encode(net);
+        let (mut node, v4) = self.root(net);
+        let mut found = Vec::new();
+        while let Some(current) = node {
+            if current.len > len || common_len(current.key, key, current.len) < current.len {
+                break;
+            }
+            if let Some(value) = &current.value {
+                found.push((decode(current.key, current.len, v4), value));
+            }
+            if current.len == len {
+                break;
+            }
+            node = &current.children[bit_at(key, current.len)];
+        }
+        found.reverse();
+        found
+    }
+
+    /// Prefixes strictly more specific than `net`, least specific first
+    pub fn covered(&self, net: &IpNet) -> Vec<(IpNet, &T)> {
+        let (key, len) = encode(net);
+        let (mut node, v4) = self.root(net);
+        let mut found = Vec::new();
+        while let Some(current) = node {
+            if common_len(current.key, key, current.len.min(len)) < current.len.min(len) {
+                break;
+            }
+            if current.len > len {
+                collect(current, v4, &mut found);
+                break;
+            }
+            if current.len == len {
+                for child in current.children.iter().flatten() {
+                    collect(child, v4, &mut found);
+                }
+                break;
+            }
+            node = &current.children[bit_at(key, current.len)];
+        }
+        found.sort_by_key(|(net, _)| (net.prefix_len(), *net));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn net(prefix: &str) -> IpNet {
+        prefix.parse().unwrap()
+    }
+
+    fn nets(found: Vec<(IpNet, &&str)>) -> Vec<String> {
+        found.into_iter().map(|(net, _)| net.to_string()).collect()
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut trie = PrefixTrie::default();
+        assert!(trie.is_empty());
+        for prefix in [
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "10.1.2.0/24",
+            "10.2.0.0/16",
+            "0.0.0.0/0",
+            "2001:db8::/32",
+            "2001:db8:1::/48",
+        ] {
+            trie.insert(&net(prefix), prefix);
+        }
+        trie.insert(&net("10.1.0.0/16"), "10.1.0.0/16");
+        assert_eq!(trie.len(), 7);
+        assert!(!trie.is_empty());
+
+        assert_eq!(
+            nets(trie.covering(&net("10.1.2.128/25"))),
+            vec!["10.1.2.0/24", "10.1.0.0/16", "10.0.0.0/8", "0.0.0.0/0"]
+        );
+        assert_eq!(
+            nets(trie.covering(&net("10.3.0.0/16"))),
+            vec!["10.0.0.0/8", "0.0.0.0/0"]
+        );
+        assert_eq!(trie.get(&net("10.1.0.0/16")), Some(&"10.1.0.0/16"));
+        assert_eq!(trie.get(&net("10.1.0.0/17")), None);
+
+        assert_eq!(
+            nets(trie.covered(&net("10.0.0.0/8"))),
+            vec!["10.1.0.0/16", "10.2.0.0/16", "10.1.2.0/24"]
+        );
+        assert_eq!(
+            nets(trie.covered(&net("10.0.0.0/14"))),
+            vec!["10.1.0.0/16", "10.2.0.0/16", "10.1.2.0/24"]
+        );
+        assert!(trie.covered(&net("10.1.2.0/24")).is_empty());
+
+        // IPv4 and IPv6 never match each other
+        assert_eq!(
+            nets(trie.covering(&net("2001:db8:1:2::/64"))),
+            vec!["2001:db8:1::/48", "2001:db8::/32"]
+        );
+        assert!(trie.covering(&net("::/0")).is_empty());
+    }
+
+    fn arb_net() -> impl Strategy<Value = IpNet> {
+        prop_oneof![
+            (any::<u32>(), 0u8..=32).prop_map(|(addr, len)| IpNet::new(
+                Ipv4Addr::from(addr).into(),
+                len
+            )
+            .unwrap()),
+            // Few distinct top bits, so that IPv6 prefixes overlap often
+            (0u128..4, any::<u64>(), 0u8..=64).prop_map(|(top, low, len)| {
+                let addr = (top << 126) | u128::from(low);
+                IpNet::new(Ipv6Addr::from(addr).into(), len).unwrap()
+            }),
+        ]
+        .prop_map(|net| net.trunc())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_matches_linear_scan(
+            prefixes in prop::collection::vec(arb_net(), 0..64),
+            query in arb_net(),
+        ) {
+            let mut trie = PrefixTrie::default();
+            for prefix in &prefixes {
+                trie.insert(prefix, *prefix);
+            }
+
+            let mut covering: Vec<IpNet> = prefixes
+                .iter()
+                .copied()
+                .filter(|p| p.contains(&query))
+                .collect();
+            covering.sort_by_key(|p| std::cmp::Reverse(p.prefix_len()));
+            covering.dedup();
+            let found: Vec<IpNet> = trie.covering(&query).into_iter().map(|(n, _)| n).collect();
+            prop_assert_eq!(found, covering);
+
+            let mut covered: Vec<IpNet> = prefixes
+                .iter()
+                .copied()
+                .filter(|p| query.contains(p) && p.prefix_len() > query.prefix_len())
+                .collect();
+            covered.sort_by_key(|p| (p.prefix_len(), *p));
+            covered.dedup();
+            let found: Vec<IpNet> = trie.covered(&query).into_iter().map(|(n, _)| n).collect();
+            prop_assert_eq!(found, covered);
+        }
+    }
+}
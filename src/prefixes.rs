@@ -0,0 +1,538 @@
+use color_eyre::eyre::eyre;
+use color_eyre::{Report, Result};
+use ipnet::IpNet;
+use serde_yaml::{Mapping, Value};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+use crate::config::{AsnInfo, DeviceInfo, PrefixInfo, PrefixesConfig, parse_asn};
+
+/// How often the prefixes file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+const OPTIONS_KEY: &str = "options";
+const MONITOR_ASNS_KEY: &str = "monitorASns";
+const MONITOR_DEVICES_KEY: &str = "monitorDevices";
+
+fn key_str<'a>(key: &'a Value, section: &str) -> Result<&'a str> {
+    key.as_str().ok_or_else(|| {
+        eyre!(
+            "Keys in {} must be quoted strings, found {:?}",
+            section,
+            key
+        )
+    })
+}
+
+fn mapping_of<'a>(value: &'a Value, section: &str) -> Result<Option<&'a Mapping>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Mapping(mapping) => Ok(Some(mapping)),
+        _ => Err(eyre!("{} must be a mapping", section)),
+    }
+}
+
+/// Parse a prefix key, which must be a network address like BGPAlerter expects
+fn parse_prefix(prefix: &str) -> Result<IpNet> {
+    let net: IpNet = prefix
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("Invalid prefix '{}'", prefix))?;
+    if net.trunc() != net {
+        return Err(eyre!(
+            "Prefix {} has host bits set, did you mean {}?",
+            prefix,
+            net.trunc()
+        ));
+    }
+    Ok(net)
+}
+
+fn validate_options(options: &Value) -> Result<()> {
+    let Some(options) = mapping_of(options, OPTIONS_KEY)? else {
+        return Ok(());
+    };
+
+    if let Some(asns) = options.get(MONITOR_ASNS_KEY)
+        && let Some(asns) = mapping_of(asns, MONITOR_ASNS_KEY)?
+    {
+        for (asn, info) in asns {
+            let asn = key_str(asn, MONITOR_ASNS_KEY)?;
+            parse_asn(asn)?;
+            serde_yaml::from_value::<AsnInfo>(info.clone())
+                .map_err(|e| eyre!("Invalid monitored ASN {}: {}", asn, e))?;
+        }
+    }
+
+    if let Some(devices) = options.get(MONITOR_DEVICES_KEY)
+        && let Some(devices) = mapping_of(devices, MONITOR_DEVICES_KEY)?
+    {
+        for (address, info) in devices {
+            let address = key_str(address, MONITOR_DEVICES_KEY)?;
+            address
+                .parse::<IpAddr>()
+                .map_err(|_| eyre!("Invalid device address '{}'", address))?;
+            serde_yaml::from_value::<DeviceInfo>(info.clone())
+                .map_err(|e| eyre!("Invalid monitored device {}: {}", address, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse prefixes.yml, rejecting the entries `PrefixesConfig::from_str` would silently skip
+pub fn parse_validated(content: &str) -> Result<PrefixesConfig> {
+    let document: Value = serde_yaml::from_str(content)?;
+    if let Some(mapping) = mapping_of(&document, "prefixes.yml")? {
+        for (key, value) in mapping {
+            let key = key_str(key, "prefixes.yml")?;
+            if key == OPTIONS_KEY {
+                validate_options(value)?;
+                continue;
+            }
+            parse_prefix(key)?;
+            serde_yaml::from_value::<PrefixInfo>(value.clone())
+                .map_err(|e| eyre!("Invalid entry for prefix {}: {}", key, e))?;
+        }
+    }
+    PrefixesConfig::from_str(content)
+}
+
+/// Why an edit of prefixes.yml was not applied
+#[derive(Debug)]
+pub enum EditError {
+    /// The entry to update or delete does not exist
+    NotFound,
+    /// The entry to create already exists
+    Exists,
+    /// The request or the resulting file is not a valid prefixes.yml
+    Invalid(String),
+    /// Reading or writing the file failed
+    Io(Report),
+}
+
+/// Whether a write creates a new entry or updates an existing one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    Create,
+    Update,
+}
+
+/// Insert or update `entry` in `section`; updates keep the fields we do not model, such as
+/// other BGPAlerter settings
+fn write_entry(
+    section: &mut Mapping,
+    existing: Option<Value>,
+    key: &str,
+    entry: Value,
+    mode: WriteMode,
+) -> Result<(), EditError> {
+    match (existing, mode) {
+        (Some(_), WriteMode::Create) => Err(EditError::Exists),
+        (None, WriteMode::Update) => Err(EditError::NotFound),
+        (None, WriteMode::Create) => {
+            section.insert(Value::String(key.to_string()), entry);
+            Ok(())
+        }
+        (Some(existing), WriteMode::Update) => {
+            match (section.get_mut(&existing), entry) {
+                (Some(Value::Mapping(current)), Value::Mapping(fields)) => {
+                    for (field, value) in fields {
+                        current.insert(field, value);
+                    }
+                }
+                (_, entry) => {
+                    section.insert(existing, entry);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Key of `net` in the document, which may be written differently, e.g. in upper case
+fn find_prefix(mapping: &Mapping, net: &IpNet) -> Option<Value> {
+    mapping
+        .keys()
+        .find(|key| {
+            key.as_str()
+                .and_then(|key| key.trim().parse::<IpNet>().ok())
+                .is_some_and(|key| key == *net)
+        })
+        .cloned()
+}
+
+fn find_asn(asns: &Mapping, asn: u32) -> Option<Value> {
+    asns.keys()
+        .find(|key| key.as_str().and_then(|key| parse_asn(key).ok()) == Some(asn))
+        .cloned()
+}
+
+/// The `options.monitorASns` section, created when missing
+fn asns_section(mapping: &mut Mapping) -> Result<&mut Mapping, EditError> {
+    let mut section = mapping;
+    for key in [OPTIONS_KEY, MONITOR_ASNS_KEY] {
+        let value = section
+            .entry(Value::String(key.to_string()))
+            .or_insert(Value::Null);
+        if value.is_null() {
+            *value = Value::Mapping(Mapping::new());
+        }
+        section = value
+            .as_mapping_mut()
+            .ok_or_else(|| EditError::Invalid(format!("{key} must be a mapping")))?;
+    }
+    Ok(section)
+}
+
+async fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// The monitored resources from prefixes.yml, swapped atomically when the file changes
+pub struct PrefixesStore {
+    path: PathBuf,
+    current: watch::Sender<Arc<PrefixesConfig>>,
+    /// Content of the applied file, so that rewriting the same content is not a change
+    content: Mutex<String>,
+    /// Edits through the API run one at a time
+    edits: tokio::sync::Mutex<()>,
+}
+
+impl PrefixesStore {
+    pub fn new(path: impl Into<PathBuf>, config: PrefixesConfig) -> Self {
+        Self {
+            path: path.into(),
+            current: watch::Sender::new(Arc::new(config)),
+            content: Mutex::new(String::new()),
+            edits: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Load the file, rejecting it on the same validation as a reload or an edit
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let content = std::fs::read_to_string(&path)?;
+        let store = Self::new(path, parse_validated(&content)?);
+        *store.content.lock().unwrap() = content;
+        Ok(store)
+    }
+
+    pub fn get(&self) -> Arc<PrefixesConfig> {
+        self.current.borrow().clone()
+    }
+
+    /// Notified every time a new configuration is applied
+    pub fn subscribe(&self) -> watch::Receiver<Arc<PrefixesConfig>> {
+        self.current.subscribe()
+    }
+
+    fn set(&self, config: PrefixesConfig, content: String) {
+        *self.content.lock().unwrap() = content;
+        self.current.send_replace(Arc::new(config));
+    }
+
+    /// Validate and apply the current file, returning whether the configuration changed
+    ///
+    /// Runs between API edits, so that a file read before an edit is never applied after it.
+    pub async fn reload(&self) -> Result<bool> {
+        let _edit = self.edits.lock().await;
+
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        if *self.content.lock().unwrap() == content {
+            return Ok(false);
+        }
+        let config = parse_validated(&content)?;
+        self.set(config, content);
+        Ok(true)
+    }
+
+    /// Apply `change` to the YAML document, then validate, write and apply the result
+    async fn edit<T>(
+        &self,
+        change: impl FnOnce(&mut Mapping) -> Result<T, EditError>,
+    ) -> Result<T, EditError> {
+        let _edit = self.edits.lock().await;
+
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(EditError::Io(e.into())),
+        };
+        let mut document: Value = serde_yaml::from_str(&content).map_err(|e| {
+            EditError::Invalid(format!("{} is not valid YAML: {}", self.path.display(), e))
+        })?;
+        if document.is_null() {
+            document = Value::Mapping(Mapping::new());
+        }
+        let mapping = document.as_mapping_mut().ok_or_else(|| {
+            EditError::Invalid(format!("{} must be a mapping", self.path.display()))
+        })?;
+        let result = change(mapping)?;
+
+        let content = serde_yaml::to_string(&document).map_err(|e| EditError::Io(e.into()))?;
+        let config = parse_validated(&content).map_err(|e| EditError::Invalid(e.to_string()))?;
+        write_atomically(&self.path, &content)
+            .await
+            .map_err(EditError::Io)?;
+        self.set(config, content);
+        Ok(result)
+    }
+
+    /// Create or update a monitored prefix, returning its normalized form
+    pub async fn write_prefix(
+        &self,
+        prefix: &str,
+        info: &PrefixInfo,
+        mode: WriteMode,
+    ) -> Result<String, EditError> {
+        let net = parse_prefix(prefix).map_err(|e| EditError::Invalid(e.to_string()))?;
+        let entry = serde_yaml::to_value(info).map_err(|e| EditError::Io(e.into()))?;
+        let key = net.to_string();
+        self.edit(|mapping| {
+            let existing = find_prefix(mapping, &net);
+            write_entry(mapping, existing, &key, entry, mode)
+        })
+        .await?;
+        Ok(key)
+    }
+
+    pub async fn remove_prefix(&self, prefix: &str) -> Result<(), EditError> {
+        let net = parse_prefix(prefix).map_err(|e| EditError::Invalid(e.to_string()))?;
+        self.edit(|mapping| {
+            let key = find_prefix(mapping, &net).ok_or(EditError::NotFound)?;
+            mapping.remove(&key);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Create or update a monitored ASN, returning its normalized form
+    pub async fn write_asn(
+        &self,
+        asn: &str,
+        info: &AsnInfo,
+        mode: WriteMode,
+    ) -> Result<String, EditError> {
+        let asn = parse_asn(asn).map_err(|e| EditError::Invalid(e.to_string()))?;
+        let entry = serde_yaml::to_value(info).map_err(|e| EditError::Io(e.into()))?;
+        let key = asn.to_string();
+        self.edit(|mapping| {
+            let asns = asns_section(mapping)?;
+            let existing = find_asn(asns, asn);
+            write_entry(asns, existing, &key, entry, mode)
+        })
+        .await?;
+        Ok(key)
+    }
+
+    pub async fn remove_asn(&self, asn: &str) -> Result<(), EditError> {
+        let asn = parse_asn(asn).map_err(|e| EditError::Invalid(e.to_string()))?;
+        self.edit(|mapping| {
+            let asns = asns_section(mapping)?;
+            let key = find_asn(asns, asn).ok_or(EditError::NotFound)?;
+            asns.remove(&key);
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Poll the prefixes file and apply it whenever it changes and is valid
+pub fn watch(store: Arc<PrefixesStore>) {
+    tokio::spawn(async move {
+        let modified = async |path: &Path| {
+            let metadata = tokio::fs::metadata(path).await;
+            metadata.and_then(|m| m.modified()).ok()
+        };
+        let mut last_modified: Option<SystemTime> = modified(&store.path).await;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let current = modified(&store.path).await;
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            match store.reload().await {
+                Ok(true) => tracing::info!(
+                    "Reloaded {} monitored prefixes from {}",
+                    store.get().prefixes.len(),
+                    store.path.display()
+                ),
+                Ok(false) => {}
+                Err(e) => tracing::error!(
+                    "Ignoring invalid {}, keeping the previous configuration: {}",
+                    store.path.display(),
+                    e
+                ),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFIXES: &str = r#"
+10.1.0.0/16:
+  description: Customer network
+  asn:
+    - 65000
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+  excludeMonitors:
+    - path
+options:
+  monitorASns:
+    '65000':
+      group: noc
+"#;
+
+    fn temp_store(name: &str, content: &str) -> PrefixesStore {
+        let path = std::env::temp_dir().join(format!(
+            "agentnoc-{}-{}-prefixes.yml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        PrefixesStore::load(path).unwrap()
+    }
+
+    fn info(description: &str, asn: u32) -> PrefixInfo {
+        PrefixInfo {
+            description: description.to_string(),
+            asn: vec![asn],
+            ignore_morespecifics: false,
+            ignore: false,
+            group: "noc".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(parse_validated(PREFIXES).is_ok());
+        assert!(parse_validated("").unwrap().prefixes.is_empty());
+
+        let err = parse_validated("10.1.0.1/16:\n  description: x\n  asn: [1]\n  group: noc\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("did you mean 10.1.0.0/16"));
+        assert!(parse_validated("not-a-prefix:\n  description: x\n").is_err());
+        // Missing fields would make from_str skip the prefix silently
+        assert!(parse_validated("10.1.0.0/16:\n  description: x\n").is_err());
+        assert!(
+            parse_validated("options:\n  monitorASns:\n    65000:\n      group: noc\n").is_err()
+        );
+        assert!(
+            parse_validated("options:\n  monitorDevices:\n    edge1:\n      name: edge1\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_file() {
+        let path =
+            std::env::temp_dir().join(format!("agentnoc-load-{}-prefixes.yml", std::process::id()));
+        // Accepted by from_str, which would skip the prefix missing its fields
+        std::fs::write(&path, format!("{PREFIXES}10.3.0.0/16:\n  description: x\n")).unwrap();
+        assert!(PrefixesStore::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_previous_config_when_invalid() {
+        let store = temp_store("reload", PREFIXES);
+        let changes = store.subscribe();
+        assert!(!store.reload().await.unwrap());
+
+        std::fs::write(&store.path, "10.1.0.0/16: [").unwrap();
+        assert!(store.reload().await.is_err());
+        assert!(store.get().is_prefix_monitored("10.1.0.0/16"));
+
+        std::fs::write(&store.path, PREFIXES.replace("10.1.0.0/16", "10.2.0.0/16")).unwrap();
+        assert!(store.reload().await.unwrap());
+        assert!(changes.has_changed().unwrap());
+        assert!(store.get().is_prefix_monitored("10.2.0.0/16"));
+        assert!(!store.get().is_prefix_monitored("10.1.0.0/16"));
+        std::fs::remove_file(&store.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_edits_write_bgpalerter_yaml() {
+        let store = temp_store("edit", PREFIXES);
+
+        let prefix = store
+            .write_prefix(
+                "2001:DB8::/32",
+                &info("New customer", 65001),
+                WriteMode::Create,
+            )
+            .await
+            .unwrap();
+        assert_eq!(prefix, "2001:db8::/32");
+        assert!(matches!(
+            store
+                .write_prefix("2001:db8::/32", &info("Again", 65001), WriteMode::Create)
+                .await,
+            Err(EditError::Exists)
+        ));
+        assert!(matches!(
+            store
+                .write_prefix("10.9.0.0/16", &info("Missing", 65001), WriteMode::Update)
+                .await,
+            Err(EditError::NotFound)
+        ));
+        assert!(matches!(
+            store
+                .write_prefix("10.9.0.1/16", &info("Host bits", 65001), WriteMode::Create)
+                .await,
+            Err(EditError::Invalid(_))
+        ));
+
+        store
+            .write_prefix("10.1.0.0/16", &info("Renamed", 65000), WriteMode::Update)
+            .await
+            .unwrap();
+        store
+            .write_asn(
+                "AS65001",
+                &AsnInfo {
+                    group: "noc".to_string(),
+                },
+                WriteMode::Create,
+            )
+            .await
+            .unwrap();
+        store.remove_asn("65000").await.unwrap();
+        assert!(matches!(
+            store.remove_asn("65000").await,
+            Err(EditError::NotFound)
+        ));
+
+        // The file is what BGPAlerter reads, and settings we do not model are kept
+        let content = std::fs::read_to_string(&store.path).unwrap();
+        let reloaded = parse_validated(&content).unwrap();
+        assert_eq!(reloaded.prefixes["10.1.0.0/16"].description, "Renamed");
+        assert_eq!(reloaded.prefixes["2001:db8::/32"].asn, vec![65001]);
+        assert!(reloaded.is_asn_monitored("65001"));
+        assert!(!reloaded.is_asn_monitored("65000"));
+        assert!(content.contains("excludeMonitors"));
+        assert!(content.contains("'65001'"));
+
+        // Edits are applied right away and are not reloaded as a change
+        assert!(store.get().is_prefix_monitored("2001:db8::/32"));
+        assert!(!store.reload().await.unwrap());
+
+        store.remove_prefix("2001:db8::/32").await.unwrap();
+        assert!(!store.get().is_prefix_monitored("2001:db8::/32"));
+        std::fs::remove_file(&store.path).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use chrono::Utc;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::{RpkiState, Vrp, VrpSet};
+use crate::config::PrefixesConfig;
+
+/// How well the ROAs protect a monitored prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageState {
+    /// No VRP covers the prefix, so every origin is RPKI not found
+    NoRoa,
+    /// VRPs cover the prefix but do not authorize every expected origin at its length
+    WrongOrigin,
+    /// Every expected origin is valid, but a VRP also authorizes more-specifics that can be
+    /// used for forged-origin sub-prefix hijacks (RFC 9319)
+    LooseMaxLength,
+    Covered,
+}
+
+impl CoverageState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverageState::NoRoa => "no_roa",
+            CoverageState::WrongOrigin => "wrong_origin",
+            CoverageState::LooseMaxLength => "loose_max_length",
+            CoverageState::Covered => "covered",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CoverageState::NoRoa => "no ROA",
+            CoverageState::WrongOrigin => "ROA for the wrong origin",
+            CoverageState::LooseMaxLength => "maxLength too loose",
+            CoverageState::Covered => "fully covered",
+        }
+    }
+}
+
+impl TryFrom<&str> for CoverageState {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "no_roa" => Ok(CoverageState::NoRoa),
+            "wrong_origin" => Ok(CoverageState::WrongOrigin),
+            "loose_max_length" => Ok(CoverageState::LooseMaxLength),
+            "covered" => Ok(CoverageState::Covered),
+            _ => Err(format!("Unknown coverage state: {}", s)),
+        }
+    }
+}
+
+/// ROA coverage of one monitored prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PrefixCoverage {
+    pub prefix: String,
+    pub expected_asns: Vec<u32>,
+    pub state: CoverageState,
+    /// What needs fixing, absent when the prefix is fully covered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// VRPs covering the prefix, formatted as `prefix-maxLength AS<asn>`
+    #[serde(default)]
+    pub covering_vrps: Vec<String>,
+}
+
+/// ROA coverage of every monitored prefix against one VRP set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CoverageReport {
+    pub generated_at: String,
+    /// Where the VRPs came from, e.g. the file path or RTR server
+    pub vrp_source: String,
+    /// When the VRP set was loaded
+    pub validated_with: String,
+    /// Monitored prefixes, ignored ones excluded, ordered by prefix
+    pub prefixes: Vec<PrefixCoverage>,
+}
+
+impl CoverageReport {
+    /// Coverage state of every prefix in the report, keyed by prefix
+    pub fn states(&self) -> HashMap<String, CoverageState> {
+        self.prefixes
+            .iter()
+            .map(|p| (p.prefix.clone(), p.state))
+            .collect()
+    }
+}
+
+/// A monitored prefix whose coverage state changed between two reports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoaCoverageAlert {
+    pub message: String,
+    pub prefix: String,
+    pub expected_asns: Vec<u32>,
+    pub previous_state: CoverageState,
+    pub state: CoverageState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub covering_vrps: Vec<String>,
+    pub vrp_source: String,
+    pub detected_at: String,
+}
+
+fn format_vrp(vrp: &Vrp) -> String {
+    format!("{}-{} AS{}", vrp.prefix, vrp.max_length, vrp.asn)
+}
+
+fn format_asns(asns: &[u32]) -> String {
+    asns.iter()
+        .map(|asn| format!("AS{asn}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Coverage of a prefix that may be originated by any of `expected_asns`
+pub fn check_prefix(prefix: &IpNet, expected_asns: &[u32], vrps: &VrpSet) -> PrefixCoverage {
+    let prefix = prefix.trunc();
+    let covering = vrps.covering(&prefix);
+    let unauthorized: Vec<u32> = expected_asns
+        .iter()
+        .copied()
+        .filter(|&asn| vrps.validate(&prefix, asn).state != RpkiState::Valid)
+        .collect();
+    let loose: Vec<&Vrp> = covering
+        .iter()
+        .filter(|v| expected_asns.contains(&v.asn) && v.max_length > prefix.prefix_len())
+        .collect();
+
+    let (state, detail) = if covering.is_empty() {
+        (
+            CoverageState::NoRoa,
+            Some(format!("No ROA covers {prefix}")),
+        )
+    } else if !unauthorized.is_empty() {
+        (
+            CoverageState::WrongOrigin,
+            Some(format!(
+                "No ROA authorizes {} to originate {prefix}",
+                format_asns(&unauthorized)
+            )),
+        )
+    } else if !loose.is_empty() {
+        (
+            CoverageState::LooseMaxLength,
+            Some(format!(
+                "{} also authorize more-specifics of {prefix}",
+                loose
+                    .iter()
+                    .map(|v| format_vrp(v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        )
+    } else {
+        (CoverageState::Covered, None)
+    };
+
+    PrefixCoverage {
+        prefix: prefix.to_string(),
+        expected_asns: expected_asns.to_vec(),
+        state,
+        detail,
+        covering_vrps: covering.iter().map(format_vrp).collect(),
+    }
+}
+
+/// Coverage of every monitored prefix that is not ignored
+pub fn check(prefixes: &PrefixesConfig, vrps: &VrpSet) -> CoverageReport {
+    let mut coverage: Vec<PrefixCoverage> = prefixes
+        .prefixes
+        .iter()
+        .filter(|(_, info)| !info.ignore)
+        .filter_map(|(prefix, info)| {
+            let Ok(net) = prefix.parse::<IpNet>() else {
+                tracing::warn!(
+                    "Skipping invalid monitored prefix {} in ROA coverage",
+                    prefix
+                );
+                return None;
+            };
+            Some(check_prefix(&net, &info.asn, vrps))
+        })
+        .collect();
+    coverage.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+
+    CoverageReport {
+        generated_at: Utc::now().to_rfc3339(),
+        vrp_source: vrps.source.clone(),
+        validated_with: vrps.loaded_at.clone(),
+        prefixes: coverage,
+    }
+}
+
+/// Prefixes whose state differs from their `previous` state; prefixes without a previous
+/// state are newly monitored, and their current state is only the baseline
+pub fn changes(
+    previous: &HashMap<String, CoverageState>,
+    current: &CoverageReport,
+) -> Vec<RoaCoverageAlert> {
+    current
+        .prefixes
+        .iter()
+        .filter_map(|coverage| {
+            let previous_state = *previous.get(&coverage.prefix)?;
+            (previous_state != coverage.state).then(|| RoaCoverageAlert {
+                message: format!(
+                    "ROA coverage of {} changed from {} to {}",
+                    coverage.prefix,
+                    previous_state.label(),
+                    coverage.state.label()
+                ),
+                prefix: coverage.prefix.clone(),
+                expected_asns: coverage.expected_asns.clone(),
+                previous_state,
+                state: coverage.state,
+                detail: coverage.detail.clone(),
+                covering_vrps: coverage.covering_vrps.clone(),
+                vrp_source: current.vrp_source.clone(),
+                detected_at: current.generated_at.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::parse_json;
+
+    fn vrps(roas: &str) -> VrpSet {
+        VrpSet::new(
+            parse_json(&format!(r#"{{"roas": [{roas}]}}"#)).unwrap(),
+            "test",
+        )
+    }
+
+    fn prefixes() -> PrefixesConfig {
+        PrefixesConfig::from_str(
+            r#"
+10.1.0.0/16:
+  description: covered
+  asn: [65000]
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+10.2.0.0/16:
+  description: loose
+  asn: [65000]
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+10.3.0.0/16:
+  description: second origin missing
+  asn: [65000, 65001]
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+10.4.0.0/16:
+  description: no roa
+  asn: [65000]
+  ignoreMorespecifics: false
+  ignore: false
+  group: noc
+10.5.0.0/16:
+  description: ignored
+  asn: [65000]
+  ignoreMorespecifics: false
+  ignore: true
+  group: noc
+"#,
+        )
+        .unwrap()
+    }
+
+    const ROAS: &str = r#"
+        {"asn": 65000, "prefix": "10.1.0.0/16", "maxLength": 16},
+        {"asn": 65000, "prefix": "10.2.0.0/16", "maxLength": 24},
+        {"asn": 65000, "prefix": "10.3.0.0/16", "maxLength": 16}
+    "#;
+
+    #[test]
+    fn test_coverage_states() {
+        let report = check(&prefixes(), &vrps(ROAS));
+        let states: Vec<_> = report
+            .prefixes
+            .iter()
+            .map(|p| (p.prefix.as_str(), p.state))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("10.1.0.0/16", CoverageState::Covered),
+                ("10.2.0.0/16", CoverageState::LooseMaxLength),
+                ("10.3.0.0/16", CoverageState::WrongOrigin),
+                ("10.4.0.0/16", CoverageState::NoRoa),
+            ]
+        );
+        assert_eq!(
+            report.prefixes[2].detail.as_deref(),
+            Some("No ROA authorizes AS65001 to originate 10.3.0.0/16")
+        );
+        assert_eq!(
+            report.prefixes[1].covering_vrps,
+            vec!["10.2.0.0/16-24 AS65000"]
+        );
+
+        // A less specific ROA authorizing the exact length covers the prefix
+        let coverage = check_prefix(
+            &"10.1.2.0/24".parse().unwrap(),
+            &[65000],
+            &vrps(r#"{"asn": 65000, "prefix": "10.1.0.0/16", "maxLength": 24}"#),
+        );
+        assert_eq!(coverage.state, CoverageState::Covered);
+        // So does a ROA for another origin, but not for the expected one
+        let coverage = check_prefix(
+            &"10.1.2.0/24".parse().unwrap(),
+            &[65000],
+            &vrps(r#"{"asn": 65099, "prefix": "10.1.0.0/16", "maxLength": 24}"#),
+        );
+        assert_eq!(coverage.state, CoverageState::WrongOrigin);
+    }
+
+    #[test]
+    fn test_changes_between_reports() {
+        let prefixes = prefixes();
+        let before = check(&prefixes, &vrps(ROAS));
+        assert!(changes(&before.states(), &before).is_empty());
+
+        // The loose ROA is tightened and the covered prefix loses its ROA
+        let after = check(
+            &prefixes,
+            &vrps(
+                r#"
+                {"asn": 65000, "prefix": "10.2.0.0/16", "maxLength": 16},
+                {"asn": 65000, "prefix": "10.3.0.0/16", "maxLength": 16}
+            "#,
+            ),
+        );
+        let alerts = changes(&before.states(), &after);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].prefix, "10.1.0.0/16");
+        assert_eq!(alerts[0].previous_state, CoverageState::Covered);
+        assert_eq!(alerts[0].state, CoverageState::NoRoa);
+        assert_eq!(
+            alerts[0].message,
+            "ROA coverage of 10.1.0.0/16 changed from fully covered to no ROA"
+        );
+        assert_eq!(alerts[1].prefix, "10.2.0.0/16");
+        assert_eq!(alerts[1].state, CoverageState::Covered);
+    }
+}
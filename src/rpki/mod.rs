@@ -0,0 +1,457 @@
+use chrono::Utc;
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::alerts::http::server::AppState;
+use crate::alerts::ingest;
+use crate::alerts::sources::AlertEnvelope;
+use crate::config::{AppConfig, parse_asn};
+use crate::database::db;
+
+pub mod coverage;
+pub mod rtr;
+
+use coverage::{CoverageReport, CoverageState};
+
+/// Validated ROA payload: `asn` may originate `prefix` and more-specifics up to `max_length`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Vrp {
+    pub prefix: IpNet,
+    pub max_length: u8,
+    pub asn: u32,
+}
+
+/// RFC 6811 route origin validation state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RpkiState {
+    Valid,
+    Invalid,
+    NotFound,
+}
+
+/// Validity of a prefix/origin pair against the local VRP set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RpkiValidation {
+    pub prefix: String,
+    pub asn: u32,
+    pub state: RpkiState,
+    /// Why an invalid route is invalid: `as` (no VRP for the origin) or `length` (more
+    /// specific than the matching VRPs allow)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// VRPs covering the prefix, formatted as `prefix-maxLength AS<asn>`
+    #[serde(default)]
+    pub covering_vrps: Vec<String>,
+    /// When the VRP set used for the validation was loaded
+    pub validated_with: String,
+}
+
+impl RpkiValidation {
+    /// One line summary for prompts, e.g. `192.0.2.0/24 from AS64500 is RPKI invalid (as)`
+    pub fn describe(&self) -> String {
+        let reason = self
+            .reason
+            .as_deref()
+            .map(|r| format!(" ({r})"))
+            .unwrap_or_default();
+        let state = match self.state {
+            RpkiState::Valid => "RPKI valid",
+            RpkiState::Invalid => "RPKI invalid",
+            RpkiState::NotFound => "not covered by any ROA (RPKI not found)",
+        };
+        let covering = if self.covering_vrps.is_empty() {
+            String::new()
+        } else {
+            format!("; covering VRPs: {}", self.covering_vrps.join(", "))
+        };
+        format!(
+            "{} from AS{} is {state}{reason}{covering}",
+            self.prefix, self.asn
+        )
+    }
+}
+
+/// A snapshot of VRPs, indexed by prefix for covering lookups
+#[derive(Debug, Clone, Default)]
+pub struct VrpSet {
+    by_prefix: HashMap<IpNet, Vec<(u8, u32)>>,
+    len: usize,
+    /// Where the VRPs came from, e.g. the file path or RTR server
+    pub source: String,
+    pub loaded_at: String,
+}
+
+impl VrpSet {
+    pub fn new(vrps: impl IntoIterator<Item = Vrp>, source: &str) -> Self {
+        let mut set = Self {
+            source: source.to_string(),
+            loaded_at: Utc::now().to_rfc3339(),
+            ..Default::default()
+        };
+        for vrp in vrps {
+            let entry = set.by_prefix.entry(vrp.prefix.trunc()).or_default();
+            if !entry.contains(&(vrp.max_length, vrp.asn)) {
+                entry.push((vrp.max_length, vrp.asn));
+                set.len += 1;
+            }
+        }
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// VRPs whose prefix equals or covers `prefix`, least specific first
+    pub fn covering(&self, prefix: &IpNet) -> Vec<Vrp> {
+        let prefix = prefix.trunc();
+        let mut covering = Vec::new();
+        for len in 0..=prefix.prefix_len() {
+            let Ok(candidate) = IpNet::new(prefix.addr(), len).map(|net| net.trunc()) else {
+                continue;
+            };
+            if let Some(entries) = self.by_prefix.get(&candidate) {
+                covering.extend(entries.iter().map(|&(max_length, asn)| Vrp {
+                    prefix: candidate,
+                    max_length,
+                    asn,
+                }));
+            }
+        }
+        covering
+    }
+
+    /// RFC 6811 validation of a route: valid when a covering VRP matches the origin and
+    /// length, invalid when covered but unmatched, not found otherwise
+    pub fn validate(&self, prefix: &IpNet, asn: u32) -> RpkiValidation {
+        let covering = self.covering(prefix);
+        let prefix_len = prefix.prefix_len();
+
+        // AS0 VRPs never match a route (RFC 6483)
+        let same_origin = covering.iter().filter(|v| v.asn == asn && asn != 0);
+        let (state, reason) = if covering.is_empty() {
+            (RpkiState::NotFound, None)
+        } else if same_origin.clone().any(|v| prefix_len <= v.max_length) {
+            (RpkiState::Valid, None)
+        } else if same_origin.count() > 0 {
+            (RpkiState::Invalid, Some("length".to_string()))
+        } else {
+            (RpkiState::Invalid, Some("as".to_string()))
+        };
+
+        RpkiValidation {
+            prefix: prefix.trunc().to_string(),
+            asn,
+            state,
+            reason,
+            covering_vrps: covering
+                .iter()
+                .map(|v| format!("{}-{} AS{}", v.prefix, v.max_length, v.asn))
+                .collect(),
+            validated_with: self.loaded_at.clone(),
+        }
+    }
+}
+
+/// The current VRP set, replaced wholesale on every refresh
+#[derive(Default)]
+pub struct VrpStore {
+    current: RwLock<Option<Arc<VrpSet>>>,
+}
+
+impl VrpStore {
+    pub fn get(&self) -> Option<Arc<VrpSet>> {
+        self.current.read().ok()?.clone()
+    }
+
+    pub fn replace(&self, set: VrpSet) {
+        if let Ok(mut current) = self.current.write() {
+            *current = Some(Arc::new(set));
+        }
+    }
+
+    /// Validate the route an alert is about, when it names a prefix and an origin
+    pub fn validate_alert(&self, alert: &AlertEnvelope) -> Option<RpkiValidation> {
+        let vrps = self.get()?;
+        let (prefix, asn) = route_of(alert)?;
+        Some(vrps.validate(&prefix, asn))
+    }
+}
+
+/// Prefix and origin announced according to an alert: the more-specific or new prefix and
+/// the observed origin when present, otherwise the monitored prefix and its expected origin
+pub fn route_of(alert: &AlertEnvelope) -> Option<(IpNet, u32)> {
+    let parse_prefix = |p: &str| p.contains('/').then(|| p.trim().parse::<IpNet>().ok())?;
+    let first_asn = |a: &str| parse_asn(a.split(',').next()?).ok();
+
+    let prefix = alert
+        .related_resource
+        .as_deref()
+        .and_then(parse_prefix)
+        .or_else(|| parse_prefix(&alert.resource))?;
+    let asn = alert
+        .observed_asn
+        .as_deref()
+        .and_then(first_asn)
+        .or_else(|| alert.asn.as_deref().and_then(first_asn))?;
+    Some((prefix, asn))
+}
+
+#[derive(Deserialize)]
+struct VrpExport {
+    roas: Vec<ExportedRoa>,
+}
+
+/// A ROA entry as exported by Routinator (`"asn": "AS64496"`) or rpki-client (`"asn": 64496`)
+#[derive(Deserialize)]
+struct ExportedRoa {
+    prefix: String,
+    #[serde(rename = "maxLength")]
+    max_length: Option<u8>,
+    #[serde(deserialize_with = "deserialize_asn")]
+    asn: u32,
+}
+
+fn deserialize_asn<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Asn {
+        Number(u32),
+        Text(String),
+    }
+
+    match Asn::deserialize(deserializer)? {
+        Asn::Number(asn) => Ok(asn),
+        Asn::Text(text) => {
+            parse_asn(&text).map_err(|_| serde::de::Error::custom(format!("invalid ASN '{text}'")))
+        }
+    }
+}
+
+/// Parse a Routinator or rpki-client JSON export
+pub fn parse_json(content: &str) -> Result<Vec<Vrp>> {
+    let export: VrpExport = serde_json::from_str(content)?;
+    export
+        .roas
+        .into_iter()
+        .map(|roa| {
+            let prefix = roa
+                .prefix
+                .parse::<IpNet>()
+                .map_err(|e| eyre!("Invalid VRP prefix '{}': {}", roa.prefix, e))?;
+            let max_length = roa.max_length.unwrap_or(prefix.prefix_len());
+            if max_length < prefix.prefix_len() || max_length > prefix.max_prefix_len() {
+                return Err(eyre!(
+                    "Invalid maxLength {} for VRP {}",
+                    max_length,
+                    roa.prefix
+                ));
+            }
+            Ok(Vrp {
+                prefix: prefix.trunc(),
+                max_length,
+                asn: roa.asn,
+            })
+        })
+        .collect()
+}
+
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<VrpSet> {
+    let path = path.as_ref();
+    let vrps = parse_json(&fs::read_to_string(path)?)?;
+    Ok(VrpSet::new(vrps, &path.display().to_string()))
+}
+
+/// Load VRPs from the configured RTR cache, or the JSON export when no cache is set
+async fn load(config: &AppConfig) -> Result<VrpSet> {
+    if let Some(server) = &config.rpki_rtr_server {
+        let vrps = rtr::fetch(server).await?;
+        return Ok(VrpSet::new(vrps, &format!("rtr://{server}")));
+    }
+    if let Some(path) = &config.rpki_vrp_file {
+        return load_file(path);
+    }
+    Err(eyre!("No VRP source configured"))
+}
+
+/// Raise an alert for every monitored prefix whose ROA coverage changed since the last check,
+/// then record the new coverage
+///
+/// The last coverage is kept in the database, so changes that happen across a restart are
+/// reported too.
+async fn report_coverage_changes(state: &AppState, current: &CoverageReport) -> Result<()> {
+    let previous: HashMap<String, CoverageState> = db::get_roa_coverage(&state.db_pool)
+        .await?
+        .into_iter()
+        .filter_map(|(prefix, s)| Some((prefix, CoverageState::try_from(s.as_str()).ok()?)))
+        .collect();
+
+    for change in coverage::changes(&previous, current) {
+        tracing::warn!("{}", change.message);
+        let result = match change.envelope() {
+            Ok(alert) => ingest::ingest(state, &alert).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to store ROA coverage alert: {}", e);
+        }
+    }
+
+    let states: Vec<(String, String)> = current
+        .states()
+        .into_iter()
+        .map(|(prefix, state)| (prefix, state.as_str().to_string()))
+        .collect();
+    db::save_roa_coverage(&state.db_pool, &states, &current.generated_at).await
+}
+
+/// Use a freshly loaded VRP set and check the ROA coverage of the monitored prefixes against it
+pub async fn apply(state: &AppState, set: VrpSet) {
+    tracing::info!("Loaded {} VRPs from {}", set.len(), set.source);
+    let report = coverage::check(&state.prefixes.get(), &set);
+    state.rpki.replace(set);
+    if let Err(e) = report_coverage_changes(state, &report).await {
+        tracing::error!("Failed to check ROA coverage changes: {}", e);
+    }
+}
+
+/// Periodically refresh the VRP set when an RTR cache or JSON export is configured, and check
+/// the ROA coverage of the monitored prefixes against every new set
+pub fn start(state: AppState) {
+    if state.config.rpki_rtr_server.is_none() && state.config.rpki_vrp_file.is_none() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let interval = Duration::from_secs(state.config.rpki_refresh_secs.max(1));
+        loop {
+            match load(&state.config).await {
+                Ok(set) => apply(&state, set).await,
+                Err(e) => tracing::error!("Failed to refresh VRPs: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::AlertKind;
+    use serde_json::json;
+
+    fn net(prefix: &str) -> IpNet {
+        prefix.parse().unwrap()
+    }
+
+    fn vrps() -> VrpSet {
+        let content = r#"{
+            "metadata": {"generated": 1736935200},
+            "roas": [
+                {"asn": "AS64500", "prefix": "192.0.2.0/24", "maxLength": 24, "ta": "ripe"},
+                {"asn": 64501, "prefix": "198.51.100.0/22", "maxLength": 24, "ta": "arin"},
+                {"asn": "AS0", "prefix": "203.0.113.0/24", "maxLength": 24, "ta": "apnic"},
+                {"asn": "AS64502", "prefix": "2001:db8::/32", "maxLength": 48, "ta": "ripe"}
+            ]
+        }"#;
+        VrpSet::new(parse_json(content).unwrap(), "test")
+    }
+
+    #[test]
+    fn test_parse_json_exports() {
+        let set = vrps();
+        assert_eq!(set.len(), 4);
+
+        assert!(parse_json(r#"{"roas": [{"asn": "ASx", "prefix": "192.0.2.0/24"}]}"#).is_err());
+        assert!(
+            parse_json(r#"{"roas": [{"asn": 1, "prefix": "192.0.2.0/24", "maxLength": 16}]}"#)
+                .is_err()
+        );
+        // Without maxLength only the exact prefix is authorized
+        let vrps = parse_json(r#"{"roas": [{"asn": 1, "prefix": "192.0.2.0/24"}]}"#).unwrap();
+        assert_eq!(vrps[0].max_length, 24);
+    }
+
+    #[test]
+    fn test_rfc6811_validation() {
+        let set = vrps();
+
+        let valid = set.validate(&net("192.0.2.0/24"), 64500);
+        assert_eq!(valid.state, RpkiState::Valid);
+        assert_eq!(valid.covering_vrps, vec!["192.0.2.0/24-24 AS64500"]);
+
+        let wrong_origin = set.validate(&net("192.0.2.0/24"), 64666);
+        assert_eq!(wrong_origin.state, RpkiState::Invalid);
+        assert_eq!(wrong_origin.reason.as_deref(), Some("as"));
+
+        let too_specific = set.validate(&net("192.0.2.128/25"), 64500);
+        assert_eq!(too_specific.state, RpkiState::Invalid);
+        assert_eq!(too_specific.reason.as_deref(), Some("length"));
+
+        assert_eq!(
+            set.validate(&net("198.51.101.0/24"), 64501).state,
+            RpkiState::Valid
+        );
+        assert_eq!(
+            set.validate(&net("203.0.113.0/24"), 0).state,
+            RpkiState::Invalid
+        );
+        assert_eq!(
+            set.validate(&net("2001:db8:1::/48"), 64502).state,
+            RpkiState::Valid
+        );
+
+        let unknown = set.validate(&net("10.0.0.0/8"), 64500);
+        assert_eq!(unknown.state, RpkiState::NotFound);
+        assert!(unknown.covering_vrps.is_empty());
+        assert_eq!(
+            unknown.describe(),
+            "10.0.0.0/8 from AS64500 is not covered by any ROA (RPKI not found)"
+        );
+    }
+
+    #[test]
+    fn test_validate_alert_route() {
+        let alert = AlertEnvelope {
+            kind: AlertKind::BgpAlerter,
+            category: "hijack".to_string(),
+            title: String::new(),
+            resource: "192.0.2.0/24".to_string(),
+            related_resource: Some("192.0.2.0/25".to_string()),
+            asn: Some("64500".to_string()),
+            observed_asn: Some("AS64666".to_string()),
+            earliest: String::new(),
+            latest: String::new(),
+            peers: 0,
+            paths: 0,
+            payload: json!({}),
+            rpki: None,
+            relevance: None,
+        };
+        assert_eq!(route_of(&alert), Some((net("192.0.2.0/25"), 64666)));
+
+        let store = VrpStore::default();
+        assert!(store.validate_alert(&alert).is_none());
+        store.replace(vrps());
+        let validation = store.validate_alert(&alert).unwrap();
+        assert_eq!(validation.state, RpkiState::Invalid);
+        assert_eq!(validation.reason.as_deref(), Some("as"));
+
+        // Device alerts carry no route
+        let device = AlertEnvelope {
+            resource: "198.51.100.1".to_string(),
+            related_resource: Some("ge-0/0/1".to_string()),
+            ..alert
+        };
+        assert!(store.validate_alert(&device).is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use ipnet::IpNet;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::Vrp;
+
+/// Time allowed to connect and to receive each PDU
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest PDU accepted; real caches send a few dozen bytes per PDU
+const MAX_PDU_LEN: usize = 64 * 1024;
+
+const SERIAL_NOTIFY: u8 = 0;
+const RESET_QUERY: u8 = 2;
+const CACHE_RESPONSE: u8 = 3;
+const IPV4_PREFIX: u8 = 4;
+const IPV6_PREFIX: u8 = 6;
+const END_OF_DATA: u8 = 7;
+const CACHE_RESET: u8 = 8;
+const ROUTER_KEY: u8 = 9;
+const ERROR_REPORT: u8 = 10;
+
+const FLAG_ANNOUNCE: u8 = 0x01;
+const UNSUPPORTED_VERSION: u16 = 4;
+
+struct Pdu {
+    version: u8,
+    pdu_type: u8,
+    /// Session ID or error code, depending on the type
+    header: u16,
+    body: Vec<u8>,
+}
+
+async fn read_pdu<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Pdu> {
+    let mut header = [0u8; 8];
+    tokio::time::timeout(TIMEOUT, reader.read_exact(&mut header))
+        .await
+        .map_err(|_| eyre!("Timed out waiting for the RTR cache"))??;
+    let len = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if !(8..=MAX_PDU_LEN).contains(&len) {
+        return Err(eyre!("Invalid RTR PDU length {}", len));
+    }
+    let mut body = vec![0u8; len - 8];
+    tokio::time::timeout(TIMEOUT, reader.read_exact(&mut body))
+        .await
+        .map_err(|_| eyre!("Timed out waiting for the RTR cache"))??;
+
+    Ok(Pdu {
+        version: header[0],
+        pdu_type: header[1],
+        header: u16::from_be_bytes([header[2], header[3]]),
+        body,
+    })
+}
+
+fn prefix_pdu(body: &[u8], addr_len: usize) -> Result<(bool, Vrp)> {
+    if body.len() != 4 + addr_len + 4 {
+        return Err(eyre!("Invalid RTR prefix PDU length"));
+    }
+    let flags = body[0];
+    let prefix_len = body[1];
+    let max_length = body[2];
+    let addr = &body[4..4 + addr_len];
+    let asn = u32::from_be_bytes(body[4 + addr_len..].try_into()?);
+
+    let addr = match addr_len {
+        4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(addr)?)),
+        _ => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(addr)?)),
+    };
+    let prefix = IpNet::new(addr, prefix_len)?.trunc();
+    if max_length < prefix_len || max_length > prefix.max_prefix_len() {
+        return Err(eyre!("Invalid maxLength {} for {}", max_length, prefix));
+    }
+
+    Ok((
+        flags & FLAG_ANNOUNCE != 0,
+        Vrp {
+            prefix,
+            max_length,
+            asn,
+        },
+    ))
+}
+
+/// Error Report text, after the encapsulated PDU
+fn error_text(body: &[u8]) -> String {
+    let text = (|| {
+        let pdu_len = u32::from_be_bytes(body.get(..4)?.try_into().ok()?) as usize;
+        let rest = body.get(4 + pdu_len..)?;
+        let text_len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        Some(String::from_utf8_lossy(rest.get(4..4 + text_len)?).into_owned())
+    })();
+    text.filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "no details".to_string())
+}
+
+/// Outcome of one Reset Query exchange
+enum Exchange {
+    Vrps(Vec<Vrp>),
+    /// The cache does not speak this protocol version
+    Downgrade,
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    version: u8,
+) -> Result<Exchange> {
+    stream
+        .write_all(&[version, RESET_QUERY, 0, 0, 0, 0, 0, 8])
+        .await?;
+
+    let mut vrps = HashSet::new();
+    let mut responded = false;
+    loop {
+        let pdu = read_pdu(stream).await?;
+        match pdu.pdu_type {
+            ERROR_REPORT if pdu.header == UNSUPPORTED_VERSION && version > 0 => {
+                return Ok(Exchange::Downgrade);
+            }
+            ERROR_REPORT => {
+                return Err(eyre!(
+                    "RTR cache reported error {}: {}",
+                    pdu.header,
+                    error_text(&pdu.body)
+                ));
+            }
+            _ if pdu.version != version => {
+                return Err(eyre!(
+                    "RTR cache answered with version {} instead of {}",
+                    pdu.version,
+                    version
+                ));
+            }
+            CACHE_RESPONSE => responded = true,
+            IPV4_PREFIX | IPV6_PREFIX if responded => {
+                let addr_len = if pdu.pdu_type == IPV4_PREFIX { 4 } else { 16 };
+                let (announce, vrp) = prefix_pdu(&pdu.body, addr_len)?;
+                if announce {
+                    vrps.insert(vrp);
+                } else {
+                    vrps.remove(&vrp);
+                }
+            }
+            END_OF_DATA if responded => return Ok(Exchange::Vrps(vrps.into_iter().collect())),
+            CACHE_RESET => return Err(eyre!("RTR cache has no data available")),
+            // Serial Notify may arrive at any time and router keys are not used for ROV
+            SERIAL_NOTIFY | ROUTER_KEY => {}
+            other => return Err(eyre!("Unexpected RTR PDU type {}", other)),
+        }
+    }
+}
+
+/// Download the full VRP set from an RTR cache (RFC 8210, falling back to RFC 6810)
+pub async fn fetch(server: &str) -> Result<Vec<Vrp>> {
+    for version in [1, 0] {
+        let mut stream = tokio::time::timeout(TIMEOUT, TcpStream::connect(server))
+            .await
+            .map_err(|_| eyre!("Timed out connecting to RTR cache {}", server))??;
+        match exchange(&mut stream, version).await? {
+            Exchange::Vrps(vrps) => return Ok(vrps),
+            Exchange::Downgrade => {
+                tracing::info!(
+                    "RTR cache {} does not support version {}, retrying",
+                    server,
+                    version
+                );
+            }
+        }
+    }
+    Err(eyre!(
+        "RTR cache {} supports no known protocol version",
+        server
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn pdu(version: u8, pdu_type: u8, header: u16, body: &[u8]) -> Vec<u8> {
+        let mut pdu = vec![version, pdu_type];
+        pdu.extend(header.to_be_bytes());
+        pdu.extend(((body.len() + 8) as u32).to_be_bytes());
+        pdu.extend(body);
+        pdu
+    }
+
+    fn ipv4(version: u8, flags: u8, addr: [u8; 4], len: u8, max: u8, asn: u32) -> Vec<u8> {
+        let mut body = vec![flags, len, max, 0];
+        body.extend(addr);
+        body.extend(asn.to_be_bytes());
+        pdu(version, IPV4_PREFIX, 0, &body)
+    }
+
+    fn ipv6(version: u8, addr: Ipv6Addr, len: u8, max: u8, asn: u32) -> Vec<u8> {
+        let mut body = vec![FLAG_ANNOUNCE, len, max, 0];
+        body.extend(addr.octets());
+        body.extend(asn.to_be_bytes());
+        pdu(version, IPV6_PREFIX, 0, &body)
+    }
+
+    /// Stand-in RTR cache that only speaks version 0, like older caches
+    async fn serve_v0(listener: TcpListener) {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut query = [0u8; 8];
+            stream.read_exact(&mut query).await.unwrap();
+            assert_eq!(query[1], RESET_QUERY);
+
+            if query[0] != 0 {
+                let mut body = 8u32.to_be_bytes().to_vec();
+                body.extend(query);
+                body.extend(0u32.to_be_bytes());
+                let error = pdu(query[0], ERROR_REPORT, UNSUPPORTED_VERSION, &body);
+                stream.write_all(&error).await.unwrap();
+                continue;
+            }
+
+            let mut response = pdu(0, CACHE_RESPONSE, 7, &[]);
+            response.extend(pdu(0, SERIAL_NOTIFY, 7, &1u32.to_be_bytes()));
+            response.extend(ipv4(0, FLAG_ANNOUNCE, [192, 0, 2, 0], 24, 24, 64500));
+            response.extend(ipv4(0, FLAG_ANNOUNCE, [198, 51, 100, 0], 22, 24, 64501));
+            response.extend(ipv4(0, 0, [198, 51, 100, 0], 22, 24, 64501));
+            response.extend(ipv6(0, "2001:db8::".parse().unwrap(), 32, 48, 64502));
+            response.extend(pdu(0, END_OF_DATA, 7, &1u32.to_be_bytes()));
+            stream.write_all(&response).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_stand_in_cache() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let cache = tokio::spawn(serve_v0(listener));
+
+        let mut vrps = fetch(&address).await.unwrap();
+        vrps.sort_by_key(|v| v.asn);
+        assert_eq!(
+            vrps,
+            vec![
+                Vrp {
+                    prefix: "192.0.2.0/24".parse().unwrap(),
+                    max_length: 24,
+                    asn: 64500,
+                },
+                Vrp {
+                    prefix: "2001:db8::/32".parse().unwrap(),
+                    max_length: 48,
+                    asn: 64502,
+                },
+            ]
+        );
+        cache.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reports_cache_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut query = [0u8; 8];
+            stream.read_exact(&mut query).await.unwrap();
+            let mut body = 0u32.to_be_bytes().to_vec();
+            body.extend(9u32.to_be_bytes());
+            body.extend(b"try later");
+            stream
+                .write_all(&pdu(1, ERROR_REPORT, 2, &body))
+                .await
+                .unwrap();
+        });
+
+        let err = fetch(&address).await.unwrap_err().to_string();
+        assert_eq!(err, "RTR cache reported error 2: try later");
+    }
+}
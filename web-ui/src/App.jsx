@@ -6,6 +6,9 @@ import SettingsPage from './components/SettingsPage'
 
 function App() {
   const [alerts, setAlerts] = useState([])
+  const [alertsTotal, setAlertsTotal] = useState(0)
+  const [nextCursor, setNextCursor] = useState(null)
+  const [alertSearch, setAlertSearch] = useState('')
   const [selectedAlertId, setSelectedAlertId] = useState(null)
   const [selectedAlertData, setSelectedAlertData] = useState(null)
   const [connected, setConnected] = useState(false)
@@ -24,17 +27,33 @@ function App() {
   const reconnectTimeoutRef = useRef(null)
   const eventSourceRef = useRef(null)
 
-  // Fetch alerts list
+  const alertSearchRef = useRef('')
+
+  const alertsUrl = (cursor) => {
+    const params = new URLSearchParams()
+    if (alertSearchRef.current.trim()) {
+      params.set('q', alertSearchRef.current.trim())
+    }
+    if (cursor) {
+      params.set('cursor', cursor)
+    }
+    const query = params.toString()
+    return query ? `/api/alerts?${query}` : '/api/alerts'
+  }
+
+  // Fetch the first page of the alerts list
   const fetchAlerts = async () => {
     setLoading((prev) => ({ ...prev, alerts: true }))
     setError(null)
     try {
-      const response = await fetch('/api/alerts')
+      const response = await fetch(alertsUrl())
       if (!response.ok) {
         throw new Error('Failed to fetch alerts')
       }
       const data = await response.json()
-      setAlerts(data)
+      setAlerts(data.alerts)
+      setAlertsTotal(data.total)
+      setNextCursor(data.next_cursor || null)
     } catch (err) {
       console.error('Error fetching alerts:', err)
       setError('Failed to load alerts. Please refresh the page.')
@@ -43,6 +62,37 @@ function App() {
     }
   }
 
+  // Append the next page to the alerts list
+  const loadMoreAlerts = async () => {
+    if (!nextCursor) return
+    setLoading((prev) => ({ ...prev, alerts: true }))
+    try {
+      const response = await fetch(alertsUrl(nextCursor))
+      if (!response.ok) {
+        throw new Error('Failed to fetch alerts')
+      }
+      const data = await response.json()
+      setAlerts((prev) => [
+        ...prev,
+        ...data.alerts.filter((alert) => !prev.some((a) => a.id === alert.id)),
+      ])
+      setAlertsTotal(data.total)
+      setNextCursor(data.next_cursor || null)
+    } catch (err) {
+      console.error('Error fetching alerts:', err)
+      setError('Failed to load more alerts.')
+    } finally {
+      setLoading((prev) => ({ ...prev, alerts: false }))
+    }
+  }
+
+  // Full-text search over the analyses and chat messages
+  const searchAlerts = (query) => {
+    setAlertSearch(query)
+    alertSearchRef.current = query
+    fetchAlerts()
+  }
+
   // Fetch alert details
   const fetchAlertDetails = async (id) => {
     if (!id) {
@@ -128,7 +178,7 @@ function App() {
     }))
 
     try {
-      const response = await fetch(`/api/alerts/${selectedAlertId}/chat`, {
+      const response = await fetch(`/api/alerts/${selectedAlertId}/chat/stream`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json',
@@ -136,18 +186,75 @@ function App() {
         body: JSON.stringify({ message }),
       })
 
-      if (!response.ok) {
+      if (!response.ok || !response.body) {
         const errorText = await response.text()
         console.error('Chat API error:', response.status, errorText)
+        if (response.status === 429) {
+          // LLM budget exhausted; the body explains when chat resumes
+          let message = 'The LLM budget is exhausted'
+          try {
+            message = JSON.parse(errorText).error || message
+          } catch {
+            // Keep the generic message
+          }
+          throw new Error(message)
+        }
         throw new Error(`Failed to send message: ${response.status}`)
       }
 
-      const result = await response.json()
-      
-      // Validate response structure
-      if (!result || typeof result.response !== 'string' || !result.message_id) {
-        console.error('Invalid response structure:', result)
-        throw new Error('Invalid response format from server')
+      // Update the in-flight assistant message as stream events arrive
+      const updateLoadingMessage = (update) => {
+        setSelectedAlertData((prev) => ({
+          ...prev,
+          chat_messages: (prev.chat_messages || []).map((msg) =>
+            msg.loading ? update(msg) : msg
+          ),
+        }))
+      }
+
+      const reader = response.body.getReader()
+      const decoder = new TextDecoder()
+      let buffer = ''
+      let result = null
+
+      while (!result) {
+        const { done, value } = await reader.read()
+        if (done) break
+        buffer += decoder.decode(value, { stream: true })
+
+        // SSE events are separated by a blank line
+        const chunks = buffer.split('\n\n')
+        buffer = chunks.pop()
+        for (const chunk of chunks) {
+          const data = chunk
+            .split('\n')
+            .filter((line) => line.startsWith('data:'))
+            .map((line) => line.slice(5).trimStart())
+            .join('\n')
+          if (!data || data === 'keep-alive-text') continue
+
+          const event = JSON.parse(data)
+          if (event.type === 'token') {
+            updateLoadingMessage((msg) => ({
+              ...msg,
+              content: msg.content + event.text,
+              status: null,
+            }))
+          } else if (event.type === 'tool_call') {
+            updateLoadingMessage((msg) => ({
+              ...msg,
+              status: `Calling ${event.name}…`,
+            }))
+          } else if (event.type === 'done') {
+            result = event
+          } else if (event.type === 'error') {
+            throw new Error(event.message)
+          }
+        }
+      }
+
+      if (!result) {
+        throw new Error('Stream ended before the response was complete')
       }
 
       // Replace loading message with actual response
@@ -201,6 +308,7 @@ function App() {
 
       // Remove from alerts list
       setAlerts((prev) => prev.filter((alert) => alert.id !== id))
+      setAlertsTotal((prev) => Math.max(prev - 1, 0))
 
       // Clear selection if deleted alert was selected
       if (selectedAlertId === id) {
@@ -218,6 +326,31 @@ function App() {
     }
   }
 
+  // Change the status or assignee of an alert ('status' or 'assignee')
+  const updateAlertLifecycle = async (id, field, body) => {
+    setError(null)
+    try {
+      const response = await fetch(`/api/alerts/${id}/${field}`, {
+        method: 'PATCH',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body),
+      })
+
+      if (!response.ok) {
+        const data = await response.json().catch(() => ({}))
+        throw new Error(data.error || `Failed to update alert ${field}`)
+      }
+
+      fetchAlerts()
+      if (selectedAlertId === id) {
+        fetchAlertDetails(id)
+      }
+    } catch (err) {
+      console.error(`Error updating alert ${field}:`, err)
+      setError(err.message)
+    }
+  }
+
   // Handle alert selection
   const handleSelectAlert = (id) => {
     setSelectedAlertId(id)
@@ -415,6 +548,31 @@ function App() {
         fetchAlerts()
         break
 
+      case 'analysis_status':
+        // Refresh list and, if open, the selected alert once its analysis moves on
+        fetchAlerts()
+        if (event.alert_id === selectedAlertId) {
+          fetchAlertDetails(selectedAlertId)
+        }
+        break
+
+      case 'incident_updated':
+        // A repeated alert was folded into an existing incident
+        fetchAlerts()
+        if (event.alert_id === selectedAlertId) {
+          fetchAlertDetails(selectedAlertId)
+        }
+        break
+
+      case 'alert_status_changed':
+      case 'alert_assigned':
+        // Someone acknowledged, resolved, reopened or (re)assigned an alert
+        fetchAlerts()
+        if (event.alert_id === selectedAlertId) {
+          fetchAlertDetails(selectedAlertId)
+        }
+        break
+
       case 'chat_message':
         // Don't refresh if we're currently sending a message (we already have the data)
         // Only refresh if we're not actively sending (e.g., message from another session)
@@ -431,7 +589,12 @@ function App() {
 
       case 'alert_deleted':
         // Remove from alerts list
-        setAlerts((prev) => prev.filter((alert) => alert.id !== event.alert_id))
+        setAlerts((prev) => {
+          if (prev.some((alert) => alert.id === event.alert_id)) {
+            setAlertsTotal((total) => Math.max(total - 1, 0))
+          }
+          return prev.filter((alert) => alert.id !== event.alert_id)
+        })
 
         // Clear selection if deleted alert was selected
         if (selectedAlertId === event.alert_id) {
@@ -445,6 +608,12 @@ function App() {
         setError(event.message)
         break
 
+      case 'budget_warning':
+        // An LLM budget crossed 80% or 100%
+        console.warn('LLM budget warning:', event.message)
+        setError(event.message)
+        break
+
       case 'health_check':
         // Optional: could show health status
         break
@@ -494,6 +663,11 @@ function App() {
           <MainView
             alerts={alerts}
             setAlerts={setAlerts}
+            alertsTotal={alertsTotal}
+            hasMoreAlerts={nextCursor !== null}
+            loadMoreAlerts={loadMoreAlerts}
+            alertSearch={alertSearch}
+            searchAlerts={searchAlerts}
             selectedAlertId={selectedAlertId}
             setSelectedAlertId={setSelectedAlertId}
             selectedAlertData={selectedAlertData}
@@ -512,6 +686,7 @@ function App() {
             fetchAlertDetails={fetchAlertDetails}
             sendChatMessage={sendChatMessage}
             deleteAlert={deleteAlert}
+            updateAlertLifecycle={updateAlertLifecycle}
             handleSelectAlert={handleSelectAlert}
             handleDeleteClick={handleDeleteClick}
             handleDeleteConfirm={handleDeleteConfirm}
@@ -18,61 +18,88 @@ function AlertDataCard({ alert, expanded = false, onToggle }) {
 
       {expanded && (
         <div className="alert-data-content">
-        <div className="alert-data-section">
-          <h4>Alert Information</h4>
-          <div className="alert-data-field">
-            <strong>Message:</strong> {alert.message || 'N/A'}
-          </div>
-          <div className="alert-data-field">
-            <strong>Description:</strong> {alert.description || 'N/A'}
-          </div>
-          <div className="alert-data-field">
-            <strong>Kind:</strong> {details.kind || 'N/A'}
+        {alert.details ? (
+          <>
+          <div className="alert-data-section">
+            <h4>Alert Information</h4>
+            <div className="alert-data-field">
+              <strong>Message:</strong> {alert.message || 'N/A'}
+            </div>
+            <div className="alert-data-field">
+              <strong>Description:</strong> {alert.description || 'N/A'}
+            </div>
+            <div className="alert-data-field">
+              <strong>Kind:</strong> {details.kind || 'N/A'}
+            </div>
           </div>
-        </div>
 
-        <div className="alert-data-section">
-          <h4>Prefix Details</h4>
-          <div className="alert-data-field">
-            <strong>Prefix:</strong> {details.prefix || 'N/A'}
-          </div>
-          {details.newprefix && (
+          <div className="alert-data-section">
+            <h4>Prefix Details</h4>
             <div className="alert-data-field">
-              <strong>New Prefix:</strong> {details.newprefix}
+              <strong>Prefix:</strong> {details.prefix || 'N/A'}
             </div>
-          )}
-          <div className="alert-data-field">
-            <strong>ASN:</strong> {details.asn || 'N/A'}
-          </div>
-          {details.neworigin && (
+            {details.newprefix && (
+              <div className="alert-data-field">
+                <strong>New Prefix:</strong> {details.newprefix}
+              </div>
+            )}
             <div className="alert-data-field">
-              <strong>New Origin:</strong> {details.neworigin}
+              <strong>ASN:</strong> {details.asn || 'N/A'}
             </div>
-          )}
-        </div>
-
-        <div className="alert-data-section">
-          <h4>BGP Details</h4>
-          <div className="alert-data-field">
-            <strong>Paths:</strong> {details.paths || 'N/A'}
-          </div>
-          <div className="alert-data-field">
-            <strong>Peers:</strong> {details.peers || 'N/A'}
+            {details.neworigin && (
+              <div className="alert-data-field">
+                <strong>New Origin:</strong> {details.neworigin}
+              </div>
+            )}
           </div>
-          <div className="alert-data-field">
-            <strong>Summary:</strong> {details.summary || 'N/A'}
+
+          <div className="alert-data-section">
+            <h4>BGP Details</h4>
+            <div className="alert-data-field">
+              <strong>Paths:</strong> {details.paths || 'N/A'}
+            </div>
+            <div className="alert-data-field">
+              <strong>Peers:</strong> {details.peers || 'N/A'}
+            </div>
+            <div className="alert-data-field">
+              <strong>Summary:</strong> {details.summary || 'N/A'}
+            </div>
           </div>
-        </div>
 
-        <div className="alert-data-section">
-          <h4>Timestamps</h4>
-          <div className="alert-data-field">
-            <strong>Earliest:</strong> {details.earliest || 'N/A'}
+          <div className="alert-data-section">
+            <h4>Timestamps</h4>
+            <div className="alert-data-field">
+              <strong>Earliest:</strong> {details.earliest || 'N/A'}
+            </div>
+            <div className="alert-data-field">
+              <strong>Latest:</strong> {details.latest || 'N/A'}
+            </div>
           </div>
-          <div className="alert-data-field">
-            <strong>Latest:</strong> {details.latest || 'N/A'}
+
+          </>
+        ) : (
+          <div className="alert-data-section">
+            <h4>Alert Information</h4>
+            <div className="alert-data-field">
+              <strong>Message:</strong> {alert.message || 'N/A'}
+            </div>
+            {alert.trap && (
+              <div className="alert-data-field">
+                <strong>Trap:</strong> {alert.trap}
+              </div>
+            )}
+            {alert.text && (
+              <div className="alert-data-field">
+                <strong>Log:</strong> {alert.text}
+              </div>
+            )}
+            {alert.device && (
+              <div className="alert-data-field">
+                <strong>Device:</strong> {alert.device.name || alert.device.address}
+              </div>
+            )}
           </div>
-        </div>
+        )}
 
         <div className="alert-data-raw-toggle">
           <button 
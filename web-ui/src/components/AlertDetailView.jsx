@@ -4,9 +4,18 @@ import ChatHistory from './ChatHistory'
 import ChatInput from './ChatInput'
 import AlertDataCard from './AlertDataCard'
 
-function AlertDetailView({ alertData, loading, onDelete, onSendMessage, sendingMessage }) {
+const ALERT_STATUSES = [
+  ['new', 'New'],
+  ['acknowledged', 'Acknowledged'],
+  ['investigating', 'Investigating'],
+  ['resolved', 'Resolved'],
+  ['false_positive', 'False positive'],
+]
+
+function AlertDetailView({ alertData, loading, onDelete, onUpdateStatus, onAssign, onSendMessage, sendingMessage }) {
   const [reportExpanded, setReportExpanded] = useState(true)
   const [alertExpanded, setAlertExpanded] = useState(false)
+  const [assignee, setAssignee] = useState('')
 
   if (loading) {
     return (
@@ -26,6 +35,24 @@ function AlertDetailView({ alertData, loading, onDelete, onSendMessage, sendingM
 
   const prefix = alertData.alert?.details?.prefix || alertData.alert?.prefix || 'Unknown'
   const timestamp = new Date(alertData.created_at || Date.now()).toLocaleString()
+  const status = alertData.status || 'new'
+  const closed = status === 'resolved' || status === 'false_positive'
+
+  const handleStatusChange = (event) => {
+    const next = event.target.value
+    const body = { status: next }
+    if (next === 'resolved' || next === 'false_positive') {
+      // Optional; cancelling the prompt still closes the alert
+      body.resolution_notes = window.prompt('Resolution notes (optional)') || null
+    }
+    onUpdateStatus(body)
+  }
+
+  const handleAssign = (event) => {
+    event.preventDefault()
+    onAssign({ assignee: assignee.trim() || null })
+    setAssignee('')
+  }
 
   return (
     <div className="alert-detail-view">
@@ -34,6 +61,31 @@ function AlertDetailView({ alertData, loading, onDelete, onSendMessage, sendingM
           <h2>{prefix}</h2>
           <span className="alert-timestamp">{timestamp}</span>
         </div>
+        <div className="alert-lifecycle">
+          <select value={status} onChange={handleStatusChange} disabled={loading}>
+            {ALERT_STATUSES.map(([value, label]) => (
+              <option
+                key={value}
+                value={value}
+                disabled={value === 'new' && status !== 'new' && !closed}
+              >
+                {label}
+              </option>
+            ))}
+          </select>
+          <form onSubmit={handleAssign}>
+            <input
+              type="text"
+              value={assignee}
+              onChange={(e) => setAssignee(e.target.value)}
+              placeholder={alertData.assignee ? `Assigned to ${alertData.assignee}` : 'Unassigned'}
+              disabled={loading}
+            />
+            <button type="submit" disabled={loading}>
+              {assignee.trim() || !alertData.assignee ? 'Assign' : 'Unassign'}
+            </button>
+          </form>
+        </div>
         <button
           className="delete-button"
           onClick={onDelete}
@@ -45,17 +97,41 @@ function AlertDetailView({ alertData, loading, onDelete, onSendMessage, sendingM
       </div>
 
       <div className="alert-detail-content">
+        {closed && alertData.resolution_notes && (
+          <div className="resolution-notes">
+            <strong>Resolution:</strong> {alertData.resolution_notes}
+          </div>
+        )}
+
         <AlertDataCard 
           alert={alertData.alert} 
           expanded={alertExpanded}
           onToggle={() => setAlertExpanded(!alertExpanded)}
         />
 
-        {reportExpanded && (
+        {reportExpanded && (alertData.analysis_status && alertData.analysis_status !== 'done' ? (
+          <div className="incident-report-card">
+            <div className="report-error">
+              <h3>
+                {alertData.analysis_status === 'analysis_skipped_budget'
+                  ? 'Analysis skipped'
+                  : `Analysis ${alertData.analysis_status}`}
+              </h3>
+              <p>
+                {alertData.analysis_status === 'failed'
+                  ? 'The analysis agent could not produce a report for this alert.'
+                  : alertData.analysis_status === 'analysis_skipped_budget'
+                    ? 'This alert was not analyzed because the LLM budget was exhausted.'
+                    : 'The analysis agent is investigating this alert. The report will appear here when ready.'}
+              </p>
+            </div>
+          </div>
+        ) : (
           <IncidentReportCard 
+            report={alertData.report}
             rawResponse={alertData.initial_response}
           />
-        )}
+        ))}
 
         <div className="chat-section">
           <h3>💬 Chat with Agent</h3>
@@ -27,8 +27,8 @@ function AlertListItem({ alert, isSelected, onClick }) {
     return date.toLocaleDateString()
   }
 
-  const prefix = alert.alert_data?.details?.prefix || 'Unknown'
-  const kind = alert.alert_data?.details?.kind || 'unknown'
+  const prefix = alert.alert_data?.details?.prefix || alert.resource || 'Unknown'
+  const kind = alert.alert_data?.details?.kind || alert.category || 'unknown'
   const severityColor = getSeverityColor(kind)
 
   return (
@@ -46,7 +46,10 @@ function AlertListItem({ alert, isSelected, onClick }) {
         </span>
       </div>
       <div className="alert-item-timestamp">
-        {formatTimestamp(alert.created_at)}
+        {formatTimestamp(alert.last_seen || alert.created_at)}
+        {alert.occurrence_count > 1 && ` · seen ${alert.occurrence_count}×`}
+        {alert.status && alert.status !== 'new' && ` · ${alert.status.replace('_', ' ')}`}
+        {alert.assignee && ` · ${alert.assignee}`}
       </div>
     </div>
   )
@@ -1,18 +1,46 @@
+import { useState } from 'react'
 import AlertListItem from './AlertListItem'
 
-function AlertsSidebar({ alerts, selectedAlertId, onSelectAlert }) {
+function AlertsSidebar({
+  alerts,
+  total,
+  hasMore,
+  loadingMore,
+  onLoadMore,
+  search,
+  onSearch,
+  selectedAlertId,
+  onSelectAlert,
+}) {
+  const [query, setQuery] = useState(search)
+
+  const handleSearch = (e) => {
+    e.preventDefault()
+    onSearch(query)
+  }
+
   return (
     <div className="alerts-sidebar">
       <div className="sidebar-header">
         <h2>Alerts</h2>
-        <span className="alert-count">{alerts.length}</span>
+        <span className="alert-count">{total}</span>
       </div>
+      <form className="alerts-search" onSubmit={handleSearch}>
+        <input
+          type="search"
+          placeholder="Search analyses and chats"
+          value={query}
+          onChange={(e) => setQuery(e.target.value)}
+        />
+      </form>
       <div className="alerts-list">
         {alerts.length === 0 ? (
           <div className="empty-state">
-            <p>No alerts yet</p>
+            <p>{search ? 'No matching alerts' : 'No alerts yet'}</p>
             <p className="empty-state-hint">
-              Alerts will appear here when detected
+              {search
+                ? 'Try other search terms'
+                : 'Alerts will appear here when detected'}
             </p>
           </div>
         ) : (
@@ -25,10 +53,18 @@ function AlertsSidebar({ alerts, selectedAlertId, onSelectAlert }) {
             />
           ))
         )}
+        {hasMore && (
+          <button
+            className="load-more-button"
+            onClick={onLoadMore}
+            disabled={loadingMore}
+          >
+            {loadingMore ? 'Loading...' : `Load more (${alerts.length} of ${total})`}
+          </button>
+        )}
       </div>
     </div>
   )
 }
 
 export default AlertsSidebar
-
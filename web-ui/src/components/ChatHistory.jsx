@@ -35,12 +35,12 @@ function ChatHistory({ messages }) {
             </span>
             <span className="chat-message-timestamp">
               {message.loading
-                ? 'Thinking...'
+                ? message.status || 'Thinking...'
                 : new Date(message.created_at).toLocaleTimeString()}
             </span>
           </div>
           <div className="chat-message-content">
-            {message.loading ? (
+            {message.loading && !message.content ? (
               <div className="loading-indicator">
                 <span className="loading-dot"></span>
                 <span className="loading-dot"></span>
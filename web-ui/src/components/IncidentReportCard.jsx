@@ -3,11 +3,11 @@ import { useState } from 'react'
 function IncidentReportCard({ report, rawResponse }) {
   const [showRaw, setShowRaw] = useState(false)
 
-  // Try to parse JSON response
-  let parsedReport = null
+  // Prefer the typed report validated by the server, fall back to parsing the raw response
+  let parsedReport = report || null
   let parseError = null
   
-  try {
+  if (!parsedReport) try {
     // Remove markdown code blocks if present
     let cleanJson = rawResponse.trim()
     if (cleanJson.startsWith('```')) {
@@ -8,6 +8,11 @@ import DeleteConfirmDialog from './DeleteConfirmDialog'
 function MainView({
   alerts,
   setAlerts,
+  alertsTotal,
+  hasMoreAlerts,
+  loadMoreAlerts,
+  alertSearch,
+  searchAlerts,
   selectedAlertId,
   setSelectedAlertId,
   selectedAlertData,
@@ -26,6 +31,7 @@ function MainView({
   fetchAlertDetails,
   sendChatMessage,
   deleteAlert,
+  updateAlertLifecycle,
   handleSelectAlert,
   handleDeleteClick,
   handleDeleteConfirm,
@@ -106,6 +112,12 @@ function MainView({
       <div className="main-container">
         <AlertsSidebar
           alerts={alerts}
+          total={alertsTotal}
+          hasMore={hasMoreAlerts}
+          loadingMore={loading.alerts}
+          onLoadMore={loadMoreAlerts}
+          search={alertSearch}
+          onSearch={searchAlerts}
           selectedAlertId={selectedAlertId}
           onSelectAlert={handleSelectAlert}
         />
@@ -116,6 +128,8 @@ function MainView({
               alertData={selectedAlertData}
               loading={loading.alertDetails}
               onDelete={handleDeleteClick}
+              onUpdateStatus={(body) => updateAlertLifecycle(selectedAlertId, 'status', body)}
+              onAssign={(body) => updateAlertLifecycle(selectedAlertId, 'assignee', body)}
               onSendMessage={sendChatMessage}
               sendingMessage={loading.sendingMessage}
             />